@@ -1,6 +1,10 @@
 use anyhow::{Ok, Result};
+use storage_layout::StorageLayout;
 use substreams_ethereum::Abigen;
 
+#[path = "build/storage_layout.rs"]
+mod storage_layout;
+
 fn main() -> Result<(), anyhow::Error> {
     Abigen::new("pool", "abis/pool.json")?
         .generate()?
@@ -8,12 +12,28 @@ fn main() -> Result<(), anyhow::Error> {
     Abigen::new("erc20", "abis/ERC20.json")?
         .generate()?
         .write_to_file("src/abi/erc20.rs")?;
+    Abigen::new("erc20_name_bytes", "abis/ERC20NameBytes.json")?
+        .generate()?
+        .write_to_file("src/abi/erc20_name_bytes.rs")?;
+    Abigen::new("erc20_symbol_bytes", "abis/ERC20SymbolBytes.json")?
+        .generate()?
+        .write_to_file("src/abi/erc20_symbol_bytes.rs")?;
     Abigen::new("factory", "abis/factory.json")?
         .generate()?
         .write_to_file("src/abi/factory.rs")?;
     Abigen::new("positionmanager", "abis/NonfungiblePositionManager.json")?
         .generate()?
         .write_to_file("src/abi/positionmanager.rs")?;
+    Abigen::new("chainlink_aggregator", "abis/ChainlinkAggregator.json")?
+        .generate()?
+        .write_to_file("src/abi/chainlink_aggregator.rs")?;
+
+    StorageLayout::new("UniswapPoolStorage", "abis/storage/UniswapV3Pool.json")?
+        .generate()?
+        .write_to_file("src/storage/uniswap_v3_pool.rs")?;
+    StorageLayout::new("PositionManagerStorage", "abis/storage/NonfungiblePositionManager.json")?
+        .generate()?
+        .write_to_file("src/storage/position_manager.rs")?;
 
     Ok(())
 }
@@ -0,0 +1,500 @@
+// Generates typed readers of the storage changes of a contract from the `storageLayout`
+// output of solc, see https://docs.soliditylang.org/en/latest/internals/layout_in_storage.html
+//
+// Every state variable gets an accessor on the `{name}` struct returning the old and new
+// values of the variable when it changed, or the reader of the struct it holds. Mapping
+// keys and array indexes become parameters of the accessor, nested mappings taking one
+// key per level. Packed fields are read at their byte offset. Strings and bytes are not
+// supported, the variables holding them are skipped.
+
+use anyhow::{anyhow, Context, Result};
+use heck::ToSnakeCase;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+
+pub struct StorageLayout {
+    name: String,
+    source: String,
+    storage: Vec<Variable>,
+    types: HashMap<String, StorageType>,
+}
+
+pub struct GeneratedStorage {
+    code: String,
+}
+
+struct Variable {
+    label: String,
+    slot: u64,
+    offset: usize,
+    type_id: String,
+}
+
+struct StorageType {
+    encoding: String,
+    label: String,
+    number_of_bytes: usize,
+    key: Option<String>,
+    value: Option<String>,
+    base: Option<String>,
+    members: Vec<Variable>,
+}
+
+// Mapping keys and array indexes between a variable and the value or struct it holds
+enum Step {
+    Key(String),
+    Index { base: String, dynamic: bool },
+}
+
+struct Param {
+    name: String,
+    rust_type: String,
+}
+
+enum Scope {
+    Contract,
+    Struct,
+}
+
+impl StorageLayout {
+    pub fn new(name: &str, path: &str) -> Result<StorageLayout> {
+        let content = fs::read_to_string(path).with_context(|| format!("reading storage layout {path}"))?;
+        let layout: Value = serde_json::from_str(&content).with_context(|| format!("parsing storage layout {path}"))?;
+
+        let storage = parse_variables(&layout["storage"])?;
+        let mut types = HashMap::new();
+        for (type_id, value) in layout["types"]
+            .as_object()
+            .ok_or_else(|| anyhow!("storage layout {path} has no types"))?
+        {
+            types.insert(type_id.clone(), parse_type(value)?);
+        }
+
+        Ok(StorageLayout {
+            name: name.to_string(),
+            source: path.to_string(),
+            storage,
+            types,
+        })
+    }
+
+    pub fn generate(&self) -> Result<GeneratedStorage> {
+        let mut code = String::new();
+        writeln!(code, "// Code generated from {} by build.rs, DO NOT EDIT.", self.source)?;
+        writeln!(code)?;
+        writeln!(code, "use crate::storage::utils;")?;
+        writeln!(code, "use substreams::scalar::BigInt;")?;
+        writeln!(code, "use substreams_ethereum::pb::eth::v2::StorageChange;")?;
+        writeln!(code)?;
+
+        let name = &self.name;
+        writeln!(code, "pub struct {name}<'a> {{")?;
+        writeln!(code, "    pub storage_changes: &'a Vec<StorageChange>,")?;
+        writeln!(code, "    pub contract_addr: [u8; 20],")?;
+        writeln!(code, "}}")?;
+        writeln!(code)?;
+        writeln!(code, "impl<'a> {name}<'a> {{")?;
+        writeln!(
+            code,
+            "    pub fn new(storage_changes: &'a Vec<StorageChange>, contract_addr: &Vec<u8>) -> {name}<'a> {{"
+        )?;
+        writeln!(code, "        Self {{")?;
+        writeln!(code, "            storage_changes,")?;
+        writeln!(code, "            contract_addr: utils::contract_pad(contract_addr),")?;
+        writeln!(code, "        }}")?;
+        writeln!(code, "    }}")?;
+
+        let mut structs = vec![];
+        for variable in &self.storage {
+            self.write_accessor(&mut code, variable, &Scope::Contract, &mut structs)?;
+            self.write_array_index(&mut code, variable)?;
+        }
+
+        writeln!(code)?;
+        writeln!(code, "    fn filtered_changes(&self) -> Vec<&StorageChange> {{")?;
+        writeln!(code, "        self.storage_changes")?;
+        writeln!(code, "            .iter()")?;
+        writeln!(
+            code,
+            "            .filter(|change| change.address == self.contract_addr)"
+        )?;
+        writeln!(code, "            .collect()")?;
+        writeln!(code, "    }}")?;
+        writeln!(code, "}}")?;
+
+        // structs are written in the order they are reached, the ones they hold included
+        let mut written = 0;
+        while written < structs.len() {
+            let type_id = structs[written].clone();
+            written += 1;
+
+            let struct_name = self.struct_name(&type_id);
+            writeln!(code)?;
+            writeln!(code, "pub struct {struct_name}<'a> {{")?;
+            writeln!(code, "    pub storage_changes: Vec<&'a StorageChange>,")?;
+            writeln!(code, "    pub struct_slot: [u8; 32],")?;
+            writeln!(code, "}}")?;
+            writeln!(code)?;
+            writeln!(code, "impl<'a> {struct_name}<'a> {{")?;
+            writeln!(
+                code,
+                "    pub fn new(storage_changes: Vec<&'a StorageChange>, struct_slot: [u8; 32]) -> {struct_name}<'a> {{"
+            )?;
+            writeln!(code, "        Self {{")?;
+            writeln!(code, "            storage_changes,")?;
+            writeln!(code, "            struct_slot,")?;
+            writeln!(code, "        }}")?;
+            writeln!(code, "    }}")?;
+            for member in &self.get_type(&type_id)?.members {
+                self.write_accessor(&mut code, member, &Scope::Struct, &mut structs)?;
+            }
+            writeln!(code, "}}")?;
+        }
+
+        Ok(GeneratedStorage { code })
+    }
+
+    fn write_accessor(
+        &self,
+        code: &mut String,
+        variable: &Variable,
+        scope: &Scope,
+        structs: &mut Vec<String>,
+    ) -> Result<()> {
+        let mut steps = vec![];
+        let mut type_id = variable.type_id.clone();
+        loop {
+            let storage_type = self.get_type(&type_id)?;
+            match storage_type.encoding.as_str() {
+                "mapping" => {
+                    let key = storage_type
+                        .key
+                        .clone()
+                        .ok_or_else(|| anyhow!("mapping {type_id} without key in storage layout {}", self.source))?;
+                    let value = storage_type
+                        .value
+                        .clone()
+                        .ok_or_else(|| anyhow!("mapping {type_id} without value in storage layout {}", self.source))?;
+                    steps.push(Step::Key(key));
+                    type_id = value;
+                }
+                "dynamic_array" | "inplace" if storage_type.base.is_some() => {
+                    let base = storage_type.base.clone().unwrap();
+                    steps.push(Step::Index {
+                        base: base.clone(),
+                        dynamic: storage_type.encoding == "dynamic_array",
+                    });
+                    type_id = base;
+                }
+                "inplace" => break,
+                // strings and bytes
+                _ => return Ok(()),
+            }
+        }
+
+        for step in &steps {
+            if let Step::Key(key_type) = step {
+                if self.key_type(key_type)?.is_none() {
+                    return Ok(());
+                }
+            }
+        }
+        let key_count = steps.iter().filter(|step| matches!(step, Step::Key(_))).count();
+        let index_count = steps.len() - key_count;
+
+        let (changes, changes_ref, slot) = match scope {
+            Scope::Contract => (
+                "self.filtered_changes()",
+                "&self.filtered_changes()",
+                format!("utils::left_pad_from_bigint(&BigInt::from({}))", variable.slot),
+            ),
+            Scope::Struct => (
+                "self.storage_changes.clone()",
+                "&self.storage_changes",
+                format!(
+                    "utils::calc_struct_slot(&self.struct_slot, BigInt::from({}))",
+                    variable.slot
+                ),
+            ),
+        };
+
+        let mut params: Vec<Param> = vec![];
+        let mut body = vec![format!("let slot_key = {slot};")];
+        let mut offset = variable.offset.to_string();
+        let (mut keys, mut indexes) = (0, 0);
+        for step in &steps {
+            match step {
+                Step::Key(key_type) => {
+                    let (rust_type, encoding) = self.key_type(key_type)?.unwrap();
+                    let name = param_name("key", keys, key_count);
+                    keys += 1;
+                    let encoded = encoding.replace("{key}", &name);
+                    body.push(format!("let slot_key = utils::calc_map_slot({encoded}, &slot_key);"));
+                    params.push(Param { name, rust_type });
+                }
+                Step::Index { base, dynamic } => {
+                    let name = param_name("index", indexes, index_count);
+                    indexes += 1;
+                    if *dynamic {
+                        body.push("let slot_key = utils::calc_array_slot(&slot_key);".to_string());
+                    }
+                    let base_type = self.get_type(base)?;
+                    if base_type.number_of_bytes < 32 && base_type.members.is_empty() {
+                        // packed values
+                        let per_slot = 32 / base_type.number_of_bytes;
+                        body.push(format!(
+                            "let slot_key = utils::calc_struct_slot(&slot_key, BigInt::from({name} / {per_slot}));"
+                        ));
+                        offset = format!("({name} % {per_slot}) as usize * {}", base_type.number_of_bytes);
+                    } else {
+                        let slots = (base_type.number_of_bytes + 31) / 32;
+                        let index = match slots {
+                            1 => name.clone(),
+                            _ => format!("{name} * {slots}"),
+                        };
+                        body.push(format!(
+                            "let slot_key = utils::calc_struct_slot(&slot_key, BigInt::from({index}));"
+                        ));
+                    }
+                    params.push(Param {
+                        name,
+                        rust_type: "u64".to_string(),
+                    });
+                }
+            }
+        }
+
+        let storage_type = self.get_type(&type_id)?;
+        let fn_name = accessor_name(&variable.label);
+        let args = params.iter().try_fold(String::new(), |mut args, param| {
+            write!(args, ", {}: {}", param.name, param.rust_type).map(|_| args)
+        })?;
+
+        writeln!(code)?;
+        writeln!(
+            code,
+            "    // {} {}, slot {}, offset {}",
+            self.get_type(&variable.type_id)?.label,
+            variable.label,
+            variable.slot,
+            variable.offset
+        )?;
+        if !storage_type.members.is_empty() {
+            let struct_name = self.struct_name(&type_id);
+            if !structs.contains(&type_id) {
+                structs.push(type_id.clone());
+            }
+            writeln!(code, "    pub fn {fn_name}(&self{args}) -> {struct_name} {{")?;
+            for line in &body {
+                writeln!(code, "        {line}")?;
+            }
+            writeln!(code)?;
+            writeln!(code, "        {struct_name}::new({changes}, slot_key)")?;
+            writeln!(code, "    }}")?;
+            return Ok(());
+        }
+
+        let (rust_type, decode) = value_type(&storage_type.label)?;
+        writeln!(
+            code,
+            "    pub fn {fn_name}(&self{args}) -> Option<({rust_type}, {rust_type})> {{"
+        )?;
+        for line in &body {
+            writeln!(code, "        {line}")?;
+        }
+        writeln!(code, "        let offset = {offset};")?;
+        writeln!(code, "        let number_of_bytes = {};", storage_type.number_of_bytes)?;
+        writeln!(code)?;
+        writeln!(
+            code,
+            "        utils::get_storage_change({changes_ref}, slot_key, offset, number_of_bytes)"
+        )?;
+        writeln!(
+            code,
+            "            .map(|(old_data, new_data)| ({decode}(old_data), {decode}(new_data)))"
+        )?;
+        writeln!(code, "    }}")?;
+
+        Ok(())
+    }
+
+    // Fixed size arrays of the contract also get the index of the element held at a storage
+    // key, to find the elements written without knowing their indexes. Packed values share
+    // their slots and are skipped.
+    fn write_array_index(&self, code: &mut String, variable: &Variable) -> Result<()> {
+        let array_type = self.get_type(&variable.type_id)?;
+        let base_type = match (&array_type.base, array_type.encoding.as_str()) {
+            (Some(base), "inplace") => self.get_type(base)?,
+            _ => return Ok(()),
+        };
+        if base_type.number_of_bytes < 32 && base_type.members.is_empty() {
+            return Ok(());
+        }
+        let element_slots = (base_type.number_of_bytes + 31) / 32;
+        let length = array_type.number_of_bytes / 32 / element_slots;
+
+        let fn_name = accessor_name(&variable.label);
+        writeln!(code)?;
+        writeln!(
+            code,
+            "    // index of the {} element held at the storage key, none outside of the array",
+            variable.label
+        )?;
+        writeln!(
+            code,
+            "    pub fn {}_index(slot_key: &[u8]) -> Option<u64> {{",
+            fn_name.trim_start_matches("r#")
+        )?;
+        writeln!(
+            code,
+            "        utils::array_index(slot_key, {}, {length}, {element_slots})",
+            variable.slot
+        )?;
+        writeln!(code, "    }}")?;
+
+        Ok(())
+    }
+
+    // The rust type of a mapping key and how it is encoded to compute the slot of the value,
+    // none for the keys hashed from dynamic data
+    fn key_type(&self, type_id: &str) -> Result<Option<(String, String)>> {
+        let label = self.get_type(type_id)?.label.as_str();
+        let key_type = if label.starts_with("uint") || label.starts_with("int") || label.starts_with("enum ") {
+            ("&BigInt".to_string(), "&utils::left_pad_from_bigint({key})".to_string())
+        } else if label == "address" || label == "address payable" || label.starts_with("contract ") {
+            (
+                "&[u8; 20]".to_string(),
+                "&utils::left_pad(&{key}.to_vec(), 0)".to_string(),
+            )
+        } else if label == "bool" {
+            (
+                "bool".to_string(),
+                "&utils::left_pad(&vec![{key} as u8], 0)".to_string(),
+            )
+        } else if label == "bytes32" {
+            ("&[u8; 32]".to_string(), "{key}".to_string())
+        } else if let Some(size) = label.strip_prefix("bytes").filter(|size| !size.is_empty()) {
+            (format!("&[u8; {size}]"), "&utils::right_pad({key})".to_string())
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(key_type))
+    }
+
+    // struct types are named after the struct, qualified by their contract or library when
+    // the name is not unique, e.g. `Tick.Info` and `Position.Info` in the pool
+    fn struct_name(&self, type_id: &str) -> String {
+        let qualified_name = struct_qualified_name(&self.types[type_id].label);
+        let name = qualified_name.rsplit('.').next().unwrap();
+        let homonyms = self
+            .types
+            .values()
+            .filter(|storage_type| !storage_type.members.is_empty())
+            .map(|storage_type| struct_qualified_name(&storage_type.label))
+            .filter(|other| other != &qualified_name && other.rsplit('.').next().unwrap() == name)
+            .count();
+
+        match homonyms {
+            0 => format!("{name}Struct"),
+            _ => format!("{}Struct", qualified_name.replace('.', "")),
+        }
+    }
+
+    fn get_type(&self, type_id: &str) -> Result<&StorageType> {
+        self.types
+            .get(type_id)
+            .ok_or_else(|| anyhow!("unknown type {type_id} in storage layout {}", self.source))
+    }
+}
+
+impl GeneratedStorage {
+    pub fn write_to_file(&self, path: &str) -> Result<()> {
+        fs::write(path, &self.code).with_context(|| format!("writing {path}"))
+    }
+}
+
+fn parse_variables(value: &Value) -> Result<Vec<Variable>> {
+    let mut variables = vec![];
+    for variable in value
+        .as_array()
+        .ok_or_else(|| anyhow!("expected a list of variables"))?
+    {
+        variables.push(Variable {
+            label: string_field(variable, "label")?,
+            slot: string_field(variable, "slot")?.parse()?,
+            offset: variable["offset"]
+                .as_u64()
+                .ok_or_else(|| anyhow!("variable without offset"))? as usize,
+            type_id: string_field(variable, "type")?,
+        });
+    }
+
+    Ok(variables)
+}
+
+fn parse_type(value: &Value) -> Result<StorageType> {
+    Ok(StorageType {
+        encoding: string_field(value, "encoding")?,
+        label: string_field(value, "label")?,
+        number_of_bytes: string_field(value, "numberOfBytes")?.parse()?,
+        key: value["key"].as_str().map(str::to_string),
+        value: value["value"].as_str().map(str::to_string),
+        base: value["base"].as_str().map(str::to_string),
+        members: match value.get("members") {
+            Some(members) => parse_variables(members)?,
+            None => vec![],
+        },
+    })
+}
+
+fn string_field(value: &Value, field: &str) -> Result<String> {
+    value[field]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("missing {field} in {value}"))
+}
+
+// The rust type of a value and the storage utils function decoding it
+fn value_type(label: &str) -> Result<(String, String)> {
+    let value_type = if label.starts_with("uint") || label.starts_with("enum ") {
+        ("BigInt".to_string(), "utils::to_uint".to_string())
+    } else if label.starts_with("int") {
+        ("BigInt".to_string(), "utils::to_int".to_string())
+    } else if label == "bool" {
+        ("bool".to_string(), "utils::to_bool".to_string())
+    } else if label == "address" || label == "address payable" || label.starts_with("contract ") {
+        ("[u8; 20]".to_string(), "utils::to_bytes::<20>".to_string())
+    } else if let Some(size) = label.strip_prefix("bytes").filter(|size| !size.is_empty()) {
+        (format!("[u8; {size}]"), format!("utils::to_bytes::<{size}>"))
+    } else {
+        return Err(anyhow!("unsupported value type {label}"));
+    };
+
+    Ok(value_type)
+}
+
+// `struct EnumerableSet.UintSet` -> `EnumerableSet.UintSet`
+fn struct_qualified_name(label: &str) -> String {
+    label.trim_start_matches("struct ").to_string()
+}
+
+// `_poolIdToPoolKey` -> `pool_id_to_pool_key`
+fn accessor_name(label: &str) -> String {
+    let name = label.trim_start_matches('_').to_snake_case();
+    match name.as_str() {
+        "type" | "struct" | "enum" | "fn" | "impl" | "mod" | "use" | "ref" | "match" | "loop" | "move" | "where"
+        | "const" | "static" | "trait" | "in" | "as" | "box" => format!("r#{name}"),
+        _ => name,
+    }
+}
+
+// `key` when there is a single key, `key0`, `key1`, ... otherwise
+fn param_name(prefix: &str, position: usize, count: usize) -> String {
+    match count {
+        1 => prefix.to_string(),
+        _ => format!("{prefix}{position}"),
+    }
+}
@@ -0,0 +1,270 @@
+    const INTERNAL_ERR: &'static str = "`ethabi_derive` internal error";
+    /// Contract's functions.
+    #[allow(dead_code, unused_imports, unused_variables)]
+    pub mod functions {
+        use super::INTERNAL_ERR;
+    }
+    /// Contract's events.
+    #[allow(dead_code, unused_imports, unused_variables)]
+    pub mod events {
+        use super::INTERNAL_ERR;
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct AnswerUpdated {
+            pub current: substreams::scalar::BigInt,
+            pub round_id: substreams::scalar::BigInt,
+            pub updated_at: substreams::scalar::BigInt,
+        }
+        impl AnswerUpdated {
+            const TOPIC_ID: [u8; 32] = [
+                5u8,
+                89u8,
+                136u8,
+                79u8,
+                211u8,
+                164u8,
+                96u8,
+                219u8,
+                48u8,
+                115u8,
+                183u8,
+                252u8,
+                137u8,
+                108u8,
+                199u8,
+                121u8,
+                134u8,
+                241u8,
+                110u8,
+                55u8,
+                130u8,
+                16u8,
+                222u8,
+                212u8,
+                49u8,
+                134u8,
+                23u8,
+                91u8,
+                246u8,
+                70u8,
+                252u8,
+                95u8,
+            ];
+            pub fn match_log(log: &substreams_ethereum::pb::eth::v2::Log) -> bool {
+                if log.topics.len() != 3usize {
+                    return false;
+                }
+                if log.data.len() != 32usize {
+                    return false;
+                }
+                return log.topics.get(0).expect("bounds already checked").as_ref()
+                    == Self::TOPIC_ID;
+            }
+            pub fn decode(
+                log: &substreams_ethereum::pb::eth::v2::Log,
+            ) -> Result<Self, String> {
+                let mut values = ethabi::decode(
+                        &[ethabi::ParamType::Uint(256usize)],
+                        log.data.as_ref(),
+                    )
+                    .map_err(|e| format!("unable to decode log.data: {:?}", e))?;
+                values.reverse();
+                Ok(Self {
+                    current: substreams::scalar::BigInt::from_signed_bytes_be(
+                        log.topics[1usize].as_ref(),
+                    ),
+                    round_id: {
+                        let mut v = [0 as u8; 32];
+                        ethabi::decode(
+                                &[ethabi::ParamType::Uint(256usize)],
+                                log.topics[2usize].as_ref(),
+                            )
+                            .map_err(|e| {
+                                format!(
+                                    "unable to decode param 'round_id' from topic of type 'uint256': {:?}",
+                                    e
+                                )
+                            })?
+                            .pop()
+                            .expect(INTERNAL_ERR)
+                            .into_uint()
+                            .expect(INTERNAL_ERR)
+                            .to_big_endian(v.as_mut_slice());
+                        substreams::scalar::BigInt::from_unsigned_bytes_be(&v)
+                    },
+                    updated_at: {
+                        let mut v = [0 as u8; 32];
+                        values
+                            .pop()
+                            .expect(INTERNAL_ERR)
+                            .into_uint()
+                            .expect(INTERNAL_ERR)
+                            .to_big_endian(v.as_mut_slice());
+                        substreams::scalar::BigInt::from_unsigned_bytes_be(&v)
+                    },
+                })
+            }
+        }
+        impl substreams_ethereum::Event for AnswerUpdated {
+            const NAME: &'static str = "AnswerUpdated";
+            fn match_log(log: &substreams_ethereum::pb::eth::v2::Log) -> bool {
+                Self::match_log(log)
+            }
+            fn decode(
+                log: &substreams_ethereum::pb::eth::v2::Log,
+            ) -> Result<Self, String> {
+                Self::decode(log)
+            }
+        }
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct NewTransmission {
+            pub aggregator_round_id: substreams::scalar::BigInt,
+            pub answer: substreams::scalar::BigInt,
+            pub transmitter: Vec<u8>,
+            pub observations: Vec<substreams::scalar::BigInt>,
+            pub observers: Vec<u8>,
+            pub raw_report_context: [u8; 32usize],
+        }
+        impl NewTransmission {
+            const TOPIC_ID: [u8; 32] = [
+                246u8,
+                169u8,
+                121u8,
+                68u8,
+                243u8,
+                30u8,
+                160u8,
+                96u8,
+                223u8,
+                222u8,
+                5u8,
+                102u8,
+                228u8,
+                22u8,
+                124u8,
+                26u8,
+                16u8,
+                130u8,
+                85u8,
+                30u8,
+                100u8,
+                182u8,
+                14u8,
+                203u8,
+                20u8,
+                213u8,
+                153u8,
+                169u8,
+                208u8,
+                35u8,
+                212u8,
+                81u8,
+            ];
+            pub fn match_log(log: &substreams_ethereum::pb::eth::v2::Log) -> bool {
+                if log.topics.len() != 2usize {
+                    return false;
+                }
+                if log.data.len() < 224usize {
+                    return false;
+                }
+                return log.topics.get(0).expect("bounds already checked").as_ref()
+                    == Self::TOPIC_ID;
+            }
+            pub fn decode(
+                log: &substreams_ethereum::pb::eth::v2::Log,
+            ) -> Result<Self, String> {
+                let mut values = ethabi::decode(
+                        &[
+                            ethabi::ParamType::Int(192usize),
+                            ethabi::ParamType::Address,
+                            ethabi::ParamType::Array(
+                                Box::new(ethabi::ParamType::Int(192usize)),
+                            ),
+                            ethabi::ParamType::Bytes,
+                            ethabi::ParamType::FixedBytes(32usize),
+                        ],
+                        log.data.as_ref(),
+                    )
+                    .map_err(|e| format!("unable to decode log.data: {:?}", e))?;
+                values.reverse();
+                Ok(Self {
+                    aggregator_round_id: {
+                        let mut v = [0 as u8; 32];
+                        ethabi::decode(
+                                &[ethabi::ParamType::Uint(32usize)],
+                                log.topics[1usize].as_ref(),
+                            )
+                            .map_err(|e| {
+                                format!(
+                                    "unable to decode param 'aggregator_round_id' from topic of type 'uint32': {:?}",
+                                    e
+                                )
+                            })?
+                            .pop()
+                            .expect(INTERNAL_ERR)
+                            .into_uint()
+                            .expect(INTERNAL_ERR)
+                            .to_big_endian(v.as_mut_slice());
+                        substreams::scalar::BigInt::from_unsigned_bytes_be(&v)
+                    },
+                    answer: {
+                        let mut v = [0 as u8; 32];
+                        values
+                            .pop()
+                            .expect(INTERNAL_ERR)
+                            .into_int()
+                            .expect(INTERNAL_ERR)
+                            .to_big_endian(v.as_mut_slice());
+                        substreams::scalar::BigInt::from_signed_bytes_be(&v)
+                    },
+                    transmitter: values
+                        .pop()
+                        .expect(INTERNAL_ERR)
+                        .into_address()
+                        .expect(INTERNAL_ERR)
+                        .as_bytes()
+                        .to_vec(),
+                    observations: values
+                        .pop()
+                        .expect(INTERNAL_ERR)
+                        .into_array()
+                        .expect(INTERNAL_ERR)
+                        .into_iter()
+                        .map(|inner| {
+                            let mut v = [0 as u8; 32];
+                            inner
+                                .into_int()
+                                .expect(INTERNAL_ERR)
+                                .to_big_endian(v.as_mut_slice());
+                            substreams::scalar::BigInt::from_signed_bytes_be(&v)
+                        })
+                        .collect(),
+                    observers: values
+                        .pop()
+                        .expect(INTERNAL_ERR)
+                        .into_bytes()
+                        .expect(INTERNAL_ERR),
+                    raw_report_context: {
+                        let mut result = [0u8; 32];
+                        let v = values
+                            .pop()
+                            .expect(INTERNAL_ERR)
+                            .into_fixed_bytes()
+                            .expect(INTERNAL_ERR);
+                        result.copy_from_slice(&v);
+                        result
+                    },
+                })
+            }
+        }
+        impl substreams_ethereum::Event for NewTransmission {
+            const NAME: &'static str = "NewTransmission";
+            fn match_log(log: &substreams_ethereum::pb::eth::v2::Log) -> bool {
+                Self::match_log(log)
+            }
+            fn decode(
+                log: &substreams_ethereum::pb::eth::v2::Log,
+            ) -> Result<Self, String> {
+                Self::decode(log)
+            }
+        }
+    }
\ No newline at end of file
@@ -1,6 +1,15 @@
+#[allow(unused_imports)]
+pub mod chainlink_aggregator;
+
 #[allow(unused_imports)]
 pub mod erc20;
 
+#[allow(unused_imports)]
+pub mod erc20_name_bytes;
+
+#[allow(unused_imports)]
+pub mod erc20_symbol_bytes;
+
 #[allow(unused_imports)]
 pub mod factory;
 
@@ -0,0 +1,487 @@
+use crate::abi;
+use crate::pb::uniswap::{Erc20Token, Pool, PoolTokenTransfer, TokenBehaviour};
+use crate::storage::utils;
+use crate::utils::{get_adjusted_amounts, ZERO_ADDRESS};
+use std::ops::Sub;
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::{BigDecimal, BigInt};
+use substreams::store::{DeltaBigDecimal, DeltaExt, Deltas, StoreGet, StoreGetProto, StoreGetRaw};
+use substreams::{key, Hex};
+use substreams_ethereum::pb::eth::v2::{Log, StorageChange, TransactionTrace};
+use substreams_ethereum::Event;
+
+// number of transactions a token has to be seen behaving as a fee-on-transfer or rebasing
+// token in before being flagged, a single one can be an accounting quirk
+pub const BEHAVIOUR_OBSERVATIONS: i64 = 3;
+
+// slots tried for the balanceOf mapping of the tokens, solidity tokens declare it among
+// their first state variables, e.g. at slot 3 for WETH or 9 for USDC. The balances of
+// tokens keeping them elsewhere are not read.
+const BALANCE_SLOTS: u64 = 16;
+
+// Every token of a pool moving in or out of it goes through an ERC20 Transfer, whether it
+// comes from a swap, a mint, a collect, a flash loan or a plain transfer to the pool. The
+// amounts are the ones actually moved, after the fees of fee-on-transfer tokens. Balance
+// changes without a Transfer, e.g. rebases, are not seen.
+pub fn extract_pool_token_transfers(
+    transfers: &mut Vec<PoolTokenTransfer>,
+    log: &Log,
+    transaction_id: &str,
+    pools_store: &StoreGetProto<Pool>,
+) {
+    let transfer = match abi::erc20::events::Transfer::match_and_decode(log) {
+        Some(transfer) => transfer,
+        None => return,
+    };
+    if transfer.from == transfer.to {
+        return;
+    }
+    let token_address = Hex(&log.address).to_string();
+
+    for (pool_address, outgoing) in [(&transfer.to, false), (&transfer.from, true)] {
+        let pool_address = Hex(pool_address).to_string();
+        let (token_idx, token) = match pool_token(pools_store, &pool_address, &token_address) {
+            Some(pool_token) => pool_token,
+            None => continue,
+        };
+
+        let amount = transfer.value.to_decimal(token.decimals);
+        transfers.push(PoolTokenTransfer {
+            pool_address,
+            token: token_address.clone(),
+            token_idx,
+            amount: match outgoing {
+                true => amount.neg().to_string(),
+                false => amount.to_string(),
+            },
+            from: Hex(&transfer.from).to_string(),
+            to: Hex(&transfer.to).to_string(),
+            transaction_id: transaction_id.to_string(),
+            ordinal: log.ordinal,
+        });
+    }
+}
+
+// Transfers of a pool token, to or from a pool, are checked against the balanceOf
+// storage of the sender and the recipient, a recipient credited less than the sender is
+// debited being charged a fee. Transfers minting or burning tokens, and tokens whose
+// balances are not found, are not checked.
+pub fn extract_token_behaviours(
+    behaviours: &mut Vec<TokenBehaviour>,
+    trx: &TransactionTrace,
+    log: &Log,
+    pools_store: &StoreGetProto<Pool>,
+) {
+    let transfer = match abi::erc20::events::Transfer::match_and_decode(log) {
+        Some(transfer) => transfer,
+        None => return,
+    };
+    if transfer.from == transfer.to || transfer.from == ZERO_ADDRESS || transfer.to == ZERO_ADDRESS {
+        return;
+    }
+    let token_address = Hex(&log.address).to_string();
+    if pool_token(pools_store, &Hex(&transfer.from).to_string(), &token_address).is_none()
+        && pool_token(pools_store, &Hex(&transfer.to).to_string(), &token_address).is_none()
+    {
+        return;
+    }
+
+    let debited = match last_balance_change(trx, &log.address, &transfer.from, log.ordinal) {
+        Some((old_balance, new_balance)) => old_balance.sub(new_balance),
+        None => return,
+    };
+    let credited = match last_balance_change(trx, &log.address, &transfer.to, log.ordinal) {
+        Some((old_balance, new_balance)) => new_balance.sub(old_balance),
+        None => return,
+    };
+    if !is_fee_on_transfer(&transfer.value, &debited, &credited) {
+        return;
+    }
+
+    behaviours.push(TokenBehaviour {
+        token: token_address,
+        kind: "fee_on_transfer".to_string(),
+        amount: transfer.value.to_string(),
+        debited: debited.to_string(),
+        credited: credited.to_string(),
+        transaction_id: Hex(&trx.hash).to_string(),
+        ordinal: log.ordinal,
+    });
+}
+
+// Writes of the balanceOf storage of a pool by the transaction without any Transfer of the
+// token to or from the pool, the token moving the balances without transfers as rebasing
+// tokens do. Only the pools emitting events in the transaction are looked at.
+pub fn extract_rebasing_behaviours(behaviours: &mut Vec<TokenBehaviour>, trx: &TransactionTrace, pools: &[Pool]) {
+    for pool in pools {
+        let pool_address = hex::decode(&pool.address).unwrap();
+        for token in [pool.token0_ref(), pool.token1_ref()] {
+            let token_address = hex::decode(&token.address).unwrap();
+            let changes = balance_changes(trx, &token_address, &pool_address);
+            let (first, last) = match (changes.first(), changes.last()) {
+                (Some(first), Some(last)) => (first, last),
+                _ => continue,
+            };
+            let transferred = trx.logs_with_calls().any(|(log, _)| {
+                log.address == token_address
+                    && abi::erc20::events::Transfer::match_and_decode(log)
+                        .map(|transfer| transfer.from == pool_address || transfer.to == pool_address)
+                        .unwrap_or(false)
+            });
+            if transferred {
+                continue;
+            }
+
+            behaviours.push(TokenBehaviour {
+                token: token.address.clone(),
+                kind: "rebasing".to_string(),
+                amount: "0".to_string(),
+                debited: "0".to_string(),
+                credited: utils::to_uint(&last.new_value)
+                    .sub(utils::to_uint(&first.old_value))
+                    .to_string(),
+                transaction_id: Hex(&trx.hash).to_string(),
+                ordinal: first.ordinal,
+            });
+        }
+    }
+}
+
+// the recipient of a transfer charged a fee is credited less than the sender is debited
+fn is_fee_on_transfer(value: &BigInt, debited: &BigInt, credited: &BigInt) -> bool {
+    credited < debited && credited < value
+}
+
+// Field of the token flagged by a behaviour in store_token_behaviours
+pub fn behaviour_field(kind: &str) -> Option<&'static str> {
+    match kind {
+        "fee_on_transfer" => Some("feeOnTransfer"),
+        "rebasing" => Some("rebasing"),
+        _ => None,
+    }
+}
+
+// Logs of the pool events after which the pool balances are looked at
+pub fn is_pool_event(log: &Log) -> bool {
+    abi::pool::events::Swap::match_log(log)
+        || abi::pool::events::Mint::match_log(log)
+        || abi::pool::events::Burn::match_log(log)
+        || abi::pool::events::Collect::match_log(log)
+        || abi::pool::events::Flash::match_log(log)
+}
+
+// The last write of balanceOf(holder) in the token storage by the transaction before the
+// ordinal, as the old and new balances
+pub fn last_balance_change(
+    trx: &TransactionTrace,
+    token_address: &[u8],
+    holder: &[u8],
+    ordinal: u64,
+) -> Option<(BigInt, BigInt)> {
+    balance_changes(trx, token_address, holder)
+        .into_iter()
+        .filter(|change| change.ordinal < ordinal)
+        .last()
+        .map(|change| (utils::to_uint(&change.old_value), utils::to_uint(&change.new_value)))
+}
+
+// The change of balanceOf(holder) in the token storage by the transaction from the first
+// ordinal up to the second one, none when the transaction writes no balance of the holder
+pub fn balance_delta(
+    trx: &TransactionTrace,
+    token_address: &[u8],
+    holder: &[u8],
+    from_ordinal: u64,
+    to_ordinal: u64,
+) -> Option<BigInt> {
+    let changes = balance_changes(trx, token_address, holder);
+    if changes.is_empty() {
+        return None;
+    }
+
+    let changes: Vec<&StorageChange> = changes
+        .into_iter()
+        .filter(|change| change.ordinal >= from_ordinal && change.ordinal < to_ordinal)
+        .collect();
+    match (changes.first(), changes.last()) {
+        (Some(first), Some(last)) => Some(utils::to_uint(&last.new_value).sub(utils::to_uint(&first.old_value))),
+        _ => Some(BigInt::zero()),
+    }
+}
+
+// The writes of balanceOf(holder) in the token storage by the transaction, by ordinal
+fn balance_changes<'a>(trx: &'a TransactionTrace, token_address: &[u8], holder: &[u8]) -> Vec<&'a StorageChange> {
+    let holder_key = utils::left_pad(&holder.to_vec(), 0);
+    let balance_keys: Vec<[u8; 32]> = (0..BALANCE_SLOTS)
+        .map(|slot| utils::calc_map_slot(&holder_key, &utils::left_pad_from_bigint(&BigInt::from(slot))))
+        .collect();
+
+    let mut changes: Vec<&StorageChange> = trx
+        .calls
+        .iter()
+        .filter(|call| !call.state_reverted)
+        .flat_map(|call| call.storage_changes.iter())
+        .filter(|change| change.address == token_address)
+        .filter(|change| balance_keys.iter().any(|key| change.key == key.as_slice()))
+        .collect();
+    changes.sort_by_key(|change| change.ordinal);
+
+    changes
+}
+
+// USD value of the balances of the pool, derived like totalValueLockedUSD. The balance of a
+// token without a derived ETH price counts as zero.
+pub fn balance_tvl_usd(
+    pool: &Pool,
+    balance_token0: &BigDecimal,
+    balance_token1: &BigDecimal,
+    token0_derived_eth_price: Option<BigDecimal>,
+    token1_derived_eth_price: Option<BigDecimal>,
+    bundle_eth_price_usd: &BigDecimal,
+) -> BigDecimal {
+    get_adjusted_amounts(
+        &pool.token0_ref().address,
+        &pool.token1_ref().address,
+        balance_token0,
+        balance_token1,
+        &token0_derived_eth_price.unwrap_or_else(BigDecimal::zero),
+        &token1_derived_eth_price.unwrap_or_else(BigDecimal::zero),
+        &bundle_eth_price_usd.clone().with_prec(100),
+    )
+    .delta_tvl_usd
+}
+
+// ordinal, pool and index and address of the token transferred
+pub type BalanceUpdate = (u64, String, Option<(i32, String)>);
+
+// The balances move with the transfers and their USD value with the derived ETH prices of
+// the tokens as well, each pool being valued again at the ordinal of the change
+pub fn balance_updates(
+    transfers: &[PoolTokenTransfer],
+    eth_prices_deltas: &Deltas<DeltaBigDecimal>,
+    token_pools_store: &StoreGetRaw,
+) -> Vec<BalanceUpdate> {
+    let mut updates: Vec<BalanceUpdate> = vec![];
+    for transfer in transfers {
+        updates.push((
+            transfer.ordinal,
+            transfer.pool_address.clone(),
+            Some((transfer.token_idx, transfer.token.clone())),
+        ));
+    }
+    for delta in eth_prices_deltas
+        .iter()
+        .operation_not_eq(Operation::Delete)
+        .filter(|delta| is_derived_eth_price_key(&delta.key))
+    {
+        let token_address = key::segment_at(&delta.key, 1);
+        if let Some(token_pools) = token_pools_store.get_at(delta.ordinal, format!("token:{token_address}")) {
+            for pool_address in String::from_utf8(token_pools.to_vec()).unwrap().split(';') {
+                if !pool_address.is_empty() {
+                    updates.push((delta.ordinal, pool_address.to_string(), None));
+                }
+            }
+        }
+    }
+    updates.sort();
+    updates.dedup();
+
+    updates
+}
+
+// `token:{token_address}:dprice:eth` keys of store_eth_prices
+pub fn is_derived_eth_price_key(key: &str) -> bool {
+    let segments: Vec<&str> = key.split(':').collect();
+    matches!(segments.as_slice(), ["token", _, "dprice", "eth"])
+}
+
+// index and definition of the token in the pool, when the address is a pool holding it
+// which is not ignored
+fn pool_token(
+    pools_store: &StoreGetProto<Pool>,
+    pool_address: &String,
+    token_address: &String,
+) -> Option<(i32, Erc20Token)> {
+    let pool = pools_store.get_last(format!("pool:{pool_address}"))?;
+    if pool.ignore_pool {
+        None
+    } else if pool.token0_ref().address == *token_address {
+        Some((0, pool.token0()))
+    } else if pool.token1_ref().address == *token_address {
+        Some((1, pool.token1()))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::balances::{
+        balance_tvl_usd, behaviour_field, extract_rebasing_behaviours, is_derived_eth_price_key, is_fee_on_transfer,
+    };
+    use crate::pb::uniswap::{Erc20Token, Pool};
+    use crate::storage::utils;
+    use substreams::hex;
+    use substreams::scalar::{BigDecimal, BigInt};
+    use substreams_ethereum::pb::eth::v2::{Call, Log, StorageChange, TransactionTrace};
+    use tiny_keccak::{Hasher, Keccak};
+
+    const WETH: &str = "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+    const USDC: &str = "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const TOKEN: &str = "1f9840a85d5af5bf1d1762f925bdaddc4201f984";
+    const POOL: [u8; 20] = hex!("8ad599c3a0ff1de082011efddc58f1908eb6e6d8");
+    const TRADER: [u8; 20] = hex!("c36442b4a4522e871399cd717abdd847ab11fe88");
+
+    #[test]
+    fn fee_on_transfer() {
+        let value = BigInt::from(100);
+        assert!(is_fee_on_transfer(&value, &BigInt::from(100), &BigInt::from(98)));
+        assert!(!is_fee_on_transfer(&value, &BigInt::from(100), &BigInt::from(100)));
+        // moved in shares, by the same amount on both sides
+        assert!(!is_fee_on_transfer(&value, &BigInt::from(80), &BigInt::from(80)));
+    }
+
+    #[test]
+    fn behaviour_fields() {
+        assert_eq!(Some("feeOnTransfer"), behaviour_field("fee_on_transfer"));
+        assert_eq!(Some("rebasing"), behaviour_field("rebasing"));
+        assert_eq!(None, behaviour_field("unknown"));
+    }
+
+    #[test]
+    fn balance_written_without_transfer_is_rebasing() {
+        let trx = transaction(vec![balance_change(TOKEN, 2, 1000, 1010)], vec![]);
+        let mut behaviours = vec![];
+        extract_rebasing_behaviours(&mut behaviours, &trx, &[pool(TOKEN, WETH)]);
+
+        assert_eq!(1, behaviours.len());
+        assert_eq!("rebasing", behaviours[0].kind);
+        assert_eq!(TOKEN, behaviours[0].token);
+        assert_eq!("10", behaviours[0].credited);
+        assert_eq!(2, behaviours[0].ordinal);
+    }
+
+    #[test]
+    fn balance_written_with_transfer_is_not_rebasing() {
+        let trx = transaction(
+            vec![balance_change(TOKEN, 2, 1000, 1010)],
+            vec![transfer_log(TOKEN, TRADER, POOL, 10, 3)],
+        );
+        let mut behaviours = vec![];
+        extract_rebasing_behaviours(&mut behaviours, &trx, &[pool(TOKEN, WETH)]);
+
+        assert!(behaviours.is_empty());
+    }
+
+    #[test]
+    fn balance_tvl_usd_of_whitelisted_tokens() {
+        let tvl = balance_tvl_usd(
+            &pool(USDC, WETH),
+            &decimal("3000"),
+            &decimal("2"),
+            Some(decimal("0.0005")),
+            Some(decimal("1")),
+            &decimal("2000"),
+        );
+
+        assert_eq!(decimal("7000"), tvl);
+    }
+
+    #[test]
+    fn balance_tvl_usd_counts_missing_price_as_zero() {
+        let tvl = balance_tvl_usd(
+            &pool(USDC, WETH),
+            &decimal("3000"),
+            &decimal("2"),
+            None,
+            Some(decimal("1")),
+            &decimal("2000"),
+        );
+
+        assert_eq!(decimal("4000"), tvl);
+    }
+
+    #[test]
+    fn balance_tvl_usd_doubles_the_whitelisted_side() {
+        let tvl = balance_tvl_usd(
+            &pool(TOKEN, WETH),
+            &decimal("100"),
+            &decimal("2"),
+            None,
+            Some(decimal("1")),
+            &decimal("2000"),
+        );
+
+        assert_eq!(decimal("8000"), tvl);
+    }
+
+    #[test]
+    fn derived_eth_price_keys() {
+        assert!(is_derived_eth_price_key(&format!("token:{WETH}:dprice:eth")));
+        assert!(!is_derived_eth_price_key("bundle"));
+        assert!(!is_derived_eth_price_key(&format!("token:{WETH}:dprice:usd")));
+        assert!(!is_derived_eth_price_key(&format!("pool:{WETH}:dprice:eth")));
+    }
+
+    fn transaction(storage_changes: Vec<StorageChange>, logs: Vec<Log>) -> TransactionTrace {
+        TransactionTrace {
+            hash: vec![1u8; 32],
+            status: 1,
+            calls: vec![Call {
+                storage_changes,
+                logs,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    // balanceOf(pool) of the token at slot 0
+    fn balance_change(token: &str, ordinal: u64, old_balance: u64, new_balance: u64) -> StorageChange {
+        let holder_key = utils::left_pad(&POOL.to_vec(), 0);
+        let balance_slot = utils::left_pad_from_bigint(&BigInt::zero());
+        StorageChange {
+            address: hex::decode(token).unwrap(),
+            key: utils::calc_map_slot(&holder_key, &balance_slot).to_vec(),
+            old_value: utils::left_pad_from_bigint(&BigInt::from(old_balance)).to_vec(),
+            new_value: utils::left_pad_from_bigint(&BigInt::from(new_balance)).to_vec(),
+            ordinal,
+        }
+    }
+
+    fn transfer_log(token: &str, from: [u8; 20], to: [u8; 20], value: u64, ordinal: u64) -> Log {
+        let mut topic = [0u8; 32];
+        let mut hasher = Keccak::v256();
+        hasher.update(b"Transfer(address,address,uint256)");
+        hasher.finalize(&mut topic);
+
+        Log {
+            address: hex::decode(token).unwrap(),
+            topics: vec![
+                topic.to_vec(),
+                utils::left_pad(&from.to_vec(), 0).to_vec(),
+                utils::left_pad(&to.to_vec(), 0).to_vec(),
+            ],
+            data: utils::left_pad_from_bigint(&BigInt::from(value)).to_vec(),
+            ordinal,
+            ..Default::default()
+        }
+    }
+
+    fn pool(token0: &str, token1: &str) -> Pool {
+        let token = |address: &str| Erc20Token {
+            address: address.to_string(),
+            ..Default::default()
+        };
+        Pool {
+            address: hex::encode(POOL),
+            token0: Some(token(token0)),
+            token1: Some(token(token1)),
+            ..Default::default()
+        }
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::try_from(value).unwrap()
+    }
+}
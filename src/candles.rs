@@ -0,0 +1,353 @@
+use crate::pb::uniswap::events::pool_event::Type::Swap as SwapEvent;
+use crate::pb::uniswap::events::PoolEvent;
+use crate::pb::uniswap::{Candle, CandleUpdate, CandleWindow, Pool};
+use crate::{price, utils};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Div;
+use substreams::errors::Error;
+use substreams::key;
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::BigDecimal;
+use substreams::store::{DeltaBigDecimal, DeltaExt, Deltas, StoreGet, StoreGetBigDecimal, StoreGetProto};
+
+const WEEK_SECONDS: i64 = 604800;
+// the unix epoch is a Thursday, the buckets of the intervals counted in weeks are shifted
+// to start on Monday
+const WEEK_START_OFFSET: i64 = 3 * 86400;
+
+const DEFAULT_INTERVALS: [&str; 7] = ["1m", "5m", "15m", "1h", "4h", "1d", "1w"];
+
+pub struct CandleInterval {
+    pub label: String,
+    pub seconds: i64,
+}
+
+impl CandleInterval {
+    pub fn bucket(&self, timestamp: i64) -> i64 {
+        (timestamp + offset(&self.label)) / self.seconds
+    }
+}
+
+// Stores the candles are read from
+pub struct CandleStores<'a> {
+    pub opens: &'a StoreGetBigDecimal,
+    pub lows: &'a StoreGetBigDecimal,
+    pub highs: &'a StoreGetBigDecimal,
+    pub volumes: &'a StoreGetBigDecimal,
+    pub pools: &'a StoreGetProto<Pool>,
+}
+
+// params are intervals separated by `&`, a count followed by one of the m, h, d or w
+// units, e.g. `5m&4h&1w`, all the default intervals are used without params
+pub fn intervals_from_params(params: &str) -> Result<Vec<CandleInterval>, Error> {
+    let mut labels: Vec<&str> = params
+        .split('&')
+        .map(str::trim)
+        .filter(|label| !label.is_empty())
+        .collect();
+    if labels.is_empty() {
+        labels = DEFAULT_INTERVALS.to_vec();
+    }
+
+    let mut intervals: Vec<CandleInterval> = vec![];
+    for label in labels {
+        if intervals.iter().any(|interval| interval.label == label) {
+            continue;
+        }
+        intervals.push(CandleInterval {
+            label: label.to_string(),
+            seconds: interval_seconds(label)?,
+        });
+    }
+
+    Ok(intervals)
+}
+
+pub fn windows(intervals: &[CandleInterval], timestamp: i64) -> Vec<CandleWindow> {
+    intervals
+        .iter()
+        .map(|interval| CandleWindow {
+            interval: interval.label.clone(),
+            seconds: interval.seconds,
+            bucket: interval.bucket(timestamp),
+        })
+        .collect()
+}
+
+pub fn open_time(interval: &str, seconds: i64, bucket: i64) -> i64 {
+    bucket * seconds - offset(interval)
+}
+
+// One update per swap and interval, carrying the prices of the pool once the swap is
+// applied and the volume of the swap, valued as the volumeUSD of the pool.
+pub fn extract_candle_updates(
+    windows: &[CandleWindow],
+    pool_events: &Vec<PoolEvent>,
+    prices_store: &StoreGetBigDecimal,
+    store_eth_prices: &StoreGetBigDecimal,
+) -> Vec<CandleUpdate> {
+    let mut updates = vec![];
+
+    for event in pool_events {
+        let swap = match event.r#type.as_ref().unwrap() {
+            SwapEvent(swap) => swap,
+            _ => continue,
+        };
+        let ord = event.log_ordinal;
+        let pool_address = &event.pool_address;
+        let token0_addr = &event.token0;
+        let token1_addr = &event.token1;
+
+        let token0_price = match prices_store.get_at(ord, format!("pool:{pool_address}:{token0_addr}:token0")) {
+            Some(price) => price,
+            None => continue,
+        };
+        let token1_price = match prices_store.get_at(ord, format!("pool:{pool_address}:{token1_addr}:token1")) {
+            Some(price) => price,
+            None => continue,
+        };
+        let token0_price_usd = price::get_token_price_usd(store_eth_prices, ord, token0_addr);
+        let token1_price_usd = price::get_token_price_usd(store_eth_prices, ord, token1_addr);
+
+        let amount0_abs = BigDecimal::try_from(swap.amount_0.clone()).unwrap().absolute();
+        let amount1_abs = BigDecimal::try_from(swap.amount_1.clone()).unwrap().absolute();
+        let volume_usd = match store_eth_prices.get_at(ord, "bundle") {
+            Some(eth_price_in_usd) => utils::get_adjusted_amounts(
+                token0_addr,
+                token1_addr,
+                &amount0_abs,
+                &amount1_abs,
+                &get_derived_eth_price(store_eth_prices, ord, token0_addr),
+                &get_derived_eth_price(store_eth_prices, ord, token1_addr),
+                &eth_price_in_usd,
+            )
+            .delta_tvl_usd
+            .div(BigDecimal::from(2)),
+            None => BigDecimal::zero(),
+        };
+
+        for window in windows {
+            updates.push(CandleUpdate {
+                interval: window.interval.clone(),
+                bucket: window.bucket,
+                pool_address: pool_address.clone(),
+                ordinal: ord,
+                token0_price: token0_price.to_string(),
+                token1_price: token1_price.to_string(),
+                token0_price_usd: usd_price(&token0_price_usd),
+                token1_price_usd: usd_price(&token1_price_usd),
+                volume_token0: amount0_abs.to_string(),
+                volume_token1: amount1_abs.to_string(),
+                volume_usd: volume_usd.to_string(),
+            });
+        }
+    }
+
+    updates
+}
+
+// A candle is emitted for every pool and interval whose prices changed in the block, and
+// once more with `closed` when its prices are deleted from store_candle_prices on the
+// first block past its close time. The close prices come from the deltas, the other
+// fields are read from the stores which keep a bucket longer than store_candle_prices.
+pub fn build_candles(
+    windows: &[CandleWindow],
+    candle_prices_deltas: &Deltas<DeltaBigDecimal>,
+    stores: &CandleStores,
+    block_number: u64,
+    timestamp: u64,
+) -> Vec<Candle> {
+    let seconds: HashMap<&String, i64> = windows
+        .iter()
+        .map(|window| (&window.interval, window.seconds))
+        .collect();
+    let mut candles: BTreeMap<(String, i64, String), Candle> = BTreeMap::new();
+
+    for delta in candle_prices_deltas.iter().key_first_segment_eq("Candle") {
+        let interval = key::segment_at(&delta.key, 1).to_string();
+        let bucket: i64 = key::segment_at(&delta.key, 2).parse().unwrap();
+        let pool_address = key::segment_at(&delta.key, 3).to_string();
+        let interval_seconds = match seconds.get(&interval) {
+            Some(interval_seconds) => *interval_seconds,
+            None => continue,
+        };
+
+        let candle = candles
+            .entry((interval.clone(), bucket, pool_address.clone()))
+            .or_insert_with(|| Candle {
+                pool_address,
+                open_time: open_time(&interval, interval_seconds, bucket),
+                close_time: open_time(&interval, interval_seconds, bucket) + interval_seconds,
+                interval,
+                block_number,
+                timestamp,
+                ..Default::default()
+            });
+
+        let close = match delta.operation {
+            Operation::Delete => {
+                candle.closed = true;
+                delta.old_value.to_string()
+            }
+            _ => delta.new_value.to_string(),
+        };
+        match key::last_segment(&delta.key) {
+            "token0" => candle.close_token0 = close,
+            "token1" => candle.close_token1 = close,
+            "token0USD" => candle.close_token0_usd = close,
+            "token1USD" => candle.close_token1_usd = close,
+            _ => {}
+        }
+    }
+
+    candles
+        .into_iter()
+        .map(|((_, bucket, _), mut candle)| {
+            let prefix = format!("Candle:{}:{}:{}", candle.interval, bucket, candle.pool_address);
+            let get = |store: &StoreGetBigDecimal, field: &str| {
+                store
+                    .get_last(format!("{prefix}:{field}"))
+                    .map(|value| value.to_string())
+                    .unwrap_or_default()
+            };
+
+            candle.open_token0 = get(stores.opens, "token0:open");
+            candle.high_token0 = get(stores.highs, "token0:high");
+            candle.low_token0 = get(stores.lows, "token0:low");
+            candle.open_token1 = get(stores.opens, "token1:open");
+            candle.high_token1 = get(stores.highs, "token1:high");
+            candle.low_token1 = get(stores.lows, "token1:low");
+            candle.open_token0_usd = get(stores.opens, "token0USD:open");
+            candle.high_token0_usd = get(stores.highs, "token0USD:high");
+            candle.low_token0_usd = get(stores.lows, "token0USD:low");
+            candle.open_token1_usd = get(stores.opens, "token1USD:open");
+            candle.high_token1_usd = get(stores.highs, "token1USD:high");
+            candle.low_token1_usd = get(stores.lows, "token1USD:low");
+            candle.volume_token0 = get(stores.volumes, "volumeToken0");
+            candle.volume_token1 = get(stores.volumes, "volumeToken1");
+            candle.volume_usd = get(stores.volumes, "volumeUSD");
+            candle.trade_count = stores
+                .volumes
+                .get_last(format!("{prefix}:tradeCount"))
+                .map(|trade_count| trade_count.to_bigint().to_u64())
+                .unwrap_or_default();
+
+            if let Some(pool) = stores.pools.get_last(format!("pool:{}", candle.pool_address)) {
+                candle.token0 = pool.token0.unwrap().address;
+                candle.token1 = pool.token1.unwrap().address;
+            }
+
+            candle
+        })
+        .collect()
+}
+
+fn get_derived_eth_price(store_eth_prices: &StoreGetBigDecimal, ord: u64, token_address: &String) -> BigDecimal {
+    store_eth_prices
+        .get_at(ord, format!("token:{token_address}:dprice:eth"))
+        .unwrap_or_else(BigDecimal::zero)
+}
+
+// USD price of a candle update, empty when the token has no price
+fn usd_price(price_usd: &BigDecimal) -> String {
+    match price_usd.is_zero() {
+        true => "".to_string(),
+        false => price_usd.to_string(),
+    }
+}
+
+fn interval_seconds(label: &str) -> Result<i64, Error> {
+    if !label.is_ascii() || label.len() < 2 {
+        return Err(invalid_interval(label));
+    }
+
+    let (count, unit) = label.split_at(label.len() - 1);
+    let unit_seconds = match unit {
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => WEEK_SECONDS,
+        _ => return Err(invalid_interval(label)),
+    };
+    match count.parse::<i64>() {
+        Ok(count) if count > 0 => Ok(count * unit_seconds),
+        _ => Err(invalid_interval(label)),
+    }
+}
+
+fn invalid_interval(label: &str) -> Error {
+    Error::msg(format!(
+        "invalid candle interval {label}, expected a count and a unit, e.g. 5m, 4h, 1d or 1w"
+    ))
+}
+
+// only the intervals counted in weeks start on Monday, a 7d interval is aligned on the epoch
+fn offset(interval: &str) -> i64 {
+    match interval.ends_with('w') {
+        true => WEEK_START_OFFSET,
+        false => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::candles::{intervals_from_params, open_time, CandleInterval};
+
+    // Monday 2023-01-02 00:00:00 UTC
+    const MONDAY: i64 = 1672617600;
+
+    #[test]
+    fn test_intervals_from_params() {
+        let labels = |params: &str| -> Vec<String> {
+            intervals_from_params(params)
+                .unwrap()
+                .into_iter()
+                .map(|interval| interval.label)
+                .collect()
+        };
+        assert_eq!(vec!["1m", "5m", "15m", "1h", "4h", "1d", "1w"], labels(""));
+        assert_eq!(vec!["5m", "4h"], labels("5m & 4h&5m"));
+
+        let intervals = intervals_from_params("15m&2w").unwrap();
+        assert_eq!(900, intervals[0].seconds);
+        assert_eq!(1209600, intervals[1].seconds);
+
+        assert!(intervals_from_params("5").is_err());
+        assert!(intervals_from_params("0h").is_err());
+        assert!(intervals_from_params("1y").is_err());
+        assert!(intervals_from_params("-1d").is_err());
+    }
+
+    #[test]
+    fn test_week_buckets_start_on_monday() {
+        let week = interval("1w", 604800);
+        let bucket = week.bucket(MONDAY);
+        assert_eq!(MONDAY, open_time("1w", week.seconds, bucket));
+        assert_eq!(bucket, week.bucket(MONDAY + 604799));
+        assert_eq!(bucket - 1, week.bucket(MONDAY - 1));
+
+        let two_weeks = interval("2w", 1209600);
+        let open = open_time("2w", two_weeks.seconds, two_weeks.bucket(MONDAY));
+        assert_eq!(0, (open - MONDAY) % 604800);
+    }
+
+    #[test]
+    fn test_day_multiples_align_on_epoch() {
+        // the same length as a week, but counted in days
+        let seven_days = interval("7d", 604800);
+        assert_eq!(
+            0,
+            open_time("7d", seven_days.seconds, seven_days.bucket(MONDAY)) % 604800
+        );
+
+        let day = interval("1d", 86400);
+        assert_eq!(MONDAY, open_time("1d", day.seconds, day.bucket(MONDAY + 3600)));
+    }
+
+    fn interval(label: &str, seconds: i64) -> CandleInterval {
+        CandleInterval {
+            label: label.to_string(),
+            seconds,
+        }
+    }
+}
@@ -8,14 +8,30 @@ use substreams::store::{
     StoreGetBigInt, StoreGetProto,
 };
 use substreams::{log, Hex};
-use substreams_entity_change::tables::Tables;
+use substreams_entity_change::pb::entity::Value;
+use substreams_entity_change::tables::{Tables, ToValue};
 
+use crate::errors::{self, Policy};
 use crate::pb::uniswap::events::pool_event::Type::{Burn as BurnEvent, Mint as MintEvent, Swap as SwapEvent};
 use crate::pb::uniswap::events::position_event::Type;
 use crate::pb::uniswap::events::{IncreaseLiquidityPosition, PoolSqrtPrice, PositionEvent};
-use crate::pb::uniswap::{events, Events, Pool};
-use crate::uniswap::{Erc20Token, Pools};
+use crate::pb::uniswap::{
+    events, Events, GapFilledPoolWindow, GapFilledTokenWindow, Pool, TokenPricePath, TokenPriceProvenance, Trade,
+};
+use crate::uniswap::{Erc20Token, FilteredPools, Pools};
 use crate::utils::{self, pool_windows_id_fields, time_as_i64_address_as_str, token_windows_id_fields};
+use crate::windows::{
+    self, WindowKind, ALL_WINDOW_KINDS, POOL_WINDOW_TABLES, TOKEN_WINDOW_TABLES, UNISWAP_WINDOW_TABLES,
+};
+
+// value clearing a nullable field
+struct Null;
+
+impl ToValue for Null {
+    fn to_value(self) -> Value {
+        Value { typed: None }
+    }
+}
 
 // -------------------
 //  Map Bundle Entities
@@ -30,7 +46,7 @@ pub fn bundle_store_eth_price_usd_bundle_entity_change(
     tables: &mut Tables,
     derived_eth_prices_deltas: &Deltas<DeltaBigDecimal>,
 ) {
-    for delta in derived_eth_prices_deltas.iter().key_first_segment_eq("bundle") {
+    for delta in derived_eth_prices_deltas.iter().filter(|delta| delta.key == "bundle") {
         tables.update_row("Bundle", "1").set("ethPriceUSD", &delta.new_value);
     }
 }
@@ -56,6 +72,10 @@ pub fn factory_created_factory_entity_change(tables: &mut Tables) {
         .set("totalValueLockedETH", &bigdecimal0)
         .set("totalValueLockedUSDUntracked", &bigdecimal0)
         .set("totalValueLockedETHUntracked", &bigdecimal0)
+        .set("tradeCount", &bigint0)
+        .set("tradeVolumeUSD", &bigdecimal0)
+        .set("liquidityProviderCount", &bigint0)
+        .set("allTimeLiquidityProviderCount", &bigint0)
         .set("owner", &format!("0x{}", Hex(utils::ZERO_ADDRESS).to_string()));
 }
 
@@ -109,6 +129,21 @@ pub fn tvl_factory_entity_change(tables: &mut Tables, derived_factory_tvl_deltas
     }
 }
 
+pub fn trade_volume_factory_entity_change(tables: &mut Tables, trade_volumes_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in trade_volumes_deltas.iter().key_first_segment_eq("factory") {
+        let row = tables.update_row("Factory", "0x1F98431c8aD98523631AE4a59f267346ea31F984");
+
+        match key::last_segment(&delta.key) {
+            "tradeCount" => {
+                row.set("tradeCount", delta.new_value.to_bigint());
+            }
+            name => {
+                row.set(name, &delta.new_value);
+            }
+        }
+    }
+}
+
 // -------------------
 //  Map Pool Entities
 // -------------------
@@ -128,6 +163,7 @@ fn create_pool(tables: &mut Tables, pool: &Pool) {
         .set("token0", format!("0x{}", pool.token0.as_ref().unwrap().address))
         .set("token1", format!("0x{}", pool.token1.as_ref().unwrap().address))
         .set_bigint("feeTier", &pool.fee_tier)
+        .set("label", &pool.label)
         .set("liquidity", &bigint0)
         .set("sqrtPrice", &bigint0)
         .set("feeGrowthGlobal0X128", &bigint0)
@@ -151,7 +187,28 @@ fn create_pool(tables: &mut Tables, pool: &Pool) {
         .set("totalValueLockedUSD", &bigdecimal0)
         .set("totalValueLockedUSDUntracked", &bigdecimal0)
         .set("totalValueLockedETHUntracked", &bigdecimal0)
-        .set("liquidityProviderCount", &bigint0);
+        .set("balanceToken0", &bigdecimal0)
+        .set("balanceToken1", &bigdecimal0)
+        .set("balanceTotalValueLockedUSD", &bigdecimal0)
+        .set("liquidityProviderCount", &bigint0)
+        .set("allTimeLiquidityProviderCount", &bigint0)
+        .set("ignored", false);
+}
+
+// The pools left out by map_pools_created have no entity, the ones ignored afterwards are
+// marked as such
+pub fn spam_pools_pool_entity_changes(tables: &mut Tables, filtered_pools: &FilteredPools) {
+    for filtered_pool in &filtered_pools.filtered_pools {
+        if filtered_pool.at_creation {
+            continue;
+        }
+        let pool_address = &filtered_pool.pool.as_ref().unwrap().address;
+        tables
+            .update_row("Pool", format!("0x{pool_address}"))
+            .set("ignored", true)
+            .set("ignoredReason", &filtered_pool.reason)
+            .set("ignoredAtBlockNumber", BigInt::from(filtered_pool.block_number));
+    }
 }
 
 fn create_pool_windows_entity(
@@ -172,25 +229,24 @@ fn create_pool_windows_entity(
         .set("feeGrowthGlobal0X128", BigInt::zero())
         .set("feeGrowthGlobal1X128", BigInt::zero())
         .set("totalValueLockedUSD", BigDecimal::zero())
+        .set("balanceToken0", BigDecimal::zero())
+        .set("balanceToken1", BigDecimal::zero())
+        .set("balanceTotalValueLockedUSD", BigDecimal::zero())
         .set("volumeToken0", BigDecimal::zero())
         .set("volumeToken1", BigDecimal::zero())
         .set("volumeUSD", BigDecimal::zero())
         .set("feesUSD", BigDecimal::zero())
         .set("txCount", BigInt::zero())
+        .set("jitCount", BigInt::zero())
+        .set("jitVolumeUSD", BigDecimal::zero())
+        .set("jitFeesUSD", BigDecimal::zero())
         .set("open", BigDecimal::zero())
         .set("high", BigDecimal::zero())
         .set("low", BigDecimal::zero())
         .set("close", BigDecimal::zero());
 
-    match table_name {
-        "PoolDayData" => {
-            row.set("date", (time_id * 86400) as i32);
-        }
-        "PoolHourData" => {
-            row.set("periodStartUnix", (time_id * 3600) as i32);
-        }
-        _ => {}
-    }
+    let kind = WindowKind::from_table(table_name);
+    row.set(kind.start_field(), kind.start_timestamp(time_id) as i32);
 }
 
 pub fn sqrt_price_and_tick_pool_entity_change(
@@ -262,8 +318,22 @@ pub fn total_value_locked_by_token_pool_entity_change(tables: &mut Tables, token
     }
 }
 
+pub fn balance_pool_entity_change(tables: &mut Tables, pool_balance_tvl_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in pool_balance_tvl_deltas.iter().key_first_segment_eq("pool") {
+        let pool_address = key::segment_at(&delta.key, 1);
+        tables
+            .update_row("Pool", &format!("0x{pool_address}"))
+            .set(key::last_segment(&delta.key), &delta.new_value);
+    }
+}
+
+// The prices removed while the pool has no price are set to zero, the prices of the pool
+// quarantined by the price guard are kept apart under `flagged:` but still are its prices
 pub fn price_pool_entity_change(tables: &mut Tables, price_deltas: &Deltas<DeltaBigDecimal>) {
-    for delta in price_deltas.iter().key_first_segment_eq("pool") {
+    for delta in price_deltas
+        .iter()
+        .filter(|delta| matches!(key::first_segment(&delta.key), "pool" | "flagged"))
+    {
         let pool_address = key::segment_at(&delta.key, 1);
         // TODO: maybe change the field name on the key itself??
         let name: &str = match key::last_segment(&delta.key) {
@@ -297,53 +367,62 @@ pub fn swap_volume_pool_entity_change(tables: &mut Tables, swaps_volume_deltas:
             "volumeUSD" => "volumeUSD",
             "volumeUntrackedUSD" => "untrackedVolumeUSD",
             "feesUSD" => "feesUSD",
-            "liquidityProviderCount" => "liquidityProviderCount",
             _ => continue,
         };
 
-        if field_name == "liquidityProviderCount" {
-            tables
-                .update_row("Pool", &format!("0x{pool_address}"))
-                .set("liquidityProviderCount", &delta.new_value.to_bigint());
-            continue;
-        } else {
-            tables
-                .update_row("Pool", &format!("0x{pool_address}"))
-                .set(field_name, &delta.new_value);
-        }
+        tables
+            .update_row("Pool", &format!("0x{pool_address}"))
+            .set(field_name, &delta.new_value);
     }
 }
 
 // --------------------
 //  Map Token Entities
 // --------------------
-pub fn tokens_created_token_entity_changes(tables: &mut Tables, pools: &Pools, tokens_store: StoreGetInt64) {
+pub fn tokens_created_token_entity_changes(
+    tables: &mut Tables,
+    pools: &Pools,
+    tokens_store: StoreGetInt64,
+    policy: &Policy,
+) {
     for pool in &pools.pools {
         let ord = pool.log_ordinal;
-        let pool_address = &pool.address;
-        let token0_addr = pool.token0_ref().address();
-        let token1_addr = pool.token1_ref().address();
-        match tokens_store.get_at(ord, format!("token:{token0_addr}")) {
-            Some(value) => {
-                if value.eq(&1) {
-                    add_token_entity_change(tables, pool.token0_ref());
-                }
-            }
-            None => {
-                panic!("pool contains token that doesn't exist {}", pool_address.as_str())
+        for token in [pool.token0_ref(), pool.token1_ref()] {
+            let pool_count = policy.check(
+                "graph_out",
+                errors::get_token_pool_count(ord, &token.address, &tokens_store),
+            );
+            // the first pool of the token creates it
+            if pool_count == Some(1) {
+                add_token_entity_change(tables, token);
             }
         }
+    }
+}
 
-        match tokens_store.get_at(ord, format!("token:{token1_addr}")) {
-            Some(value) => {
-                if value.eq(&1) {
-                    add_token_entity_change(tables, pool.token1_ref());
-                }
-            }
-            None => {
-                panic!("pool contains token that doesn't exist {}", pool_address.as_str())
-            }
-        }
+pub fn behaviour_token_entity_change(tables: &mut Tables, token_behaviours_deltas: &Deltas<DeltaInt64>) {
+    for delta in token_behaviours_deltas
+        .iter()
+        .key_first_segment_eq("token")
+        .operation_eq(Operation::Create)
+    {
+        let token_address = key::segment_at(&delta.key, 1);
+        tables
+            .update_row("Token", format!("0x{token_address}"))
+            .set(key::last_segment(&delta.key), true);
+    }
+}
+
+// current and all time unique liquidity providers of the pools, tokens and factory
+pub fn lp_count_entity_change(tables: &mut Tables, lp_counts_deltas: &Deltas<DeltaBigInt>) {
+    for delta in lp_counts_deltas.deltas.iter() {
+        let row = match key::first_segment(&delta.key) {
+            "pool" => tables.update_row("Pool", format!("0x{}", key::segment_at(&delta.key, 1))),
+            "token" => tables.update_row("Token", format!("0x{}", key::segment_at(&delta.key, 1))),
+            "factory" => tables.update_row("Factory", "0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+            _ => continue,
+        };
+        row.set(key::last_segment(&delta.key), &delta.new_value);
     }
 }
 
@@ -358,6 +437,10 @@ fn add_token_entity_change(tables: &mut Tables, token: &Erc20Token) {
         .create_row("Token", format!("0x{token_addr}"))
         .set("symbol", &token.symbol)
         .set("name", &token.name)
+        .set("displayName", &token.display_name)
+        .set("displaySymbol", &token.display_symbol)
+        .set("homoglyphSymbol", token.homoglyph_symbol)
+        .set("impersonatingSymbol", token.impersonating_symbol)
         .set("decimals", token.decimals)
         .set_bigint("totalSupply", &token.total_supply)
         .set("volume", &bigdecimal0)
@@ -370,6 +453,16 @@ fn add_token_entity_change(tables: &mut Tables, token: &Erc20Token) {
         .set("totalValueLockedUSD", &bigdecimal0)
         .set("totalValueLockedUSDUntracked", &bigdecimal0)
         .set("derivedETH", &bigdecimal0)
+        .set("derivedETHPath", vec![format!("0x{token_addr}")])
+        .set("derivedETHPools", Vec::<String>::new())
+        .set("derivedETHDepth", 0)
+        .set("derivedETHConfidence", &bigdecimal0)
+        .set("feeOnTransfer", false)
+        .set("rebasing", false)
+        .set("liquidityProviderCount", &bigint0)
+        .set("allTimeLiquidityProviderCount", &bigint0)
+        .set("bytes32Metadata", token.bytes32_metadata)
+        .set("missingDecimals", token.missing_decimals)
         .set("whitelistPools", &whitelist);
 }
 
@@ -397,15 +490,8 @@ fn create_token_windows_entity(
         .set("low", &bigdecimal0)
         .set("close", &bigdecimal0);
 
-    match table_name {
-        "TokenDayData" => {
-            row.set("date", (time_id * 86400) as i32);
-        }
-        "TokenHourData" => {
-            row.set("periodStartUnix", (time_id * 3600) as i32);
-        }
-        _ => {}
-    }
+    let kind = WindowKind::from_table(table_name);
+    row.set(kind.start_field(), kind.start_timestamp(time_id) as i32);
 }
 
 pub fn swap_volume_token_entity_change(tables: &mut Tables, swaps_volume_deltas: &Deltas<DeltaBigDecimal>) {
@@ -480,6 +566,50 @@ pub fn derived_eth_prices_token_entity_change(
     }
 }
 
+pub fn price_path_token_entity_change(
+    tables: &mut Tables,
+    token_price_paths_deltas: &Deltas<DeltaProto<TokenPricePath>>,
+) {
+    for delta in token_price_paths_deltas.iter().key_first_segment_eq("token") {
+        let token_address = key::segment_at(&delta.key, 1);
+        let token_price_path = &delta.new_value;
+
+        tables
+            .update_row("Token", format!("0x{token_address}"))
+            .set(
+                "derivedETHPath",
+                token_price_path
+                    .tokens
+                    .iter()
+                    .map(|token| format!("0x{token}"))
+                    .collect::<Vec<String>>(),
+            )
+            .set(
+                "derivedETHPools",
+                token_price_path
+                    .pools
+                    .iter()
+                    .map(|pool| format!("0x{pool}"))
+                    .collect::<Vec<String>>(),
+            )
+            .set("derivedETHDepth", token_price_path.depth as i32);
+    }
+}
+
+pub fn price_provenance_token_entity_change(tables: &mut Tables, provenances: &Vec<TokenPriceProvenance>) {
+    for provenance in provenances {
+        let row = tables
+            .update_row("Token", format!("0x{}", provenance.token))
+            .set_bigdecimal("derivedETHConfidence", &provenance.confidence);
+
+        if provenance.source_pool.is_empty() {
+            row.set("priceSourcePool", Null);
+        } else {
+            row.set("priceSourcePool", format!("0x{}", provenance.source_pool));
+        }
+    }
+}
+
 pub fn whitelist_token_entity_change(tables: &mut Tables, tokens_whitelist_pools_deltas: Deltas<DeltaArray<String>>) {
     for delta in tokens_whitelist_pools_deltas.into_iter() {
         let token_address = key::segment_at(&delta.key, 1);
@@ -683,6 +813,8 @@ pub fn position_create_entity_change(tables: &mut Tables, positions: &Vec<events
             .set("collectedFeesToken0", &bigdecimal0)
             .set("collectedFeesToken1", &bigdecimal0)
             .set("transaction", format!("0x{}", position.transaction))
+            .set("approved", &utils::ZERO_ADDRESS.to_vec())
+            .set("closed", false)
             .set_bigint(
                 "feeGrowthInside0LastX128",
                 &position.fee_growth_inside0_last_x128.clone().unwrap_or("0".to_string()),
@@ -767,14 +899,92 @@ pub fn collect_position_entity_change(tables: &mut Tables, positions: &Vec<event
     }
 }
 
-pub fn transfer_position_entity_change(tables: &mut Tables, positions: &Vec<events::TransferPosition>) {
+pub fn transfer_position_entity_change(
+    tables: &mut Tables,
+    positions: &Vec<events::TransferPosition>,
+    store_positions: &StoreGetProto<PositionEvent>,
+) {
     for position in positions {
+        if !position_exists(&position.token_id, store_positions) {
+            continue;
+        }
+
+        let id = format!(
+            "{}#{}#{}",
+            position.token_id, position.block_number, position.log_ordinal
+        );
+        tables
+            .create_row("PositionTransfer", &id)
+            .set("position", &position.token_id)
+            .set("from", &hex::decode(&position.from).unwrap())
+            .set("to", &hex::decode(&position.owner).unwrap())
+            .set("transaction", format!("0x{}", position.transaction))
+            .set("blockNumber", position.block_number)
+            .set("timestamp", position.timestamp);
+
+        // a burned position keeps its last owner, the `closed` flag tells it apart
+        if position.is_burn() {
+            continue;
+        }
+
         tables
             .update_row("Position", position.token_id.clone())
             .set("owner", &hex::decode(&position.owner).unwrap());
     }
 }
 
+pub fn closed_position_entity_change(
+    tables: &mut Tables,
+    positions: &Vec<events::ClosedPosition>,
+    store_positions: &StoreGetProto<PositionEvent>,
+) {
+    for position in positions {
+        if !position_exists(&position.token_id, store_positions) {
+            continue;
+        }
+
+        tables
+            .update_row("Position", &position.token_id)
+            .set("closed", true)
+            .set("closedAtTransaction", format!("0x{}", position.transaction))
+            .set("closedAtBlockNumber", position.block_number)
+            .set("closedAtTimestamp", position.timestamp);
+    }
+}
+
+pub fn approval_position_entity_change(
+    tables: &mut Tables,
+    positions: &Vec<events::ApprovalPosition>,
+    store_positions: &StoreGetProto<PositionEvent>,
+) {
+    for position in positions {
+        if !position_exists(&position.token_id, store_positions) {
+            continue;
+        }
+
+        tables
+            .update_row("Position", &position.token_id)
+            .set("approved", &hex::decode(&position.approved).unwrap());
+    }
+}
+
+pub fn operator_approval_entity_change(tables: &mut Tables, operator_approvals: &Vec<events::OperatorApproval>) {
+    for approval in operator_approvals {
+        let id = format!("0x{}#0x{}", approval.owner, approval.operator);
+        tables
+            .update_row("PositionOperator", &id)
+            .set("owner", &hex::decode(&approval.owner).unwrap())
+            .set("operator", &hex::decode(&approval.operator).unwrap())
+            .set("approved", approval.approved);
+    }
+}
+
+// Positions of ignored pools are never created, so we don't want to emit
+// partial entities for their ERC721 events.
+fn position_exists(token_id: &String, store_positions: &StoreGetProto<PositionEvent>) -> bool {
+    store_positions.has_last(format!("position_created:{}", token_id))
+}
+
 // --------------------
 //  Map Snapshot Position Entities
 // --------------------
@@ -920,6 +1130,10 @@ pub fn transfer_snapshot_position_entity_change(
     store_positions: &StoreGetProto<PositionEvent>,
 ) {
     for position in positions {
+        if !position_exists(&position.token_id, store_positions) {
+            continue;
+        }
+
         let id = format!("{}#{}", position.token_id, block_number);
         fetch_and_update_snapshot_position(tables, &position.token_id, &id, &store_positions);
         transfer_snapshot_position(tables, &id, &position);
@@ -927,6 +1141,10 @@ pub fn transfer_snapshot_position_entity_change(
 }
 
 fn transfer_snapshot_position(tables: &mut Tables, id: &String, position: &events::TransferPosition) {
+    if position.is_burn() {
+        return;
+    }
+
     tables
         .update_row("PositionSnapshot", id)
         .set("owner", &hex::decode(&position.owner).unwrap());
@@ -1066,7 +1284,8 @@ pub fn swaps_mints_burns_created_entity_change(
                         .set("amountUSD", &amount_total_usd_tracked)
                         .set("sqrtPriceX96", &BigInt::try_from(swap.sqrt_price.to_string()).unwrap())
                         .set("tick", &BigInt::try_from(swap.tick.to_string()).unwrap())
-                        .set("logIndex", pool_event.log_index);
+                        .set("logIndex", pool_event.log_index)
+                        .set("source", &swap.source);
                 }
                 MintEvent(mint) => {
                     let amount0 = BigDecimal::try_from(mint.amount_0.as_str()).unwrap();
@@ -1151,111 +1370,234 @@ pub fn swaps_mints_burns_created_entity_change(
 // }
 
 // --------------------
-//  Map Uniswap Day Data Entities
+//  Map Trade Entities
 // --------------------
-pub fn uniswap_day_data_create(mut tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
-    uniswap_day_data_create_entity(&mut tables, &tx_count_deltas);
+pub fn trade_entity_change(tables: &mut Tables, trades: &Vec<Trade>) {
+    for trade in trades {
+        let transaction_id = &trade.transaction;
+        let id = format!("0x{transaction_id}#{}", trade.log_indexes[0]);
+
+        tables
+            .create_row("Trade", &id)
+            .set("transaction", format!("0x{transaction_id}"))
+            .set("timestamp", trade.timestamp)
+            .set("blockNumber", trade.block_number)
+            .set("origin", &hex::decode(&trade.origin).unwrap())
+            .set("tokenIn", format!("0x{}", trade.token_in))
+            .set("tokenOut", format!("0x{}", trade.token_out))
+            .set("amountIn", BigDecimal::try_from(trade.amount_in.as_str()).unwrap())
+            .set("amountOut", BigDecimal::try_from(trade.amount_out.as_str()).unwrap())
+            .set(
+                "path",
+                trade
+                    .path
+                    .iter()
+                    .map(|token| format!("0x{token}"))
+                    .collect::<Vec<String>>(),
+            )
+            .set(
+                "pools",
+                trade
+                    .pools
+                    .iter()
+                    .map(|pool| format!("0x{pool}"))
+                    .collect::<Vec<String>>(),
+            )
+            .set("hops", trade.hops as i32)
+            .set(
+                "executionPrice",
+                BigDecimal::try_from(trade.execution_price.as_str()).unwrap(),
+            )
+            .set("amountUSD", BigDecimal::try_from(trade.amount_usd.as_str()).unwrap());
+    }
 }
 
-pub fn uniswap_day_data_update(
-    mut tables: &mut Tables,
+// ---------------------------------------
+//  Map Uniswap Day/Week/Month Data Entities
+// ---------------------------------------
+pub fn uniswap_windows_create(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
+    create_uniswap_windows(tables, tx_count_deltas);
+}
+
+pub fn uniswap_windows_update(
+    tables: &mut Tables,
     swaps_volume_deltas: &Deltas<DeltaBigDecimal>,
     derived_factory_tvl_deltas: &Deltas<DeltaBigDecimal>,
     tx_count_deltas: &Deltas<DeltaBigInt>,
 ) {
-    tx_count_uniswap_day_data_update(&mut tables, &tx_count_deltas);
-    totals_uniswap_day_data_update(&mut tables, &derived_factory_tvl_deltas);
-    volumes_uniswap_day_data_update(&mut tables, &swaps_volume_deltas);
+    tx_count_uniswap_windows(tables, tx_count_deltas);
+    totals_uniswap_windows(tables, derived_factory_tvl_deltas);
+    volumes_uniswap_windows(tables, swaps_volume_deltas);
 }
 
-pub fn uniswap_day_data_create_entity(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
+pub fn create_uniswap_windows(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
     for delta in tx_count_deltas
         .iter()
-        .key_first_segment_eq("UniswapDayData")
+        .key_first_segment_in(UNISWAP_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
         if !delta.new_value.is_one() {
             continue;
         }
 
-        let day_id = key::segment_at(&delta.key, 1).parse::<i64>().unwrap();
-        let day_start_timestamp = (day_id * 86400) as i32;
-        create_uniswap_day_data(tables, day_id, day_start_timestamp, &delta);
+        let table_name = key::first_segment(&delta.key);
+        let time_id = key::segment_at(&delta.key, 1).parse::<i64>().unwrap();
+        create_uniswap_windows_entity(tables, table_name, time_id, delta);
     }
 }
 
-pub fn tx_count_uniswap_day_data_update(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
+pub fn tx_count_uniswap_windows(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
     for delta in tx_count_deltas
         .iter()
-        .key_first_segment_eq("UniswapDayData")
+        .key_first_segment_in(UNISWAP_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
-        let day_id = key::segment_at(&delta.key, 1);
+        let time_id = key::segment_at(&delta.key, 1);
 
         tables
-            .update_row("UniswapDayData", day_id)
+            .update_row(key::first_segment(&delta.key), time_id)
             .set("txCount", &delta.new_value);
     }
 }
 
-pub fn totals_uniswap_day_data_update(tables: &mut Tables, derived_factory_tvl_deltas: &Deltas<DeltaBigDecimal>) {
+pub fn totals_uniswap_windows(tables: &mut Tables, derived_factory_tvl_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in derived_factory_tvl_deltas
         .iter()
-        .key_first_segment_eq("UniswapDayData")
+        .key_first_segment_in(UNISWAP_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
-        let day_id = key::segment_at(&delta.key, 1);
+        let time_id = key::segment_at(&delta.key, 1);
 
         tables
-            .update_row("UniswapDayData", day_id)
+            .update_row(key::first_segment(&delta.key), time_id)
             .set("totalValueLockedUSD", &delta.new_value);
     }
 }
 
-pub fn volumes_uniswap_day_data_update(tables: &mut Tables, swaps_volume_deltas: &Deltas<DeltaBigDecimal>) {
+pub fn volumes_uniswap_windows(tables: &mut Tables, swaps_volume_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in swaps_volume_deltas
         .iter()
-        .key_first_segment_eq("UniswapDayData")
+        .key_first_segment_in(UNISWAP_WINDOW_TABLES)
         .key_last_segment_in(["volumeETH", "volumeUSD", "feesUSD"])
         .operation_not_eq(Operation::Delete)
     {
-        let day_id = key::segment_at(&delta.key, 1);
+        let time_id = key::segment_at(&delta.key, 1);
 
         tables
-            .update_row("UniswapDayData", &day_id)
+            .update_row(key::first_segment(&delta.key), time_id)
             .set(key::last_segment(&delta.key), &delta.new_value);
     }
 }
 
-fn create_uniswap_day_data(tables: &mut Tables, day_id: i64, day_start_timestamp: i32, delta: &DeltaBigInt) {
+pub fn trade_volume_uniswap_windows(tables: &mut Tables, trade_volumes_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in trade_volumes_deltas
+        .iter()
+        .key_first_segment_in(UNISWAP_WINDOW_TABLES)
+        .operation_not_eq(Operation::Delete)
+    {
+        let time_id = key::segment_at(&delta.key, 1);
+        let row = tables.update_row(key::first_segment(&delta.key), time_id);
+
+        match key::last_segment(&delta.key) {
+            "tradeCount" => {
+                row.set("tradeCount", delta.new_value.to_bigint());
+            }
+            name => {
+                row.set(name, &delta.new_value);
+            }
+        }
+    }
+}
+
+fn create_uniswap_windows_entity(tables: &mut Tables, table_name: &str, time_id: i64, delta: &DeltaBigInt) {
     let bigdecimal0 = BigDecimal::zero();
-    let id = day_id.to_string();
+    let kind = WindowKind::from_table(table_name);
     tables
-        .create_row("UniswapDayData", &id)
-        .set("date", day_start_timestamp)
+        .create_row(table_name, time_id.to_string())
+        .set(kind.start_field(), kind.start_timestamp(time_id) as i32)
         .set("volumeETH", &bigdecimal0)
         .set("volumeUSD", &bigdecimal0)
         .set("volumeUSDUntracked", &bigdecimal0) // TODO: NEED TO SET THIS VALUE IN THE SUBSTREAMS
         .set("totalValueLockedUSD", &bigdecimal0)
         .set("feesUSD", &bigdecimal0)
+        .set("tradeCount", BigInt::zero())
+        .set("tradeVolumeUSD", &bigdecimal0)
         .set("txCount", &delta.new_value);
 }
 
+// --------------------
+//  Map Source Day Data Entities
+// --------------------
+pub fn source_day_data_create(tables: &mut Tables, swaps_volume_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in swaps_volume_deltas
+        .iter()
+        .key_first_segment_eq("SourceDayData")
+        .key_last_segment_eq("swapCount")
+        .operation_not_eq(Operation::Delete)
+    {
+        if !delta.new_value.eq(&BigDecimal::one()) {
+            continue;
+        }
+
+        let day_id = key::segment_at(&delta.key, 1).parse::<i64>().unwrap();
+        let source = key::segment_at(&delta.key, 2);
+        let bigdecimal0 = BigDecimal::zero();
+
+        tables
+            .create_row("SourceDayData", format!("{source}-{day_id}"))
+            .set("date", (day_id * 86400) as i32)
+            .set("source", source)
+            .set("volumeUSD", &bigdecimal0)
+            .set("feesUSD", &bigdecimal0)
+            .set("swapCount", BigInt::zero());
+    }
+}
+
+pub fn swap_volume_source_day_data_update(tables: &mut Tables, swaps_volume_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in swaps_volume_deltas
+        .iter()
+        .key_first_segment_eq("SourceDayData")
+        .operation_not_eq(Operation::Delete)
+    {
+        let day_id = key::segment_at(&delta.key, 1);
+        let source = key::segment_at(&delta.key, 2);
+        let row = tables.update_row("SourceDayData", format!("{source}-{day_id}"));
+
+        match key::last_segment(&delta.key) {
+            "swapCount" => {
+                row.set("swapCount", delta.new_value.to_bigint());
+            }
+            name => {
+                row.set(name, &delta.new_value);
+            }
+        }
+    }
+}
+
 // -----------------------
-//  Map Pool Day/Hour Data
+//  Map Pool Windows Data
 // -----------------------
-pub fn pool_windows_create(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
-    upsert_entity_change_pool_windows(tables, tx_count_deltas);
+pub fn pool_windows_create(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>, gap_filled_tables: &[String]) {
+    upsert_entity_change_pool_windows(tables, tx_count_deltas, gap_filled_tables);
 }
 
 // See above `upsert_initialized_entity_change_pool_windows` info. Also we have to send the update for
 // the PoolDayData or the PoolHourData when we get the first pool event on a new day_id or hour_id
-pub fn upsert_entity_change_pool_windows(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
+pub fn upsert_entity_change_pool_windows(
+    tables: &mut Tables,
+    tx_count_deltas: &Deltas<DeltaBigInt>,
+    gap_filled_tables: &[String],
+) {
     for delta in tx_count_deltas
         .iter()
-        .key_first_segment_in(["PoolDayData", "PoolHourData"])
+        .key_first_segment_in(POOL_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
         .filter(|d| d.new_value.eq(&BigInt::one()))
+        .filter(|d| {
+            !gap_filled_tables
+                .iter()
+                .any(|table| table == key::first_segment(&d.key))
+        })
     {
         let time_id = key::segment_at(&delta.key, 1).parse::<i64>().unwrap();
         let pool_address = key::segment_at(&delta.key, 2);
@@ -1271,6 +1613,52 @@ pub fn upsert_entity_change_pool_windows(tables: &mut Tables, tx_count_deltas: &
     }
 }
 
+// The gap filled windows are created with the values of the previous window and no
+// volume, the updates of the block are applied on top of them.
+pub fn gap_filled_pool_windows(tables: &mut Tables, pool_windows: &Vec<GapFilledPoolWindow>) {
+    for pool_window in pool_windows {
+        let pool_address = &pool_window.pool_address;
+        let token0_price = BigDecimal::try_from(&pool_window.token0_price).unwrap();
+
+        let pool_time_id = format!("0x{pool_address}-{}", pool_window.time_id);
+
+        create_pool_windows_entity(
+            tables,
+            &pool_window.table,
+            pool_window.time_id,
+            &pool_time_id,
+            pool_address,
+        );
+        tables
+            .update_row(&pool_window.table, &pool_time_id)
+            .set("liquidity", BigInt::try_from(&pool_window.liquidity).unwrap())
+            .set("sqrtPrice", BigInt::try_from(&pool_window.sqrt_price).unwrap())
+            .set("tick", BigInt::try_from(&pool_window.tick).unwrap())
+            .set("token0Price", &token0_price)
+            .set("token1Price", BigDecimal::try_from(&pool_window.token1_price).unwrap())
+            .set(
+                "totalValueLockedUSD",
+                BigDecimal::try_from(&pool_window.total_value_locked_usd).unwrap(),
+            )
+            .set(
+                "balanceToken0",
+                BigDecimal::try_from(&pool_window.balance_token0).unwrap(),
+            )
+            .set(
+                "balanceToken1",
+                BigDecimal::try_from(&pool_window.balance_token1).unwrap(),
+            )
+            .set(
+                "balanceTotalValueLockedUSD",
+                BigDecimal::try_from(&pool_window.balance_total_value_locked_usd).unwrap(),
+            )
+            .set("open", &token0_price)
+            .set("high", &token0_price)
+            .set("low", &token0_price)
+            .set("close", &token0_price);
+    }
+}
+
 pub fn pool_windows_update(
     mut tables: &mut Tables,
     timestamp: i64,
@@ -1301,7 +1689,7 @@ pub fn pool_windows_update(
 pub fn tx_count_pool_windows(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
     for delta in tx_count_deltas
         .iter()
-        .key_first_segment_in(["PoolDayData", "PoolHourData"])
+        .key_first_segment_in(POOL_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
         let (table_name, time_id, pool_address) = pool_windows_id_fields(&delta.key);
@@ -1327,15 +1715,10 @@ pub fn mint_burn_prices_pool_windows(
             continue;
         }
 
-        let day_id = timestamp / 86400;
-        let hour_id = timestamp / 3600;
-
         if pool_event.r#type.is_some() {
             let token0_address = &pool_event.token0;
             let token1_address = &pool_event.token1;
             let pool_address = &pool_event.pool_address;
-            let pool_day_id = format!("0x{pool_address}-{day_id}");
-            let pool_hour_id = format!("0x{pool_address}-{hour_id}");
 
             let mut token0_price = BigDecimal::zero();
             let mut token1_price = BigDecimal::zero();
@@ -1360,23 +1743,16 @@ pub fn mint_burn_prices_pool_windows(
                 _ => {}
             }
 
-            tables
-                .update_row("PoolDayData", &pool_day_id)
-                .set("open", &token0_price)
-                .set("close", &token0_price)
-                .set("high", &token0_price)
-                .set("low", &token0_price)
-                .set("token0Price", &token0_price)
-                .set("token1Price", &token1_price);
-
-            tables
-                .update_row("PoolHourData", &pool_hour_id)
-                .set("open", &token0_price)
-                .set("close", &token0_price)
-                .set("high", &token0_price)
-                .set("low", &token0_price)
-                .set("token0Price", &token0_price)
-                .set("token1Price", &token1_price);
+            for window in windows::current_windows(&ALL_WINDOW_KINDS, timestamp) {
+                tables
+                    .update_row(&window.kind.table("Pool"), window.entity_id(pool_address))
+                    .set("open", &token0_price)
+                    .set("close", &token0_price)
+                    .set("high", &token0_price)
+                    .set("low", &token0_price)
+                    .set("token0Price", &token0_price)
+                    .set("token1Price", &token1_price);
+            }
         }
     }
 }
@@ -1384,7 +1760,7 @@ pub fn mint_burn_prices_pool_windows(
 pub fn prices_pool_windows(tables: &mut Tables, price_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in price_deltas
         .iter()
-        .key_first_segment_in(["PoolDayData", "PoolHourData"])
+        .key_first_segment_in(POOL_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
         let (table_name, time_id, pool_address) = pool_windows_id_fields(&delta.key);
@@ -1405,7 +1781,7 @@ pub fn prices_pool_windows(tables: &mut Tables, price_deltas: &Deltas<DeltaBigDe
 pub fn prices_min_pool_windows(tables: &mut Tables, min_pool_prices_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in min_pool_prices_deltas
         .iter()
-        .key_first_segment_in(["PoolDayData", "PoolHourData"])
+        .key_first_segment_in(POOL_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
         .key_last_segment_in(["low", "open"])
     {
@@ -1421,7 +1797,7 @@ pub fn prices_min_pool_windows(tables: &mut Tables, min_pool_prices_deltas: &Del
 pub fn prices_max_pool_windows(tables: &mut Tables, max_pool_prices_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in max_pool_prices_deltas
         .iter()
-        .key_first_segment_in(["PoolDayData", "PoolHourData"])
+        .key_first_segment_in(POOL_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
         let (table_name, time_id, pool_address) = pool_windows_id_fields(&delta.key);
@@ -1436,7 +1812,7 @@ pub fn prices_max_pool_windows(tables: &mut Tables, max_pool_prices_deltas: &Del
 pub fn prices_close_pool_windows(tables: &mut Tables, prices_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in prices_deltas
         .iter()
-        .key_first_segment_in(["PoolDayData", "PoolHourData"])
+        .key_first_segment_in(POOL_WINDOW_TABLES)
         .operation_eq(Operation::Delete)
     {
         let (table_name, time_id, pool_address) = pool_windows_id_fields(&delta.key);
@@ -1456,7 +1832,7 @@ pub fn liquidities_and_sqrt_tick_pool_windows(
 ) {
     for delta in pool_liquidities_store_deltas
         .iter()
-        .key_first_segment_in(["PoolDayData", "PoolHourData"])
+        .key_first_segment_in(POOL_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
         let (table_name, time_id, pool_address) = pool_windows_id_fields(&delta.key);
@@ -1485,8 +1861,7 @@ pub fn sqrt_price_and_tick_pool_windows(
     pool_sqrt_price_store: &StoreGetProto<PoolSqrtPrice>,
     pool_events: &Vec<events::PoolEvent>,
 ) {
-    let day_id = timestamp / 86400;
-    let hour_id = timestamp / 3600;
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, timestamp);
 
     for pool_event in pool_events {
         let pool_address = &pool_event.pool_address;
@@ -1497,15 +1872,12 @@ pub fn sqrt_price_and_tick_pool_windows(
                 let sqrt_price = BigInt::try_from(pool_sqrt_price.sqrt_price).unwrap();
                 let tick = BigInt::try_from(pool_sqrt_price.tick).unwrap();
 
-                tables
-                    .update_row("PoolDayData", format!("0x{pool_address}-{day_id}"))
-                    .set("sqrtPrice", &sqrt_price)
-                    .set("tick", &tick);
-
-                tables
-                    .update_row("PoolHourData", format!("0x{pool_address}-{hour_id}"))
-                    .set("sqrtPrice", sqrt_price)
-                    .set("tick", tick);
+                for window in &windows {
+                    tables
+                        .update_row(&window.kind.table("Pool"), window.entity_id(pool_address))
+                        .set("sqrtPrice", &sqrt_price)
+                        .set("tick", &tick);
+                }
             }
         }
     }
@@ -1514,7 +1886,7 @@ pub fn sqrt_price_and_tick_pool_windows(
 pub fn swap_volume_pool_windows(tables: &mut Tables, swaps_volume_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in swaps_volume_deltas
         .iter()
-        .key_first_segment_in(["PoolDayData", "PoolHourData"])
+        .key_first_segment_in(POOL_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
         .key_last_segment_in(["volumeToken0", "volumeToken1", "volumeUSD", "feesUSD"])
     {
@@ -1526,33 +1898,58 @@ pub fn swap_volume_pool_windows(tables: &mut Tables, swaps_volume_deltas: &Delta
     }
 }
 
+pub fn jit_liquidity_pool_windows(tables: &mut Tables, jit_windows_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in jit_windows_deltas
+        .iter()
+        .key_first_segment_in(POOL_WINDOW_TABLES)
+        .operation_not_eq(Operation::Delete)
+    {
+        let (table_name, time_id, pool_address) = pool_windows_id_fields(&delta.key);
+        let row = tables.update_row(table_name, format!("0x{pool_address}-{time_id}"));
+
+        match key::last_segment(&delta.key) {
+            "jitCount" => {
+                row.set("jitCount", delta.new_value.to_bigint());
+            }
+            name => {
+                row.set(name, &delta.new_value);
+            }
+        }
+    }
+}
+
+pub fn balance_pool_windows(tables: &mut Tables, pool_balance_tvl_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in pool_balance_tvl_deltas
+        .iter()
+        .key_first_segment_in(POOL_WINDOW_TABLES)
+        .operation_not_eq(Operation::Delete)
+    {
+        let (table_name, time_id, pool_address) = pool_windows_id_fields(&delta.key);
+        tables
+            .update_row(table_name, format!("0x{pool_address}-{time_id}"))
+            .set(key::last_segment(&delta.key), &delta.new_value);
+    }
+}
+
 pub fn fee_growth_global_x128_pool_windows(
     tables: &mut Tables,
     timestamp: i64,
     updates: &Vec<events::FeeGrowthGlobal>,
 ) {
-    for update in updates {
-        let day_id = timestamp / 86400;
-        let hour_id = timestamp / 3600;
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, timestamp);
 
+    for update in updates {
         let pool_address = &update.pool_address;
+        let field_name = match update.token_idx {
+            0 => "feeGrowthGlobal0X128",
+            1 => "feeGrowthGlobal1X128",
+            _ => continue,
+        };
 
-        if update.token_idx == 0 {
-            tables
-                .update_row("PoolDayData", format!("0x{pool_address}-{day_id}"))
-                .set("feeGrowthGlobal0X128", &BigInt::try_from(&update.new_value).unwrap());
-
-            tables
-                .update_row("PoolHourData", format!("0x{pool_address}-{hour_id}"))
-                .set("feeGrowthGlobal0X128", &BigInt::try_from(&update.new_value).unwrap());
-        } else if update.token_idx == 1 {
-            tables
-                .update_row("PoolDayData", format!("0x{pool_address}-{day_id}"))
-                .set("feeGrowthGlobal1X128", &BigInt::try_from(&update.new_value).unwrap());
-
+        for window in &windows {
             tables
-                .update_row("PoolHourData", format!("0x{pool_address}-{hour_id}"))
-                .set("feeGrowthGlobal1X128", &BigInt::try_from(&update.new_value).unwrap());
+                .update_row(&window.kind.table("Pool"), window.entity_id(pool_address))
+                .set(field_name, &BigInt::try_from(&update.new_value).unwrap());
         }
     }
 }
@@ -1560,7 +1957,7 @@ pub fn fee_growth_global_x128_pool_windows(
 pub fn total_value_locked_usd_pool_windows(tables: &mut Tables, derived_tvl_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in derived_tvl_deltas
         .iter()
-        .key_first_segment_in(["PoolDayData", "PoolHourData"])
+        .key_first_segment_in(POOL_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
         .key_last_segment_eq("totalValueLockedUSD")
     {
@@ -1573,14 +1970,46 @@ pub fn total_value_locked_usd_pool_windows(tables: &mut Tables, derived_tvl_delt
 }
 
 // ---------------------------------
-//  Map Token Day/Hour Data Entities
+//  Map Token Windows Data Entities
 // ---------------------------------
-pub fn token_windows_create(mut tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
-    create_token_windows(&mut tables, &tx_count_deltas);
+pub fn token_windows_create(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>, gap_filled_tables: &[String]) {
+    create_token_windows(tables, tx_count_deltas, gap_filled_tables);
+}
+
+pub fn gap_filled_token_windows(tables: &mut Tables, token_windows: &Vec<GapFilledTokenWindow>) {
+    for token_window in token_windows {
+        let token_address = &token_window.token_address;
+        let price_usd = BigDecimal::try_from(&token_window.price_usd).unwrap();
+
+        let token_time_id = format!("0x{token_address}-{}", token_window.time_id);
+
+        create_token_windows_entity(
+            tables,
+            &token_window.table,
+            token_window.time_id,
+            &token_time_id,
+            token_address,
+        );
+        tables
+            .update_row(&token_window.table, &token_time_id)
+            .set(
+                "totalValueLocked",
+                BigDecimal::try_from(&token_window.total_value_locked).unwrap(),
+            )
+            .set(
+                "totalValueLockedUSD",
+                BigDecimal::try_from(&token_window.total_value_locked_usd).unwrap(),
+            )
+            .set("priceUSD", &price_usd)
+            .set("open", &price_usd)
+            .set("high", &price_usd)
+            .set("low", &price_usd)
+            .set("close", &price_usd);
+    }
 }
 
 pub fn token_windows_update(
-    mut tables: &mut Tables,
+    tables: &mut Tables,
     timestamp: i64,
     swaps_volume_deltas: &Deltas<DeltaBigDecimal>,
     derived_tvl_deltas: &Deltas<DeltaBigDecimal>,
@@ -1589,21 +2018,26 @@ pub fn token_windows_update(
     derived_eth_prices_deltas: &Deltas<DeltaBigDecimal>,
     token_tvl_deltas: &Deltas<DeltaBigDecimal>,
 ) {
-    swap_volume_token_windows(&mut tables, &swaps_volume_deltas);
-    total_value_locked_usd_token_windows(&mut tables, &derived_tvl_deltas);
-    total_value_locked_token_windows(&mut tables, timestamp, &token_tvl_deltas);
-    total_prices_token_windows(&mut tables, &derived_eth_prices_deltas);
-    prices_min_token_windows(&mut tables, &min_windows_deltas);
-    prices_max_token_windows(&mut tables, &max_windows_deltas);
-    prices_close_token_windows(&mut tables, &derived_eth_prices_deltas);
+    swap_volume_token_windows(tables, swaps_volume_deltas);
+    total_value_locked_usd_token_windows(tables, derived_tvl_deltas);
+    total_value_locked_token_windows(tables, timestamp, token_tvl_deltas);
+    total_prices_token_windows(tables, derived_eth_prices_deltas);
+    prices_min_token_windows(tables, min_windows_deltas);
+    prices_max_token_windows(tables, max_windows_deltas);
+    prices_close_token_windows(tables, derived_eth_prices_deltas);
 }
 
-pub fn create_token_windows(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
+pub fn create_token_windows(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>, gap_filled_tables: &[String]) {
     for delta in tx_count_deltas
         .iter()
-        .key_first_segment_in(["TokenDayData", "TokenHourData"])
+        .key_first_segment_in(TOKEN_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
         .filter(|d| d.new_value.eq(&BigInt::one()))
+        .filter(|d| {
+            !gap_filled_tables
+                .iter()
+                .any(|table| table == key::first_segment(&d.key))
+        })
     {
         let (time_id, token_address) = time_as_i64_address_as_str(&delta.key);
 
@@ -1621,7 +2055,7 @@ pub fn create_token_windows(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaB
 pub fn swap_volume_token_windows(tables: &mut Tables, swaps_volume_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in swaps_volume_deltas
         .iter()
-        .key_first_segment_in(["TokenDayData", "TokenHourData"])
+        .key_first_segment_in(TOKEN_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
         let (table_name, time_id, token_address) = pool_windows_id_fields(&delta.key);
@@ -1643,7 +2077,7 @@ pub fn swap_volume_token_windows(tables: &mut Tables, swaps_volume_deltas: &Delt
 pub fn total_value_locked_usd_token_windows(tables: &mut Tables, derived_tvl_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in derived_tvl_deltas
         .iter()
-        .key_first_segment_in(["TokenDayData", "TokenHourData"])
+        .key_first_segment_in(TOKEN_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
         let (table_name, time_id, token_address) = pool_windows_id_fields(&delta.key);
@@ -1659,8 +2093,7 @@ pub fn total_value_locked_token_windows(
     timestamp: i64,
     token_tvl_deltas: &Deltas<DeltaBigDecimal>,
 ) {
-    let day_id = timestamp / 86400;
-    let hour_id = timestamp / 3600;
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, timestamp);
 
     for delta in token_tvl_deltas
         .iter()
@@ -1668,18 +2101,14 @@ pub fn total_value_locked_token_windows(
         .operation_not_eq(Operation::Delete)
     {
         let token_address = key::segment_at(&delta.key, 1);
-        total_value_locked_token_windows_update(
-            tables,
-            "TokenDayData",
-            format!("0x{token_address}-{day_id}"),
-            &delta.new_value,
-        );
-        total_value_locked_token_windows_update(
-            tables,
-            "TokenHourData",
-            format!("0x{token_address}-{hour_id}"),
-            &delta.new_value,
-        );
+        for window in &windows {
+            total_value_locked_token_windows_update(
+                tables,
+                &window.kind.table("Token"),
+                window.entity_id(token_address),
+                &delta.new_value,
+            );
+        }
     }
 }
 
@@ -1697,7 +2126,7 @@ fn total_value_locked_token_windows_update(
 pub fn total_prices_token_windows(tables: &mut Tables, derived_eth_prices_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in derived_eth_prices_deltas
         .iter()
-        .key_first_segment_in(["TokenDayData", "TokenHourData"])
+        .key_first_segment_in(TOKEN_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
         let (table_name, time_id, token_address) = token_windows_id_fields(&delta.key);
@@ -1711,7 +2140,7 @@ pub fn total_prices_token_windows(tables: &mut Tables, derived_eth_prices_deltas
 pub fn prices_min_token_windows(tables: &mut Tables, min_token_prices_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in min_token_prices_deltas
         .iter()
-        .key_first_segment_in(["TokenDayData", "TokenHourData"])
+        .key_first_segment_in(TOKEN_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
         .key_last_segment_in(["low", "open"])
     {
@@ -1727,7 +2156,7 @@ pub fn prices_min_token_windows(tables: &mut Tables, min_token_prices_deltas: &D
 pub fn prices_max_token_windows(tables: &mut Tables, max_token_prices_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in max_token_prices_deltas
         .iter()
-        .key_first_segment_in(["TokenDayData", "TokenHourData"])
+        .key_first_segment_in(TOKEN_WINDOW_TABLES)
         .operation_not_eq(Operation::Delete)
     {
         let (table_name, time_id, token_address) = token_windows_id_fields(&delta.key);
@@ -1742,7 +2171,7 @@ pub fn prices_max_token_windows(tables: &mut Tables, max_token_prices_deltas: &D
 pub fn prices_close_token_windows(tables: &mut Tables, eth_prices_deltas: &Deltas<DeltaBigDecimal>) {
     for delta in eth_prices_deltas
         .iter()
-        .key_first_segment_in(["TokenDayData", "TokenHourData"])
+        .key_first_segment_in(TOKEN_WINDOW_TABLES)
         .operation_eq(Operation::Delete)
     {
         let (table_name, time_id, token_address) = token_windows_id_fields(&delta.key);
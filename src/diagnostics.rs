@@ -0,0 +1,141 @@
+use crate::errors::{DataError, Policy};
+use crate::pb::uniswap::events::PoolEvent;
+use crate::pb::uniswap::{Diagnostic, PoolTokenTransfer};
+use std::collections::BTreeMap;
+
+// Item skipped by a handler, the transaction and the block being filled in by
+// DiagnosticsCollector
+pub fn skipped_item(module: &str, ordinal: u64, pool_address: &str, err: &DataError) -> Diagnostic {
+    Diagnostic {
+        module: module.to_string(),
+        reason: err.reason().to_string(),
+        detail: err.to_string(),
+        pool_address: pool_address.to_string(),
+        token: err.token().cloned().unwrap_or_default(),
+        ordinal,
+        ..Default::default()
+    }
+}
+
+// Items skipped by a handler under the skip_and_report policy. The map handlers carry them in
+// their output, and map_diagnostics checks the items of the stores again to collect theirs as
+// the stores only keep their own values.
+pub struct SkippedItems {
+    policy: Policy,
+    items: Vec<Diagnostic>,
+}
+
+impl SkippedItems {
+    pub fn new(policy: Policy) -> Self {
+        SkippedItems { policy, items: vec![] }
+    }
+
+    // The value, or None when the item is to be skipped, following the policy
+    pub fn check<T>(
+        &mut self,
+        module: &str,
+        ordinal: u64,
+        pool_address: &str,
+        result: Result<T, DataError>,
+    ) -> Option<T> {
+        let items = &mut self.items;
+        self.policy.check_or_report(module, result, |err| {
+            items.push(skipped_item(module, ordinal, pool_address, err))
+        })
+    }
+
+    pub fn extend(&mut self, items: Vec<Diagnostic>) {
+        self.items.extend(items);
+    }
+
+    pub fn into_items(self) -> Vec<Diagnostic> {
+        self.items
+    }
+}
+
+// Fills in the transaction of the skipped items from the pool events and token transfers
+// of the block, by ordinal
+pub struct DiagnosticsCollector<'a> {
+    transaction_ids: BTreeMap<u64, &'a String>,
+    block_number: u64,
+    timestamp: u64,
+}
+
+impl<'a> DiagnosticsCollector<'a> {
+    pub fn new(
+        pool_events: &'a [PoolEvent],
+        transfers: &'a [PoolTokenTransfer],
+        block_number: u64,
+        timestamp: u64,
+    ) -> Self {
+        DiagnosticsCollector {
+            transaction_ids: pool_events
+                .iter()
+                .map(|event| (event.log_ordinal, &event.transaction_id))
+                .chain(
+                    transfers
+                        .iter()
+                        .map(|transfer| (transfer.ordinal, &transfer.transaction_id)),
+                )
+                .collect(),
+            block_number,
+            timestamp,
+        }
+    }
+
+    pub fn collect(&self, skipped_items: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = skipped_items
+            .into_iter()
+            .map(|diagnostic| Diagnostic {
+                transaction_id: self
+                    .transaction_ids
+                    .get(&diagnostic.ordinal)
+                    .map(|transaction_id| transaction_id.to_string())
+                    .unwrap_or_default(),
+                block_number: self.block_number,
+                timestamp: self.timestamp,
+                ..diagnostic
+            })
+            .collect();
+        diagnostics.sort_by(|a, b| a.ordinal.cmp(&b.ordinal));
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::SkippedItems;
+    use crate::errors::{DataError, Policy};
+
+    #[test]
+    fn test_skipped_items() {
+        let err = DataError::TokenTvlNotFound("token".to_string(), "pool:pool:token:token0".to_string());
+        let mut skipped_items = SkippedItems::new(Policy::SkipAndReport);
+        assert_eq!(Some(1), skipped_items.check("store_derived_tvl", 6, "pool", Ok(1)));
+        assert_eq!(
+            None,
+            skipped_items.check::<i32>("store_derived_tvl", 7, "pool", Err(err))
+        );
+
+        let items = skipped_items.into_items();
+        assert_eq!(1, items.len());
+        assert_eq!("store_derived_tvl", items[0].module);
+        assert_eq!("token_tvl_not_found", items[0].reason);
+        assert_eq!("token tvl not found at pool:pool:token:token0", items[0].detail);
+        assert_eq!("pool", items[0].pool_address);
+        assert_eq!("token", items[0].token);
+        assert_eq!(7, items[0].ordinal);
+    }
+
+    #[test]
+    fn test_skipped_items_not_reported() {
+        let mut skipped_items = SkippedItems::new(Policy::Skip);
+        let err = DataError::PoolNotFound("pool".to_string());
+        assert_eq!(
+            None,
+            skipped_items.check::<i32>("store_eth_prices", 7, "pool", Err(err))
+        );
+        assert!(skipped_items.into_items().is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::pb::uniswap::{ErrorPolicy, Pool};
+use std::fmt;
+use std::str::FromStr;
+use substreams::log;
+use substreams::scalar::{BigDecimal, BigInt};
+use substreams::store::{StoreGet, StoreGetBigDecimal, StoreGetInt64, StoreGetProto};
+
+// Data issues met by the handlers, which used to panic on them. What is done with the item
+// is decided by the policy given through the params of map_error_policy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataError {
+    // pool address
+    PoolNotFound(String),
+    BundlePriceNotFound,
+    // token address
+    DerivedEthPriceNotFound(String),
+    // token address and store key
+    TokenTvlNotFound(String, String),
+    // field and value
+    InvalidNumber(&'static str, String),
+    // token address, for a token of a created pool missing from store_tokens
+    TokenNotFound(String),
+    // kind of event expected, for an event without its type or of another type
+    UnexpectedEvent(&'static str),
+}
+
+impl DataError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            DataError::PoolNotFound(_) => "pool_not_found",
+            DataError::BundlePriceNotFound => "bundle_price_not_found",
+            DataError::DerivedEthPriceNotFound(_) => "derived_eth_price_not_found",
+            DataError::TokenTvlNotFound(_, _) => "token_tvl_not_found",
+            DataError::InvalidNumber(_, _) => "invalid_number",
+            DataError::TokenNotFound(_) => "token_not_found",
+            DataError::UnexpectedEvent(_) => "unexpected_event",
+        }
+    }
+
+    pub fn token(&self) -> Option<&String> {
+        match self {
+            DataError::DerivedEthPriceNotFound(token)
+            | DataError::TokenTvlNotFound(token, _)
+            | DataError::TokenNotFound(token) => Some(token),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataError::PoolNotFound(pool_address) => write!(f, "pool not found {pool_address}"),
+            DataError::BundlePriceNotFound => write!(f, "bundle eth price not found"),
+            DataError::DerivedEthPriceNotFound(token_address) => {
+                write!(f, "token eth price not found for token {token_address}")
+            }
+            DataError::TokenTvlNotFound(_, key) => write!(f, "token tvl not found at {key}"),
+            DataError::InvalidNumber(field, value) => write!(f, "invalid {field} {value}"),
+            DataError::TokenNotFound(token_address) => {
+                write!(f, "pool contains token that doesn't exist {token_address}")
+            }
+            DataError::UnexpectedEvent(kind) => write!(f, "expected a {kind} event"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    // panic, halting the stream as before
+    Fail,
+    Skip,
+    // skip, and report the item in map_diagnostics
+    SkipAndReport,
+}
+
+impl Policy {
+    // params of map_error_policy, `fail` by default
+    pub fn from_name(name: &str) -> Policy {
+        match name.trim() {
+            "" | "fail" => Policy::Fail,
+            "skip" => Policy::Skip,
+            "skip_and_report" => Policy::SkipAndReport,
+            _ => panic!("unknown error policy {name}, expected fail, skip or skip_and_report"),
+        }
+    }
+
+    pub fn from_error_policy(error_policy: &ErrorPolicy) -> Policy {
+        Policy::from_name(&error_policy.policy)
+    }
+
+    // The value, or None when the item is to be skipped
+    pub fn check<T>(&self, module: &str, result: Result<T, DataError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) if *self == Policy::Fail => panic!("{module}: {err}"),
+            Err(err) => {
+                log::info!("{}: skipping item, {}", module, err);
+                None
+            }
+        }
+    }
+
+    // Same as check, the skipped item being handed to report under skip_and_report for it to
+    // be collected with the other skipped items
+    pub fn check_or_report<T>(
+        &self,
+        module: &str,
+        result: Result<T, DataError>,
+        report: impl FnOnce(&DataError),
+    ) -> Option<T> {
+        if let (Err(err), Policy::SkipAndReport) = (&result, self) {
+            report(err);
+        }
+        self.check(module, result)
+    }
+}
+
+pub fn get_pool(pools_store: &StoreGetProto<Pool>, pool_address: &String) -> Result<Pool, DataError> {
+    pools_store
+        .get_last(format!("pool:{pool_address}"))
+        .ok_or_else(|| DataError::PoolNotFound(pool_address.clone()))
+}
+
+// number of created pools holding the token, 1 for the pool creating it
+pub fn get_token_pool_count(
+    ordinal: u64,
+    token_address: &String,
+    tokens_store: &StoreGetInt64,
+) -> Result<i64, DataError> {
+    tokens_store
+        .get_at(ordinal, format!("token:{token_address}"))
+        .ok_or_else(|| DataError::TokenNotFound(token_address.clone()))
+}
+
+pub fn get_bundle_eth_price(ordinal: u64, eth_prices_store: &StoreGetBigDecimal) -> Result<BigDecimal, DataError> {
+    eth_prices_store
+        .get_at(ordinal, "bundle")
+        .ok_or(DataError::BundlePriceNotFound)
+}
+
+pub fn parse_decimal(field: &'static str, value: &str) -> Result<BigDecimal, DataError> {
+    BigDecimal::try_from(value).map_err(|_| DataError::InvalidNumber(field, value.to_string()))
+}
+
+pub fn parse_int(field: &'static str, value: &str) -> Result<BigInt, DataError> {
+    BigInt::from_str(value).map_err(|_| DataError::InvalidNumber(field, value.to_string()))
+}
+
+// The values of an item parsed together, the first invalid one being the error
+pub fn parse_decimals<const N: usize>(values: [(&'static str, &str); N]) -> Result<[BigDecimal; N], DataError> {
+    let mut decimals = Vec::with_capacity(N);
+    for (field, value) in values {
+        decimals.push(parse_decimal(field, value)?);
+    }
+    Ok(decimals.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::errors::{parse_decimals, DataError, Policy};
+
+    #[test]
+    fn test_policy_from_name() {
+        assert_eq!(Policy::Fail, Policy::from_name(""));
+        assert_eq!(Policy::Skip, Policy::from_name("skip"));
+        assert_eq!(Policy::SkipAndReport, Policy::from_name(" skip_and_report "));
+    }
+
+    #[test]
+    fn test_policy_check() {
+        assert_eq!(Some(1), Policy::Fail.check("test", Ok(1)));
+        assert_eq!(None, Policy::Skip.check::<i32>("test", Err(DataError::BundlePriceNotFound)));
+    }
+
+    #[test]
+    fn test_parse_decimals() {
+        let [amount0, amount1] = parse_decimals([("amount0", "1.5"), ("amount1", "-2")]).unwrap();
+        assert_eq!("1.5", amount0.to_string());
+        assert_eq!("-2", amount1.to_string());
+        assert_eq!(
+            Err(DataError::InvalidNumber("amount1", "abc".to_string())),
+            parse_decimals([("amount0", "1.5"), ("amount1", "abc")])
+        );
+    }
+
+    #[test]
+    fn test_policy_check_or_report() {
+        let mut reported = vec![];
+        assert_eq!(
+            None,
+            Policy::Skip.check_or_report::<i32>("test", Err(DataError::BundlePriceNotFound), |err| {
+                reported.push(err.clone())
+            })
+        );
+        assert!(reported.is_empty());
+
+        assert_eq!(
+            None,
+            Policy::SkipAndReport.check_or_report::<i32>("test", Err(DataError::BundlePriceNotFound), |err| {
+                reported.push(err.clone())
+            })
+        );
+        assert_eq!(vec![DataError::BundlePriceNotFound], reported);
+    }
+
+    #[test]
+    #[should_panic(expected = "test: bundle eth price not found")]
+    fn test_policy_check_fail() {
+        Policy::Fail.check::<i32>("test", Err(DataError::BundlePriceNotFound));
+    }
+}
@@ -15,15 +15,34 @@ impl Display for DecodeError {
 
 impl Error for DecodeError {}
 
+// A bytes32 name or symbol is padded with NULs on the right, anything after the first
+// one is padding left dirty by the token and is dropped
 pub fn read_string_from_bytes(input: &[u8]) -> String {
-    // we have to check if we have a valid utf8 representation and if we do
-    // we return the value if not we return a DecodeError
-    if let Some(last) = input.to_vec().iter().rev().position(|&pos| pos != 0) {
-        return String::from_utf8_lossy(&input[0..input.len() - last]).to_string();
+    let end = input.iter().position(|&byte| byte == 0).unwrap_or(input.len());
+    String::from_utf8_lossy(&input[..end]).to_string()
+}
+
+// Return data of a name() or symbol() call the strict decodings rejected: an ABI encoded
+// string which is not valid UTF-8, or a bytes32 of another length than expected
+pub fn read_string_from_return_data(input: &[u8]) -> String {
+    if input.len() >= 64 && read_word(&input[0..32]) == Some(32) {
+        if let Some(length) = read_word(&input[32..64]) {
+            if input.len() - 64 >= length {
+                return read_string_from_bytes(&input[64..64 + length]);
+            }
+        }
     }
 
-    // use case when all the bytes are set to 0
-    "".to_string()
+    read_string_from_bytes(&input[..input.len().min(32)])
+}
+
+// A uint256 word, when it fits in a usize
+fn read_word(word: &[u8]) -> Option<usize> {
+    let (high, low) = word.split_at(word.len() - 8);
+    if high.iter().any(|&byte| byte != 0) {
+        return None;
+    }
+    usize::try_from(u64::from_be_bytes(low.try_into().unwrap())).ok()
 }
 
 #[cfg(test)]
@@ -75,4 +94,31 @@ mod tests {
 
         assert_eq!("".to_string(), read_string_from_bytes(bytes));
     }
+
+    #[test]
+    fn test_read_string_from_bytes32_trailing_garbage() {
+        let bytes: &[u8; 32] = &[
+            77, 75, 82, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 12, 255,
+        ];
+
+        assert_eq!("MKR".to_string(), read_string_from_bytes(bytes));
+    }
+
+    #[test]
+    fn test_read_string_from_return_data_abi_string() {
+        let mut bytes = vec![0u8; 96];
+        bytes[31] = 32;
+        bytes[63] = 4;
+        bytes[64..68].copy_from_slice(&[77, 75, 82, 255]);
+
+        assert_eq!("MKR\u{fffd}".to_string(), read_string_from_return_data(&bytes));
+    }
+
+    #[test]
+    fn test_read_string_from_return_data_bytes32() {
+        let mut bytes = vec![0u8; 32];
+        bytes[0..3].copy_from_slice(&[77, 75, 82]);
+
+        assert_eq!("MKR".to_string(), read_string_from_return_data(&bytes));
+    }
 }
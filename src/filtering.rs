@@ -1,31 +1,27 @@
 use crate::math::compute_price_from_tick_idx;
-use crate::pb::uniswap::events;
+use crate::pb::uniswap::{events, Events};
+use crate::sources::SourceRegistry;
 use crate::storage::position_manager::PositionManagerStorage;
 use crate::storage::uniswap_v3_pool::UniswapPoolStorage;
-use crate::utils::NON_FUNGIBLE_POSITION_MANAGER;
-use crate::{abi, math, utils, BurnEvent, EventTrait, MintEvent, Pool, SwapEvent};
+use crate::utils::{NON_FUNGIBLE_POSITION_MANAGER, ZERO_ADDRESS};
+use crate::{abi, math, price, storage, utils, BurnEvent, EventTrait, MintEvent, Pool, SwapEvent};
 use substreams::prelude::{BigDecimal, BigInt};
+use substreams::store::{StoreGet, StoreGetProto};
 use substreams::{log, Hex};
 use substreams_ethereum::block_view::CallView;
 use substreams_ethereum::pb::eth::v2::{Call, Log, StorageChange, TransactionTrace};
 
-pub fn extract_pool_events_and_positions(
-    pool_events: &mut Vec<events::PoolEvent>,
-    ticks_created: &mut Vec<events::TickCreated>,
-    ticks_updated: &mut Vec<events::TickUpdated>,
-    created_positions: &mut Vec<events::CreatedPosition>,
-    increase_liquidity_positions: &mut Vec<events::IncreaseLiquidityPosition>,
-    decrease_liquidity_positions: &mut Vec<events::DecreaseLiquidityPosition>,
-    collect_positions: &mut Vec<events::CollectPosition>,
-    transfer_positions: &mut Vec<events::TransferPosition>,
-    transaction_id: &String,
-    origin: &String,
+pub fn extract_pool_events(
+    events: &mut Events,
     log: &Log,
     call_view: &CallView,
     pool: &Pool,
+    source_registry: &SourceRegistry,
     timestamp_seconds: u64,
     block_number: u64,
 ) {
+    let transaction_id = &Hex(&call_view.transaction.hash).to_string();
+    let origin = &Hex(&call_view.transaction.from).to_string();
     let common_tick_updated = events::TickUpdated {
         log_ordinal: log.ordinal,
         pool_address: pool.address.to_string(),
@@ -47,7 +43,7 @@ pub fn extract_pool_events_and_positions(
         let amount0 = swap.amount0.to_decimal(token0.decimals);
         let amount1 = swap.amount1.to_decimal(token1.decimals);
 
-        pool_events.push(events::PoolEvent {
+        events.pool_events.push(events::PoolEvent {
             log_ordinal: log.ordinal,
             log_index: log.block_index as u64,
             pool_address: pool.address.to_string(),
@@ -66,20 +62,9 @@ pub fn extract_pool_events_and_positions(
                 sqrt_price: swap.sqrt_price_x96.into(),
                 liquidity: swap.liquidity.into(),
                 tick: swap.tick.into(),
+                source: source_registry.attribute(call_view),
             })),
         });
-
-        //TODO: verify if a swap changes the fee growth inside 0x128 and 1x128
-        if let Some(position_manager_contract_call) = call_view.parent() {
-            extract_positions(
-                pool,
-                increase_liquidity_positions,
-                decrease_liquidity_positions,
-                collect_positions,
-                transfer_positions,
-                &position_manager_contract_call,
-            );
-        }
     } else if let Some(mint) = abi::pool::events::Mint::match_and_decode(log) {
         log::info!("MINT: transaction: {}", transaction_id.to_string());
         if !pool.should_handle_mint_and_burn() {
@@ -91,7 +76,7 @@ pub fn extract_pool_events_and_positions(
         let amount0 = mint.amount0.to_decimal(token0.decimals);
         let amount1 = mint.amount1.to_decimal(token1.decimals);
 
-        pool_events.push(events::PoolEvent {
+        events.pool_events.push(events::PoolEvent {
             log_ordinal: log.ordinal,
             log_index: log.block_index as u64,
             pool_address: pool.address.to_string(),
@@ -127,85 +112,23 @@ pub fn extract_pool_events_and_positions(
         lower_tick.idx = mint.tick_lower.as_ref().into();
         lower_tick.price0 = price0.into();
         lower_tick.price1 = price1.into();
-        ticks_created.push(lower_tick);
+        events.ticks_created.push(lower_tick);
 
         let mut upper_tick = common_tick.clone();
         let (price0, price1) = prices_from_tick_index(mint.tick_upper.to_i32());
         upper_tick.idx = mint.tick_upper.as_ref().into();
         upper_tick.price0 = price0.into();
         upper_tick.price1 = price1.into();
-        ticks_created.push(upper_tick);
+        events.ticks_created.push(upper_tick);
 
         let storage = UniswapPoolStorage::new(&call_view.call.storage_changes, &log.address);
 
-        ticks_updated.push(events::TickUpdated {
-            idx: mint.tick_upper.as_ref().into(),
-            fee_growth_outside_0x_128: bigint_if_some(storage.ticks(&mint.tick_upper).fee_growth_outside_0_x128()),
-            fee_growth_outside_1x_128: bigint_if_some(storage.ticks(&mint.tick_upper).fee_growth_outside_1_x128()),
-            ..common_tick_updated.clone()
-        });
-        ticks_updated.push(events::TickUpdated {
-            idx: mint.tick_lower.as_ref().into(),
-            fee_growth_outside_0x_128: bigint_if_some(storage.ticks(&mint.tick_lower).fee_growth_outside_0_x128()),
-            fee_growth_outside_1x_128: bigint_if_some(storage.ticks(&mint.tick_lower).fee_growth_outside_1_x128()),
-            ..common_tick_updated.clone()
-        });
-
-        if let Some(position_manager_contract_call) = call_view.parent() {
-            if position_manager_contract_call.address != NON_FUNGIBLE_POSITION_MANAGER {
-                return;
-            }
-
-            let uniswap_pool_manager_storage = PositionManagerStorage::new(
-                &position_manager_contract_call.storage_changes,
-                &position_manager_contract_call.address,
-            );
-
-            if let Some((old_value, _new_value)) = uniswap_pool_manager_storage.next_id() {
-                let token_id = old_value;
-
-                let mut fee_growth_inside0_last_x128 = None;
-                let mut fee_growth_inside1_last_x128 = None;
-
-                if let Some((_old_value, new_value)) = uniswap_pool_manager_storage
-                    .positions(&token_id)
-                    .fee_growth_inside0last_x128()
-                {
-                    fee_growth_inside0_last_x128 = Some(new_value.to_string());
-                }
-
-                if let Some((_old_value, new_value)) = uniswap_pool_manager_storage
-                    .positions(&token_id)
-                    .fee_growth_inside1last_x128()
-                {
-                    fee_growth_inside1_last_x128 = Some(new_value.to_string());
-                }
-
-                created_positions.push(events::CreatedPosition {
-                    token_id: token_id.to_string(),
-                    pool: pool.address.clone(),
-                    token0: token0.address.clone(),
-                    token1: token1.address.clone(),
-                    tick_lower: mint.tick_lower.to_string(),
-                    tick_upper: mint.tick_upper.to_string(),
-                    transaction: transaction_id.to_string(),
-                    log_ordinal: log.ordinal,
-                    timestamp: timestamp_seconds,
-                    block_number,
-                    fee_growth_inside0_last_x128,
-                    fee_growth_inside1_last_x128,
-                });
-            }
-
-            extract_positions(
-                pool,
-                increase_liquidity_positions,
-                decrease_liquidity_positions,
-                collect_positions,
-                transfer_positions,
-                &position_manager_contract_call,
-            );
-        }
+        events
+            .ticks_updated
+            .push(tick_updated(&storage, &mint.tick_upper, &common_tick_updated));
+        events
+            .ticks_updated
+            .push(tick_updated(&storage, &mint.tick_lower, &common_tick_updated));
     } else if let Some(burn) = abi::pool::events::Burn::match_and_decode(log) {
         log::info!("BURN: transaction: {}", transaction_id.to_string());
         if !pool.should_handle_mint_and_burn() {
@@ -220,7 +143,7 @@ pub fn extract_pool_events_and_positions(
         let amount0 = amount0_bi.to_decimal(token0.decimals);
         let amount1 = amount1_bi.to_decimal(token1.decimals);
 
-        pool_events.push(events::PoolEvent {
+        events.pool_events.push(events::PoolEvent {
             log_ordinal: log.ordinal,
             log_index: log.block_index as u64,
             pool_address: pool.address.to_string(),
@@ -243,41 +166,138 @@ pub fn extract_pool_events_and_positions(
 
         let storage = UniswapPoolStorage::new(&call_view.call.storage_changes, &log.address);
 
-        ticks_updated.push(events::TickUpdated {
-            idx: burn.tick_upper.as_ref().into(),
-            fee_growth_outside_0x_128: bigint_if_some(storage.ticks(&burn.tick_upper).fee_growth_outside_0_x128()),
-            fee_growth_outside_1x_128: bigint_if_some(storage.ticks(&burn.tick_upper).fee_growth_outside_1_x128()),
-            ..common_tick_updated.clone()
-        });
-        ticks_updated.push(events::TickUpdated {
-            idx: burn.tick_lower.as_ref().into(),
-            fee_growth_outside_0x_128: bigint_if_some(storage.ticks(&burn.tick_lower).fee_growth_outside_0_x128()),
-            fee_growth_outside_1x_128: bigint_if_some(storage.ticks(&burn.tick_lower).fee_growth_outside_1_x128()),
-            ..common_tick_updated.clone()
-        });
+        events
+            .ticks_updated
+            .push(tick_updated(&storage, &burn.tick_upper, &common_tick_updated));
+        events
+            .ticks_updated
+            .push(tick_updated(&storage, &burn.tick_lower, &common_tick_updated));
+    }
+}
+
+fn tick_updated(storage: &UniswapPoolStorage, tick_idx: &BigInt, common: &events::TickUpdated) -> events::TickUpdated {
+    let tick = storage.ticks(tick_idx);
+    events::TickUpdated {
+        idx: tick_idx.into(),
+        fee_growth_outside_0x_128: bigint_if_some(tick.fee_growth_outside0_x128()),
+        fee_growth_outside_1x_128: bigint_if_some(tick.fee_growth_outside1_x128()),
+        liquidity_gross: bigint_if_some(tick.liquidity_gross()),
+        liquidity_net: bigint_if_some(tick.liquidity_net()),
+        tick_cumulative_outside: bigint_if_some(tick.tick_cumulative_outside()),
+        seconds_per_liquidity_outside_x128: bigint_if_some(tick.seconds_per_liquidity_outside_x128()),
+        seconds_outside: bigint_if_some(tick.seconds_outside()),
+        ..common.clone()
+    }
+}
+
+// The tick bitmap, oracle observations, protocol fees and positions written in the pool
+// storage by the call emitting the log
+pub fn extract_pool_storage_updates(events: &mut Events, log: &Log, storage_changes: &Vec<StorageChange>, pool: &Pool) {
+    let storage = UniswapPoolStorage::new(storage_changes, &log.address);
+    let pool_address = &pool.address;
 
-        if let Some(position_manager_contract_call) = call_view.parent() {
-            extract_positions(
-                pool,
-                increase_liquidity_positions,
-                decrease_liquidity_positions,
-                collect_positions,
-                transfer_positions,
-                &position_manager_contract_call,
-            );
+    let position = if let Some(mint) = abi::pool::events::Mint::match_and_decode(log) {
+        Some((mint.owner, mint.tick_lower.to_i32(), mint.tick_upper.to_i32()))
+    } else if let Some(burn) = abi::pool::events::Burn::match_and_decode(log) {
+        Some((burn.owner, burn.tick_lower.to_i32(), burn.tick_upper.to_i32()))
+    } else if let Some(collect) = abi::pool::events::Collect::match_and_decode(log) {
+        Some((collect.owner, collect.tick_lower.to_i32(), collect.tick_upper.to_i32()))
+    } else {
+        None
+    };
+
+    if let Some((owner, tick_lower, tick_upper)) = position {
+        let mut word_positions = vec![
+            tick_word_position(tick_lower, pool.tick_spacing),
+            tick_word_position(tick_upper, pool.tick_spacing),
+        ];
+        word_positions.dedup();
+        for word_position in word_positions {
+            if let Some((_, new_value)) = storage.tick_bitmap(&BigInt::from(word_position)) {
+                events.tick_bitmap_updates.push(events::TickBitmapUpdate {
+                    pool_address: pool_address.to_string(),
+                    ordinal: log.ordinal,
+                    word_position,
+                    new_value: new_value.into(),
+                });
+            }
         }
-    } else if abi::pool::events::Collect::match_log(log) {
-        if let Some(position_manager_contract_call) = call_view.parent() {
-            extract_positions(
-                pool,
-                increase_liquidity_positions,
-                decrease_liquidity_positions,
-                collect_positions,
-                transfer_positions,
-                &position_manager_contract_call,
-            );
+
+        let position_info = storage.positions(&storage::utils::calc_position_key(&owner, tick_lower, tick_upper));
+        let position_update = events::PoolPositionUpdate {
+            pool_address: pool_address.to_string(),
+            ordinal: log.ordinal,
+            owner: Hex(&owner).to_string(),
+            tick_lower,
+            tick_upper,
+            liquidity: bigint_if_some(position_info.liquidity()),
+            fee_growth_inside_0_last_x128: bigint_if_some(position_info.fee_growth_inside0_last_x128()),
+            fee_growth_inside_1_last_x128: bigint_if_some(position_info.fee_growth_inside1_last_x128()),
+            tokens_owed_0: bigint_if_some(position_info.tokens_owed0()),
+            tokens_owed_1: bigint_if_some(position_info.tokens_owed1()),
         };
+        // e.g. a collect of nothing leaves the position untouched
+        if !position_update.liquidity.is_empty()
+            || !position_update.fee_growth_inside_0_last_x128.is_empty()
+            || !position_update.fee_growth_inside_1_last_x128.is_empty()
+            || !position_update.tokens_owed_0.is_empty()
+            || !position_update.tokens_owed_1.is_empty()
+        {
+            events.pool_position_updates.push(position_update);
+        }
     }
+
+    for index in written_observation_indexes(&storage) {
+        let observation = storage.observations(index);
+        let block_timestamp = match observation.block_timestamp() {
+            Some((_, new_value)) => new_value.to_u64(),
+            None => continue,
+        };
+        // increaseObservationCardinalityNext fills the new slots with a placeholder
+        // timestamp of 1, they are not observations yet
+        if block_timestamp == 1 {
+            continue;
+        }
+        events.observation_updates.push(events::ObservationUpdate {
+            pool_address: pool_address.to_string(),
+            ordinal: log.ordinal,
+            index: index as u32,
+            block_timestamp,
+            tick_cumulative: bigint_if_some(observation.tick_cumulative()),
+            seconds_per_liquidity_cumulative_x128: bigint_if_some(observation.seconds_per_liquidity_cumulative_x128()),
+        });
+    }
+
+    let protocol_fees = storage.protocol_fees();
+    for (token_idx, protocol_fee) in [protocol_fees.token0(), protocol_fees.token1()].into_iter().enumerate() {
+        if let Some((_, new_value)) = protocol_fee {
+            events.protocol_fees_updates.push(events::ProtocolFeesUpdate {
+                pool_address: pool_address.to_string(),
+                ordinal: log.ordinal,
+                token_idx: token_idx as i32,
+                new_value: new_value.into(),
+            });
+        }
+    }
+}
+
+// the word of the tick bitmap flipped when a tick gets initialized or cleared
+fn tick_word_position(tick: i32, tick_spacing: i32) -> i32 {
+    tick.div_euclid(tick_spacing.max(1)).div_euclid(256)
+}
+
+// the observations of the pool written by the call, from their storage keys
+fn written_observation_indexes(storage: &UniswapPoolStorage) -> Vec<u64> {
+    let mut indexes: Vec<u64> = storage
+        .storage_changes
+        .iter()
+        .filter(|change| change.address == storage.contract_addr)
+        .filter_map(|change| UniswapPoolStorage::observations_index(&change.key))
+        .collect();
+    indexes.sort();
+    indexes.dedup();
+
+    indexes
 }
 
 fn bigint_if_some(input: Option<(BigInt, BigInt)>) -> String {
@@ -393,12 +413,15 @@ pub fn extract_fee_growth_update(
 
 pub fn extract_pool_sqrt_prices(pool_sqrt_prices: &mut Vec<events::PoolSqrtPrice>, log: &Log, pool_address: &String) {
     if let Some(event) = abi::pool::events::Initialize::match_and_decode(log) {
+        let price_unavailable = price::is_price_unavailable(&event.sqrt_price_x96, None);
         pool_sqrt_prices.push(events::PoolSqrtPrice {
             pool_address: pool_address.to_string(),
             ordinal: log.ordinal,
             sqrt_price: event.sqrt_price_x96.into(),
             tick: event.tick.into(),
             initialized: true,
+            liquidity: "".to_string(),
+            price_unavailable,
         });
     } else if let Some(event) = abi::pool::events::Swap::match_and_decode(log) {
         pool_sqrt_prices.push(events::PoolSqrtPrice {
@@ -407,6 +430,8 @@ pub fn extract_pool_sqrt_prices(pool_sqrt_prices: &mut Vec<events::PoolSqrtPrice
             sqrt_price: event.sqrt_price_x96.to_string(),
             tick: event.tick.to_string(),
             initialized: false,
+            liquidity: event.liquidity.to_string(),
+            price_unavailable: price::is_price_unavailable(&event.sqrt_price_x96, Some(&event.liquidity)),
         });
     }
 }
@@ -422,10 +447,6 @@ pub fn extract_transactions(
     if abi::pool::events::Burn::match_log(log)
         || abi::pool::events::Mint::match_log(log)
         || abi::pool::events::Swap::match_log(log)
-        || abi::positionmanager::events::IncreaseLiquidity::match_log(log)
-        || abi::positionmanager::events::Collect::match_log(log)
-        || abi::positionmanager::events::DecreaseLiquidity::match_log(log)
-        || abi::positionmanager::events::Transfer::match_log(log)
     {
         add_transaction = true
     }
@@ -440,95 +461,193 @@ pub fn extract_transactions(
     }
 }
 
-fn extract_positions(
-    pool: &Pool,
-    increase_liquidity_positions: &mut Vec<events::IncreaseLiquidityPosition>,
-    decrease_liquidity_positions: &mut Vec<events::DecreaseLiquidityPosition>,
-    collect_positions: &mut Vec<events::CollectPosition>,
-    transfer_positions: &mut Vec<events::TransferPosition>,
+// Walks a NonfungiblePositionManager call frame. Every log emitted by the frame is
+// decoded, whether the transaction touched a pool or not (plain NFT transfers and
+// approvals don't).
+pub fn extract_position_manager_events(
+    events: &mut Events,
     call: &Call,
+    transaction_trace: &TransactionTrace,
+    pools_store: &StoreGetProto<Pool>,
+    timestamp_seconds: u64,
+    block_number: u64,
 ) {
+    let transaction_id = Hex(&transaction_trace.hash).to_string();
+    let manager_storage = PositionManagerStorage::new(&call.storage_changes, &call.address);
+
     for log in call.logs.iter() {
         if log.address != NON_FUNGIBLE_POSITION_MANAGER {
-            return;
+            continue;
         }
 
-        let mut fee_growth_inside0_last_x128 = None;
-        let mut fee_growth_inside1_last_x128 = None;
-
-        let manager_storage = PositionManagerStorage::new(&call.storage_changes, &call.address);
-
-        if let Some(event) = abi::positionmanager::events::IncreaseLiquidity::match_and_decode(log) {
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside0last_x128()
-            {
-                fee_growth_inside0_last_x128 = Some(new_value.to_string());
-            }
-
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside1last_x128()
-            {
-                fee_growth_inside1_last_x128 = Some(new_value.to_string());
+        if let Some(event) = abi::positionmanager::events::Transfer::match_and_decode(log) {
+            if event.from == ZERO_ADDRESS {
+                if let Some(pool) =
+                    resolve_position_pool(&event.token_id, &manager_storage, call, transaction_trace, pools_store)
+                {
+                    let position = manager_storage.positions(&event.token_id);
+                    // the positions slot of a new token id is empty, a tick which did not change is the tick 0
+                    let tick_lower = position.tick_lower().map(|(_, v)| v).unwrap_or(BigInt::zero());
+                    let tick_upper = position.tick_upper().map(|(_, v)| v).unwrap_or(BigInt::zero());
+
+                    events.created_positions.push(events::CreatedPosition {
+                        token_id: event.token_id.to_string(),
+                        pool: pool.address.clone(),
+                        token0: pool.token0_ref().address.clone(),
+                        token1: pool.token1_ref().address.clone(),
+                        tick_lower: tick_lower.to_string(),
+                        tick_upper: tick_upper.to_string(),
+                        transaction: transaction_id.to_string(),
+                        log_ordinal: log.ordinal,
+                        timestamp: timestamp_seconds,
+                        block_number,
+                        fee_growth_inside0_last_x128: position
+                            .fee_growth_inside0_last_x128()
+                            .map(|(_, v)| v.to_string()),
+                        fee_growth_inside1_last_x128: position
+                            .fee_growth_inside1_last_x128()
+                            .map(|(_, v)| v.to_string()),
+                    });
+                }
             }
 
-            increase_liquidity_positions.push(events::IncreaseLiquidityPosition {
+            events.transfer_positions.push(events::TransferPosition {
                 token_id: event.token_id.to_string(),
-                liquidity: event.liquidity.to_string(),
-                deposited_token0: event.amount0.to_decimal(pool.token0().decimals).to_string(),
-                deposited_token1: event.amount1.to_decimal(pool.token1().decimals).to_string(),
-                fee_growth_inside0_last_x128,
-                fee_growth_inside1_last_x128,
+                owner: Hex(&event.to).to_string(),
+                from: Hex(&event.from).to_string(),
+                transaction: transaction_id.to_string(),
+                block_number,
+                timestamp: timestamp_seconds,
                 log_ordinal: log.ordinal,
             });
-        } else if let Some(event) = abi::positionmanager::events::DecreaseLiquidity::match_and_decode(log) {
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside0last_x128()
-            {
-                fee_growth_inside0_last_x128 = Some(new_value.to_string());
-            }
 
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside1last_x128()
-            {
-                fee_growth_inside1_last_x128 = Some(new_value.to_string());
+            if event.to == ZERO_ADDRESS {
+                events.closed_positions.push(events::ClosedPosition {
+                    token_id: event.token_id.to_string(),
+                    owner: Hex(&event.from).to_string(),
+                    transaction: transaction_id.to_string(),
+                    block_number,
+                    timestamp: timestamp_seconds,
+                    log_ordinal: log.ordinal,
+                });
             }
-            decrease_liquidity_positions.push(events::DecreaseLiquidityPosition {
+        } else if let Some(event) = abi::positionmanager::events::IncreaseLiquidity::match_and_decode(log) {
+            let pool =
+                match resolve_position_pool(&event.token_id, &manager_storage, call, transaction_trace, pools_store) {
+                    Some(pool) => pool,
+                    None => continue,
+                };
+            let position = manager_storage.positions(&event.token_id);
+
+            events
+                .increase_liquidity_positions
+                .push(events::IncreaseLiquidityPosition {
+                    token_id: event.token_id.to_string(),
+                    liquidity: event.liquidity.to_string(),
+                    deposited_token0: event.amount0.to_decimal(pool.token0_ref().decimals).to_string(),
+                    deposited_token1: event.amount1.to_decimal(pool.token1_ref().decimals).to_string(),
+                    fee_growth_inside0_last_x128: position.fee_growth_inside0_last_x128().map(|(_, v)| v.to_string()),
+                    fee_growth_inside1_last_x128: position.fee_growth_inside1_last_x128().map(|(_, v)| v.to_string()),
+                    log_ordinal: log.ordinal,
+                });
+        } else if let Some(event) = abi::positionmanager::events::DecreaseLiquidity::match_and_decode(log) {
+            let pool =
+                match resolve_position_pool(&event.token_id, &manager_storage, call, transaction_trace, pools_store) {
+                    Some(pool) => pool,
+                    None => continue,
+                };
+            let position = manager_storage.positions(&event.token_id);
+
+            events
+                .decrease_liquidity_positions
+                .push(events::DecreaseLiquidityPosition {
+                    token_id: event.token_id.to_string(),
+                    liquidity: event.liquidity.to_string(),
+                    withdrawn_token0: event.amount0.to_decimal(pool.token0_ref().decimals).to_string(),
+                    withdrawn_token1: event.amount1.to_decimal(pool.token1_ref().decimals).to_string(),
+                    fee_growth_inside0_last_x128: position.fee_growth_inside0_last_x128().map(|(_, v)| v.to_string()),
+                    fee_growth_inside1_last_x128: position.fee_growth_inside1_last_x128().map(|(_, v)| v.to_string()),
+                    log_ordinal: log.ordinal,
+                });
+        } else if let Some(event) = abi::positionmanager::events::Collect::match_and_decode(log) {
+            let pool =
+                match resolve_position_pool(&event.token_id, &manager_storage, call, transaction_trace, pools_store) {
+                    Some(pool) => pool,
+                    None => continue,
+                };
+            let position = manager_storage.positions(&event.token_id);
+
+            events.collect_positions.push(events::CollectPosition {
                 token_id: event.token_id.to_string(),
-                liquidity: event.liquidity.to_string(),
-                withdrawn_token0: event.amount0.to_decimal(pool.token0().decimals).to_string(),
-                withdrawn_token1: event.amount1.to_decimal(pool.token1().decimals).to_string(),
-                fee_growth_inside0_last_x128,
-                fee_growth_inside1_last_x128,
+                collected_fees_token0: event.amount0.to_decimal(pool.token0_ref().decimals).to_string(),
+                collected_fees_token1: event.amount1.to_decimal(pool.token1_ref().decimals).to_string(),
+                fee_growth_inside0_last_x128: position.fee_growth_inside0_last_x128().map(|(_, v)| v.to_string()),
+                fee_growth_inside1_last_x128: position.fee_growth_inside1_last_x128().map(|(_, v)| v.to_string()),
                 log_ordinal: log.ordinal,
             });
-        } else if let Some(event) = abi::positionmanager::events::Collect::match_and_decode(log) {
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside0last_x128()
-            {
-                fee_growth_inside0_last_x128 = Some(new_value.to_string());
-            }
-
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside1last_x128()
-            {
-                fee_growth_inside1_last_x128 = Some(new_value.to_string());
-            }
-            collect_positions.push(events::CollectPosition {
+        } else if let Some(event) = abi::positionmanager::events::Approval::match_and_decode(log) {
+            events.approval_positions.push(events::ApprovalPosition {
                 token_id: event.token_id.to_string(),
-                collected_fees_token0: event.amount0.to_decimal(pool.token0().decimals).to_string(),
-                collected_fees_token1: event.amount1.to_decimal(pool.token1().decimals).to_string(),
-                fee_growth_inside0_last_x128,
-                fee_growth_inside1_last_x128,
+                owner: Hex(&event.owner).to_string(),
+                approved: Hex(&event.approved).to_string(),
                 log_ordinal: log.ordinal,
             });
-        } else if let Some(event) = abi::positionmanager::events::Transfer::match_and_decode(log) {
-            transfer_positions.push(events::TransferPosition {
-                token_id: event.token_id.to_string(),
-                owner: Hex(&event.to).to_string(),
+        } else if let Some(event) = abi::positionmanager::events::ApprovalForAll::match_and_decode(log) {
+            events.operator_approvals.push(events::OperatorApproval {
+                owner: Hex(&event.owner).to_string(),
+                operator: Hex(&event.operator).to_string(),
+                approved: event.approved,
                 log_ordinal: log.ordinal,
             });
+        } else {
+            continue;
+        }
+
+        events.transactions.push(utils::load_transaction(
+            block_number,
+            timestamp_seconds,
+            log.ordinal,
+            transaction_trace,
+        ));
+    }
+}
+
+// The pool of a position is only written in the manager storage when the position is
+// created, and its pool key when the manager sees the pool for the first time. Otherwise
+// the pool is the one the manager frame called into to mint, burn or collect.
+fn resolve_position_pool(
+    token_id: &BigInt,
+    manager_storage: &PositionManagerStorage,
+    call: &Call,
+    transaction_trace: &TransactionTrace,
+    pools_store: &StoreGetProto<Pool>,
+) -> Option<Pool> {
+    let mut pool_address = None;
+
+    if let Some((_, pool_id)) = manager_storage.positions(token_id).pool_id() {
+        let pool_key = manager_storage.pool_id_to_pool_key(&pool_id);
+        if let (Some((_, token0)), Some((_, token1)), Some((_, fee))) =
+            (pool_key.token0(), pool_key.token1(), pool_key.fee())
+        {
+            pool_address = Some(Hex(utils::compute_pool_address(&token0, &token1, &fee)).to_string());
         }
     }
+
+    if pool_address.is_none() {
+        pool_address = transaction_trace
+            .calls
+            .iter()
+            .filter(|child| child.parent_index == call.index && !child.state_reverted)
+            .map(|child| Hex(&child.address).to_string())
+            .find(|address| pools_store.has_last(format!("pool:{address}")));
+    }
+
+    let pool = pools_store.get_last(format!("pool:{}", pool_address?))?;
+    if !pool.should_handle_mint_and_burn() {
+        return None;
+    }
+
+    Some(pool)
 }
 
 // pub fn extract_flashes(flashes: &mut Vec<events::Flash>, log: &Log) {
@@ -547,3 +666,64 @@ fn extract_positions(
 //         });
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use crate::filtering::{tick_word_position, written_observation_indexes};
+    use crate::storage::uniswap_v3_pool::UniswapPoolStorage;
+    use substreams::hex;
+    use substreams_ethereum::pb::eth::v2::StorageChange;
+
+    #[test]
+    fn tick_word_positions() {
+        assert_eq!(0, tick_word_position(0, 60));
+        assert_eq!(0, tick_word_position(15359, 60));
+        assert_eq!(1, tick_word_position(15360, 60));
+        assert_eq!(-1, tick_word_position(-60, 60));
+        assert_eq!(-1, tick_word_position(-1, 10));
+        assert_eq!(-58, tick_word_position(-887220, 60));
+    }
+
+    #[test]
+    fn observation_indexes_from_storage_keys() {
+        let pool = hex!("8ad599c3a0ff1de082011efddc58f1908eb6e6d8").to_vec();
+        let change = |address: &Vec<u8>, key: [u8; 32]| StorageChange {
+            address: address.clone(),
+            key: key.to_vec(),
+            old_value: vec![0u8; 32],
+            new_value: vec![1u8; 32],
+            ordinal: 0,
+        };
+        let storage_changes = vec![
+            // slot0
+            change(
+                &pool,
+                hex!("0000000000000000000000000000000000000000000000000000000000000000"),
+            ),
+            change(
+                &pool,
+                hex!("0000000000000000000000000000000000000000000000000000000000000009"),
+            ),
+            change(
+                &pool,
+                hex!("0000000000000000000000000000000000000000000000000000000000000008"),
+            ),
+            change(
+                &pool,
+                hex!("0000000000000000000000000000000000000000000000000000000000000009"),
+            ),
+            // a tick of the ticks mapping
+            change(
+                &pool,
+                hex!("290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"),
+            ),
+            change(
+                &hex!("88e6a0c2ddd26feeb64f039a2c41296fcb3f5640").to_vec(),
+                hex!("000000000000000000000000000000000000000000000000000000000000000a"),
+            ),
+        ];
+        let storage = UniswapPoolStorage::new(&storage_changes, &pool);
+
+        assert_eq!(vec![0, 1], written_observation_indexes(&storage));
+    }
+}
@@ -0,0 +1,419 @@
+use crate::pb::uniswap::events::PoolSqrtPrice;
+use crate::pb::uniswap::{GapFilledPoolWindow, GapFilledTokenWindow, Pool};
+use crate::price;
+use crate::windows::{Window, WindowKind};
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::{BigDecimal, BigInt};
+use substreams::store::{
+    DeltaInt64, Deltas, StoreGet, StoreGetBigDecimal, StoreGetBigInt, StoreGetInt64, StoreGetProto, StoreGetString,
+};
+
+// Windows starting with this block, seen from the time ids of the previous block kept by
+// store_window_time_ids. Nothing starts on the first block, there is no window before it.
+pub fn starting_windows(kinds: &[WindowKind], window_time_ids_deltas: &Deltas<DeltaInt64>) -> Vec<Window> {
+    window_time_ids_deltas
+        .deltas
+        .iter()
+        .filter(|delta| delta.operation == Operation::Update && delta.old_value != delta.new_value)
+        .map(|delta| Window {
+            kind: WindowKind::from_name(&delta.key),
+            time_id: delta.new_value,
+        })
+        .filter(|window| kinds.contains(&window.kind))
+        .collect()
+}
+
+// Addresses set in store_tracked_addresses under `kind`, indexed from 1 up to the count of
+// store_tracked_address_counts as of the ordinal
+pub fn tracked_addresses(
+    tracked_addresses_store: &StoreGetString,
+    tracked_address_counts_store: &StoreGetInt64,
+    ord: u64,
+    kind: &str,
+) -> Vec<String> {
+    let count = tracked_address_counts_store.get_at(ord, kind).unwrap_or(0);
+    (1..=count)
+        .filter_map(|index| tracked_addresses_store.get_at(ord, format!("{kind}:{index}")))
+        .collect()
+}
+
+// Tokens of the pool seen for the first time with it
+pub fn new_tokens(pool: &Pool, tokens_store: &StoreGetInt64) -> Vec<String> {
+    [pool.token0_ref().address(), pool.token1_ref().address()]
+        .into_iter()
+        .filter(|token_addr| tokens_store.get_at(pool.log_ordinal, format!("token:{token_addr}")) == Some(1))
+        .cloned()
+        .collect()
+}
+
+// Tables of the window kinds given through the params, graph_out leaving their windows
+// to the gap filled ones. Without params nothing is gap filled.
+pub fn gap_filled_tables(kinds: &[WindowKind]) -> Vec<String> {
+    kinds
+        .iter()
+        .flat_map(|kind| [kind.table("Pool"), kind.table("Token")])
+        .collect()
+}
+
+// Values the gap filled windows are made of, as of an ordinal
+pub trait WindowValues {
+    fn pool(&self, pool_address: &str) -> Option<Pool>;
+    fn pool_sqrt_price(&self, ord: u64, pool_address: &str) -> Option<PoolSqrtPrice>;
+    fn pool_liquidity(&self, ord: u64, pool_address: &str) -> Option<BigInt>;
+    // value of store_prices
+    fn price(&self, ord: u64, key: &str) -> Option<BigDecimal>;
+    fn token_tvl(&self, ord: u64, token_address: &str) -> Option<BigDecimal>;
+    // value of store_derived_tvl
+    fn derived_tvl(&self, ord: u64, key: &str) -> Option<BigDecimal>;
+    // value of store_pool_balance_tvl
+    fn pool_balance_tvl(&self, ord: u64, key: &str) -> Option<BigDecimal>;
+    fn token_price_usd(&self, ord: u64, token_address: &str) -> BigDecimal;
+}
+
+// Stores the values of the gap filled windows are read from
+pub struct WindowStores<'a> {
+    pub pools: &'a StoreGetProto<Pool>,
+    pub pool_sqrt_prices: &'a StoreGetProto<PoolSqrtPrice>,
+    pub pool_liquidities: &'a StoreGetBigInt,
+    pub prices: &'a StoreGetBigDecimal,
+    pub token_tvl: &'a StoreGetBigDecimal,
+    pub derived_tvl: &'a StoreGetBigDecimal,
+    pub eth_prices: &'a StoreGetBigDecimal,
+    pub pool_balance_tvl: &'a StoreGetBigDecimal,
+}
+
+impl WindowValues for WindowStores<'_> {
+    fn pool(&self, pool_address: &str) -> Option<Pool> {
+        self.pools.get_last(format!("pool:{pool_address}"))
+    }
+
+    fn pool_sqrt_price(&self, ord: u64, pool_address: &str) -> Option<PoolSqrtPrice> {
+        self.pool_sqrt_prices.get_at(ord, format!("pool:{pool_address}"))
+    }
+
+    fn pool_liquidity(&self, ord: u64, pool_address: &str) -> Option<BigInt> {
+        self.pool_liquidities.get_at(ord, format!("pool:{pool_address}"))
+    }
+
+    fn price(&self, ord: u64, key: &str) -> Option<BigDecimal> {
+        self.prices.get_at(ord, key)
+    }
+
+    fn token_tvl(&self, ord: u64, token_address: &str) -> Option<BigDecimal> {
+        self.token_tvl.get_at(ord, format!("token:{token_address}"))
+    }
+
+    fn derived_tvl(&self, ord: u64, key: &str) -> Option<BigDecimal> {
+        self.derived_tvl.get_at(ord, key)
+    }
+
+    fn pool_balance_tvl(&self, ord: u64, key: &str) -> Option<BigDecimal> {
+        self.pool_balance_tvl.get_at(ord, key)
+    }
+
+    fn token_price_usd(&self, ord: u64, token_address: &str) -> BigDecimal {
+        price::get_token_price_usd(self.eth_prices, ord, &token_address.to_string())
+    }
+}
+
+// The liquidity, prices, TVL and balances of the pool as of the ordinal, zero when the pool has none yet
+pub fn pool_window<V: WindowValues>(
+    window: &Window,
+    pool_address: &String,
+    ord: u64,
+    values: &V,
+) -> GapFilledPoolWindow {
+    let get_balance = |field: &str| {
+        values
+            .pool_balance_tvl(ord, &format!("pool:{pool_address}:{field}"))
+            .unwrap_or_else(BigDecimal::zero)
+            .to_string()
+    };
+
+    let mut pool_window = GapFilledPoolWindow {
+        table: window.kind.table("Pool"),
+        time_id: window.time_id,
+        pool_address: pool_address.clone(),
+        liquidity: values
+            .pool_liquidity(ord, pool_address)
+            .unwrap_or_else(BigInt::zero)
+            .to_string(),
+        sqrt_price: "0".to_string(),
+        tick: "0".to_string(),
+        token0_price: "0".to_string(),
+        token1_price: "0".to_string(),
+        total_value_locked_usd: values
+            .derived_tvl(ord, &format!("pool:{pool_address}:totalValueLockedUSD"))
+            .unwrap_or_else(BigDecimal::zero)
+            .to_string(),
+        balance_token0: get_balance("balanceToken0"),
+        balance_token1: get_balance("balanceToken1"),
+        balance_total_value_locked_usd: get_balance("balanceTotalValueLockedUSD"),
+    };
+
+    if let Some(pool_sqrt_price) = values.pool_sqrt_price(ord, pool_address) {
+        pool_window.sqrt_price = pool_sqrt_price.sqrt_price;
+        pool_window.tick = pool_sqrt_price.tick;
+    }
+
+    if let Some(pool) = values.pool(pool_address) {
+        let token0_addr = pool.token0_ref().address();
+        let token1_addr = pool.token1_ref().address();
+        if let Some(price) = values.price(ord, &format!("pool:{pool_address}:{token0_addr}:token0")) {
+            pool_window.token0_price = price.to_string();
+        }
+        if let Some(price) = values.price(ord, &format!("pool:{pool_address}:{token1_addr}:token1")) {
+            pool_window.token1_price = price.to_string();
+        }
+    }
+
+    pool_window
+}
+
+// The TVL and USD price of the token as of the ordinal, zero when the token has none yet
+pub fn token_window<V: WindowValues>(
+    window: &Window,
+    token_address: &String,
+    ord: u64,
+    values: &V,
+) -> GapFilledTokenWindow {
+    GapFilledTokenWindow {
+        table: window.kind.table("Token"),
+        time_id: window.time_id,
+        token_address: token_address.clone(),
+        total_value_locked: values
+            .token_tvl(ord, token_address)
+            .unwrap_or_else(BigDecimal::zero)
+            .to_string(),
+        total_value_locked_usd: values
+            .derived_tvl(ord, &format!("token:{token_address}:totalValueLockedUSD"))
+            .unwrap_or_else(BigDecimal::zero)
+            .to_string(),
+        price_usd: values.token_price_usd(ord, token_address).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gap_fill::{gap_filled_tables, pool_window, starting_windows, token_window, WindowValues};
+    use crate::pb::uniswap::events::PoolSqrtPrice;
+    use crate::pb::uniswap::{Erc20Token, Pool};
+    use crate::windows;
+    use crate::windows::{Window, WindowKind, ALL_WINDOW_KINDS};
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use substreams::pb::substreams::store_delta::Operation;
+    use substreams::scalar::{BigDecimal, BigInt};
+    use substreams::store::{DeltaInt64, Deltas};
+
+    // 2021-05-05T00:00:00Z
+    const MIDNIGHT: i64 = 1620172800;
+
+    // deltas of store_window_time_ids between a block and the previous one, None on the
+    // first block
+    fn time_id_deltas(previous_timestamp: Option<i64>, timestamp: i64) -> Deltas<DeltaInt64> {
+        let deltas = windows::current_windows(&ALL_WINDOW_KINDS, timestamp)
+            .into_iter()
+            .map(|window| {
+                let previous_time_id =
+                    previous_timestamp.map(|previous_timestamp| window.kind.time_id(previous_timestamp));
+                DeltaInt64 {
+                    operation: match previous_time_id {
+                        Some(_) => Operation::Update,
+                        None => Operation::Create,
+                    },
+                    ordinal: 0,
+                    key: window.kind.name().to_string(),
+                    old_value: previous_time_id.unwrap_or_default(),
+                    new_value: window.time_id,
+                }
+            })
+            .collect();
+        Deltas { deltas }
+    }
+
+    fn kinds(windows: Vec<Window>) -> Vec<WindowKind> {
+        windows.into_iter().map(|window| window.kind).collect()
+    }
+
+    #[test]
+    fn test_starting_windows_on_boundaries() {
+        // within the same hour
+        let deltas = time_id_deltas(Some(MIDNIGHT + 60), MIDNIGHT + 72);
+        assert!(starting_windows(&ALL_WINDOW_KINDS, &deltas).is_empty());
+
+        // first block of an hour
+        let deltas = time_id_deltas(Some(MIDNIGHT + 3599), MIDNIGHT + 3600);
+        let starting = starting_windows(&ALL_WINDOW_KINDS, &deltas);
+        assert_eq!(1, starting.len());
+        assert_eq!(WindowKind::Hour.time_id(MIDNIGHT + 3600), starting[0].time_id);
+        assert_eq!(vec![WindowKind::Hour], kinds(starting));
+
+        // first block of a day, which is also the first one of an hour
+        let deltas = time_id_deltas(Some(MIDNIGHT - 12), MIDNIGHT);
+        let starting = kinds(starting_windows(&ALL_WINDOW_KINDS, &deltas));
+        assert!(starting.contains(&WindowKind::Hour));
+        assert!(starting.contains(&WindowKind::Day));
+        assert!(!starting.contains(&WindowKind::Week));
+        assert!(!starting.contains(&WindowKind::Month));
+
+        // only the kinds given through the params
+        let starting = kinds(starting_windows(&[WindowKind::Day], &deltas));
+        assert_eq!(vec![WindowKind::Day], starting);
+    }
+
+    #[test]
+    fn test_no_starting_windows_on_first_block() {
+        let deltas = time_id_deltas(None, MIDNIGHT);
+        assert!(starting_windows(&ALL_WINDOW_KINDS, &deltas).is_empty());
+    }
+
+    #[test]
+    fn test_gap_fill_off_by_default() {
+        let kinds = windows::kinds_from_params("");
+        assert!(kinds.is_empty());
+        assert!(gap_filled_tables(&kinds).is_empty());
+
+        let kinds = windows::kinds_from_params("day&hour&day");
+        assert_eq!(
+            vec!["PoolDayData", "TokenDayData", "PoolHourData", "TokenHourData"],
+            gap_filled_tables(&kinds)
+        );
+    }
+
+    // values left by the last block the pool and token traded in
+    #[derive(Default)]
+    struct LastValues {
+        pools: HashMap<String, Pool>,
+        pool_sqrt_prices: HashMap<String, PoolSqrtPrice>,
+        big_ints: HashMap<String, BigInt>,
+        big_decimals: HashMap<String, BigDecimal>,
+    }
+
+    impl WindowValues for LastValues {
+        fn pool(&self, pool_address: &str) -> Option<Pool> {
+            self.pools.get(pool_address).cloned()
+        }
+
+        fn pool_sqrt_price(&self, _ord: u64, pool_address: &str) -> Option<PoolSqrtPrice> {
+            self.pool_sqrt_prices.get(pool_address).cloned()
+        }
+
+        fn pool_liquidity(&self, _ord: u64, pool_address: &str) -> Option<BigInt> {
+            self.big_ints.get(pool_address).cloned()
+        }
+
+        fn price(&self, _ord: u64, key: &str) -> Option<BigDecimal> {
+            self.big_decimals.get(key).cloned()
+        }
+
+        fn token_tvl(&self, _ord: u64, token_address: &str) -> Option<BigDecimal> {
+            self.big_decimals.get(&format!("token:{token_address}")).cloned()
+        }
+
+        fn derived_tvl(&self, _ord: u64, key: &str) -> Option<BigDecimal> {
+            self.big_decimals.get(key).cloned()
+        }
+
+        fn pool_balance_tvl(&self, _ord: u64, key: &str) -> Option<BigDecimal> {
+            self.big_decimals.get(key).cloned()
+        }
+
+        fn token_price_usd(&self, _ord: u64, token_address: &str) -> BigDecimal {
+            self.big_decimals
+                .get(&format!("token:{token_address}:usd"))
+                .cloned()
+                .unwrap_or_else(BigDecimal::zero)
+        }
+    }
+
+    fn last_values() -> LastValues {
+        let mut values = LastValues::default();
+        values.pools.insert(
+            "pool".to_string(),
+            Pool {
+                address: "pool".to_string(),
+                token0: Some(Erc20Token {
+                    address: "token0".to_string(),
+                    ..Default::default()
+                }),
+                token1: Some(Erc20Token {
+                    address: "token1".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        values.pool_sqrt_prices.insert(
+            "pool".to_string(),
+            PoolSqrtPrice {
+                sqrt_price: "79228162514264337593543950336".to_string(),
+                tick: "-12".to_string(),
+                ..Default::default()
+            },
+        );
+        values.big_ints.insert("pool".to_string(), BigInt::from(1000));
+        for (key, value) in [
+            ("pool:pool:token0:token0", "2"),
+            ("pool:pool:token1:token1", "0.5"),
+            ("pool:pool:totalValueLockedUSD", "300"),
+            ("pool:pool:balanceToken0", "10"),
+            ("pool:pool:balanceToken1", "20"),
+            ("pool:pool:balanceTotalValueLockedUSD", "310"),
+            ("token:token0", "40"),
+            ("token:token0:totalValueLockedUSD", "80"),
+            ("token:token0:usd", "2"),
+        ] {
+            values
+                .big_decimals
+                .insert(key.to_string(), BigDecimal::from_str(value).unwrap());
+        }
+        values
+    }
+
+    #[test]
+    fn test_pool_window_carries_last_values() {
+        let window = Window {
+            kind: WindowKind::Day,
+            time_id: WindowKind::Day.time_id(MIDNIGHT),
+        };
+        let carried = pool_window(&window, &"pool".to_string(), 0, &last_values());
+
+        assert_eq!("PoolDayData", carried.table);
+        assert_eq!(window.time_id, carried.time_id);
+        assert_eq!("1000", carried.liquidity);
+        assert_eq!("79228162514264337593543950336", carried.sqrt_price);
+        assert_eq!("-12", carried.tick);
+        assert_eq!("2", carried.token0_price);
+        assert_eq!("0.5", carried.token1_price);
+        assert_eq!("300", carried.total_value_locked_usd);
+        assert_eq!("10", carried.balance_token0);
+        assert_eq!("20", carried.balance_token1);
+        assert_eq!("310", carried.balance_total_value_locked_usd);
+
+        // a pool without any value yet
+        let empty = pool_window(&window, &"other".to_string(), 0, &last_values());
+        assert_eq!("0", empty.liquidity);
+        assert_eq!("0", empty.sqrt_price);
+        assert_eq!("0", empty.token0_price);
+        assert_eq!("0", empty.total_value_locked_usd);
+    }
+
+    #[test]
+    fn test_token_window_carries_last_values() {
+        let window = Window {
+            kind: WindowKind::Hour,
+            time_id: WindowKind::Hour.time_id(MIDNIGHT),
+        };
+        let carried = token_window(&window, &"token0".to_string(), 0, &last_values());
+
+        assert_eq!("TokenHourData", carried.table);
+        assert_eq!("40", carried.total_value_locked);
+        assert_eq!("80", carried.total_value_locked_usd);
+        assert_eq!("2", carried.price_usd);
+
+        let empty = token_window(&window, &"token1".to_string(), 0, &last_values());
+        assert_eq!("0", empty.total_value_locked);
+        assert_eq!("0", empty.price_usd);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::pb::uniswap::events::PoolSqrtPrice;
+use crate::pb::uniswap::PriceGuardFlag;
+use std::collections::BTreeMap;
+use std::ops::{Div, Mul, Sub};
+use std::str::FromStr;
+use substreams::scalar::{BigDecimal, BigInt};
+use substreams::store::{StoreGet, StoreGetBigInt, StoreGetInt64, StoreGetProto};
+
+// largest price move of a pool within a block before its price updates are flagged
+const MAX_PRICE_MOVE: &str = "0.1";
+// largest price move when the active liquidity of the pool has dropped below half of
+// what it was at the end of the previous block, which makes the price cheaper to move
+const MAX_PRICE_MOVE_THIN_LIQUIDITY: &str = "0.02";
+// blocks following a flagged update during which the prices of the pool stay out of the
+// token prices: the end of the previous block being the reference of the guard, a price
+// has to hold that long before it is trusted
+pub const QUARANTINE_BLOCKS: u64 = 5;
+
+// Compares every price update of a pool with its price at the end of the previous block,
+// read from the stores at ordinal 0, before any change of the current block. Updates
+// leaving the pool without a price are not moves, neither from nor to such a state.
+pub fn extract_price_guard_flags(
+    pool_sqrt_prices: &Vec<PoolSqrtPrice>,
+    pool_sqrt_price_store: &StoreGetProto<PoolSqrtPrice>,
+    pool_liquidities_store: &StoreGetBigInt,
+    block_number: u64,
+    timestamp: u64,
+) -> Vec<PriceGuardFlag> {
+    let mut flags = vec![];
+    let mut references: BTreeMap<&String, Option<(BigDecimal, BigInt)>> = BTreeMap::new();
+
+    for pool_sqrt_price in pool_sqrt_prices {
+        if pool_sqrt_price.initialized || pool_sqrt_price.price_unavailable {
+            continue;
+        }
+        let ord = pool_sqrt_price.ordinal;
+        let pool_address = &pool_sqrt_price.pool_address;
+
+        let reference = references.entry(pool_address).or_insert_with(|| {
+            let reference_sqrt_price = pool_sqrt_price_store.get_at(0, format!("pool:{pool_address}"))?;
+            if reference_sqrt_price.price_unavailable {
+                return None;
+            }
+            let reference_liquidity = pool_liquidities_store
+                .get_at(0, format!("pool:{pool_address}"))
+                .unwrap_or_default();
+            Some((
+                BigDecimal::try_from(reference_sqrt_price.sqrt_price).unwrap(),
+                reference_liquidity,
+            ))
+        });
+        let (reference_sqrt_price, reference_liquidity) = match reference {
+            Some(reference) if !reference.0.is_zero() => reference,
+            _ => continue,
+        };
+
+        let sqrt_price = BigDecimal::try_from(pool_sqrt_price.sqrt_price.clone()).unwrap();
+        let sqrt_price_ratio = sqrt_price.div(reference_sqrt_price.clone());
+        let deviation = sqrt_price_ratio
+            .clone()
+            .mul(sqrt_price_ratio)
+            .sub(BigDecimal::one())
+            .absolute();
+
+        let liquidity = pool_liquidities_store
+            .get_at(ord, format!("pool:{pool_address}"))
+            .unwrap_or_default();
+        let max_deviation = if liquidity.clone().mul(BigInt::from(2)) < *reference_liquidity {
+            BigDecimal::from_str(MAX_PRICE_MOVE_THIN_LIQUIDITY).unwrap()
+        } else {
+            BigDecimal::from_str(MAX_PRICE_MOVE).unwrap()
+        };
+        if deviation <= max_deviation {
+            continue;
+        }
+
+        flags.push(PriceGuardFlag {
+            pool_address: pool_address.clone(),
+            ordinal: ord,
+            reference_sqrt_price: reference_sqrt_price.to_bigint().to_string(),
+            sqrt_price: pool_sqrt_price.sqrt_price.clone(),
+            deviation: deviation.to_string(),
+            max_deviation: max_deviation.to_string(),
+            reference_liquidity: reference_liquidity.to_string(),
+            liquidity: liquidity.to_string(),
+            block_number,
+            timestamp,
+        });
+    }
+
+    flags
+}
+
+// Last block of the quarantine started by a flagged update
+pub fn quarantined_until(flag: &PriceGuardFlag) -> i64 {
+    (flag.block_number + QUARANTINE_BLOCKS) as i64
+}
+
+// Whether the prices of a pool are kept out of the token prices at the given ordinal,
+// from a flagged update until QUARANTINE_BLOCKS blocks later
+pub fn is_quarantined(
+    price_guard_quarantines_store: &StoreGetInt64,
+    pool_address: &str,
+    block_number: u64,
+    ordinal: u64,
+) -> bool {
+    in_quarantine(
+        price_guard_quarantines_store.get_at(ordinal, format!("pool:{pool_address}")),
+        block_number,
+    )
+}
+
+fn in_quarantine(quarantined_until: Option<i64>, block_number: u64) -> bool {
+    quarantined_until.map_or(false, |quarantined_until| block_number as i64 <= quarantined_until)
+}
+
+// Keys of the token0 and token1 prices of a pool price update: the pool prices and the
+// pair prices of find_eth_per_token, or while the pool is quarantined, only its `flagged:`
+// prices which the pricing never reads
+pub fn price_keys(
+    pool_address: &str,
+    token0_addr: &str,
+    token1_addr: &str,
+    quarantined: bool,
+) -> (Vec<String>, Vec<String>) {
+    if quarantined {
+        return (
+            vec![format!("flagged:{pool_address}:{token0_addr}:token0")],
+            vec![format!("flagged:{pool_address}:{token1_addr}:token1")],
+        );
+    }
+
+    (
+        vec![
+            format!("pool:{pool_address}:{token0_addr}:token0"),
+            format!("pair:{token0_addr}:{token1_addr}"),
+        ],
+        vec![
+            format!("pool:{pool_address}:{token1_addr}:token1"),
+            format!("pair:{token1_addr}:{token0_addr}"),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::guard::{in_quarantine, price_keys, quarantined_until, QUARANTINE_BLOCKS};
+    use crate::pb::uniswap::PriceGuardFlag;
+
+    #[test]
+    fn test_quarantine_outlives_the_flagged_block() {
+        let flag = PriceGuardFlag {
+            block_number: 100,
+            ..Default::default()
+        };
+        let until = Some(quarantined_until(&flag));
+
+        assert!(!in_quarantine(None, 100));
+        assert!(in_quarantine(until, 100));
+        // a manipulated price held by the next blocks is still kept out of the token prices
+        assert!(in_quarantine(until, 101));
+        assert!(in_quarantine(until, 100 + QUARANTINE_BLOCKS));
+        assert!(!in_quarantine(until, 101 + QUARANTINE_BLOCKS));
+    }
+
+    #[test]
+    fn test_quarantined_price_keys() {
+        let (token0_keys, token1_keys) = price_keys("pool", "token0", "token1", false);
+        assert_eq!(vec!["pool:pool:token0:token0", "pair:token0:token1"], token0_keys);
+        assert_eq!(vec!["pool:pool:token1:token1", "pair:token1:token0"], token1_keys);
+
+        // none of the keys read by the pricing routes, the reference pools and the windows
+        let (token0_keys, token1_keys) = price_keys("pool", "token0", "token1", true);
+        assert_eq!(vec!["flagged:pool:token0:token0"], token0_keys);
+        assert_eq!(vec!["flagged:pool:token1:token1"], token1_keys);
+        for key in token0_keys.iter().chain(token1_keys.iter()) {
+            assert!(!key.starts_with("pool:") && !key.starts_with("pair:"));
+        }
+    }
+}
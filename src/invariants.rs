@@ -0,0 +1,371 @@
+use crate::pb::uniswap::events::pool_event::Type::Swap as SwapEvent;
+use crate::pb::uniswap::events::{PoolEvent, TickUpdated};
+use crate::pb::uniswap::{InvariantDiscrepancy, Pool};
+use crate::{abi, balances};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Sub;
+use substreams::scalar::BigInt;
+use substreams::store::{StoreGet, StoreGetBigInt};
+use substreams::Hex;
+use substreams_ethereum::pb::eth::v2::{Block, Log};
+use substreams_ethereum::Event;
+
+pub struct InvariantChecker<'a> {
+    pool_events: BTreeMap<u64, &'a PoolEvent>,
+    block_number: u64,
+    timestamp: u64,
+}
+
+impl<'a> InvariantChecker<'a> {
+    pub fn new(pool_events: &'a [PoolEvent], block_number: u64, timestamp: u64) -> Self {
+        InvariantChecker {
+            pool_events: pool_events.iter().map(|event| (event.log_ordinal, event)).collect(),
+            block_number,
+            timestamp,
+        }
+    }
+
+    // liquidityGross and liquidityNet summed by store_ticks_liquidities from the mints and
+    // burns against the tick read from the pool storage
+    pub fn tick_liquidities(
+        &self,
+        ticks_updated: &[TickUpdated],
+        ticks_liquidities_store: &StoreGetBigInt,
+    ) -> Vec<InvariantDiscrepancy> {
+        let mut discrepancies = vec![];
+
+        for tick in ticks_updated {
+            let pool_address = &tick.pool_address;
+            let tick_idx = &tick.idx;
+            for (check, field, storage_value) in [
+                ("tick_liquidity_gross", "liquidityGross", &tick.liquidity_gross),
+                ("tick_liquidity_net", "liquidityNet", &tick.liquidity_net),
+            ] {
+                if storage_value.is_empty() {
+                    continue;
+                }
+                let storage_value = BigInt::try_from(storage_value).unwrap();
+                let tracked_value = ticks_liquidities_store
+                    .get_at(tick.log_ordinal, format!("tick:{pool_address}:{tick_idx}:{field}"))
+                    .unwrap_or_default();
+                if storage_value == tracked_value {
+                    continue;
+                }
+
+                discrepancies.push(InvariantDiscrepancy {
+                    tick_idx: tick_idx.clone(),
+                    difference: storage_value.clone().sub(tracked_value.clone()).to_string(),
+                    storage_value: storage_value.to_string(),
+                    tracked_value: tracked_value.to_string(),
+                    ..self.discrepancy(check, pool_address, tick.log_ordinal)
+                });
+            }
+        }
+
+        discrepancies
+    }
+
+    // in-range liquidity emitted by the swaps against the one kept by store_pool_liquidities,
+    // which goes stale when a liquidity change of the pool is missed
+    pub fn pool_liquidities(&self, pool_liquidities_store: &StoreGetBigInt) -> Vec<InvariantDiscrepancy> {
+        let mut discrepancies = vec![];
+
+        for event in self.pool_events.values() {
+            let swap = match event.r#type.as_ref().unwrap() {
+                SwapEvent(swap) => swap,
+                _ => continue,
+            };
+            let pool_address = &event.pool_address;
+            let tracked_value = match pool_liquidities_store.get_at(event.log_ordinal, format!("pool:{pool_address}")) {
+                Some(liquidity) => liquidity,
+                None => continue,
+            };
+            let storage_value = BigInt::try_from(&swap.liquidity).unwrap();
+            if storage_value == tracked_value {
+                continue;
+            }
+
+            discrepancies.push(InvariantDiscrepancy {
+                difference: storage_value.clone().sub(tracked_value.clone()).to_string(),
+                storage_value: storage_value.to_string(),
+                tracked_value: tracked_value.to_string(),
+                ..self.discrepancy("pool_liquidity", pool_address, event.log_ordinal)
+            });
+        }
+
+        discrepancies
+    }
+
+    // change of the balanceOf storage of the tokens of a pool over each of its mints, burns,
+    // swaps and collects against the amounts of the event. The change is taken since the
+    // previous log of the pool in the transaction, so tokens sent to a pool without going
+    // through it, e.g. donations, show up with the event following them. Tokens keeping
+    // their balances elsewhere than in the first slots are not checked.
+    pub fn pool_balances<F>(&self, block: &Block, get_pool: F) -> Vec<InvariantDiscrepancy>
+    where
+        F: Fn(&str) -> Option<Pool>,
+    {
+        let mut discrepancies = vec![];
+
+        for trx in block.transactions() {
+            let mut last_pool_logs: HashMap<&Vec<u8>, u64> = HashMap::new();
+            for (log, _) in trx.logs_with_calls() {
+                let amounts = event_amounts(log);
+                if amounts.is_none() && !is_pool_log(log) {
+                    continue;
+                }
+                let pool_address = Hex(&log.address).to_string();
+                let pool = match get_pool(&pool_address) {
+                    Some(pool) => pool,
+                    None => continue,
+                };
+                let from_ordinal = last_pool_logs
+                    .insert(&log.address, log.ordinal)
+                    .unwrap_or(trx.begin_ordinal);
+                let amounts = match amounts {
+                    Some(amounts) => amounts,
+                    None => continue,
+                };
+
+                for (token, amount) in [pool.token0(), pool.token1()].iter().zip(amounts) {
+                    let token_address = &token.address;
+                    let delta = match balances::balance_delta(
+                        trx,
+                        &hex::decode(token_address).unwrap(),
+                        &log.address,
+                        from_ordinal,
+                        log.ordinal,
+                    ) {
+                        Some(delta) => delta,
+                        None => continue,
+                    };
+                    let storage_value = delta.to_decimal(token.decimals);
+                    let tracked_value = amount.to_decimal(token.decimals);
+                    if storage_value == tracked_value {
+                        continue;
+                    }
+
+                    discrepancies.push(InvariantDiscrepancy {
+                        token: token_address.clone(),
+                        difference: storage_value.clone().sub(tracked_value.clone()).to_string(),
+                        storage_value: storage_value.to_string(),
+                        tracked_value: tracked_value.to_string(),
+                        transaction_id: Hex(&trx.hash).to_string(),
+                        ..self.discrepancy("pool_balance", &pool_address, log.ordinal)
+                    });
+                }
+            }
+        }
+
+        discrepancies
+    }
+
+    fn discrepancy(&self, check: &str, pool_address: &str, ordinal: u64) -> InvariantDiscrepancy {
+        InvariantDiscrepancy {
+            check: check.to_string(),
+            pool_address: pool_address.to_string(),
+            transaction_id: self
+                .pool_events
+                .get(&ordinal)
+                .map(|event| event.transaction_id.clone())
+                .unwrap_or_default(),
+            ordinal,
+            block_number: self.block_number,
+            timestamp: self.timestamp,
+            ..Default::default()
+        }
+    }
+}
+
+// amounts of the pool tokens moved in, positive, or out, negative, of the pool by the event
+// of the log, none for the logs not moving them or not tracking the amounts
+fn event_amounts(log: &Log) -> Option<[BigInt; 2]> {
+    if let Some(mint) = abi::pool::events::Mint::match_and_decode(log) {
+        Some([mint.amount0, mint.amount1])
+    } else if abi::pool::events::Burn::match_log(log) {
+        // burnt amounts are owed to the position until collected
+        Some([BigInt::zero(), BigInt::zero()])
+    } else if let Some(swap) = abi::pool::events::Swap::match_and_decode(log) {
+        Some([swap.amount0, swap.amount1])
+    } else if let Some(collect) = abi::pool::events::Collect::match_and_decode(log) {
+        Some([collect.amount0.neg(), collect.amount1.neg()])
+    } else {
+        None
+    }
+}
+
+// the other logs of a pool moving its tokens, which end the balance change of the event before
+fn is_pool_log(log: &Log) -> bool {
+    abi::pool::events::Flash::match_log(log) || abi::pool::events::CollectProtocol::match_log(log)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::invariants::InvariantChecker;
+    use crate::pb::uniswap::{Erc20Token, Pool};
+    use crate::storage::utils;
+    use ethabi::{ethereum_types::U256, Token};
+    use substreams::hex;
+    use substreams::scalar::BigInt;
+    use substreams_ethereum::pb::eth::v2::{Block, Call, Log, StorageChange, TransactionTrace};
+    use tiny_keccak::{Hasher, Keccak};
+
+    const POOL: [u8; 20] = hex!("8ad599c3a0ff1de082011efddc58f1908eb6e6d8");
+    const TOKEN0: [u8; 20] = hex!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+    const TOKEN1: [u8; 20] = hex!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+    const OWNER: [u8; 20] = hex!("c36442b4a4522e871399cd717abdd847ab11fe88");
+
+    #[test]
+    fn mint_burn_and_collect_match_balances() {
+        let block = block(
+            vec![
+                balance_change(TOKEN0, 1, 0, 100),
+                balance_change(TOKEN1, 2, 0, 200),
+                balance_change(TOKEN0, 6, 100, 40),
+                balance_change(TOKEN1, 7, 200, 80),
+            ],
+            vec![mint_log(3, 100, 200), burn_log(5, 60, 120), collect_log(8, 60, 120)],
+        );
+        let checker = InvariantChecker::new(&[], 1, 1);
+
+        assert!(checker.pool_balances(&block, |_| Some(pool())).is_empty());
+    }
+
+    #[test]
+    fn donation_shows_with_next_event() {
+        let block = block(
+            vec![
+                balance_change(TOKEN0, 1, 0, 100),
+                balance_change(TOKEN1, 2, 0, 200),
+                // donation of token0 between the mint and the burn
+                balance_change(TOKEN0, 4, 100, 150),
+            ],
+            vec![mint_log(3, 100, 200), burn_log(5, 0, 0)],
+        );
+        let checker = InvariantChecker::new(&[], 1, 1);
+
+        let discrepancies = checker.pool_balances(&block, |_| Some(pool()));
+        assert_eq!(1, discrepancies.len());
+        assert_eq!("pool_balance", discrepancies[0].check);
+        assert_eq!(hex::encode(TOKEN0), discrepancies[0].token);
+        assert_eq!("50", discrepancies[0].storage_value);
+        assert_eq!("0", discrepancies[0].tracked_value);
+        assert_eq!("50", discrepancies[0].difference);
+        assert_eq!(5, discrepancies[0].ordinal);
+    }
+
+    #[test]
+    fn unknown_pools_are_not_checked() {
+        let block = block(vec![balance_change(TOKEN0, 1, 0, 1)], vec![mint_log(3, 100, 200)]);
+        let checker = InvariantChecker::new(&[], 1, 1);
+
+        assert!(checker.pool_balances(&block, |_| None).is_empty());
+    }
+
+    fn pool() -> Pool {
+        let token = |address: [u8; 20]| Erc20Token {
+            address: hex::encode(address),
+            decimals: 0,
+            ..Default::default()
+        };
+        Pool {
+            address: hex::encode(POOL),
+            token0: Some(token(TOKEN0)),
+            token1: Some(token(TOKEN1)),
+            ..Default::default()
+        }
+    }
+
+    fn block(storage_changes: Vec<StorageChange>, logs: Vec<Log>) -> Block {
+        Block {
+            transaction_traces: vec![TransactionTrace {
+                hash: vec![1u8; 32],
+                status: 1,
+                calls: vec![Call {
+                    storage_changes,
+                    logs,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    // balanceOf(pool) of the token at slot 3, as WETH keeps it
+    fn balance_change(token: [u8; 20], ordinal: u64, old_balance: u64, new_balance: u64) -> StorageChange {
+        let holder_key = utils::left_pad(&POOL.to_vec(), 0);
+        let balance_slot = utils::left_pad_from_bigint(&BigInt::from(3));
+        StorageChange {
+            address: token.to_vec(),
+            key: utils::calc_map_slot(&holder_key, &balance_slot).to_vec(),
+            old_value: utils::left_pad_from_bigint(&BigInt::from(old_balance)).to_vec(),
+            new_value: utils::left_pad_from_bigint(&BigInt::from(new_balance)).to_vec(),
+            ordinal,
+        }
+    }
+
+    fn mint_log(ordinal: u64, amount0: u64, amount1: u64) -> Log {
+        pool_log(
+            ordinal,
+            "Mint(address,address,int24,int24,uint128,uint256,uint256)",
+            vec![address_topic(OWNER), int24_topic(-60), int24_topic(60)],
+            vec![
+                Token::Address(OWNER.into()),
+                Token::Uint(U256::from(1000)),
+                Token::Uint(U256::from(amount0)),
+                Token::Uint(U256::from(amount1)),
+            ],
+        )
+    }
+
+    fn burn_log(ordinal: u64, amount0: u64, amount1: u64) -> Log {
+        pool_log(
+            ordinal,
+            "Burn(address,int24,int24,uint128,uint256,uint256)",
+            vec![address_topic(OWNER), int24_topic(-60), int24_topic(60)],
+            vec![
+                Token::Uint(U256::from(600)),
+                Token::Uint(U256::from(amount0)),
+                Token::Uint(U256::from(amount1)),
+            ],
+        )
+    }
+
+    fn collect_log(ordinal: u64, amount0: u64, amount1: u64) -> Log {
+        pool_log(
+            ordinal,
+            "Collect(address,address,int24,int24,uint128,uint128)",
+            vec![address_topic(OWNER), int24_topic(-60), int24_topic(60)],
+            vec![
+                Token::Address(OWNER.into()),
+                Token::Uint(U256::from(amount0)),
+                Token::Uint(U256::from(amount1)),
+            ],
+        )
+    }
+
+    fn pool_log(ordinal: u64, signature: &str, mut topics: Vec<Vec<u8>>, data: Vec<Token>) -> Log {
+        let mut topic = [0u8; 32];
+        let mut hasher = Keccak::v256();
+        hasher.update(signature.as_bytes());
+        hasher.finalize(&mut topic);
+        topics.insert(0, topic.to_vec());
+
+        Log {
+            address: POOL.to_vec(),
+            topics,
+            data: ethabi::encode(&data),
+            ordinal,
+            ..Default::default()
+        }
+    }
+
+    fn address_topic(address: [u8; 20]) -> Vec<u8> {
+        utils::left_pad(&address.to_vec(), 0).to_vec()
+    }
+
+    fn int24_topic(value: i64) -> Vec<u8> {
+        utils::left_pad_from_bigint(&BigInt::from(value)).to_vec()
+    }
+}
@@ -0,0 +1,459 @@
+use crate::diagnostics::SkippedItems;
+use crate::errors::{self, DataError};
+use crate::math;
+use crate::pb::uniswap::events::pool_event::Type::{Burn as BurnEvent, Mint as MintEvent, Swap as SwapEvent};
+use crate::pb::uniswap::events::PoolEvent;
+use crate::pb::uniswap::{Events, JitLiquidity};
+use crate::utils::NON_FUNGIBLE_POSITION_MANAGER;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Add, Div, Mul};
+use substreams::scalar::BigDecimal;
+use substreams::Hex;
+
+const MODULE: &str = "map_jit_liquidity";
+
+// Looks, pool by pool, for a mint followed by a burn of the same liquidity and position
+// with at least one swap in between. The fees are the share of the swap inputs earned by
+// the minted liquidity, using the active liquidity reported by each swap when its tick is
+// within the range of the position. The USD prices of the tokens are given by
+// token_price_usd, by ordinal and token address. A JIT liquidity whose amounts don't parse is
+// skipped.
+pub fn extract_jit_liquidities<P>(
+    pool_events: &Vec<PoolEvent>,
+    position_manager_events: &Events,
+    token_price_usd: P,
+    skipped_items: &mut SkippedItems,
+    block_number: u64,
+    timestamp: u64,
+) -> Vec<JitLiquidity>
+where
+    P: Fn(u64, &String) -> BigDecimal,
+{
+    let mut jit_liquidities = vec![];
+    let position_manager_token_ids = extract_position_manager_token_ids(pool_events, position_manager_events);
+
+    let mut events_by_pool: BTreeMap<&String, Vec<&PoolEvent>> = BTreeMap::new();
+    for event in pool_events {
+        let event_type = event
+            .r#type
+            .as_ref()
+            .ok_or(DataError::UnexpectedEvent("mint, burn or swap"));
+        if skipped_items
+            .check(MODULE, event.log_ordinal, &event.pool_address, event_type)
+            .is_some()
+        {
+            events_by_pool.entry(&event.pool_address).or_default().push(event);
+        }
+    }
+
+    for (_, mut events) in events_by_pool {
+        events.sort_by_key(|event| event.log_ordinal);
+        let mut matched_burns: HashSet<u64> = HashSet::new();
+
+        'mints: for (i, mint_event) in events.iter().enumerate() {
+            let mint = match &mint_event.r#type {
+                Some(MintEvent(mint)) => mint,
+                _ => continue,
+            };
+            let mint_position = match position_key(mint_event, &position_manager_token_ids) {
+                Some(position) => position,
+                None => continue,
+            };
+
+            let burn_position = events.iter().skip(i + 1).position(|event| match &event.r#type {
+                Some(BurnEvent(burn)) => {
+                    !matched_burns.contains(&event.log_ordinal)
+                        && burn.amount == mint.amount
+                        && position_key(event, &position_manager_token_ids).as_ref() == Some(&mint_position)
+                }
+                _ => false,
+            });
+            let burn_idx = match burn_position {
+                Some(offset) => i + 1 + offset,
+                None => continue,
+            };
+
+            let swaps: Vec<&PoolEvent> = events[i + 1..burn_idx]
+                .iter()
+                .filter(|event| matches!(event.r#type, Some(SwapEvent(_))))
+                .copied()
+                .collect();
+            if swaps.is_empty() {
+                continue;
+            }
+
+            let burn_event = events[burn_idx];
+            let burn = match &burn_event.r#type {
+                Some(BurnEvent(burn)) => burn,
+                _ => continue,
+            };
+            matched_burns.insert(burn_event.log_ordinal);
+
+            let mint_amounts = (|| {
+                Ok((
+                    errors::parse_int("tick lower", &mint.tick_lower)?,
+                    errors::parse_int("tick upper", &mint.tick_upper)?,
+                    errors::parse_decimal("liquidity", &mint.amount)?,
+                    errors::parse_decimal("fee", &mint_event.fee)?,
+                ))
+            })();
+            let (tick_lower, tick_upper, liquidity, fee) =
+                match skipped_items.check(MODULE, mint_event.log_ordinal, &mint_event.pool_address, mint_amounts) {
+                    Some(mint_amounts) => mint_amounts,
+                    None => continue,
+                };
+            let fee_rate = fee.div(BigDecimal::from(1000000u64));
+
+            let mut fees_token0 = BigDecimal::zero();
+            let mut fees_token1 = BigDecimal::zero();
+            let mut swap_volume_usd = BigDecimal::zero();
+            let mut jit_volume_usd = BigDecimal::zero();
+
+            for swap_event in swaps.iter() {
+                let swap = match &swap_event.r#type {
+                    Some(SwapEvent(swap)) => swap,
+                    _ => continue,
+                };
+                let ord = swap_event.log_ordinal;
+                let swap_amounts = (|| {
+                    Ok((
+                        errors::parse_decimal("amount0", &swap.amount_0)?,
+                        errors::parse_decimal("amount1", &swap.amount_1)?,
+                        errors::parse_int("tick", &swap.tick)?,
+                        errors::parse_decimal("liquidity", &swap.liquidity)?,
+                    ))
+                })();
+                // the fees of the JIT liquidity are unknown without the ones of every swap
+                let (amount0, amount1, tick, swap_liquidity) =
+                    match skipped_items.check(MODULE, ord, &swap_event.pool_address, swap_amounts) {
+                        Some(swap_amounts) => swap_amounts,
+                        None => continue 'mints,
+                    };
+
+                let volume_usd = swap_volume_in_usd(&token_price_usd, swap_event, &amount0, &amount1, ord);
+                swap_volume_usd = swap_volume_usd.add(volume_usd.clone());
+
+                if tick < tick_lower || tick >= tick_upper {
+                    continue;
+                }
+
+                let share = math::safe_div(&liquidity, &swap_liquidity);
+                jit_volume_usd = jit_volume_usd.add(volume_usd.mul(share.clone()));
+
+                // the fees are paid on the input side of the swap
+                if amount0.gt(&BigDecimal::zero()) {
+                    fees_token0 = fees_token0.add(amount0.mul(fee_rate.clone()).mul(share));
+                } else {
+                    fees_token1 = fees_token1.add(amount1.mul(fee_rate.clone()).mul(share));
+                }
+            }
+
+            let burn_ord = burn_event.log_ordinal;
+            let fees_usd = fees_token0
+                .clone()
+                .mul(token_price_usd(burn_ord, &mint_event.token0))
+                .add(fees_token1.clone().mul(token_price_usd(burn_ord, &mint_event.token1)));
+
+            jit_liquidities.push(JitLiquidity {
+                pool_address: mint_event.pool_address.clone(),
+                owner: mint.owner.clone(),
+                origin: mint.origin.clone(),
+                mint_transaction: mint_event.transaction_id.clone(),
+                burn_transaction: burn_event.transaction_id.clone(),
+                tick_lower: mint.tick_lower.clone(),
+                tick_upper: mint.tick_upper.clone(),
+                liquidity: mint.amount.clone(),
+                amount0_added: mint.amount_0.clone(),
+                amount1_added: mint.amount_1.clone(),
+                amount0_removed: burn.amount_0.clone(),
+                amount1_removed: burn.amount_1.clone(),
+                swap_count: swaps.len() as u64,
+                fees_token0: fees_token0.to_string(),
+                fees_token1: fees_token1.to_string(),
+                fees_usd: fees_usd.to_string(),
+                volume_share: math::safe_div(&jit_volume_usd, &swap_volume_usd).to_string(),
+                swap_volume_usd: swap_volume_usd.to_string(),
+                jit_volume_usd: jit_volume_usd.to_string(),
+                mint_ordinal: mint_event.log_ordinal,
+                burn_ordinal: burn_ord,
+                block_number,
+                timestamp,
+            });
+        }
+    }
+
+    jit_liquidities
+}
+
+// Token ids of the positions of the NonfungiblePositionManager minted and burned by the pool
+// events, by log ordinal. The manager emits its IncreaseLiquidity or DecreaseLiquidity right
+// after the Mint or Burn of the pool, with the same liquidity.
+fn extract_position_manager_token_ids(
+    pool_events: &Vec<PoolEvent>,
+    position_manager_events: &Events,
+) -> HashMap<u64, String> {
+    let position_manager = Hex(NON_FUNGIBLE_POSITION_MANAGER).to_string();
+    let mut token_ids = HashMap::new();
+
+    for event in pool_events {
+        let token_id = match &event.r#type {
+            Some(MintEvent(mint)) if mint.owner == position_manager => position_manager_events
+                .increase_liquidity_positions
+                .iter()
+                .filter(|pos| pos.log_ordinal > event.log_ordinal && pos.liquidity == mint.amount)
+                .min_by_key(|pos| pos.log_ordinal)
+                .map(|pos| pos.token_id.clone()),
+            Some(BurnEvent(burn)) if burn.owner == position_manager => position_manager_events
+                .decrease_liquidity_positions
+                .iter()
+                .filter(|pos| pos.log_ordinal > event.log_ordinal && pos.liquidity == burn.amount)
+                .min_by_key(|pos| pos.log_ordinal)
+                .map(|pos| pos.token_id.clone()),
+            _ => None,
+        };
+        if let Some(token_id) = token_id {
+            token_ids.insert(event.log_ordinal, token_id);
+        }
+    }
+
+    token_ids
+}
+
+// Position of a mint or burn: its owner and tick range, or the token id of the position when
+// the owner is the NonfungiblePositionManager, which owns all of its positions. None when the
+// token id of a position of the manager is unknown.
+fn position_key(event: &PoolEvent, position_manager_token_ids: &HashMap<u64, String>) -> Option<String> {
+    let (owner, tick_lower, tick_upper) = match &event.r#type {
+        Some(MintEvent(mint)) => (&mint.owner, &mint.tick_lower, &mint.tick_upper),
+        Some(BurnEvent(burn)) => (&burn.owner, &burn.tick_lower, &burn.tick_upper),
+        _ => return None,
+    };
+    if *owner == Hex(NON_FUNGIBLE_POSITION_MANAGER).to_string() {
+        return position_manager_token_ids
+            .get(&event.log_ordinal)
+            .map(|token_id| format!("position:{token_id}"));
+    }
+    Some(format!("{owner}:{tick_lower}:{tick_upper}"))
+}
+
+fn swap_volume_in_usd<P>(
+    token_price_usd: &P,
+    swap_event: &PoolEvent,
+    amount0: &BigDecimal,
+    amount1: &BigDecimal,
+    ordinal: u64,
+) -> BigDecimal
+where
+    P: Fn(u64, &String) -> BigDecimal,
+{
+    let volume_usd = amount0.absolute().mul(token_price_usd(ordinal, &swap_event.token0));
+    if !volume_usd.is_zero() {
+        return volume_usd;
+    }
+
+    amount1.absolute().mul(token_price_usd(ordinal, &swap_event.token1))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::SkippedItems;
+    use crate::errors::Policy;
+    use crate::jit::extract_jit_liquidities;
+    use crate::pb::uniswap::events::pool_event::{self, Burn, Mint, Swap};
+    use crate::pb::uniswap::events::{DecreaseLiquidityPosition, IncreaseLiquidityPosition, PoolEvent};
+    use crate::pb::uniswap::{Events, JitLiquidity};
+    use crate::utils::NON_FUNGIBLE_POSITION_MANAGER;
+    use substreams::scalar::BigDecimal;
+    use substreams::Hex;
+
+    fn pool_event(ordinal: u64, r#type: pool_event::Type) -> PoolEvent {
+        PoolEvent {
+            log_ordinal: ordinal,
+            pool_address: "pool".to_string(),
+            token0: "token0".to_string(),
+            token1: "token1".to_string(),
+            fee: "3000".to_string(),
+            r#type: Some(r#type),
+            ..Default::default()
+        }
+    }
+
+    fn mint(owner: &str, amount: &str) -> pool_event::Type {
+        pool_event::Type::Mint(Mint {
+            owner: owner.to_string(),
+            amount: amount.to_string(),
+            tick_lower: "-60".to_string(),
+            tick_upper: "60".to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn burn(owner: &str, amount: &str) -> pool_event::Type {
+        pool_event::Type::Burn(Burn {
+            owner: owner.to_string(),
+            amount: amount.to_string(),
+            tick_lower: "-60".to_string(),
+            tick_upper: "60".to_string(),
+            ..Default::default()
+        })
+    }
+
+    // 100 token0 in for 99 token1 out, at the tick and with the active liquidity given
+    fn swap(tick: &str, liquidity: &str) -> pool_event::Type {
+        pool_event::Type::Swap(Swap {
+            amount_0: "100".to_string(),
+            amount_1: "-99".to_string(),
+            tick: tick.to_string(),
+            liquidity: liquidity.to_string(),
+            ..Default::default()
+        })
+    }
+
+    // every token is worth 1 USD
+    fn extract(pool_events: Vec<PoolEvent>, position_manager_events: &Events) -> Vec<JitLiquidity> {
+        extract_jit_liquidities(
+            &pool_events,
+            position_manager_events,
+            |_, _| BigDecimal::one(),
+            &mut SkippedItems::new(Policy::Fail),
+            1,
+            1,
+        )
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn test_mint_swap_burn_of_the_same_position() {
+        let jit_liquidities = extract(
+            vec![
+                pool_event(1, mint("lp", "1000")),
+                pool_event(2, swap("0", "4000")),
+                pool_event(3, burn("lp", "1000")),
+            ],
+            &Events::default(),
+        );
+
+        assert_eq!(1, jit_liquidities.len());
+        let jit_liquidity = &jit_liquidities[0];
+        assert_eq!("lp", jit_liquidity.owner);
+        assert_eq!((1, 3), (jit_liquidity.mint_ordinal, jit_liquidity.burn_ordinal));
+        assert_eq!(1, jit_liquidity.swap_count);
+        // a quarter of the active liquidity earns a quarter of the 0.3% fee of the input
+        assert_eq!(decimal("0.075"), decimal(&jit_liquidity.fees_token0));
+        assert_eq!(decimal("0"), decimal(&jit_liquidity.fees_token1));
+        assert_eq!(decimal("0.075"), decimal(&jit_liquidity.fees_usd));
+        assert_eq!(decimal("100"), decimal(&jit_liquidity.swap_volume_usd));
+        assert_eq!(decimal("25"), decimal(&jit_liquidity.jit_volume_usd));
+        assert_eq!(decimal("0.25"), decimal(&jit_liquidity.volume_share));
+    }
+
+    #[test]
+    fn test_swap_outside_of_the_range() {
+        let jit_liquidities = extract(
+            vec![
+                pool_event(1, mint("lp", "1000")),
+                pool_event(2, swap("60", "4000")),
+                pool_event(3, burn("lp", "1000")),
+            ],
+            &Events::default(),
+        );
+
+        assert_eq!(1, jit_liquidities.len());
+        let jit_liquidity = &jit_liquidities[0];
+        assert_eq!(decimal("0"), decimal(&jit_liquidity.fees_token0));
+        assert_eq!(decimal("0"), decimal(&jit_liquidity.fees_usd));
+        assert_eq!(decimal("100"), decimal(&jit_liquidity.swap_volume_usd));
+        assert_eq!(decimal("0"), decimal(&jit_liquidity.volume_share));
+    }
+
+    #[test]
+    fn test_position_manager_matched_by_token_id() {
+        let position_manager = Hex(NON_FUNGIBLE_POSITION_MANAGER).to_string();
+        let position_manager_events = Events {
+            increase_liquidity_positions: vec![IncreaseLiquidityPosition {
+                token_id: "7".to_string(),
+                liquidity: "1000".to_string(),
+                log_ordinal: 2,
+                ..Default::default()
+            }],
+            decrease_liquidity_positions: vec![DecreaseLiquidityPosition {
+                token_id: "7".to_string(),
+                liquidity: "1000".to_string(),
+                log_ordinal: 5,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let jit_liquidities = extract(
+            vec![
+                pool_event(1, mint(&position_manager, "1000")),
+                pool_event(3, swap("0", "4000")),
+                pool_event(4, burn(&position_manager, "1000")),
+            ],
+            &position_manager_events,
+        );
+        assert_eq!(1, jit_liquidities.len());
+        assert_eq!(
+            (1, 4),
+            (jit_liquidities[0].mint_ordinal, jit_liquidities[0].burn_ordinal)
+        );
+
+        // the burn of another position of the manager, with the same range and liquidity
+        let other_position_events = Events {
+            decrease_liquidity_positions: vec![DecreaseLiquidityPosition {
+                token_id: "8".to_string(),
+                ..position_manager_events.decrease_liquidity_positions[0].clone()
+            }],
+            ..position_manager_events
+        };
+        let jit_liquidities = extract(
+            vec![
+                pool_event(1, mint(&position_manager, "1000")),
+                pool_event(3, swap("0", "4000")),
+                pool_event(4, burn(&position_manager, "1000")),
+            ],
+            &other_position_events,
+        );
+        assert!(jit_liquidities.is_empty());
+    }
+
+    #[test]
+    fn test_burn_of_another_liquidity() {
+        let jit_liquidities = extract(
+            vec![
+                pool_event(1, mint("lp", "1000")),
+                pool_event(2, swap("0", "4000")),
+                pool_event(3, burn("lp", "999")),
+            ],
+            &Events::default(),
+        );
+        assert!(jit_liquidities.is_empty());
+    }
+
+    #[test]
+    fn test_unparsable_swap_skipped() {
+        let mut skipped_items = SkippedItems::new(Policy::SkipAndReport);
+        let jit_liquidities = extract_jit_liquidities(
+            &vec![
+                pool_event(1, mint("lp", "1000")),
+                pool_event(2, swap("0", "n/a")),
+                pool_event(3, burn("lp", "1000")),
+            ],
+            &Events::default(),
+            |_, _| BigDecimal::one(),
+            &mut skipped_items,
+            1,
+            1,
+        );
+        assert!(jit_liquidities.is_empty());
+
+        let items = skipped_items.into_items();
+        assert_eq!(1, items.len());
+        assert_eq!(("map_jit_liquidity", 2), (items[0].module.as_str(), items[0].ordinal));
+        assert_eq!("invalid liquidity n/a", items[0].detail);
+    }
+}
@@ -2,17 +2,34 @@ extern crate core;
 
 pub mod abi;
 mod ast;
+mod balances;
+mod candles;
 mod db;
+mod diagnostics;
+mod errors;
 mod eth;
 mod filtering;
+mod gap_fill;
+mod guard;
+mod invariants;
+mod jit;
+mod lps;
 mod math;
+mod metadata;
+mod mev;
+mod oracle;
 mod pb;
 mod price;
 mod rpc;
+mod sources;
+mod spam;
 mod storage;
 mod ticks_idx;
+mod trades;
 mod utils;
+mod windows;
 
+use crate::errors::Policy;
 use crate::ethpb::v2::{Block, StorageChange};
 use crate::pb::uniswap;
 use crate::pb::uniswap::events::pool_event::Type;
@@ -22,9 +39,20 @@ use crate::pb::uniswap::events::position_event::Type::{
 };
 use crate::pb::uniswap::events::{PoolSqrtPrice, PositionEvent};
 use crate::pb::uniswap::{events, Events};
-use crate::pb::uniswap::{Erc20Token, Erc20Tokens, Pool, Pools};
+use crate::pb::uniswap::{
+    CandleUpdates, Candles, Diagnostics, Erc20Token, Erc20Tokens, ErrorPolicy, FilteredPools, GapFilledWindows,
+    InvariantChecks, JitLiquidities, MevEvents, OracleDeviations, OraclePrices, Pool, PoolTokenTransfers, Pools,
+    PriceGuardFlags, SnapshotPosition, SnapshotPositions, SpamFilterParams, TokenBehaviours, TokenPricePath,
+    TokenPriceProvenance, TokenPriceProvenances, Trades,
+};
 use crate::price::WHITELIST_TOKENS;
-use crate::utils::{ERROR_POOL, UNISWAP_V3_FACTORY};
+use crate::spam::SpamFilter;
+use crate::utils::{PoolTvlInputs, SwapVolumeInputs};
+use crate::utils::{ERROR_POOL, NON_FUNGIBLE_POSITION_MANAGER, UNISWAP_V3_FACTORY};
+use crate::windows::{
+    ALL_WINDOW_KINDS, POOL_WINDOW_TABLES, SOURCE_WINDOW_KINDS, TOKEN_WINDOW_TABLES, UNISWAP_WINDOW_KINDS,
+};
+use std::collections::HashSet;
 use std::ops::{Div, Mul, Sub};
 use substreams::errors::Error;
 use substreams::key;
@@ -32,84 +60,196 @@ use substreams::pb::substreams::{store_delta, Clock};
 use substreams::prelude::*;
 use substreams::scalar::{BigDecimal, BigInt};
 use substreams::store::{
-    DeltaArray, DeltaBigDecimal, DeltaBigInt, DeltaExt, DeltaProto, StoreAddBigDecimal, StoreAddBigInt, StoreAppend,
-    StoreGetBigDecimal, StoreGetBigInt, StoreGetProto, StoreGetRaw, StoreSetBigDecimal, StoreSetBigInt, StoreSetProto,
+    DeltaArray, DeltaBigDecimal, DeltaBigInt, DeltaExt, DeltaInt64, DeltaProto, StoreAddBigDecimal, StoreAddBigInt,
+    StoreAppend, StoreGetBigDecimal, StoreGetBigInt, StoreGetProto, StoreGetRaw, StoreMaxBigDecimal,
+    StoreMinBigDecimal, StoreSetBigDecimal, StoreSetBigInt, StoreSetIfNotExistsBigDecimal, StoreSetIfNotExistsInt64,
+    StoreSetInt64, StoreSetProto, StoreSetString,
 };
 use substreams::{log, Hex};
 use substreams_entity_change::pb::entity::EntityChanges;
 use substreams_entity_change::tables::Tables;
 use substreams_ethereum::{pb::eth as ethpb, Event as EventTrait};
 
+// The spam filter of map_pools_created and map_spam_pools. Params only reach their own
+// module, both take this one's output so that they filter the same pools.
+#[substreams::handlers::map]
+pub fn map_spam_filter(params: String, _clock: Clock) -> Result<SpamFilterParams, Error> {
+    Ok(SpamFilter::from_params(&params)?.to_spam_filter_params())
+}
+
 #[substreams::handlers::map]
-pub fn map_pools_created(block: Block) -> Result<Pools, Error> {
+pub fn map_pools_created(
+    spam_filter_params: SpamFilterParams, /* map_spam_filter */
+    block: Block,
+) -> Result<Pools, Error> {
     use abi::factory::events::PoolCreated;
 
-    Ok(Pools {
-        pools: block
-            .events::<PoolCreated>(&[&UNISWAP_V3_FACTORY])
-            .filter_map(|(event, log)| {
-                log::info!("pool addr: {}", Hex(&event.pool));
+    let spam_filter = SpamFilter::from_spam_filter_params(&spam_filter_params);
+    let mut pools = Pools::default();
 
-                if event.pool == ERROR_POOL {
-                    return None;
-                }
+    for pool in block
+        .events::<PoolCreated>(&[&UNISWAP_V3_FACTORY])
+        .filter_map(|(event, log)| {
+            log::info!("pool addr: {}", Hex(&event.pool));
+
+            if event.pool == ERROR_POOL {
+                return None;
+            }
 
-                let token0_address = Hex(&event.token0).to_string();
-                let token1_address = Hex(&event.token1).to_string();
-
-                //todo: question regarding the ignore_pool line. In the
-                // uniswap-v3 subgraph, they seem to bail out when they
-                // match the addr, should we do the same ?
-                Some(Pool {
-                    address: Hex(&log.data()[44..64]).to_string(),
-                    transaction_id: Hex(&log.receipt.transaction.hash).to_string(),
-                    created_at_block_number: block.number,
-                    created_at_timestamp: block.timestamp_seconds(),
-                    fee_tier: event.fee.to_string(),
-                    tick_spacing: event.tick_spacing.into(),
-                    log_ordinal: log.ordinal(),
-                    ignore_pool: event.pool == ERROR_POOL,
-                    token0: Some(match rpc::create_uniswap_token(&token0_address) {
-                        Some(mut token) => {
-                            token.total_supply = rpc::token_total_supply_call(&token0_address)
-                                .unwrap_or(BigInt::zero())
-                                .to_string();
-                            token
-                        }
-                        None => {
-                            // We were unable to create the uniswap token, so we discard this event entirely
-                            log::info!("ignoring creating of pool addr: {}", Hex(&event.pool));
-                            return None;
-                        }
-                    }),
-                    token1: Some(match rpc::create_uniswap_token(&token1_address) {
-                        Some(mut token) => {
-                            token.total_supply = rpc::token_total_supply_call(&token1_address)
-                                .unwrap_or(BigInt::zero())
-                                .to_string();
-                            token
-                        }
-                        None => {
-                            // We were unable to create the uniswap token, so we discard this event entirely
-                            log::info!("ignoring creating of pool addr: {}", Hex(&event.pool));
-                            return None;
-                        }
-                    }),
-                    ..Default::default()
-                })
+            let token0_address = Hex(&event.token0).to_string();
+            let token1_address = Hex(&event.token1).to_string();
+
+            //todo: question regarding the ignore_pool line. In the
+            // uniswap-v3 subgraph, they seem to bail out when they
+            // match the addr, should we do the same ?
+            Some(Pool {
+                address: Hex(&log.data()[44..64]).to_string(),
+                transaction_id: Hex(&log.receipt.transaction.hash).to_string(),
+                created_at_block_number: block.number,
+                created_at_timestamp: block.timestamp_seconds(),
+                fee_tier: event.fee.to_string(),
+                tick_spacing: event.tick_spacing.into(),
+                log_ordinal: log.ordinal(),
+                ignore_pool: event.pool == ERROR_POOL,
+                token0: Some(match rpc::create_uniswap_token(&token0_address) {
+                    Some(mut token) => {
+                        token.total_supply = rpc::token_total_supply_call(&token0_address)
+                            .unwrap_or(BigInt::zero())
+                            .to_string();
+                        token
+                    }
+                    None => {
+                        // We were unable to create the uniswap token, so we discard this event entirely
+                        log::info!("ignoring creating of pool addr: {}", Hex(&event.pool));
+                        return None;
+                    }
+                }),
+                token1: Some(match rpc::create_uniswap_token(&token1_address) {
+                    Some(mut token) => {
+                        token.total_supply = rpc::token_total_supply_call(&token1_address)
+                            .unwrap_or(BigInt::zero())
+                            .to_string();
+                        token
+                    }
+                    None => {
+                        // We were unable to create the uniswap token, so we discard this event entirely
+                        log::info!("ignoring creating of pool addr: {}", Hex(&event.pool));
+                        return None;
+                    }
+                }),
+                ..Default::default()
             })
-            .collect(),
-    })
+        })
+    {
+        let pool = Pool {
+            label: metadata::pool_label(&pool),
+            ..pool
+        };
+        match spam_filter.filter_created(&pool) {
+            Some(filtered_pool) => {
+                log::info!("filtering pool addr: {} ({})", pool.address, filtered_pool.reason);
+                pools.filtered_pools.push(filtered_pool);
+            }
+            None => pools.pools.push(pool),
+        }
+    }
+
+    Ok(pools)
+}
+
+#[substreams::handlers::store]
+pub fn store_pools_created(
+    pools: Pools,                  /* map_pools_created */
+    filtered_pools: FilteredPools, /* map_spam_pools */
+    store: StoreSetProto<Pool>,
+) {
+    for pool in pools.pools {
+        let pool_address = &pool.address;
+        store.set(pool.log_ordinal, format!("pool:{pool_address}"), &pool);
+    }
+
+    // pools ignored after their creation, with ignore_pool set their events are skipped
+    for filtered_pool in filtered_pools.filtered_pools {
+        if filtered_pool.at_creation {
+            continue;
+        }
+        let pool = filtered_pool.pool.unwrap();
+        let pool_address = &pool.address;
+        store.set(filtered_pool.ordinal, format!("pool:{pool_address}"), &pool);
+    }
 }
 
+// pools watched by the spam filter heuristics, kept apart from store_pools_created which
+// depends on their outcome
 #[substreams::handlers::store]
-pub fn store_pools_created(pools: Pools, store: StoreSetProto<Pool>) {
+pub fn store_spam_candidates(pools: Pools, store: StoreSetProto<Pool>) {
     for pool in pools.pools {
         let pool_address = &pool.address;
         store.set(pool.log_ordinal, format!("pool:{pool_address}"), &pool);
     }
 }
 
+#[substreams::handlers::store]
+pub fn store_spam_creation_blocks(pools: Pools, store: StoreAppend<String>) {
+    for pool in pools.pools {
+        store.append(
+            pool.log_ordinal,
+            format!("block:{}", pool.created_at_block_number),
+            pool.address,
+        );
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_spam_pool_liquidities(
+    block: Block,
+    spam_candidates_store: StoreGetProto<Pool>, /* store_spam_candidates */
+    store: StoreAddBigInt,
+) {
+    for (pool_address, amount, ordinal) in spam::extract_liquidity_changes(&block, &spam_candidates_store) {
+        store.add(ordinal, format!("pool:{pool_address}"), amount);
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_spam_transfer_failures(
+    block: Block,
+    spam_candidates_store: StoreGetProto<Pool>, /* store_spam_candidates */
+    store: StoreAddInt64,
+) {
+    for (pool_address, ordinal) in spam::extract_transfer_failures(&block, &spam_candidates_store) {
+        store.add(ordinal, format!("pool:{pool_address}"), 1);
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_spam_pools(
+    spam_filter_params: SpamFilterParams, /* map_spam_filter */
+    clock: Clock,
+    pools_created: Pools,                              /* map_pools_created */
+    spam_candidates_store: StoreGetProto<Pool>,        /* store_spam_candidates */
+    spam_creation_blocks_store: StoreGetRaw,           /* store_spam_creation_blocks */
+    spam_pool_liquidities_store: StoreGetBigInt,       /* store_spam_pool_liquidities */
+    spam_transfer_failures_deltas: Deltas<DeltaInt64>, /* store_spam_transfer_failures */
+) -> Result<FilteredPools, Error> {
+    let spam_filter = SpamFilter::from_spam_filter_params(&spam_filter_params);
+
+    let mut filtered_pools = pools_created.filtered_pools;
+    filtered_pools.extend(spam_filter.zero_liquidity_pools(
+        clock.number,
+        &spam_candidates_store,
+        &spam_creation_blocks_store,
+        &spam_pool_liquidities_store,
+    ));
+    filtered_pools.extend(spam_filter.transfer_failure_pools(
+        clock.number,
+        &spam_candidates_store,
+        &spam_transfer_failures_deltas,
+    ));
+
+    Ok(FilteredPools { filtered_pools })
+}
+
 #[substreams::handlers::store]
 pub fn store_tokens(pools: Pools, store: StoreAddInt64) {
     for pool in pools.pools {
@@ -165,31 +305,36 @@ pub fn store_tokens_whitelist_pools(tokens: Erc20Tokens, output_append: StoreApp
     }
 }
 
+// pools of every token, the nodes of the pricing graph of store_token_price_paths
+#[substreams::handlers::store]
+pub fn store_token_pools(pools: Pools, output_append: StoreAppend<String>) {
+    for pool in pools.pools {
+        for token in [pool.token0_ref(), pool.token1_ref()] {
+            output_append.append(
+                pool.log_ordinal,
+                format!("token:{}", token.address),
+                pool.address.clone(),
+            );
+        }
+    }
+}
+
 #[substreams::handlers::map]
-pub fn map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) -> Result<Events, Error> {
+pub fn map_extract_data_types(params: String, block: Block, pools_store: StoreGetProto<Pool>) -> Result<Events, Error> {
     let mut events = Events::default();
+    let source_registry = sources::SourceRegistry::from_params(&params)?;
 
     let mut pool_sqrt_prices: Vec<events::PoolSqrtPrice> = vec![];
     let mut pool_liquidities: Vec<events::PoolLiquidity> = vec![];
     let mut fee_growth_global_updates: Vec<events::FeeGrowthGlobal> = vec![];
-    let mut pool_events: Vec<events::PoolEvent> = vec![];
     let mut transactions: Vec<events::Transaction> = vec![];
     // let mut flashes: Vec<events::Flash> = vec![];
-    let mut ticks_created: Vec<events::TickCreated> = vec![];
-    let mut ticks_updated: Vec<events::TickUpdated> = vec![];
-
-    let mut positions_created: Vec<events::CreatedPosition> = vec![];
-    let mut positions_increase_liquidity: Vec<events::IncreaseLiquidityPosition> = vec![];
-    let mut positions_decrease_liquidity: Vec<events::DecreaseLiquidityPosition> = vec![];
-    let mut positions_collect: Vec<events::CollectPosition> = vec![];
-    let mut positions_transfer: Vec<events::TransferPosition> = vec![];
 
     let timestamp = block.timestamp_seconds();
 
     for trx in block.transactions() {
         for (log, call_view) in trx.logs_with_calls() {
             let pool_address = &Hex(log.clone().address).to_string();
-            let transactions_id = Hex(&trx.hash).to_string();
 
             let pool_opt = pools_store.get_last(format!("pool:{pool_address}"));
             if pool_opt.is_none() {
@@ -204,21 +349,14 @@ pub fn map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) ->
                 &call_view.call.storage_changes,
                 &pool,
             );
+            filtering::extract_pool_storage_updates(&mut events, log, &call_view.call.storage_changes, &pool);
 
-            filtering::extract_pool_events_and_positions(
-                &mut pool_events,
-                &mut ticks_created,
-                &mut ticks_updated,
-                &mut positions_created,
-                &mut positions_increase_liquidity,
-                &mut positions_decrease_liquidity,
-                &mut positions_collect,
-                &mut positions_transfer,
-                &transactions_id,
-                &Hex(&trx.from).to_string(),
+            filtering::extract_pool_events(
+                &mut events,
                 log,
                 &call_view,
                 &pool,
+                &source_registry,
                 timestamp,
                 block.number,
             );
@@ -232,16 +370,26 @@ pub fn map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) ->
     events.pool_sqrt_prices = pool_sqrt_prices;
     events.pool_liquidities = pool_liquidities;
     events.fee_growth_global_updates = fee_growth_global_updates;
-    events.pool_events = pool_events;
     events.transactions = transactions;
-    events.created_positions = positions_created;
-    events.increase_liquidity_positions = positions_increase_liquidity;
-    events.decrease_liquidity_positions = positions_decrease_liquidity;
-    events.collect_positions = positions_collect;
-    events.transfer_positions = positions_transfer;
     // events.flashes = flashes;
-    events.ticks_created = ticks_created;
-    events.ticks_updated = ticks_updated;
+
+    Ok(events)
+}
+
+#[substreams::handlers::map]
+pub fn map_position_manager_events(block: Block, pools_store: StoreGetProto<Pool>) -> Result<Events, Error> {
+    let mut events = Events::default();
+    let timestamp = block.timestamp_seconds();
+
+    for trx in block.transactions() {
+        for call in trx.calls.iter() {
+            if call.state_reverted || call.address != NON_FUNGIBLE_POSITION_MANAGER {
+                continue;
+            }
+
+            filtering::extract_position_manager_events(&mut events, call, trx, &pools_store, timestamp, block.number);
+        }
+    }
 
     Ok(events)
 }
@@ -254,16 +402,51 @@ pub fn store_pool_sqrt_price(events: Events, store: StoreSetProto<PoolSqrtPrice>
     }
 }
 
+#[substreams::handlers::map]
+pub fn map_price_guard(
+    clock: Clock,
+    events: Events,                                      /* map_extract_data_types */
+    pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>, /* store_pool_sqrt_price */
+    pool_liquidities_store: StoreGetBigInt,              /* store_pool_liquidities */
+) -> Result<PriceGuardFlags, Error> {
+    let timestamp = clock.timestamp.unwrap().seconds as u64;
+
+    Ok(PriceGuardFlags {
+        flags: guard::extract_price_guard_flags(
+            &events.pool_sqrt_prices,
+            &pool_sqrt_price_store,
+            &pool_liquidities_store,
+            clock.number,
+            timestamp,
+        ),
+    })
+}
+
 #[substreams::handlers::store]
-pub fn store_prices(clock: Clock, events: Events, pools_store: StoreGetProto<Pool>, store: StoreSetBigDecimal) {
-    let timestamp_seconds = clock.timestamp.unwrap().seconds;
-    let day_id: i64 = timestamp_seconds / 86400;
-    let hour_id: i64 = timestamp_seconds / 3600;
-    let prev_day_id = day_id - 1;
-    let prev_hour_id = hour_id - 1;
+pub fn store_price_guard_quarantines(price_guard_flags: PriceGuardFlags, store: StoreSetInt64) {
+    for flag in price_guard_flags.flags {
+        store.set(
+            flag.ordinal,
+            format!("pool:{}", flag.pool_address),
+            &guard::quarantined_until(&flag),
+        );
+    }
+}
 
-    store.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
-    store.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
+#[substreams::handlers::store]
+pub fn store_prices(
+    clock: Clock,
+    events: Events,                               /* map_extract_data_types */
+    pools_store: StoreGetProto<Pool>,             /* store_pools_created */
+    price_guard_quarantines_store: StoreGetInt64, /* store_price_guard_quarantines */
+    error_policy: ErrorPolicy,                    /* map_error_policy */
+    store: StoreSetBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, clock.timestamp.unwrap().seconds);
+    for window in &windows {
+        store.delete_prefix(0, &window.prev_prefix("Pool"));
+    }
 
     for sqrt_price_update in events.pool_sqrt_prices {
         let pool_address = &sqrt_price_update.pool_address;
@@ -273,14 +456,29 @@ pub fn store_prices(clock: Clock, events: Events, pools_store: StoreGetProto<Poo
                 continue;
             }
             Some(pool) => {
-                // This sqrt price has this value when there is no liquidity in the pool
-                if sqrt_price_update.sqrt_price == "1461446703485210103287273052203988822378723970341" {
+                let token0 = pool.token0.as_ref().unwrap();
+                let token1 = pool.token1.as_ref().unwrap();
+
+                // The pool has no price until its next swap: its prices are removed, which the
+                // pricing routes and the reference pools skip, and the windows keep their last
+                // prices. The pair prices may come from another fee tier and are left as is.
+                if sqrt_price_update.price_unavailable {
+                    log::info!(
+                        "pool {} price unavailable at {}",
+                        pool_address,
+                        sqrt_price_update.sqrt_price
+                    );
+                    store.delete_prefix(
+                        sqrt_price_update.ordinal as i64,
+                        &format!("pool:{pool_address}:{}:token0", token0.address),
+                    );
+                    store.delete_prefix(
+                        sqrt_price_update.ordinal as i64,
+                        &format!("pool:{pool_address}:{}:token1", token1.address),
+                    );
                     continue;
                 }
-                // maybe check for this sqrt price also : 4295128739 -> on the other side
 
-                let token0 = pool.token0.as_ref().unwrap();
-                let token1 = pool.token1.as_ref().unwrap();
                 log::debug!(
                     "pool addr: {}, token 0 addr: {}, token 1 addr: {}",
                     pool.address,
@@ -288,131 +486,193 @@ pub fn store_prices(clock: Clock, events: Events, pools_store: StoreGetProto<Poo
                     token1.address
                 );
 
-                let sqrt_price = BigDecimal::try_from(sqrt_price_update.sqrt_price).unwrap();
+                let sqrt_price = match policy.check(
+                    "store_prices",
+                    errors::parse_decimal("sqrt price", &sqrt_price_update.sqrt_price),
+                ) {
+                    Some(sqrt_price) => sqrt_price,
+                    None => continue,
+                };
                 log::debug!("sqrtPrice: {}", sqrt_price.to_string());
 
                 let tokens_price: (BigDecimal, BigDecimal) =
                     price::sqrt_price_x96_to_token_prices(sqrt_price, &token0, &token1);
                 log::debug!("token prices: {} {}", tokens_price.0, tokens_price.1);
 
-                let token0_addr = &token0.address;
-                let token1_addr = &token1.address;
-                store.set_many(
-                    sqrt_price_update.ordinal,
-                    &vec![
-                        format!("pool:{pool_address}:{token0_addr}:token0"),
-                        format!("pair:{token0_addr}:{token1_addr}"), // used for find_eth_per_token
-                    ],
-                    &tokens_price.0,
-                );
-
-                store.set_many(
+                // A flagged update is the real price of the pool, but it must not leak into the
+                // prices of the tokens routed through this pool, nor into its windows, until it
+                // has held for the whole quarantine
+                let quarantined = guard::is_quarantined(
+                    &price_guard_quarantines_store,
+                    pool_address,
+                    clock.number,
                     sqrt_price_update.ordinal,
-                    &vec![
-                        format!("pool:{pool_address}:{token1_addr}:token1"),
-                        format!("pair:{token1_addr}:{token0_addr}"), // used for find_eth_per_token
-                    ],
-                    &tokens_price.1,
                 );
+                if quarantined {
+                    log::info!("quarantined pool price update {}", pool_address);
+                }
+                let (token0_price_keys, token1_price_keys) =
+                    guard::price_keys(pool_address, &token0.address, &token1.address, quarantined);
+                store.set_many(sqrt_price_update.ordinal, &token0_price_keys, &tokens_price.0);
+                store.set_many(sqrt_price_update.ordinal, &token1_price_keys, &tokens_price.1);
 
-                // We only want to set the prices of PoolDayData and PoolHourData when
+                // We only want to set the prices of the pool windows when
                 // the pool is post-initialized, not on the initialized event.
-                if sqrt_price_update.initialized {
+                if sqrt_price_update.initialized || quarantined {
                     continue;
                 }
 
-                store.set_many(
-                    sqrt_price_update.ordinal,
-                    &vec![
-                        // We only need the token0Prices to compute the open, high, low and close
-                        format!("PoolDayData:{day_id}:{pool_address}:token0"),
-                        format!("PoolHourData:{hour_id}:{pool_address}:token0"),
-                    ],
-                    &tokens_price.0,
-                );
-
-                store.set_many(
-                    sqrt_price_update.ordinal,
-                    &vec![
-                        format!("PoolDayData:{day_id}:{pool_address}:token1"),
-                        format!("PoolHourData:{hour_id}:{pool_address}:token1"),
-                    ],
-                    &tokens_price.1,
-                );
+                for window in &windows {
+                    let window_key = window.key("Pool");
+                    // We only need the token0Prices to compute the open, high, low and close
+                    store.set(
+                        sqrt_price_update.ordinal,
+                        format!("{window_key}:{pool_address}:token0"),
+                        &tokens_price.0,
+                    );
+                    store.set(
+                        sqrt_price_update.ordinal,
+                        format!("{window_key}:{pool_address}:token1"),
+                        &tokens_price.1,
+                    );
+                }
             }
         }
     }
 }
 
+// Pool which last wrote each `pair:` price of store_prices, under the same key
 #[substreams::handlers::store]
-pub fn store_pool_liquidities(clock: Clock, events: Events, store: StoreSetBigInt) {
-    let timestamp_seconds = clock.timestamp.unwrap().seconds;
-    let day_id: i64 = timestamp_seconds / 86400;
-    let hour_id: i64 = timestamp_seconds / 3600;
-    let prev_day_id = day_id - 1;
-    let prev_hour_id = hour_id - 1;
+pub fn store_pair_pools(
+    clock: Clock,
+    events: Events,                               /* map_extract_data_types */
+    pools_store: StoreGetProto<Pool>,             /* store_pools_created */
+    price_guard_quarantines_store: StoreGetInt64, /* store_price_guard_quarantines */
+    output: StoreSetString,
+) {
+    for sqrt_price_update in events.pool_sqrt_prices {
+        let ord = sqrt_price_update.ordinal;
+        let pool_address = &sqrt_price_update.pool_address;
+        let pool = match pools_store.get_last(format!("pool:{pool_address}")) {
+            Some(pool) => pool,
+            None => continue,
+        };
+        // store_prices leaves the pair prices as is on these updates, its skipped items
+        // being reported by map_diagnostics
+        if sqrt_price_update.price_unavailable
+            || errors::parse_decimal("sqrt price", &sqrt_price_update.sqrt_price).is_err()
+            || guard::is_quarantined(&price_guard_quarantines_store, pool_address, clock.number, ord)
+        {
+            continue;
+        }
+
+        let token0_addr = &pool.token0_ref().address;
+        let token1_addr = &pool.token1_ref().address;
+        output.set_many(
+            ord,
+            &vec![
+                format!("pair:{token0_addr}:{token1_addr}"),
+                format!("pair:{token1_addr}:{token0_addr}"),
+            ],
+            pool_address,
+        );
+    }
+}
 
-    store.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
-    store.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
+#[substreams::handlers::store]
+pub fn store_pool_liquidities(
+    clock: Clock,
+    events: Events,
+    error_policy: ErrorPolicy, /* map_error_policy */
+    store: StoreSetBigInt,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, clock.timestamp.unwrap().seconds);
+    for window in &windows {
+        store.delete_prefix(0, &window.prev_prefix("Pool"));
+    }
 
     for pool_liquidity in events.pool_liquidities {
+        let ord = pool_liquidity.log_ordinal;
         let pool_address = &pool_liquidity.pool_address;
+        let liquidity = match policy.check(
+            "store_pool_liquidities",
+            errors::parse_int("liquidity", &pool_liquidity.liquidity),
+        ) {
+            Some(liquidity) => liquidity,
+            None => continue,
+        };
         let token0_address = &pool_liquidity.token0;
         let token1_address = &pool_liquidity.token1;
-        store.set_many(
-            pool_liquidity.log_ordinal,
-            &vec![
-                format!("pool:{pool_address}"),
-                format!("pair:{token0_address}:{token1_address}"),
-                format!("pair:{token1_address}:{token0_address}"),
-                format!("PoolDayData:{day_id}:{pool_address}"),
-                format!("PoolHourData:{hour_id}:{pool_address}"),
-            ],
-            &BigInt::try_from(pool_liquidity.liquidity).unwrap(),
-        )
+        let mut keys = vec![
+            format!("pool:{pool_address}"),
+            format!("pair:{token0_address}:{token1_address}"),
+            format!("pair:{token1_address}:{token0_address}"),
+        ];
+        for window in &windows {
+            keys.push(format!("{}:{pool_address}", window.key("Pool")));
+        }
+        store.set_many(ord, &keys, &liquidity)
     }
 }
 
 #[substreams::handlers::store]
 pub fn store_total_tx_counts(clock: Clock, events: Events, output: StoreAddBigInt) {
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
-    let day_id = timestamp_seconds / 86400;
-    let hour_id = timestamp_seconds / 3600;
-    let prev_day_id = day_id - 1;
-    let prev_hour_id = hour_id - 1;
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, timestamp_seconds);
+    let uniswap_windows = windows::current_windows(&UNISWAP_WINDOW_KINDS, timestamp_seconds);
     let factory_addr = Hex(UNISWAP_V3_FACTORY);
 
-    output.delete_prefix(0, &format!("UniswapDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
-    output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+    for window in &uniswap_windows {
+        output.delete_prefix(0, &window.prev_prefix("Uniswap"));
+    }
+    for window in &windows {
+        output.delete_prefix(0, &window.prev_prefix("Pool"));
+        output.delete_prefix(0, &window.prev_prefix("Token"));
+    }
 
     for event in events.pool_events {
         let pool_address = &event.pool_address;
         let token0_addr = &event.token0;
         let token1_addr = &event.token1;
 
-        output.add_many(
-            event.log_ordinal,
-            &vec![
-                format!("pool:{pool_address}"),
-                format!("token:{token0_addr}"),
-                format!("token:{token1_addr}"),
-                format!("factory:{factory_addr}"),
-                format!("UniswapDayData:{day_id}"),
-                format!("PoolDayData:{day_id}:{pool_address}"),
-                format!("PoolHourData:{hour_id}:{pool_address}"),
-                format!("TokenDayData:{day_id}:{token0_addr}"),
-                format!("TokenDayData:{day_id}:{token1_addr}"),
-                format!("TokenHourData:{hour_id}:{token0_addr}"),
-                format!("TokenHourData:{hour_id}:{token1_addr}"),
-            ],
-            &BigInt::from(1 as i32),
-        );
+        let mut keys = vec![
+            format!("pool:{pool_address}"),
+            format!("token:{token0_addr}"),
+            format!("token:{token1_addr}"),
+            format!("factory:{factory_addr}"),
+        ];
+        for window in &uniswap_windows {
+            keys.push(window.key("Uniswap"));
+        }
+        for window in &windows {
+            let pool_window_key = window.key("Pool");
+            let token_window_key = window.key("Token");
+            keys.push(format!("{pool_window_key}:{pool_address}"));
+            keys.push(format!("{token_window_key}:{token0_addr}"));
+            keys.push(format!("{token_window_key}:{token1_addr}"));
+        }
+
+        output.add_many(event.log_ordinal, &keys, &BigInt::one());
     }
 }
 
+// The policy followed by the handlers on data issues: `fail`, the default, `skip` or
+// `skip_and_report`. Params only reach their own module, the handlers take this one's
+// output instead.
+#[substreams::handlers::map]
+pub fn map_error_policy(params: String, _clock: Clock) -> Result<ErrorPolicy, Error> {
+    let policy = match Policy::from_name(&params) {
+        Policy::Fail => "fail",
+        Policy::Skip => "skip",
+        Policy::SkipAndReport => "skip_and_report",
+    };
+
+    Ok(ErrorPolicy {
+        policy: policy.to_string(),
+    })
+}
+
 #[substreams::handlers::store]
 pub fn store_swaps_volume(
     clock: Clock,
@@ -420,24 +680,33 @@ pub fn store_swaps_volume(
     store_pool: StoreGetProto<Pool>,
     store_total_tx_counts: StoreGetBigInt,
     store_eth_prices: StoreGetBigDecimal,
+    error_policy: ErrorPolicy, /* map_error_policy */
     output: StoreAddBigDecimal,
 ) {
+    let policy = Policy::from_error_policy(&error_policy);
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
-    let day_id = timestamp_seconds / 86400;
-    let hour_id = timestamp_seconds / 3600;
-    let prev_day_id = day_id - 1;
-    let prev_hour_id = hour_id - 1;
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, timestamp_seconds);
+    let uniswap_windows = windows::current_windows(&UNISWAP_WINDOW_KINDS, timestamp_seconds);
+    let source_windows = windows::current_windows(&SOURCE_WINDOW_KINDS, timestamp_seconds);
 
-    output.delete_prefix(0, &format!("UniswapDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
-    output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+    for window in &uniswap_windows {
+        output.delete_prefix(0, &window.prev_prefix("Uniswap"));
+    }
+    for window in &windows {
+        output.delete_prefix(0, &window.prev_prefix("Pool"));
+        output.delete_prefix(0, &window.prev_prefix("Token"));
+    }
+    for window in &source_windows {
+        output.delete_prefix(0, &window.prev_prefix("Source"));
+    }
 
     for event in events.pool_events {
         let ord = event.log_ordinal;
         let pool_address = &event.pool_address;
-        let pool = store_pool.must_get_last(format!("pool:{pool_address}"));
+        let pool = match policy.check("store_swaps_volume", errors::get_pool(&store_pool, pool_address)) {
+            Some(pool) => pool,
+            None => continue,
+        };
         if !store_total_tx_counts.has_last(format!("pool:{pool_address}")) {
             continue;
         }
@@ -445,38 +714,27 @@ pub fn store_swaps_volume(
         let token0_addr = &event.token0;
         let token1_addr = &event.token1;
         match event.r#type.unwrap() {
-            MintEvent(_) => output.add(
-                ord,
-                format!("pool:{pool_address}:liquidityProviderCount"),
-                &BigDecimal::one(),
-            ),
             SwapEvent(swap) => {
                 log::info!("transaction: {}", pool.transaction_id);
-                let eth_price_in_usd: BigDecimal = match store_eth_prices.get_at(ord, "bundle") {
-                    None => {
-                        panic!("bundle eth price not found")
-                    }
-                    Some(price) => price,
+                let SwapVolumeInputs {
+                    eth_price_in_usd,
+                    token0_derived_eth_price,
+                    token1_derived_eth_price,
+                    amount0_abs,
+                    amount1_abs,
+                    fee_tier,
+                } = match policy.check(
+                    "store_swaps_volume",
+                    utils::get_swap_volume_inputs(ord, &swap, &pool, &store_eth_prices),
+                ) {
+                    Some(swap_volume_inputs) => swap_volume_inputs,
+                    None => continue,
                 };
 
-                let token0_derived_eth_price =
-                    match store_eth_prices.get_at(ord, format!("token:{token0_addr}:dprice:eth")) {
-                        None => continue,
-                        Some(price) => price,
-                    };
-
-                let token1_derived_eth_price =
-                    match store_eth_prices.get_at(ord, format!("token:{token1_addr}:dprice:eth")) {
-                        None => continue,
-                        Some(price) => price,
-                    };
+                let source = swap.source.clone();
 
                 log::info!("token0_derived_eth_price {}", token0_derived_eth_price);
                 log::info!("token1_derived_eth_price {}", token1_derived_eth_price);
-
-                let amount0_abs = BigDecimal::try_from(swap.amount_0).unwrap().absolute();
-                let amount1_abs = BigDecimal::try_from(swap.amount_1).unwrap().absolute();
-
                 log::info!("amount0_abs {}", amount0_abs);
                 log::info!("amount1_abs {}", amount1_abs);
 
@@ -495,14 +753,10 @@ pub fn store_swaps_volume(
                 log::info!("volumeAmounts.untrackedETH {}", volume_amounts.stable_eth_untracked);
                 log::info!("volumeAmounts.untrackedUSD {}", volume_amounts.stable_usd_untracked);
 
-                let volume_eth = volume_amounts.delta_tvl_eth.clone().div(BigDecimal::from(2 as i32));
-                let volume_usd = volume_amounts.delta_tvl_usd.clone().div(BigDecimal::from(2 as i32));
-                let volume_usd_untracked = volume_amounts
-                    .stable_usd_untracked
-                    .clone()
-                    .div(BigDecimal::from(2 as i32));
+                let volume_eth = volume_amounts.delta_tvl_eth.clone().div(BigDecimal::from(2));
+                let volume_usd = volume_amounts.delta_tvl_usd.clone().div(BigDecimal::from(2));
+                let volume_usd_untracked = volume_amounts.stable_usd_untracked.clone().div(BigDecimal::from(2));
 
-                let fee_tier = BigDecimal::try_from(pool.fee_tier).unwrap();
                 let fee_eth: BigDecimal = volume_eth
                     .clone()
                     .mul(fee_tier.clone())
@@ -519,90 +773,79 @@ pub fn store_swaps_volume(
                 log::info!("fee_usd {}", fee_usd);
                 log::info!("fee_tier {}", fee_tier);
 
-                output.add_many(
-                    ord,
-                    &vec![
-                        format!("pool:{pool_address}:volumeToken0"),
-                        // FIXME: why compute volumes only for one side of the tokens?!  We should compute them for both sides no?
-                        //  Does it really matter which side the volume comes from?
-                        format!("token:{token0_addr}:volume"),
-                        format!("PoolDayData:{day_id}:{pool_address}:{token0_addr}:volumeToken0"),
-                        format!("TokenDayData:{day_id}:{token0_addr}:volume"),
-                        format!("PoolHourData:{hour_id}:{pool_address}:{token0_addr}:volumeToken0"),
-                        format!("TokenHourData:{hour_id}:{token0_addr}:volume"),
-                    ],
-                    &amount0_abs,
-                );
-                output.add_many(
-                    ord,
-                    &vec![
-                        format!("pool:{pool_address}:volumeToken1"),
-                        format!("token:{token1_addr}:volume"),
-                        format!("PoolDayData:{day_id}:{pool_address}:{token1_addr}:volumeToken1"),
-                        format!("TokenDayData:{day_id}:{token1_addr}:volume"),
-                        format!("PoolHourData:{hour_id}:{pool_address}:{token1_addr}:volumeToken1"),
-                        format!("TokenHourData:{hour_id}:{token1_addr}:volume"),
-                    ],
-                    &amount1_abs,
-                );
-                output.add_many(
-                    ord,
-                    &vec![
-                        format!("pool:{pool_address}:volumeUSD"),
-                        format!("token:{token0_addr}:volume:usd"), // TODO: does this make sens that the volume usd is the same
-                        format!("token:{token1_addr}:volume:usd"), // TODO: does this make sens that the volume usd is the same
-                        format!("factory:totalVolumeUSD"),
-                        format!("UniswapDayData:{day_id}:volumeUSD"),
-                        format!("PoolDayData:{day_id}:{pool_address}:volumeUSD"),
-                        format!("TokenDayData:{day_id}:{token0_addr}:volumeUSD"),
-                        format!("TokenDayData:{day_id}:{token1_addr}:volumeUSD"),
-                        format!("PoolHourData:{hour_id}:{pool_address}:volumeUSD"),
-                        format!("TokenHourData:{hour_id}:{token0_addr}:volumeUSD"),
-                        format!("TokenHourData:{hour_id}:{token1_addr}:volumeUSD"),
-                    ],
-                    //TODO: CONFIRM EQUALS -> IN THE SUBGRAPH THIS IS THE VOLUME USD
-                    &volume_usd,
-                );
-                output.add_many(
-                    ord,
-                    &vec![
-                        format!("factory:untrackedVolumeUSD"),
-                        format!("pool:{pool_address}:volumeUntrackedUSD"),
-                        format!("token:{token0_addr}:volume:untrackedUSD"),
-                        format!("token:{token1_addr}:volume:untrackedUSD"),
-                        format!("TokenDayData:{day_id}:{token0_addr}:volume:untrackedUSD"),
-                        format!("TokenDayData:{day_id}:{token1_addr}:volume:untrackedUSD"),
-                        format!("TokenHourData:{hour_id}:{token0_addr}:volume:untrackedUSD"),
-                        format!("TokenHourData:{hour_id}:{token1_addr}:volume:untrackedUSD"),
-                    ],
-                    &volume_usd_untracked,
-                );
-                output.add_many(
-                    ord,
-                    &vec![
-                        format!("factory:totalVolumeETH"),
-                        format!("UniswapDayData:{day_id}:volumeETH"),
-                    ],
-                    &volume_eth.clone(),
-                );
-                output.add_many(
-                    ord,
-                    &vec![
-                        format!("pool:{pool_address}:feesUSD"),
-                        format!("token:{token0_addr}:feesUSD"),
-                        format!("token:{token1_addr}:feesUSD"),
-                        format!("factory:totalFeesUSD"),
-                        format!("UniswapDayData:{day_id}:feesUSD"),
-                        format!("PoolDayData:{day_id}:{pool_address}:feesUSD"),
-                        format!("TokenDayData:{day_id}:{token0_addr}:feesUSD"),
-                        format!("TokenDayData:{day_id}:{token1_addr}:feesUSD"),
-                        format!("PoolHourData:{hour_id}:{pool_address}:feesUSD"),
-                        format!("TokenHourData:{hour_id}:{token0_addr}:feesUSD"),
-                        format!("TokenHourData:{hour_id}:{token1_addr}:feesUSD"),
-                    ],
-                    &fee_usd,
-                );
-                output.add(ord, format!("factory:totalFeesETH"), &fee_eth);
+                let mut volume_token0_keys = vec![
+                    format!("pool:{pool_address}:volumeToken0"),
+                    // FIXME: why compute volumes only for one side of the tokens?!  We should compute them for both sides no?
+                    //  Does it really matter which side the volume comes from?
+                    format!("token:{token0_addr}:volume"),
+                ];
+                let mut volume_token1_keys = vec![
+                    format!("pool:{pool_address}:volumeToken1"),
+                    format!("token:{token1_addr}:volume"),
+                ];
+                let mut volume_usd_keys = vec![
+                    format!("pool:{pool_address}:volumeUSD"),
+                    format!("token:{token0_addr}:volume:usd"), // TODO: does this make sens that the volume usd is the same
+                    format!("token:{token1_addr}:volume:usd"), // TODO: does this make sens that the volume usd is the same
+                    format!("factory:totalVolumeUSD"),
+                ];
+                let mut volume_untracked_usd_keys = vec![
+                    format!("factory:untrackedVolumeUSD"),
+                    format!("pool:{pool_address}:volumeUntrackedUSD"),
+                    format!("token:{token0_addr}:volume:untrackedUSD"),
+                    format!("token:{token1_addr}:volume:untrackedUSD"),
+                ];
+                let mut volume_eth_keys = vec![format!("factory:totalVolumeETH")];
+                let mut fees_usd_keys = vec![
+                    format!("pool:{pool_address}:feesUSD"),
+                    format!("token:{token0_addr}:feesUSD"),
+                    format!("token:{token1_addr}:feesUSD"),
+                    format!("factory:totalFeesUSD"),
+                ];
+
+                for window in &uniswap_windows {
+                    let uniswap_window_key = window.key("Uniswap");
+                    volume_usd_keys.push(format!("{uniswap_window_key}:volumeUSD"));
+                    volume_eth_keys.push(format!("{uniswap_window_key}:volumeETH"));
+                    fees_usd_keys.push(format!("{uniswap_window_key}:feesUSD"));
+                }
+                for window in &source_windows {
+                    let source_window_key = window.key("Source");
+                    volume_usd_keys.push(format!("{source_window_key}:{source}:volumeUSD"));
+                    fees_usd_keys.push(format!("{source_window_key}:{source}:feesUSD"));
+                }
+                for window in &windows {
+                    let pool_window_key = window.key("Pool");
+                    let token_window_key = window.key("Token");
+                    volume_token0_keys.push(format!("{pool_window_key}:{pool_address}:{token0_addr}:volumeToken0"));
+                    volume_token0_keys.push(format!("{token_window_key}:{token0_addr}:volume"));
+                    volume_token1_keys.push(format!("{pool_window_key}:{pool_address}:{token1_addr}:volumeToken1"));
+                    volume_token1_keys.push(format!("{token_window_key}:{token1_addr}:volume"));
+                    volume_usd_keys.push(format!("{pool_window_key}:{pool_address}:volumeUSD"));
+                    volume_usd_keys.push(format!("{token_window_key}:{token0_addr}:volumeUSD"));
+                    volume_usd_keys.push(format!("{token_window_key}:{token1_addr}:volumeUSD"));
+                    volume_untracked_usd_keys.push(format!("{token_window_key}:{token0_addr}:volume:untrackedUSD"));
+                    volume_untracked_usd_keys.push(format!("{token_window_key}:{token1_addr}:volume:untrackedUSD"));
+                    fees_usd_keys.push(format!("{pool_window_key}:{pool_address}:feesUSD"));
+                    fees_usd_keys.push(format!("{token_window_key}:{token0_addr}:feesUSD"));
+                    fees_usd_keys.push(format!("{token_window_key}:{token1_addr}:feesUSD"));
+                }
+
+                output.add_many(ord, &volume_token0_keys, &amount0_abs);
+                output.add_many(ord, &volume_token1_keys, &amount1_abs);
+                //TODO: CONFIRM EQUALS -> IN THE SUBGRAPH THIS IS THE VOLUME USD
+                output.add_many(ord, &volume_usd_keys, &volume_usd);
+                output.add_many(ord, &volume_untracked_usd_keys, &volume_usd_untracked);
+                output.add_many(ord, &volume_eth_keys, &volume_eth.clone());
+                output.add_many(ord, &fees_usd_keys, &fee_usd);
+                output.add(ord, "factory:totalFeesETH", &fee_eth);
+                for window in &source_windows {
+                    output.add(
+                        ord,
+                        format!("{}:{source}:swapCount", window.key("Source")),
+                        &BigDecimal::one(),
+                    );
+                }
             }
             _ => {}
         }
@@ -613,14 +856,19 @@ pub fn store_swaps_volume(
  * STORE NATIVE AMOUNTS -> spits out any mint, swap and burn amounts
  */
 #[substreams::handlers::store]
-pub fn store_native_amounts(events: Events, store: StoreSetBigDecimal) {
+pub fn store_native_amounts(
+    events: Events,
+    error_policy: ErrorPolicy, /* map_error_policy */
+    store: StoreSetBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
     for pool_event in events.pool_events {
         log::info!(
             "transaction_id: {} and type of pool event {:?}",
             pool_event.transaction_id,
-            pool_event.r#type.as_ref().unwrap(),
+            pool_event.r#type,
         );
-        if let Some(token_amounts) = pool_event.get_amounts() {
+        if let Some(token_amounts) = policy.check("store_native_amounts", pool_event.get_amounts()) {
             let amount0 = token_amounts.amount0;
             let amount1 = token_amounts.amount1;
             log::info!("amount 0: {} amount 1: {}", amount0, amount1);
@@ -649,26 +897,156 @@ pub fn store_native_amounts(events: Events, store: StoreSetBigDecimal) {
     }
 }
 
+#[substreams::handlers::map]
+pub fn map_oracle_prices(params: String, block: Block) -> Result<OraclePrices, Error> {
+    let feeds = oracle::OracleFeeds::from_params(&params)?;
+    let mut prices = vec![];
+    if feeds.is_empty() {
+        return Ok(OraclePrices { prices });
+    }
+
+    for trx in block.transactions() {
+        for (log, _) in trx.logs_with_calls() {
+            let feed = match feeds.feed(&log.address) {
+                Some(feed) => feed,
+                None => continue,
+            };
+            if let Some(price_usd) = oracle::decode_usd_price(log, feed.decimals) {
+                prices.push(uniswap::OraclePrice {
+                    token: feed.token.clone(),
+                    aggregator: Hex(&log.address).to_string(),
+                    price_usd: price_usd.to_string(),
+                    ordinal: log.ordinal,
+                });
+            }
+        }
+    }
+
+    Ok(OraclePrices { prices })
+}
+
 #[substreams::handlers::store]
-pub fn store_eth_prices(
+pub fn store_oracle_prices(oracle_prices: OraclePrices, output: StoreSetBigDecimal) {
+    for oracle_price in oracle_prices.prices {
+        output.set(
+            oracle_price.ordinal,
+            format!("token:{}:usd", oracle_price.token),
+            &BigDecimal::try_from(oracle_price.price_usd).unwrap(),
+        );
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_oracle_deviations(
     clock: Clock,
+    oracle_prices_deltas: Deltas<DeltaBigDecimal>, /* store_oracle_prices */
+    store_eth_prices: StoreGetBigDecimal,          /* store_eth_prices */
+) -> Result<OracleDeviations, Error> {
+    let timestamp = clock.timestamp.unwrap().seconds as u64;
+    let max_deviation = BigDecimal::try_from(price::MAX_ORACLE_PRICE_DEVIATION).unwrap();
+    let mut deviations = vec![];
+
+    for delta in oracle_prices_deltas
+        .iter()
+        .key_first_segment_eq("token")
+        .operation_not_eq(store_delta::Operation::Delete)
+    {
+        let token_address = key::segment_at(&delta.key, 1).to_string();
+        let derived_price_usd = price::get_token_price_usd(&store_eth_prices, delta.ordinal, &token_address);
+        if derived_price_usd.is_zero() || delta.new_value.is_zero() {
+            continue;
+        }
+
+        let deviation = derived_price_usd
+            .clone()
+            .sub(delta.new_value.clone())
+            .absolute()
+            .div(delta.new_value.clone());
+        if deviation <= max_deviation {
+            continue;
+        }
+
+        deviations.push(uniswap::OracleDeviation {
+            token: token_address,
+            oracle_price_usd: delta.new_value.to_string(),
+            derived_price_usd: derived_price_usd.to_string(),
+            deviation: deviation.to_string(),
+            ordinal: delta.ordinal,
+            block_number: clock.number,
+            timestamp,
+        });
+    }
+
+    Ok(OracleDeviations { deviations })
+}
+
+#[substreams::handlers::store]
+pub fn store_token_price_paths(
     events: Events,                                /* map_extract_data_types */
     pools_store: StoreGetProto<Pool>,              /* store_pools_created */
+    token_pools_store: StoreGetRaw,                /* store_token_pools */
     prices_store: StoreGetBigDecimal,              /* store_prices */
     tokens_whitelist_pools_store: StoreGetRaw,     /* store_tokens_whitelist_pools */
     total_native_amount_store: StoreGetBigDecimal, /* store_native_amounts */
     pool_liquidities_store: StoreGetBigInt,        /* store_pool_liquidities */
-    output: StoreSetBigDecimal,
+    pair_pools_store: StoreGetString,              /* store_pair_pools */
+    error_policy: ErrorPolicy,                     /* map_error_policy */
+    output: StoreSetProto<TokenPricePath>,
 ) {
-    let timestamp_seconds = clock.timestamp.unwrap().seconds;
-    let day_id = timestamp_seconds / 86400;
-    let hour_id = timestamp_seconds / 3600;
-    let prev_day_id = day_id - 1;
-    let prev_hour_id = hour_id - 1;
+    let policy = Policy::from_error_policy(&error_policy);
+    let pricing_stores = price::PricingStores {
+        pools: &pools_store,
+        pool_liquidities: &pool_liquidities_store,
+        pair_pools: &pair_pools_store,
+        token_pools: &token_pools_store,
+        tokens_whitelist_pools: &tokens_whitelist_pools_store,
+        total_native_amounts: &total_native_amount_store,
+        prices: &prices_store,
+    };
+
+    for pool_sqrt_price in events.pool_sqrt_prices {
+        let ord = pool_sqrt_price.ordinal;
+        let pool_address = &pool_sqrt_price.pool_address;
+        let pool = match policy.check("store_token_price_paths", errors::get_pool(&pools_store, pool_address)) {
+            Some(pool) => pool,
+            None => continue,
+        };
+
+        for token in [pool.token0.as_ref().unwrap(), pool.token1.as_ref().unwrap()] {
+            let token_price_path = price::find_token_price_path(ord, &pool.address, &token.address, &pricing_stores);
+            log::info!(
+                "token {} priced in {} through {} pools",
+                token.address,
+                token_price_path.target,
+                token_price_path.depth
+            );
+
+            output.set(ord, format!("token:{}", token.address), &token_price_path);
+        }
+    }
+}
 
-    output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+#[substreams::handlers::store]
+pub fn store_eth_prices(
+    params: String,
+    clock: Clock,
+    events: Events,                                         /* map_extract_data_types */
+    pools_store: StoreGetProto<Pool>,                       /* store_pools_created */
+    prices_store: StoreGetBigDecimal,                       /* store_prices */
+    pool_liquidities_store: StoreGetBigInt,                 /* store_pool_liquidities */
+    token_price_paths_store: StoreGetProto<TokenPricePath>, /* store_token_price_paths */
+    oracle_prices_store: StoreGetBigDecimal,                /* store_oracle_prices */
+    price_guard_quarantines_store: StoreGetInt64,           /* store_price_guard_quarantines */
+    error_policy: ErrorPolicy,                              /* map_error_policy */
+    output: StoreSetBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, clock.timestamp.unwrap().seconds);
+    for window in &windows {
+        output.delete_prefix(0, &window.prev_prefix("Token"));
+    }
 
+    let bundle_config = price::bundle_config_from_params(&params);
     for pool_sqrt_price in events.pool_sqrt_prices {
         let ord = pool_sqrt_price.ordinal;
         log::debug!(
@@ -677,7 +1055,10 @@ pub fn store_eth_prices(
             pool_sqrt_price.sqrt_price
         );
         let pool_address = &pool_sqrt_price.pool_address;
-        let pool = pools_store.must_get_last(format!("pool:{pool_address}"));
+        let pool = match policy.check("store_eth_prices", errors::get_pool(&pools_store, pool_address)) {
+            Some(pool) => pool,
+            None => continue,
+        };
         let token0 = pool.token0.as_ref().unwrap();
         let token1 = pool.token1.as_ref().unwrap();
         let token0_addr = &token0.address;
@@ -686,38 +1067,58 @@ pub fn store_eth_prices(
         token0.log();
         token1.log();
 
-        let bundle_eth_price_usd = price::get_eth_price_in_usd(&prices_store, ord);
-        log::info!("bundle_eth_price_usd: {}", bundle_eth_price_usd);
-
-        let token0_derived_eth_price: BigDecimal = price::find_eth_per_token(
-            ord,
-            &pool.address,
-            token0_addr,
+        let reference_prices = price::get_reference_eth_prices_in_usd(
+            &bundle_config.reference_pools,
             &pools_store,
-            &pool_liquidities_store,
-            &tokens_whitelist_pools_store,
-            &total_native_amount_store,
             &prices_store,
+            &pool_liquidities_store,
+            ord,
+        );
+        let oracle_eth_price_usd = match bundle_config.oracle {
+            true => price::get_oracle_eth_price_in_usd(&oracle_prices_store, ord),
+            false => None,
+        };
+        let bundle_eth_price_usd = match oracle_eth_price_usd {
+            Some(price) => price,
+            None => price::get_bundle_eth_price_in_usd(&reference_prices, &prices_store, ord),
+        };
+        log::info!("bundle_eth_price_usd: {}", bundle_eth_price_usd);
+
+        let token0_derived_eth_price = price::get_derived_eth_price(
+            token_price_paths_store.get_at(ord, format!("token:{token0_addr}")),
+            &bundle_eth_price_usd,
         );
         log::info!(format!(
             "token 0 {token0_addr} derived eth price: {token0_derived_eth_price}"
         ));
 
-        let token1_derived_eth_price: BigDecimal = price::find_eth_per_token(
-            ord,
-            &pool.address,
-            token1_addr,
-            &pools_store,
-            &pool_liquidities_store,
-            &tokens_whitelist_pools_store,
-            &total_native_amount_store,
-            &prices_store,
+        let token1_derived_eth_price = price::get_derived_eth_price(
+            token_price_paths_store.get_at(ord, format!("token:{token1_addr}")),
+            &bundle_eth_price_usd,
         );
         log::info!(format!(
             "token 1 {token1_addr} derived eth price: {token1_derived_eth_price}"
         ));
 
         output.set(ord, "bundle", &bundle_eth_price_usd);
+        for reference_price in reference_prices {
+            output.set(
+                ord,
+                format!("bundle:{}", reference_price.pool_address),
+                &reference_price.price,
+            );
+        }
+
+        // The token prices keep their last value while the pool is quarantined by the price
+        // guard, the bundle above is protected by its own outlier rejection.
+        if guard::is_quarantined(&price_guard_quarantines_store, pool_address, clock.number, ord) {
+            log::info!(
+                "skipping token prices of quarantined pool price update {}",
+                pool_address
+            );
+            continue;
+        }
+
         output.set(
             ord,
             format!("token:{token0_addr}:dprice:eth"),
@@ -735,35 +1136,120 @@ pub fn store_eth_prices(
         log::info!("token0 price usd: {}", token0_price_usd);
         log::info!("token1 price usd: {}", token1_price_usd);
 
-        // We only want to set the prices of TokenDayData and TokenHourData when
+        // We only want to set the prices of the token windows when
         // the pool is post-initialized, not on the initialized event.
         if pool_sqrt_price.initialized {
             continue;
         }
 
-        output.set_many(
+        // A token left without a route, e.g. when this pool was its only one and has no price
+        // anymore, has a zero derived price, which the windows skip to keep their last price
+        for (token_addr, token_price_usd) in [(token0_addr, &token0_price_usd), (token1_addr, &token1_price_usd)] {
+            if token_price_usd.is_zero() {
+                continue;
+            }
+            for window in &windows {
+                let window_key = window.key("Token");
+                output.set(ord, format!("{window_key}:{token_addr}"), token_price_usd);
+            }
+        }
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_token_price_provenance(
+    events: Events,                                         /* map_extract_data_types */
+    pools_store: StoreGetProto<Pool>,                       /* store_pools_created */
+    token_price_paths_store: StoreGetProto<TokenPricePath>, /* store_token_price_paths */
+    store_eth_prices: StoreGetBigDecimal,                   /* store_eth_prices */
+    total_native_amount_store: StoreGetBigDecimal,          /* store_native_amounts */
+    error_policy: ErrorPolicy,                              /* map_error_policy */
+) -> Result<TokenPriceProvenances, Error> {
+    let mut provenances = vec![];
+    let mut skipped_items = diagnostics::SkippedItems::new(Policy::from_error_policy(&error_policy));
+
+    for pool_sqrt_price in events.pool_sqrt_prices {
+        let ord = pool_sqrt_price.ordinal;
+        let pool_address = &pool_sqrt_price.pool_address;
+        let pool = match skipped_items.check(
+            "map_token_price_provenance",
             ord,
-            &vec![
-                format!("TokenDayData:{day_id}:{token0_addr}"),
-                format!("TokenHourData:{hour_id}:{token0_addr}"),
-            ],
-            &token0_price_usd,
+            pool_address,
+            errors::get_pool(&pools_store, pool_address),
+        ) {
+            Some(pool) => pool,
+            None => continue,
+        };
+
+        for token in [pool.token0.as_ref().unwrap(), pool.token1.as_ref().unwrap()] {
+            let token_address = &token.address;
+            provenances.push(price::get_token_price_provenance(
+                ord,
+                token_address,
+                token_price_paths_store.get_at(ord, format!("token:{token_address}")),
+                &store_eth_prices,
+                &total_native_amount_store,
+            ));
+        }
+    }
+
+    Ok(TokenPriceProvenances {
+        provenances,
+        skipped_items: skipped_items.into_items(),
+    })
+}
+
+#[substreams::handlers::store]
+pub fn store_token_price_provenance(provenances: TokenPriceProvenances, output: StoreSetProto<TokenPriceProvenance>) {
+    for provenance in provenances.provenances {
+        output.set(provenance.ordinal, format!("token:{}", provenance.token), &provenance);
+    }
+}
+
+// amounts of the pools kept apart from store_token_tvl, which takes them out of the token
+// totals when the pool is ignored by the spam filter
+#[substreams::handlers::store]
+pub fn store_pool_token_tvl(
+    events: Events,
+    error_policy: ErrorPolicy, /* map_error_policy */
+    output: StoreAddBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+    for pool_event in events.pool_events {
+        let token_amounts = match policy.check("store_pool_token_tvl", pool_event.get_amounts()) {
+            Some(token_amounts) => token_amounts,
+            None => continue,
+        };
+        let pool_address = &pool_event.pool_address;
+        let ord = pool_event.log_ordinal;
+
+        output.add(
+            ord,
+            format!("pool:{pool_address}:{}:token0", pool_event.token0),
+            &token_amounts.amount0,
         );
-        output.set_many(
+        output.add(
             ord,
-            &vec![
-                format!("TokenDayData:{day_id}:{token1_addr}"),
-                format!("TokenHourData:{hour_id}:{token1_addr}"),
-            ],
-            &token1_price_usd,
+            format!("pool:{pool_address}:{}:token1", pool_event.token1),
+            &token_amounts.amount1,
         );
     }
 }
 
 #[substreams::handlers::store]
-pub fn store_token_tvl(events: Events, output: StoreAddBigDecimal) {
+pub fn store_token_tvl(
+    events: Events,
+    filtered_pools: FilteredPools,            /* map_spam_pools */
+    pool_token_tvl_store: StoreGetBigDecimal, /* store_pool_token_tvl */
+    error_policy: ErrorPolicy,                /* map_error_policy */
+    output: StoreAddBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
     for pool_event in events.pool_events {
-        let token_amounts = pool_event.get_amounts().unwrap();
+        let token_amounts = match policy.check("store_token_tvl", pool_event.get_amounts()) {
+            Some(token_amounts) => token_amounts,
+            None => continue,
+        };
         let pool_address = pool_event.pool_address.to_string();
         let token0_addr = pool_event.token0.to_string();
         let token1_addr = pool_event.token1.to_string();
@@ -787,38 +1273,78 @@ pub fn store_token_tvl(events: Events, output: StoreAddBigDecimal) {
             &token_amounts.amount1,
         );
     }
+
+    // the pools ignored after their creation no longer have any event, what they hold is
+    // taken out of the pool and token totals
+    for filtered_pool in filtered_pools.filtered_pools {
+        if filtered_pool.at_creation {
+            continue;
+        }
+        let pool = filtered_pool.pool.unwrap();
+        let pool_address = &pool.address;
+        for (token, token_idx) in [(pool.token0_ref(), "token0"), (pool.token1_ref(), "token1")] {
+            let token_addr = &token.address;
+            let pool_key = format!("pool:{pool_address}:{token_addr}:{token_idx}");
+            let amount = match pool_token_tvl_store.get_last(&pool_key) {
+                Some(amount) => amount,
+                None => continue,
+            };
+            output.add_many(
+                filtered_pool.ordinal,
+                &vec![pool_key, format!("token:{token_addr}")],
+                &amount.neg(),
+            );
+        }
+    }
 }
 
 #[substreams::handlers::store]
 pub fn store_derived_tvl(
     clock: Clock,
     events: Events,
+    filtered_pools: FilteredPools,                /* map_spam_pools */
     token_total_value_locked: StoreGetBigDecimal, /* store_token_tvl  */
     pools_store: StoreGetProto<Pool>,
     eth_prices_store: StoreGetBigDecimal,
+    error_policy: ErrorPolicy, /* map_error_policy */
     output: StoreSetBigDecimal,
 ) {
-    let timestamp_seconds = clock.timestamp.unwrap().seconds;
-    let day_id: i64 = timestamp_seconds / 86400;
-    let hour_id: i64 = timestamp_seconds / 3600;
-    let prev_day_id = day_id - 1;
-    let prev_hour_id = hour_id - 1;
-
-    output.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
-    output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+    let policy = Policy::from_error_policy(&error_policy);
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, clock.timestamp.unwrap().seconds);
+    for window in &windows {
+        output.delete_prefix(0, &window.prev_prefix("Pool"));
+        output.delete_prefix(0, &window.prev_prefix("Token"));
+    }
 
-    for pool_event in events.pool_events {
-        let ord = pool_event.log_ordinal;
+    for (ord, pool_address) in utils::pool_tvl_updates(&events.pool_events, &filtered_pools.filtered_pools) {
         let eth_price_usd = match &eth_prices_store.get_at(ord, "bundle") {
             None => continue,
             Some(price) => price.with_prec(100),
         };
         log::info!("eth_price_usd {}", eth_price_usd);
 
-        let pool = pools_store.must_get_last(format!("pool:{}", pool_event.pool_address));
-        let pool_address = &pool_event.pool_address;
+        let pool_address = &pool_address;
+        let PoolTvlInputs {
+            pool,
+            token0_derived_eth_price: token0_derive_eth,
+            token1_derived_eth_price: token1_derive_eth,
+            token0_tvl_in_pool: tvl_token0_in_pool,
+            token1_tvl_in_pool: tvl_token1_in_pool,
+            token0_tvl: tvl_for_token0,
+            token1_tvl: tvl_for_token1,
+        } = match policy.check(
+            "store_derived_tvl",
+            utils::get_pool_tvl_inputs(
+                ord,
+                pool_address,
+                &pools_store,
+                &eth_prices_store,
+                &token_total_value_locked,
+            ),
+        ) {
+            Some(pool_tvl_inputs) => pool_tvl_inputs,
+            None => continue,
+        };
         let token0_addr = &pool.token0.as_ref().unwrap().address();
         let token1_addr = &pool.token1.as_ref().unwrap().address();
 
@@ -826,17 +1352,6 @@ pub fn store_derived_tvl(
         log::info!("token0 address {}", token0_addr);
         log::info!("token1 address {}", token1_addr);
 
-        let token0_derive_eth = utils::get_derived_eth_price(ord, token0_addr, &eth_prices_store);
-        let token1_derive_eth = utils::get_derived_eth_price(ord, token1_addr, &eth_prices_store);
-
-        let tvl_token0_in_pool =
-            utils::get_token_tvl_in_pool(ord, pool_address, token0_addr, "token0", &token_total_value_locked);
-        let tvl_token1_in_pool =
-            utils::get_token_tvl_in_pool(ord, pool_address, token1_addr, "token1", &token_total_value_locked);
-
-        let tvl_for_token0 = utils::get_token_tvl(ord, token0_addr, &token_total_value_locked);
-        let tvl_for_token1 = utils::get_token_tvl(ord, token1_addr, &token_total_value_locked);
-
         log::info!("total_value_locked_token0 in pool: {}", tvl_token0_in_pool);
         log::info!("total_value_locked_token1 in pool: {}", tvl_token1_in_pool);
         log::info!("total_value_locked_token0 for token: {}", tvl_for_token0);
@@ -874,22 +1389,24 @@ pub fn store_derived_tvl(
             .clone()
             .mul(token1_derive_eth.clone().mul(eth_price_usd.clone()));
 
+        let mut token0_tvl_usd_keys = vec![format!("token:{token0_addr}:totalValueLockedUSD")];
+        let mut token1_tvl_usd_keys = vec![format!("token:{token1_addr}:totalValueLockedUSD")];
+        let mut pool_tvl_usd_keys = vec![format!("pool:{pool_address}:totalValueLockedUSD")];
+        for window in &windows {
+            let token_window_key = window.key("Token");
+            token0_tvl_usd_keys.push(format!("{token_window_key}:{token0_addr}:totalValueLockedUSD"));
+            token1_tvl_usd_keys.push(format!("{token_window_key}:{token1_addr}:totalValueLockedUSD"));
+            pool_tvl_usd_keys.push(format!("{}:{pool_address}:totalValueLockedUSD", window.key("Pool")));
+        }
+
         output.set_many(
             ord,
-            &vec![
-                format!("token:{token0_addr}:totalValueLockedUSD"),
-                format!("TokenDayData:{day_id}:{token0_addr}:totalValueLockedUSD"),
-                format!("TokenHourData:{hour_id}:{token0_addr}:totalValueLockedUSD"),
-            ],
+            &token0_tvl_usd_keys,
             &derived_tvl_usd_for_token0, // token0.totalValueLockedUSD
         );
         output.set_many(
             ord,
-            &vec![
-                format!("token:{token1_addr}:totalValueLockedUSD"),
-                format!("TokenDayData:{day_id}:{token1_addr}:totalValueLockedUSD"),
-                format!("TokenHourData:{hour_id}:{token1_addr}:totalValueLockedUSD"),
-            ],
+            &token1_tvl_usd_keys,
             &derived_tvl_usd_for_token1, // token1.totalValueLockedUSD
         );
 
@@ -901,17 +1418,13 @@ pub fn store_derived_tvl(
 
         output.set_many(
             ord,
-            &vec![
-                format!("pool:{pool_address}:totalValueLockedUSD"),
-                format!("PoolDayData:{day_id}:{pool_address}:totalValueLockedUSD"),
-                format!("PoolHourData:{hour_id}:{pool_address}:totalValueLockedUSD"),
-            ],
+            &pool_tvl_usd_keys,
             &amounts_in_pool.delta_tvl_usd, // pool.totalValueLockedUSD
         );
 
         // pool.totalValueLockedETHUntracked
         output.set(
-            pool_event.log_ordinal,
+            ord,
             format!("pool:{pool_address}:totalValueLockedETHUntracked"),
             &amounts_in_pool.stable_eth_untracked,
         );
@@ -925,16 +1438,168 @@ pub fn store_derived_tvl(
     }
 }
 
+#[substreams::handlers::map]
+pub fn map_pool_token_transfers(block: Block, pools_store: StoreGetProto<Pool>) -> Result<PoolTokenTransfers, Error> {
+    let mut transfers = vec![];
+
+    for trx in block.transactions() {
+        let transaction_id = Hex(&trx.hash).to_string();
+        for (log, _) in trx.logs_with_calls() {
+            balances::extract_pool_token_transfers(&mut transfers, log, &transaction_id, &pools_store);
+        }
+    }
+
+    Ok(PoolTokenTransfers { transfers })
+}
+
+#[substreams::handlers::store]
+pub fn store_pool_balances(
+    transfers: PoolTokenTransfers,
+    error_policy: ErrorPolicy, /* map_error_policy */
+    output: StoreAddBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+
+    for transfer in transfers.transfers {
+        let ord = transfer.ordinal;
+        let pool_address = &transfer.pool_address;
+        let token_address = &transfer.token;
+        let amount = match policy.check("store_pool_balances", errors::parse_decimal("amount", &transfer.amount)) {
+            Some(amount) => amount,
+            None => continue,
+        };
+        output.add(
+            ord,
+            format!("pool:{pool_address}:{token_address}:token{}", transfer.token_idx),
+            amount,
+        );
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_pool_balance_tvl(
+    clock: Clock,
+    transfers: PoolTokenTransfers,              /* map_pool_token_transfers */
+    eth_prices_deltas: Deltas<DeltaBigDecimal>, /* store_eth_prices */
+    pools_store: StoreGetProto<Pool>,           /* store_pools_created */
+    pool_balances_store: StoreGetBigDecimal,    /* store_pool_balances */
+    eth_prices_store: StoreGetBigDecimal,       /* store_eth_prices */
+    token_pools_store: StoreGetRaw,             /* store_token_pools */
+    error_policy: ErrorPolicy,                  /* map_error_policy */
+    output: StoreSetBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, clock.timestamp.unwrap().seconds);
+    for window in &windows {
+        output.delete_prefix(0, &window.prev_prefix("Pool"));
+    }
+    let set_field = |ord: u64, pool_address: &String, field: &str, value: &BigDecimal| {
+        let mut keys = vec![format!("pool:{pool_address}:{field}")];
+        for window in &windows {
+            keys.push(format!("{}:{pool_address}:{field}", window.key("Pool")));
+        }
+        output.set_many(ord, &keys, value);
+    };
+
+    let updates = balances::balance_updates(&transfers.transfers, &eth_prices_deltas, &token_pools_store);
+    for (ord, pool_address, transfer_token) in updates {
+        // the balance of the token transferred is known without the pool
+        if let Some((token_idx, token_address)) = transfer_token {
+            let balance = pool_balances_store
+                .get_at(ord, format!("pool:{pool_address}:{token_address}:token{token_idx}"))
+                .unwrap_or_else(BigDecimal::zero);
+            set_field(ord, &pool_address, &format!("balanceToken{token_idx}"), &balance);
+        }
+
+        let pool = match policy.check("store_pool_balance_tvl", errors::get_pool(&pools_store, &pool_address)) {
+            Some(pool) => pool,
+            None => continue,
+        };
+        let token0_addr = &pool.token0_ref().address;
+        let token1_addr = &pool.token1_ref().address;
+
+        let balance_tvl_usd = match eth_prices_store.get_at(ord, "bundle") {
+            Some(eth_price_usd) => balances::balance_tvl_usd(
+                &pool,
+                &pool_balances_store
+                    .get_at(ord, format!("pool:{pool_address}:{token0_addr}:token0"))
+                    .unwrap_or_else(BigDecimal::zero),
+                &pool_balances_store
+                    .get_at(ord, format!("pool:{pool_address}:{token1_addr}:token1"))
+                    .unwrap_or_else(BigDecimal::zero),
+                utils::get_derived_eth_price(ord, token0_addr, &eth_prices_store).ok(),
+                utils::get_derived_eth_price(ord, token1_addr, &eth_prices_store).ok(),
+                &eth_price_usd,
+            ),
+            None => BigDecimal::zero(),
+        };
+        set_field(ord, &pool_address, "balanceTotalValueLockedUSD", &balance_tvl_usd);
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_token_behaviours(block: Block, pools_store: StoreGetProto<Pool>) -> Result<TokenBehaviours, Error> {
+    let mut behaviours = vec![];
+
+    for trx in block.transactions() {
+        let mut pools: Vec<Pool> = vec![];
+        for (log, _) in trx.logs_with_calls() {
+            balances::extract_token_behaviours(&mut behaviours, trx, log, &pools_store);
+
+            if !balances::is_pool_event(log) {
+                continue;
+            }
+            if let Some(pool) = pools_store.get_last(format!("pool:{}", Hex(&log.address))) {
+                if !pool.ignore_pool && !pools.contains(&pool) {
+                    pools.push(pool);
+                }
+            }
+        }
+        balances::extract_rebasing_behaviours(&mut behaviours, trx, &pools);
+    }
+
+    Ok(TokenBehaviours { behaviours })
+}
+
+#[substreams::handlers::store]
+pub fn store_token_behaviour_counts(behaviours: TokenBehaviours, output: StoreAddInt64) {
+    let mut seen = HashSet::new();
+    for behaviour in behaviours.behaviours {
+        let field = match balances::behaviour_field(&behaviour.kind) {
+            Some(field) => field,
+            None => continue,
+        };
+        let key = format!("token:{}:{field}", behaviour.token);
+        // once per transaction
+        if seen.insert((key.clone(), behaviour.transaction_id)) {
+            output.add(behaviour.ordinal, key, 1);
+        }
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_token_behaviours(
+    clock: Clock,
+    behaviour_counts_deltas: Deltas<DeltaInt64>, /* store_token_behaviour_counts */
+    output: StoreSetIfNotExistsInt64,
+) {
+    for delta in behaviour_counts_deltas.deltas {
+        if delta.old_value < balances::BEHAVIOUR_OBSERVATIONS && delta.new_value >= balances::BEHAVIOUR_OBSERVATIONS {
+            output.set_if_not_exists(delta.ordinal, delta.key, &(clock.number as i64));
+        }
+    }
+}
+
 #[substreams::handlers::store]
 pub fn store_derived_factory_tvl(
     clock: Clock,
     derived_tvl_deltas: Deltas<DeltaBigDecimal>,
     output: StoreAddBigDecimal,
 ) {
-    let timestamp_seconds = clock.timestamp.unwrap().seconds;
-    let day_id: i64 = timestamp_seconds / 86400;
-    let prev_day_id = day_id - 1;
-    output.delete_prefix(0, &format!("UniswapDayData:{prev_day_id}:"));
+    let uniswap_windows = windows::current_windows(&UNISWAP_WINDOW_KINDS, clock.timestamp.unwrap().seconds);
+    for window in &uniswap_windows {
+        output.delete_prefix(0, &window.prev_prefix("Uniswap"));
+    }
 
     for delta in derived_tvl_deltas.into_iter().key_first_segment_eq("pool") {
         log::info!("delta key {}", delta.key);
@@ -954,14 +1619,13 @@ pub fn store_derived_factory_tvl(
             "totalValueLockedETHUntracked" => {
                 output.add(ord, &format!("factory:totalValueLockedETHUntracked"), delta_diff)
             }
-            "totalValueLockedUSD" => output.add_many(
-                ord,
-                &vec![
-                    format!("factory:totalValueLockedUSD"),
-                    format!("UniswapDayData:{day_id}:totalValueLockedUSD"),
-                ],
-                delta_diff,
-            ),
+            "totalValueLockedUSD" => {
+                let mut keys = vec![format!("factory:totalValueLockedUSD")];
+                for window in &uniswap_windows {
+                    keys.push(format!("{}:totalValueLockedUSD", window.key("Uniswap")));
+                }
+                output.add_many(ord, &keys, delta_diff)
+            }
             "totalValueLockedUSDUntracked" => {
                 output.add(ord, &format!("factory:totalValueLockedUSDUntracked"), delta_diff)
             }
@@ -977,7 +1641,13 @@ fn calculate_diff(delta: &DeltaBigDecimal) -> BigDecimal {
 }
 
 #[substreams::handlers::store]
-pub fn store_ticks_liquidities(clock: Clock, events: Events, output: StoreAddBigInt) {
+pub fn store_ticks_liquidities(
+    clock: Clock,
+    events: Events,
+    error_policy: ErrorPolicy, /* map_error_policy */
+    output: StoreAddBigInt,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id = timestamp_seconds / 86400;
     let hour_id = timestamp_seconds / 3600;
@@ -986,11 +1656,16 @@ pub fn store_ticks_liquidities(clock: Clock, events: Events, output: StoreAddBig
 
     output.delete_prefix(0, &format!("TickDayData:{prev_day_id}:"));
     output.delete_prefix(0, &format!("TickHourData:{prev_hour_id}:"));
+    let parse_amount = |amount: &str| policy.check("store_ticks_liquidities", errors::parse_int("amount", amount));
 
     for event in events.pool_events {
         let pool = event.pool_address;
         match event.r#type.unwrap() {
             Type::Mint(mint) => {
+                let amount = match parse_amount(&mint.amount) {
+                    Some(amount) => amount,
+                    None => continue,
+                };
                 let tick_lower = &mint.tick_lower;
                 let tick_upper = &mint.tick_upper;
                 output.add_many(
@@ -1006,7 +1681,7 @@ pub fn store_ticks_liquidities(clock: Clock, events: Events, output: StoreAddBig
                         format!("TickHourData:{hour_id}:{pool}:{tick_lower}:liquidityNet"),
                         format!("TickHourData:{hour_id}:{pool}:{tick_upper}:liquidityGross"),
                     ],
-                    &BigInt::try_from(mint.amount.clone()).unwrap(),
+                    &amount,
                 );
                 output.add_many(
                     event.log_ordinal,
@@ -1015,10 +1690,14 @@ pub fn store_ticks_liquidities(clock: Clock, events: Events, output: StoreAddBig
                         format!("TickDayData:{day_id}:{pool}:{tick_upper}:liquidityNet"),
                         format!("TickHourData:{hour_id}:{pool}:{tick_upper}:liquidityNet"),
                     ],
-                    &BigInt::try_from(mint.amount.clone()).unwrap().neg(),
+                    &amount.neg(),
                 );
             }
             Type::Burn(burn) => {
+                let amount = match parse_amount(&burn.amount) {
+                    Some(amount) => amount,
+                    None => continue,
+                };
                 let tick_lower = &burn.tick_lower;
                 let tick_upper = &burn.tick_upper;
                 output.add_many(
@@ -1034,7 +1713,7 @@ pub fn store_ticks_liquidities(clock: Clock, events: Events, output: StoreAddBig
                         format!("TickHourData:{hour_id}:{pool}:{tick_lower}:liquidityNet"),
                         format!("TickHourData:{hour_id}:{pool}:{tick_upper}:liquidityGross"),
                     ],
-                    &BigInt::try_from(&burn.amount).unwrap().neg(),
+                    &amount.clone().neg(),
                 );
                 output.add_many(
                     event.log_ordinal,
@@ -1043,7 +1722,7 @@ pub fn store_ticks_liquidities(clock: Clock, events: Events, output: StoreAddBig
                         format!("TickDayData:{day_id}:{pool}:{tick_upper}:liquidityNet"),
                         format!("TickHourData:{hour_id}:{pool}:{tick_upper}:liquidityNet"),
                     ],
-                    &BigInt::try_from(&burn.amount).unwrap(),
+                    &amount,
                 );
             }
             _ => {}
@@ -1051,42 +1730,296 @@ pub fn store_ticks_liquidities(clock: Clock, events: Events, output: StoreAddBig
     }
 }
 
-#[substreams::handlers::store]
-pub fn store_positions(events: Events, output: StoreSetProto<PositionEvent>) {
-    let mut positions_events: Vec<PositionEvent> = vec![];
-    for pos in events.created_positions {
-        positions_events.push(PositionEvent {
-            r#type: Some(CreatedPosition(pos)),
-        });
-    }
+#[substreams::handlers::map]
+pub fn map_invariant_checks(
+    block: Block,
+    events: Events,                          /* map_extract_data_types */
+    pools_store: StoreGetProto<Pool>,        /* store_pools_created */
+    ticks_liquidities_store: StoreGetBigInt, /* store_ticks_liquidities */
+    pool_liquidities_store: StoreGetBigInt,  /* store_pool_liquidities */
+) -> Result<InvariantChecks, Error> {
+    let checker = invariants::InvariantChecker::new(&events.pool_events, block.number, block.timestamp_seconds());
+
+    let mut discrepancies = checker.tick_liquidities(&events.ticks_updated, &ticks_liquidities_store);
+    discrepancies.extend(checker.pool_liquidities(&pool_liquidities_store));
+    discrepancies.extend(checker.pool_balances(&block, |pool_address| {
+        pools_store.get_last(format!("pool:{pool_address}"))
+    }));
+
+    Ok(InvariantChecks { discrepancies })
+}
 
-    for pos in events.increase_liquidity_positions {
-        positions_events.push(PositionEvent {
-            r#type: Some(IncreaseLiquidityPosition(pos)),
-        });
+// The stores only keep their own values: the items they skip are found by running their
+// checks again, through the same helpers, while the map handlers carry theirs.
+#[substreams::handlers::map]
+pub fn map_diagnostics(
+    clock: Clock,
+    events: Events,                                /* map_extract_data_types */
+    position_manager_events: Events,               /* map_position_manager_events */
+    transfers: PoolTokenTransfers,                 /* map_pool_token_transfers */
+    filtered_pools: FilteredPools,                 /* map_spam_pools */
+    pools_created: Pools,                          /* map_pools_created */
+    jit_liquidities: JitLiquidities,               /* map_jit_liquidity */
+    mev_events: MevEvents,                         /* map_mev */
+    trades: Trades,                                /* map_trades */
+    candle_updates: CandleUpdates,                 /* map_candle_updates */
+    provenances: TokenPriceProvenances,            /* map_token_price_provenance */
+    eth_prices_deltas: Deltas<DeltaBigDecimal>,    /* store_eth_prices */
+    pools_store: StoreGetProto<Pool>,              /* store_pools_created */
+    tx_count_store: StoreGetBigInt,                /* store_total_tx_counts */
+    eth_prices_store: StoreGetBigDecimal,          /* store_eth_prices */
+    token_tvl_store: StoreGetBigDecimal,           /* store_token_tvl */
+    token_pools_store: StoreGetRaw,                /* store_token_pools */
+    store_positions: StoreGetProto<PositionEvent>, /* store_positions */
+    position_changes_store: StoreGetBigDecimal,    /* store_position_changes */
+    tokens_store: StoreGetInt64,                   /* store_tokens */
+    error_policy: ErrorPolicy,                     /* map_error_policy */
+) -> Result<Diagnostics, Error> {
+    let policy = Policy::from_error_policy(&error_policy);
+    if policy != Policy::SkipAndReport {
+        return Ok(Diagnostics::default());
+    }
+    let mut skipped = diagnostics::SkippedItems::new(policy);
+
+    for update in &events.pool_sqrt_prices {
+        let ord = update.ordinal;
+        let pool_address = &update.pool_address;
+        if pools_store.has_last(format!("pool:{pool_address}")) && !update.price_unavailable {
+            skipped.check(
+                "store_prices",
+                ord,
+                pool_address,
+                errors::parse_decimal("sqrt price", &update.sqrt_price),
+            );
+        }
+        for module in ["store_token_price_paths", "store_eth_prices"] {
+            skipped.check(module, ord, pool_address, errors::get_pool(&pools_store, pool_address));
+        }
     }
 
-    for pos in events.decrease_liquidity_positions {
-        positions_events.push(PositionEvent {
-            r#type: Some(DecreaseLiquidityPosition(pos)),
-        });
+    for pool_liquidity in &events.pool_liquidities {
+        skipped.check(
+            "store_pool_liquidities",
+            pool_liquidity.log_ordinal,
+            &pool_liquidity.pool_address,
+            errors::parse_int("liquidity", &pool_liquidity.liquidity),
+        );
     }
 
-    for pos in events.collect_positions {
-        positions_events.push(PositionEvent {
-            r#type: Some(CollectPosition(pos)),
-        });
+    for event in &events.pool_events {
+        let ord = event.log_ordinal;
+        let pool_address = &event.pool_address;
+        for module in ["store_native_amounts", "store_pool_token_tvl", "store_token_tvl"] {
+            skipped.check(module, ord, pool_address, event.get_amounts());
+        }
+
+        match event.r#type.as_ref() {
+            Some(Type::Mint(mint)) => {
+                skipped.check(
+                    "store_ticks_liquidities",
+                    ord,
+                    pool_address,
+                    errors::parse_int("amount", &mint.amount),
+                );
+            }
+            Some(Type::Burn(burn)) => {
+                skipped.check(
+                    "store_ticks_liquidities",
+                    ord,
+                    pool_address,
+                    errors::parse_int("amount", &burn.amount),
+                );
+            }
+            _ => {}
+        }
+
+        let pool = match skipped.check(
+            "store_swaps_volume",
+            ord,
+            pool_address,
+            errors::get_pool(&pools_store, pool_address),
+        ) {
+            Some(pool) => pool,
+            None => continue,
+        };
+        if let Some(SwapEvent(swap)) = &event.r#type {
+            if tx_count_store.has_last(format!("pool:{pool_address}")) {
+                skipped.check(
+                    "store_swaps_volume",
+                    ord,
+                    pool_address,
+                    utils::get_swap_volume_inputs(ord, swap, &pool, &eth_prices_store),
+                );
+            }
+        }
     }
 
-    for pos in events.transfer_positions {
-        positions_events.push(PositionEvent {
-            r#type: Some(TransferPosition(pos)),
-        });
+    for (ord, pool_address) in utils::pool_tvl_updates(&events.pool_events, &filtered_pools.filtered_pools) {
+        if eth_prices_store.has_at(ord, "bundle") {
+            skipped.check(
+                "store_derived_tvl",
+                ord,
+                &pool_address,
+                utils::get_pool_tvl_inputs(ord, &pool_address, &pools_store, &eth_prices_store, &token_tvl_store),
+            );
+        }
     }
 
-    positions_events.sort_by(|x, y| x.get_ordinal().cmp(&y.get_ordinal()));
+    for transfer in &transfers.transfers {
+        skipped.check(
+            "store_pool_balances",
+            transfer.ordinal,
+            &transfer.pool_address,
+            errors::parse_decimal("amount", &transfer.amount),
+        );
+    }
+    for (ord, pool_address, _) in
+        balances::balance_updates(&transfers.transfers, &eth_prices_deltas, &token_pools_store)
+    {
+        skipped.check(
+            "store_pool_balance_tvl",
+            ord,
+            &pool_address,
+            errors::get_pool(&pools_store, &pool_address),
+        );
+    }
 
-    for position in positions_events {
+    // the position events carry no pool address
+    for position in position_manager_events.position_events() {
+        let (ord, amounts) = match position.r#type.unwrap() {
+            IncreaseLiquidityPosition(pos) => (
+                pos.log_ordinal,
+                errors::parse_decimals([
+                    ("liquidity", &pos.liquidity),
+                    ("deposited token0", &pos.deposited_token0),
+                    ("deposited token1", &pos.deposited_token1),
+                ])
+                .map(|_| ()),
+            ),
+            DecreaseLiquidityPosition(pos) => (
+                pos.log_ordinal,
+                errors::parse_decimals([
+                    ("liquidity", &pos.liquidity),
+                    ("withdrawn token0", &pos.withdrawn_token0),
+                    ("withdrawn token1", &pos.withdrawn_token1),
+                ])
+                .map(|_| ()),
+            ),
+            CollectPosition(pos) => (
+                pos.log_ordinal,
+                errors::parse_decimals([
+                    ("collected fees token0", &pos.collected_fees_token0),
+                    ("collected fees token1", &pos.collected_fees_token1),
+                ])
+                .map(|_| ()),
+            ),
+            _ => continue,
+        };
+        skipped.check("store_position_changes", ord, "", amounts);
+    }
+
+    for change in lps::extract_lp_liquidity_changes(
+        &events.pool_events,
+        &position_manager_events,
+        |token_id| lps::position_pool(&store_positions, token_id),
+        |token_id, ord| lps::position_owner(&store_positions, token_id, ord),
+        |token_id, ord| {
+            position_changes_store
+                .get_at(ord, format!("position:{token_id}:liquidity"))
+                .map(|liquidity| liquidity.to_bigint())
+        },
+    ) {
+        skipped.check(
+            "store_lp_liquidities",
+            change.ordinal,
+            &change.pool_address,
+            change.liquidity,
+        );
+    }
+
+    for jit_liquidity in &jit_liquidities.jit_liquidities {
+        skipped.check(
+            "store_jit_windows",
+            jit_liquidity.burn_ordinal,
+            &jit_liquidity.pool_address,
+            errors::parse_decimals([
+                ("jit volume usd", &jit_liquidity.jit_volume_usd),
+                ("fees usd", &jit_liquidity.fees_usd),
+            ]),
+        );
+    }
+
+    for trade in &trades.trades {
+        // a trade goes through several pools, the first one is reported
+        skipped.check(
+            "store_trade_volumes",
+            trade.log_ordinal,
+            trade.pools.first().map(String::as_str).unwrap_or_default(),
+            errors::parse_decimal("amount usd", &trade.amount_usd),
+        );
+    }
+
+    for update in &candle_updates.updates {
+        let ord = update.ordinal;
+        let pool_address = &update.pool_address;
+        // the USD prices are left empty without a bundle price
+        let prices = [
+            ("token0 price", &update.token0_price),
+            ("token1 price", &update.token1_price),
+            ("token0 price usd", &update.token0_price_usd),
+            ("token1 price usd", &update.token1_price_usd),
+        ];
+        for (name, price) in prices.into_iter().filter(|(_, price)| !price.is_empty()) {
+            skipped.check(
+                "store_candle_prices",
+                ord,
+                pool_address,
+                errors::parse_decimal(name, price),
+            );
+        }
+        skipped.check(
+            "store_candle_volumes",
+            ord,
+            pool_address,
+            errors::parse_decimals([
+                ("volume token0", &update.volume_token0),
+                ("volume token1", &update.volume_token1),
+                ("volume usd", &update.volume_usd),
+            ]),
+        );
+    }
+
+    for pool in &pools_created.pools {
+        for token in [pool.token0_ref(), pool.token1_ref()] {
+            skipped.check(
+                "graph_out",
+                pool.log_ordinal,
+                &pool.address,
+                errors::get_token_pool_count(pool.log_ordinal, &token.address, &tokens_store),
+            );
+        }
+    }
+
+    skipped.extend(provenances.skipped_items);
+    skipped.extend(jit_liquidities.skipped_items);
+    skipped.extend(mev_events.skipped_items);
+    skipped.extend(trades.skipped_items);
+
+    let collector = diagnostics::DiagnosticsCollector::new(
+        &events.pool_events,
+        &transfers.transfers,
+        clock.number,
+        clock.timestamp.unwrap().seconds as u64,
+    );
+
+    Ok(Diagnostics {
+        diagnostics: collector.collect(skipped.into_items()),
+    })
+}
+
+#[substreams::handlers::store]
+pub fn store_positions(events: Events, output: StoreSetProto<PositionEvent>) {
+    for position in events.position_events() {
         match position.r#type.as_ref().unwrap() {
             CreatedPosition(pos) => {
                 output.set(pos.log_ordinal, format!("position_created:{}", pos.token_id), &position)
@@ -1110,7 +2043,274 @@ pub fn store_positions(events: Events, output: StoreSetProto<PositionEvent>) {
                 &position,
             ),
         }
+
+        // last event carrying the fee growths of the position, carried forward by the events
+        // without them
+        if let (Some(_), Some(_)) = position.get_fee_growth_inside_last_x128() {
+            output.set(
+                position.get_ordinal(),
+                format!("position_fee_growth:{}", position.get_token_id()),
+                &position,
+            );
+        }
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_position_changes(
+    events: Events,
+    error_policy: ErrorPolicy, /* map_error_policy */
+    output: StoreAddBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+
+    for position in events.position_events() {
+        match position.r#type.unwrap() {
+            IncreaseLiquidityPosition(pos) => {
+                let ord = pos.log_ordinal;
+                let token_id = &pos.token_id;
+                let [liquidity, deposited_token0, deposited_token1] = match policy.check(
+                    "store_position_changes",
+                    errors::parse_decimals([
+                        ("liquidity", &pos.liquidity),
+                        ("deposited token0", &pos.deposited_token0),
+                        ("deposited token1", &pos.deposited_token1),
+                    ]),
+                ) {
+                    Some(amounts) => amounts,
+                    None => continue,
+                };
+                output.add(ord, format!("position:{token_id}:liquidity"), &liquidity);
+                output.add(ord, format!("position:{token_id}:depositedToken0"), &deposited_token0);
+                output.add(ord, format!("position:{token_id}:depositedToken1"), &deposited_token1);
+            }
+            DecreaseLiquidityPosition(pos) => {
+                let ord = pos.log_ordinal;
+                let token_id = &pos.token_id;
+                let [liquidity, withdrawn_token0, withdrawn_token1] = match policy.check(
+                    "store_position_changes",
+                    errors::parse_decimals([
+                        ("liquidity", &pos.liquidity),
+                        ("withdrawn token0", &pos.withdrawn_token0),
+                        ("withdrawn token1", &pos.withdrawn_token1),
+                    ]),
+                ) {
+                    Some(amounts) => amounts,
+                    None => continue,
+                };
+                output.add(ord, format!("position:{token_id}:liquidity"), &liquidity.neg());
+                output.add(ord, format!("position:{token_id}:withdrawnToken0"), &withdrawn_token0);
+                output.add(ord, format!("position:{token_id}:withdrawnToken1"), &withdrawn_token1);
+            }
+            CollectPosition(pos) => {
+                let ord = pos.log_ordinal;
+                let token_id = &pos.token_id;
+                let [collected_fees_token0, collected_fees_token1] = match policy.check(
+                    "store_position_changes",
+                    errors::parse_decimals([
+                        ("collected fees token0", &pos.collected_fees_token0),
+                        ("collected fees token1", &pos.collected_fees_token1),
+                    ]),
+                ) {
+                    Some(amounts) => amounts,
+                    None => continue,
+                };
+                output.add(
+                    ord,
+                    format!("position:{token_id}:collectedFeesToken0"),
+                    &collected_fees_token0,
+                );
+                output.add(
+                    ord,
+                    format!("position:{token_id}:collectedFeesToken1"),
+                    &collected_fees_token1,
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_lp_liquidities(
+    events: Events,                                /* map_extract_data_types */
+    position_manager_events: Events,               /* map_position_manager_events */
+    store_positions: StoreGetProto<PositionEvent>, /* store_positions */
+    position_changes_store: StoreGetBigDecimal,    /* store_position_changes */
+    error_policy: ErrorPolicy,                     /* map_error_policy */
+    output: StoreAddBigInt,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+
+    for change in lps::extract_lp_liquidity_changes(
+        &events.pool_events,
+        &position_manager_events,
+        |token_id| lps::position_pool(&store_positions, token_id),
+        |token_id, ord| lps::position_owner(&store_positions, token_id, ord),
+        |token_id, ord| {
+            position_changes_store
+                .get_at(ord, format!("position:{token_id}:liquidity"))
+                .map(|liquidity| liquidity.to_bigint())
+        },
+    ) {
+        let ord = change.ordinal;
+        let pool_address = &change.pool_address;
+        let liquidity = match policy.check("store_lp_liquidities", change.liquidity) {
+            Some(liquidity) => liquidity,
+            None => continue,
+        };
+        output.add(ord, format!("lp:{pool_address}:{}", change.owner), &liquidity);
+    }
+}
+
+// An owner is an active liquidity provider of a pool while it holds liquidity in it. The
+// active providers of the tokens and the factory count the pools they are active in.
+#[substreams::handlers::store]
+pub fn store_active_lps(
+    lp_liquidities_deltas: Deltas<DeltaBigInt>, /* store_lp_liquidities */
+    pools_store: StoreGetProto<Pool>,           /* store_pools_created */
+    output: StoreAddInt64,
+) {
+    for delta in lp_liquidities_deltas.deltas.iter() {
+        let change = match lps::active_lp_change(&delta.old_value, &delta.new_value) {
+            Some(change) => change,
+            None => continue,
+        };
+
+        let pool_address = key::segment_at(&delta.key, 1);
+        let owner = key::segment_at(&delta.key, 2);
+        let mut keys = vec![format!("pool:{pool_address}:{owner}"), format!("factory:{owner}")];
+        if let Some(pool) = pools_store.get_last(format!("pool:{pool_address}")) {
+            keys.push(format!("token:{}:{owner}", pool.token0_ref().address));
+            keys.push(format!("token:{}:{owner}", pool.token1_ref().address));
+        }
+        output.add_many(delta.ordinal, &keys, change);
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_all_time_lps(
+    clock: Clock,
+    active_lps_deltas: Deltas<DeltaInt64>, /* store_active_lps */
+    output: StoreSetIfNotExistsInt64,
+) {
+    for delta in active_lps_deltas.deltas {
+        if delta.new_value > 0 {
+            output.set_if_not_exists(delta.ordinal, &delta.key, &(clock.number as i64));
+        }
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_lp_counts(
+    active_lps_deltas: Deltas<DeltaInt64>,   /* store_active_lps */
+    all_time_lps_deltas: Deltas<DeltaInt64>, /* store_all_time_lps */
+    output: StoreAddBigInt,
+) {
+    for change in lps::lp_count_changes(&active_lps_deltas, &all_time_lps_deltas) {
+        output.add(change.ordinal, change.key, &change.change);
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_position_snapshots(
+    clock: Clock,
+    events: Events,                                      /* map_position_manager_events */
+    store_positions: StoreGetProto<PositionEvent>,       /* store_positions */
+    position_changes_store: StoreGetBigDecimal,          /* store_position_changes */
+    pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>, /* store_pool_sqrt_price */
+    store_eth_prices: StoreGetBigDecimal,                /* store_eth_prices */
+) -> Result<SnapshotPositions, Error> {
+    let mut snapshot_positions = SnapshotPositions::default();
+
+    for position_event in events.position_events() {
+        let ord = position_event.get_ordinal();
+        let token_id = position_event.get_token_id().clone();
+
+        let created_position = match store_positions.get_last(format!("position_created:{token_id}")) {
+            Some(PositionEvent {
+                r#type: Some(CreatedPosition(created_position)),
+            }) => created_position,
+            _ => continue,
+        };
+        let pool_address = &created_position.pool;
+        let token0_addr = &created_position.token0;
+        let token1_addr = &created_position.token1;
+
+        let mut owner = Hex(utils::ZERO_ADDRESS).to_string();
+        let mut closed = false;
+        if let Some(PositionEvent {
+            r#type: Some(TransferPosition(transfer)),
+        }) = store_positions.get_at(ord, format!("position_transfer:{token_id}"))
+        {
+            // a burned position keeps its last owner
+            closed = transfer.is_burn();
+            owner = if closed { transfer.from } else { transfer.owner };
+        }
+
+        let (fee_growth_inside0_last_x128, fee_growth_inside1_last_x128) = utils::position_fee_growths(
+            &position_event,
+            store_positions.get_at(ord, format!("position_fee_growth:{token_id}")),
+        );
+
+        let position_change = |field: &str| -> String {
+            position_changes_store
+                .get_at(ord, format!("position:{token_id}:{field}"))
+                .unwrap_or(BigDecimal::zero())
+                .to_string()
+        };
+
+        let (sqrt_price, tick) = match pool_sqrt_price_store.get_at(ord, format!("pool:{pool_address}")) {
+            Some(pool_sqrt_price) => (pool_sqrt_price.sqrt_price, pool_sqrt_price.tick),
+            None => ("0".to_string(), "0".to_string()),
+        };
+
+        let eth_price_in_usd = store_eth_prices.get_at(ord, "bundle").unwrap_or(BigDecimal::zero());
+        let token_price_usd = |token_addr: &String| -> String {
+            store_eth_prices
+                .get_at(ord, format!("token:{token_addr}:dprice:eth"))
+                .unwrap_or(BigDecimal::zero())
+                .mul(eth_price_in_usd.clone())
+                .to_string()
+        };
+
+        let transaction = events
+            .transactions
+            .iter()
+            .find(|transaction| transaction.log_ordinal == ord)
+            .map(|transaction| transaction.id.clone())
+            .unwrap_or(created_position.transaction.clone());
+
+        snapshot_positions.snapshot_positions.push(SnapshotPosition {
+            pool: pool_address.clone(),
+            position: token_id.clone(),
+            block_number: clock.number,
+            owner,
+            timestamp: clock.timestamp.as_ref().unwrap().seconds as u64,
+            liquidity: position_change("liquidity"),
+            deposited_token0: position_change("depositedToken0"),
+            deposited_token1: position_change("depositedToken1"),
+            withdrawn_token0: position_change("withdrawnToken0"),
+            withdrawn_token1: position_change("withdrawnToken1"),
+            collected_fees_token0: position_change("collectedFeesToken0"),
+            collected_fees_token1: position_change("collectedFeesToken1"),
+            transaction,
+            fee_growth_inside_0_last_x_128: fee_growth_inside0_last_x128.unwrap_or("0".to_string()),
+            fee_growth_inside_1_last_x_128: fee_growth_inside1_last_x128.unwrap_or("0".to_string()),
+            log_ordinal: ord,
+            token0: token0_addr.clone(),
+            token1: token1_addr.clone(),
+            tick_lower: created_position.tick_lower.clone(),
+            tick_upper: created_position.tick_upper.clone(),
+            sqrt_price,
+            tick,
+            token0_price_usd: token_price_usd(token0_addr),
+            token1_price_usd: token_price_usd(token1_addr),
+            closed,
+        });
     }
+
+    Ok(snapshot_positions)
 }
 
 #[substreams::handlers::store]
@@ -1125,39 +2325,25 @@ pub fn store_min_windows(
     deltas.append(&mut eth_deltas);
     deltas.sort_by(|x, y| x.ordinal.cmp(&y.ordinal));
 
-    let timestamp_seconds = clock.timestamp.unwrap().seconds;
-    let day_id = timestamp_seconds / 86400;
-    let hour_id = timestamp_seconds / 3600;
-    let prev_day_id = day_id - 1;
-    let prev_hour_id = hour_id - 1;
-
-    output.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
-    output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, clock.timestamp.unwrap().seconds);
+    for window in &windows {
+        output.delete_prefix(0, &window.prev_prefix("Pool"));
+        output.delete_prefix(0, &window.prev_prefix("Token"));
+    }
 
     for delta in deltas {
         if delta.operation == store_delta::Operation::Delete {
             continue;
         }
 
-        let table_name = match key::first_segment(&delta.key) {
-            "PoolDayData" => {
-                if key::last_segment(&delta.key) != "token0" {
-                    continue;
-                }
-                "PoolDayData"
-            }
-            "PoolHourData" => {
-                if key::last_segment(&delta.key) != "token0" {
-                    continue;
-                }
-                "PoolHourData"
+        let table_name = key::first_segment(&delta.key);
+        if POOL_WINDOW_TABLES.contains(&table_name) {
+            if key::last_segment(&delta.key) != "token0" {
+                continue;
             }
-            "TokenDayData" => "TokenDayData",
-            "TokenHourData" => "TokenHourData",
-            _ => continue,
-        };
+        } else if !TOKEN_WINDOW_TABLES.contains(&table_name) {
+            continue;
+        }
 
         let time_id = key::segment_at(&delta.key, 1);
         let address = key::segment_at(&delta.key, 2);
@@ -1190,51 +2376,496 @@ pub fn store_max_windows(
     deltas.append(&mut eth_deltas);
     deltas.sort_by(|x, y| x.ordinal.cmp(&y.ordinal));
 
-    let timestamp_seconds = clock.timestamp.unwrap().seconds;
-    let day_id = timestamp_seconds / 86400;
-    let hour_id = timestamp_seconds / 3600;
-    let prev_day_id = day_id - 1;
-    let prev_hour_id = hour_id - 1;
-
-    output.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
-    output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
-    output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, clock.timestamp.unwrap().seconds);
+    for window in &windows {
+        output.delete_prefix(0, &window.prev_prefix("Pool"));
+        output.delete_prefix(0, &window.prev_prefix("Token"));
+    }
 
     for delta in deltas {
         if delta.operation == store_delta::Operation::Delete {
             continue;
         }
 
-        let table_name = match key::first_segment(&delta.key) {
-            "PoolDayData" => {
-                if key::last_segment(&delta.key) != "token0" {
-                    continue;
-                }
-                "PoolDayData"
-            }
-            "PoolHourData" => {
-                if key::last_segment(&delta.key) != "token0" {
-                    continue;
-                }
-                "PoolHourData"
+        let table_name = key::first_segment(&delta.key);
+        if POOL_WINDOW_TABLES.contains(&table_name) {
+            if key::last_segment(&delta.key) != "token0" {
+                continue;
             }
-            "TokenDayData" => "TokenDayData",
-            "TokenHourData" => "TokenHourData",
-            _ => continue,
-        };
+        } else if !TOKEN_WINDOW_TABLES.contains(&table_name) {
+            continue;
+        }
 
-        let day_id = key::segment_at(&delta.key, 1);
-        let pool_address = key::segment_at(&delta.key, 2);
+        let time_id = key::segment_at(&delta.key, 1);
+        let address = key::segment_at(&delta.key, 2);
 
         output.max(
             delta.ordinal,
-            format!("{table_name}:{day_id}:{pool_address}:high"),
+            format!("{table_name}:{time_id}:{address}:high"),
             delta.new_value,
         );
     }
 }
 
+#[substreams::handlers::map]
+pub fn map_jit_liquidity(
+    clock: Clock,
+    events: Events,                       /* map_extract_data_types */
+    position_manager_events: Events,      /* map_position_manager_events */
+    store_eth_prices: StoreGetBigDecimal, /* store_eth_prices */
+    error_policy: ErrorPolicy,            /* map_error_policy */
+) -> Result<JitLiquidities, Error> {
+    let timestamp = clock.timestamp.unwrap().seconds as u64;
+    let mut skipped_items = diagnostics::SkippedItems::new(Policy::from_error_policy(&error_policy));
+
+    Ok(JitLiquidities {
+        jit_liquidities: jit::extract_jit_liquidities(
+            &events.pool_events,
+            &position_manager_events,
+            |ordinal, token_address| price::get_token_price_usd(&store_eth_prices, ordinal, token_address),
+            &mut skipped_items,
+            clock.number,
+            timestamp,
+        ),
+        skipped_items: skipped_items.into_items(),
+    })
+}
+
+#[substreams::handlers::store]
+pub fn store_jit_windows(
+    clock: Clock,
+    jit_liquidities: JitLiquidities,
+    error_policy: ErrorPolicy, /* map_error_policy */
+    output: StoreAddBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+    let windows = windows::current_windows(&ALL_WINDOW_KINDS, clock.timestamp.unwrap().seconds);
+    for window in &windows {
+        output.delete_prefix(0, &window.prev_prefix("Pool"));
+    }
+
+    for jit_liquidity in jit_liquidities.jit_liquidities {
+        let ord = jit_liquidity.burn_ordinal;
+        let pool_address = &jit_liquidity.pool_address;
+        let [jit_volume_usd, jit_fees_usd] = match policy.check(
+            "store_jit_windows",
+            errors::parse_decimals([
+                ("jit volume usd", &jit_liquidity.jit_volume_usd),
+                ("fees usd", &jit_liquidity.fees_usd),
+            ]),
+        ) {
+            Some(amounts) => amounts,
+            None => continue,
+        };
+
+        for window in &windows {
+            let window_key = window.key("Pool");
+            output.add(ord, format!("{window_key}:{pool_address}:jitCount"), &BigDecimal::one());
+            output.add(
+                ord,
+                format!("{window_key}:{pool_address}:jitVolumeUSD"),
+                &jit_volume_usd,
+            );
+            output.add(ord, format!("{window_key}:{pool_address}:jitFeesUSD"), &jit_fees_usd);
+        }
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_mev(
+    clock: Clock,
+    events: Events,                                      /* map_extract_data_types */
+    pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>, /* store_pool_sqrt_price */
+    store_eth_prices: StoreGetBigDecimal,                /* store_eth_prices */
+    error_policy: ErrorPolicy,                           /* map_error_policy */
+) -> Result<MevEvents, Error> {
+    let timestamp = clock.timestamp.unwrap().seconds as u64;
+    let mut skipped_items = diagnostics::SkippedItems::new(Policy::from_error_policy(&error_policy));
+    let token_price_usd =
+        |ordinal, token_address: &String| price::get_token_price_usd(&store_eth_prices, ordinal, token_address);
+    let swaps = mev::parse_swaps(&events.pool_events, &mut skipped_items);
+
+    Ok(MevEvents {
+        sandwiches: mev::extract_sandwiches(
+            &swaps,
+            |pool_address, ordinal| {
+                pool_sqrt_price_store
+                    .get_at(ordinal, format!("pool:{pool_address}"))
+                    .map(|pool_sqrt_price| pool_sqrt_price.sqrt_price)
+            },
+            token_price_usd,
+            &mut skipped_items,
+            clock.number,
+            timestamp,
+        ),
+        cyclic_arbitrages: mev::extract_cyclic_arbitrages(&swaps, token_price_usd, clock.number, timestamp),
+        skipped_items: skipped_items.into_items(),
+    })
+}
+
+#[substreams::handlers::map]
+pub fn map_trades(
+    clock: Clock,
+    events: Events,                       /* map_extract_data_types */
+    store_eth_prices: StoreGetBigDecimal, /* store_eth_prices */
+    error_policy: ErrorPolicy,            /* map_error_policy */
+) -> Result<Trades, Error> {
+    let timestamp = clock.timestamp.unwrap().seconds as u64;
+    let mut skipped_items = diagnostics::SkippedItems::new(Policy::from_error_policy(&error_policy));
+
+    Ok(Trades {
+        trades: trades::extract_trades(
+            &events.pool_events,
+            |ordinal, token_address| price::get_token_price_usd(&store_eth_prices, ordinal, token_address),
+            &mut skipped_items,
+            clock.number,
+            timestamp,
+        ),
+        skipped_items: skipped_items.into_items(),
+    })
+}
+
+#[substreams::handlers::store]
+pub fn store_trade_volumes(
+    clock: Clock,
+    trades: Trades,
+    error_policy: ErrorPolicy, /* map_error_policy */
+    output: StoreAddBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+    let uniswap_windows = windows::current_windows(&UNISWAP_WINDOW_KINDS, clock.timestamp.unwrap().seconds);
+    for window in &uniswap_windows {
+        output.delete_prefix(0, &window.prev_prefix("Uniswap"));
+    }
+
+    for trade in trades.trades {
+        let ord = trade.log_ordinal;
+        let amount_usd = match policy.check(
+            "store_trade_volumes",
+            errors::parse_decimal("amount usd", &trade.amount_usd),
+        ) {
+            Some(amount_usd) => amount_usd,
+            None => continue,
+        };
+
+        for (key, value) in trades::trade_volume_additions(&amount_usd, &uniswap_windows) {
+            output.add(ord, key, value);
+        }
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_candle_updates(
+    params: String,
+    clock: Clock,
+    events: Events,                       /* map_extract_data_types */
+    prices_store: StoreGetBigDecimal,     /* store_prices */
+    store_eth_prices: StoreGetBigDecimal, /* store_eth_prices */
+) -> Result<CandleUpdates, Error> {
+    let intervals = candles::intervals_from_params(&params)?;
+    let windows = candles::windows(&intervals, clock.timestamp.unwrap().seconds);
+    let updates = candles::extract_candle_updates(&windows, &events.pool_events, &prices_store, &store_eth_prices);
+
+    Ok(CandleUpdates { windows, updates })
+}
+
+#[substreams::handlers::store]
+pub fn store_candle_prices(
+    candle_updates: CandleUpdates,
+    error_policy: ErrorPolicy, /* map_error_policy */
+    output: StoreSetBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+
+    // Deleting the prices of a bucket closes its candles. The bucket before is deleted
+    // as well for the intervals short enough to go a whole bucket without any block.
+    for window in &candle_updates.windows {
+        let interval = &window.interval;
+        output.delete_prefix(0, &format!("Candle:{interval}:{}:", window.bucket - 1));
+        output.delete_prefix(0, &format!("Candle:{interval}:{}:", window.bucket - 2));
+    }
+
+    for update in candle_updates.updates {
+        let ord = update.ordinal;
+        let pool_address = &update.pool_address;
+        let prefix = format!("Candle:{}:{}:{pool_address}", update.interval, update.bucket);
+
+        // the USD prices are left empty without a bundle price
+        let prices = [
+            ("token0", "token0 price", &update.token0_price),
+            ("token1", "token1 price", &update.token1_price),
+            ("token0USD", "token0 price usd", &update.token0_price_usd),
+            ("token1USD", "token1 price usd", &update.token1_price_usd),
+        ];
+        for (field, name, price) in prices.into_iter().filter(|(_, _, price)| !price.is_empty()) {
+            let price = match policy.check("store_candle_prices", errors::parse_decimal(name, price)) {
+                Some(price) => price,
+                None => continue,
+            };
+            output.set(ord, format!("{prefix}:{field}"), &price);
+        }
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_candle_opens(
+    candle_updates: CandleUpdates,                 /* map_candle_updates */
+    candle_prices_deltas: Deltas<DeltaBigDecimal>, /* store_candle_prices */
+    output: StoreSetIfNotExistsBigDecimal,
+) {
+    // kept two buckets longer than the prices so that map_candles can read the closing candles
+    for window in &candle_updates.windows {
+        let interval = &window.interval;
+        output.delete_prefix(0, &format!("Candle:{interval}:{}:", window.bucket - 3));
+        output.delete_prefix(0, &format!("Candle:{interval}:{}:", window.bucket - 4));
+    }
+
+    // the first price of a bucket is its open
+    for delta in candle_prices_deltas
+        .iter()
+        .key_first_segment_eq("Candle")
+        .operation_not_eq(store_delta::Operation::Delete)
+    {
+        output.set_if_not_exists(delta.ordinal, format!("{}:open", delta.key), &delta.new_value);
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_candle_lows(
+    candle_updates: CandleUpdates,                 /* map_candle_updates */
+    candle_prices_deltas: Deltas<DeltaBigDecimal>, /* store_candle_prices */
+    output: StoreMinBigDecimal,
+) {
+    // kept two buckets longer than the prices so that map_candles can read the closing candles
+    for window in &candle_updates.windows {
+        let interval = &window.interval;
+        output.delete_prefix(0, &format!("Candle:{interval}:{}:", window.bucket - 3));
+        output.delete_prefix(0, &format!("Candle:{interval}:{}:", window.bucket - 4));
+    }
+
+    for delta in candle_prices_deltas
+        .iter()
+        .key_first_segment_eq("Candle")
+        .operation_not_eq(store_delta::Operation::Delete)
+    {
+        output.min(delta.ordinal, format!("{}:low", delta.key), &delta.new_value);
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_candle_highs(
+    candle_updates: CandleUpdates,                 /* map_candle_updates */
+    candle_prices_deltas: Deltas<DeltaBigDecimal>, /* store_candle_prices */
+    output: StoreMaxBigDecimal,
+) {
+    for window in &candle_updates.windows {
+        let interval = &window.interval;
+        output.delete_prefix(0, &format!("Candle:{interval}:{}:", window.bucket - 3));
+        output.delete_prefix(0, &format!("Candle:{interval}:{}:", window.bucket - 4));
+    }
+
+    for delta in candle_prices_deltas
+        .iter()
+        .key_first_segment_eq("Candle")
+        .operation_not_eq(store_delta::Operation::Delete)
+    {
+        output.max(delta.ordinal, format!("{}:high", delta.key), &delta.new_value);
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_candle_volumes(
+    candle_updates: CandleUpdates,
+    error_policy: ErrorPolicy, /* map_error_policy */
+    output: StoreAddBigDecimal,
+) {
+    let policy = Policy::from_error_policy(&error_policy);
+
+    for window in &candle_updates.windows {
+        let interval = &window.interval;
+        output.delete_prefix(0, &format!("Candle:{interval}:{}:", window.bucket - 3));
+        output.delete_prefix(0, &format!("Candle:{interval}:{}:", window.bucket - 4));
+    }
+
+    for update in candle_updates.updates {
+        let ord = update.ordinal;
+        let pool_address = &update.pool_address;
+        let prefix = format!("Candle:{}:{}:{pool_address}", update.interval, update.bucket);
+        let [volume_token0, volume_token1, volume_usd] = match policy.check(
+            "store_candle_volumes",
+            errors::parse_decimals([
+                ("volume token0", &update.volume_token0),
+                ("volume token1", &update.volume_token1),
+                ("volume usd", &update.volume_usd),
+            ]),
+        ) {
+            Some(volumes) => volumes,
+            None => continue,
+        };
+
+        output.add(ord, format!("{prefix}:tradeCount"), &BigDecimal::one());
+        output.add(ord, format!("{prefix}:volumeToken0"), &volume_token0);
+        output.add(ord, format!("{prefix}:volumeToken1"), &volume_token1);
+        output.add(ord, format!("{prefix}:volumeUSD"), &volume_usd);
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_candles(
+    clock: Clock,
+    candle_updates: CandleUpdates,                 /* map_candle_updates */
+    candle_prices_deltas: Deltas<DeltaBigDecimal>, /* store_candle_prices */
+    candle_opens_store: StoreGetBigDecimal,        /* store_candle_opens */
+    candle_lows_store: StoreGetBigDecimal,         /* store_candle_lows */
+    candle_highs_store: StoreGetBigDecimal,        /* store_candle_highs */
+    candle_volumes_store: StoreGetBigDecimal,      /* store_candle_volumes */
+    pools_store: StoreGetProto<Pool>,              /* store_pools_created */
+) -> Result<Candles, Error> {
+    let timestamp = clock.timestamp.unwrap().seconds as u64;
+    let stores = candles::CandleStores {
+        opens: &candle_opens_store,
+        lows: &candle_lows_store,
+        highs: &candle_highs_store,
+        volumes: &candle_volumes_store,
+        pools: &pools_store,
+    };
+
+    Ok(Candles {
+        candles: candles::build_candles(
+            &candle_updates.windows,
+            &candle_prices_deltas,
+            &stores,
+            clock.number,
+            timestamp,
+        ),
+    })
+}
+
+#[substreams::handlers::store]
+pub fn store_window_time_ids(clock: Clock, output: StoreSetInt64) {
+    let timestamp = clock.timestamp.unwrap().seconds;
+    for window in windows::current_windows(&ALL_WINDOW_KINDS, timestamp) {
+        output.set(0, window.kind.name(), &window.time_id);
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_tracked_address_counts(
+    pools: Pools,                /* map_pools_created */
+    tokens_store: StoreGetInt64, /* store_tokens */
+    output: StoreAddInt64,
+) {
+    for pool in pools.pools {
+        let ord = pool.log_ordinal;
+        output.add(ord, "pools", 1);
+        let new_tokens = gap_fill::new_tokens(&pool, &tokens_store);
+        output.add(ord, "tokens", new_tokens.len() as i64);
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_tracked_addresses(
+    pools: Pools,                                /* map_pools_created */
+    tokens_store: StoreGetInt64,                 /* store_tokens */
+    tracked_address_counts_store: StoreGetInt64, /* store_tracked_address_counts */
+    output: StoreSetString,
+) {
+    for pool in pools.pools {
+        let ord = pool.log_ordinal;
+        let pool_count = tracked_address_counts_store.get_at(ord, "pools").unwrap_or(0);
+        output.set(ord, format!("pools:{pool_count}"), &pool.address);
+
+        // Both tokens of the pool may be new, the count then already holds the two of them.
+        let new_tokens = gap_fill::new_tokens(&pool, &tokens_store);
+        let token_count = tracked_address_counts_store.get_at(ord, "tokens").unwrap_or(0);
+        let first_index = token_count - new_tokens.len() as i64 + 1;
+        for (i, token_addr) in new_tokens.into_iter().enumerate() {
+            output.set(ord, format!("tokens:{}", first_index + i as i64), &token_addr);
+        }
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_gap_filled_windows(
+    params: String,
+    clock: Clock,
+    window_time_ids_deltas: Deltas<DeltaInt64>,  /* store_window_time_ids */
+    pools_created: Pools,                        /* map_pools_created */
+    tokens_store: StoreGetInt64,                 /* store_tokens */
+    tracked_address_counts_store: StoreGetInt64, /* store_tracked_address_counts */
+    tracked_addresses_store: StoreGetString,     /* store_tracked_addresses */
+    pools_store: StoreGetProto<Pool>,            /* store_pools_created */
+    pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>, /* store_pool_sqrt_price */
+    pool_liquidities_store: StoreGetBigInt,      /* store_pool_liquidities */
+    prices_store: StoreGetBigDecimal,            /* store_prices */
+    token_tvl_store: StoreGetBigDecimal,         /* store_token_tvl */
+    derived_tvl_store: StoreGetBigDecimal,       /* store_derived_tvl */
+    eth_prices_store: StoreGetBigDecimal,        /* store_eth_prices */
+    pool_balance_tvl_store: StoreGetBigDecimal,  /* store_pool_balance_tvl */
+) -> Result<GapFilledWindows, Error> {
+    let kinds = windows::kinds_from_params(&params);
+    let mut output = GapFilledWindows::default();
+    if kinds.is_empty() {
+        return Ok(output);
+    }
+
+    output.tables = gap_fill::gap_filled_tables(&kinds);
+    let stores = gap_fill::WindowStores {
+        pools: &pools_store,
+        pool_sqrt_prices: &pool_sqrt_price_store,
+        pool_liquidities: &pool_liquidities_store,
+        prices: &prices_store,
+        token_tvl: &token_tvl_store,
+        derived_tvl: &derived_tvl_store,
+        eth_prices: &eth_prices_store,
+        pool_balance_tvl: &pool_balance_tvl_store,
+    };
+
+    // Every pool and token tracked before this block carries its values at the start
+    // of the block over to the windows starting with this block.
+    for window in gap_fill::starting_windows(&kinds, &window_time_ids_deltas) {
+        for pool_address in
+            gap_fill::tracked_addresses(&tracked_addresses_store, &tracked_address_counts_store, 0, "pools")
+        {
+            output
+                .pools
+                .push(gap_fill::pool_window(&window, &pool_address, 0, &stores));
+        }
+        for token_address in
+            gap_fill::tracked_addresses(&tracked_addresses_store, &tracked_address_counts_store, 0, "tokens")
+        {
+            output
+                .tokens
+                .push(gap_fill::token_window(&window, &token_address, 0, &stores));
+        }
+    }
+
+    // Pools and tokens created in this block get their first windows right away, the
+    // gap filled windows are then never created from the tx counts in graph_out.
+    let windows = windows::current_windows(&kinds, clock.timestamp.unwrap().seconds);
+    for pool in &pools_created.pools {
+        let ord = pool.log_ordinal;
+        for window in &windows {
+            output
+                .pools
+                .push(gap_fill::pool_window(window, &pool.address, ord, &stores));
+        }
+
+        for token_addr in [pool.token0_ref().address(), pool.token1_ref().address()] {
+            if tokens_store.get_at(ord, format!("token:{token_addr}")) != Some(1) {
+                continue;
+            }
+            for window in &windows {
+                output
+                    .tokens
+                    .push(gap_fill::token_window(window, token_addr, ord, &stores));
+            }
+        }
+    }
+
+    Ok(output)
+}
+
 #[substreams::handlers::map]
 pub fn graph_out(
     clock: Clock,
@@ -1244,7 +2875,9 @@ pub fn graph_out(
     derived_factory_tvl_deltas: Deltas<DeltaBigDecimal>, /* store_derived_factory_tvl */
     derived_eth_prices_deltas: Deltas<DeltaBigDecimal>,  /* store_eth_prices */
     events: Events,                                      /* map_extract_data_types */
+    position_events: Events,                             /* map_position_manager_events */
     pools_created: Pools,                                /* map_pools_created */
+    filtered_pools: FilteredPools,                       /* map_spam_pools */
     pool_sqrt_price_deltas: Deltas<DeltaProto<PoolSqrtPrice>>, /* store_pool_sqrt_price */
     pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>, /* store_pool_sqrt_price */
     pool_liquidities_store_deltas: Deltas<DeltaBigInt>,  /* store_pool_liquidities */
@@ -1260,7 +2893,18 @@ pub fn graph_out(
     store_positions: StoreGetProto<PositionEvent>,       /* store_positions */
     min_windows_deltas: Deltas<DeltaBigDecimal>,         /* store_min_windows */
     max_windows_deltas: Deltas<DeltaBigDecimal>,         /* store_max_windows */
+    jit_windows_deltas: Deltas<DeltaBigDecimal>,         /* store_jit_windows */
+    trades: Trades,                                      /* map_trades */
+    trade_volumes_deltas: Deltas<DeltaBigDecimal>,       /* store_trade_volumes */
+    token_price_paths_deltas: Deltas<DeltaProto<TokenPricePath>>, /* store_token_price_paths */
+    token_price_provenances: TokenPriceProvenances,      /* map_token_price_provenance */
+    gap_filled_windows: GapFilledWindows,                /* map_gap_filled_windows */
+    pool_balance_tvl_deltas: Deltas<DeltaBigDecimal>,    /* store_pool_balance_tvl */
+    token_behaviours_deltas: Deltas<DeltaInt64>,         /* store_token_behaviours */
+    lp_counts_deltas: Deltas<DeltaBigInt>,               /* store_lp_counts */
+    error_policy: ErrorPolicy,                           /* map_error_policy */
 ) -> Result<EntityChanges, Error> {
+    let policy = Policy::from_error_policy(&error_policy);
     let mut tables = Tables::new();
     let timestamp = clock.timestamp.unwrap().seconds;
 
@@ -1279,9 +2923,11 @@ pub fn graph_out(
     db::tx_count_factory_entity_change(&mut tables, &tx_count_deltas);
     db::swap_volume_factory_entity_change(&mut tables, &swaps_volume_deltas);
     db::tvl_factory_entity_change(&mut tables, &derived_factory_tvl_deltas);
+    db::trade_volume_factory_entity_change(&mut tables, &trade_volumes_deltas);
 
     // Pool:
     db::pools_created_pool_entity_changes(&mut tables, &pools_created);
+    db::spam_pools_pool_entity_changes(&mut tables, &filtered_pools);
     db::sqrt_price_and_tick_pool_entity_change(&mut tables, &pool_sqrt_price_deltas);
     db::liquidities_pool_entity_change(&mut tables, &pool_liquidities_store_deltas);
     db::fee_growth_global_pool_entity_change(&mut tables, &events.fee_growth_global_updates);
@@ -1290,15 +2936,20 @@ pub fn graph_out(
     db::price_pool_entity_change(&mut tables, &price_deltas);
     db::tx_count_pool_entity_change(&mut tables, &tx_count_deltas);
     db::swap_volume_pool_entity_change(&mut tables, &swaps_volume_deltas);
+    db::balance_pool_entity_change(&mut tables, &pool_balance_tvl_deltas);
 
     // Tokens:
-    db::tokens_created_token_entity_changes(&mut tables, &pools_created, tokens_store);
+    db::tokens_created_token_entity_changes(&mut tables, &pools_created, tokens_store, &policy);
     db::swap_volume_token_entity_change(&mut tables, &swaps_volume_deltas);
     db::tx_count_token_entity_change(&mut tables, &tx_count_deltas);
     db::total_value_locked_by_token_token_entity_change(&mut tables, &token_tvl_deltas);
     db::total_value_locked_usd_token_entity_change(&mut tables, &derived_tvl_deltas);
     db::derived_eth_prices_token_entity_change(&mut tables, &derived_eth_prices_deltas);
+    db::price_path_token_entity_change(&mut tables, &token_price_paths_deltas);
+    db::price_provenance_token_entity_change(&mut tables, &token_price_provenances.provenances);
     db::whitelist_token_entity_change(&mut tables, tokens_whitelist_pools_deltas);
+    db::behaviour_token_entity_change(&mut tables, &token_behaviours_deltas);
+    db::lp_count_entity_change(&mut tables, &lp_counts_deltas);
 
     // Tick:
     db::create_tick_entity_change(&mut tables, &events.ticks_created);
@@ -1312,56 +2963,74 @@ pub fn graph_out(
 
     // Position:
     // TODO: validate all the positions here
-    db::position_create_entity_change(&mut tables, &events.created_positions);
-    db::increase_liquidity_position_entity_change(&mut tables, &events.increase_liquidity_positions);
-    db::decrease_liquidity_position_entity_change(&mut tables, &events.decrease_liquidity_positions);
-    db::collect_position_entity_change(&mut tables, &events.collect_positions);
-    db::transfer_position_entity_change(&mut tables, &events.transfer_positions);
+    db::position_create_entity_change(&mut tables, &position_events.created_positions);
+    db::increase_liquidity_position_entity_change(&mut tables, &position_events.increase_liquidity_positions);
+    db::decrease_liquidity_position_entity_change(&mut tables, &position_events.decrease_liquidity_positions);
+    db::collect_position_entity_change(&mut tables, &position_events.collect_positions);
+    db::transfer_position_entity_change(&mut tables, &position_events.transfer_positions, &store_positions);
+    db::closed_position_entity_change(&mut tables, &position_events.closed_positions, &store_positions);
+    db::approval_position_entity_change(&mut tables, &position_events.approval_positions, &store_positions);
+    db::operator_approval_entity_change(&mut tables, &position_events.operator_approvals);
 
     // PositionSnapshot:
     // TODO: validate all the snapshot positions here
-    db::snapshot_positions_create_entity_change(&mut tables, &events.created_positions);
+    db::snapshot_positions_create_entity_change(&mut tables, &position_events.created_positions);
     db::increase_liquidity_snapshot_position_entity_change(
         &mut tables,
         clock.number,
-        &events.increase_liquidity_positions,
+        &position_events.increase_liquidity_positions,
         &store_positions,
     );
     db::decrease_liquidity_snapshot_position_entity_change(
         &mut tables,
         clock.number,
-        &events.decrease_liquidity_positions,
+        &position_events.decrease_liquidity_positions,
+        &store_positions,
+    );
+    db::collect_snapshot_position_entity_change(
+        &mut tables,
+        clock.number,
+        &position_events.collect_positions,
         &store_positions,
     );
-    db::collect_snapshot_position_entity_change(&mut tables, clock.number, &events.collect_positions, &store_positions);
     db::transfer_snapshot_position_entity_change(
         &mut tables,
         clock.number,
-        &events.transfer_positions,
+        &position_events.transfer_positions,
         &store_positions,
     );
 
     // Transaction:
     db::transaction_entity_change(&mut tables, &events.transactions);
+    db::transaction_entity_change(&mut tables, &position_events.transactions);
 
     // Swap, Mint, Burn:
     db::swaps_mints_burns_created_entity_change(&mut tables, &events.pool_events, tx_count_store, store_eth_prices);
 
+    // Trades:
+    db::trade_entity_change(&mut tables, &trades.trades);
+
     // Flashes:
     // TODO: should we implement flashes entity change - UNISWAP has not done this part
     // db::flashes_update_pool_fee_entity_change(&mut tables, events.flashes);
 
-    // Uniswap day data:
-    db::uniswap_day_data_create(&mut tables, &tx_count_deltas);
-    db::uniswap_day_data_update(
+    // Uniswap Day/Week/Month data:
+    db::uniswap_windows_create(&mut tables, &tx_count_deltas);
+    db::uniswap_windows_update(
         &mut tables,
         &swaps_volume_deltas,
         &derived_factory_tvl_deltas,
         &tx_count_deltas,
     );
+    db::trade_volume_uniswap_windows(&mut tables, &trade_volumes_deltas);
+
+    // Source day data:
+    db::source_day_data_create(&mut tables, &swaps_volume_deltas);
+    db::swap_volume_source_day_data_update(&mut tables, &swaps_volume_deltas);
 
-    // Pool Day/Hour data:
-    db::pool_windows_create(&mut tables, &tx_count_deltas);
+    // Pool Day/Hour/Week/Month data:
+    db::pool_windows_create(&mut tables, &tx_count_deltas, &gap_filled_windows.tables);
+    db::gap_filled_pool_windows(&mut tables, &gap_filled_windows.pools);
     db::pool_windows_update(
         &mut tables,
         timestamp,
@@ -1376,9 +3045,12 @@ pub fn graph_out(
         &min_windows_deltas,
         &max_windows_deltas,
     );
+    db::jit_liquidity_pool_windows(&mut tables, &jit_windows_deltas);
+    db::balance_pool_windows(&mut tables, &pool_balance_tvl_deltas);
 
-    // Token Day/Hour data:
-    db::token_windows_create(&mut tables, &tx_count_deltas);
+    // Token Day/Hour/Week/Month data:
+    db::token_windows_create(&mut tables, &tx_count_deltas, &gap_filled_windows.tables);
+    db::gap_filled_token_windows(&mut tables, &gap_filled_windows.tokens);
     db::token_windows_update(
         &mut tables,
         timestamp,
@@ -0,0 +1,398 @@
+use crate::errors::{self, DataError};
+use crate::pb::uniswap::events::pool_event::Type::{Burn as BurnEvent, Mint as MintEvent};
+use crate::pb::uniswap::events::position_event::Type::{
+    CreatedPosition, DecreaseLiquidityPosition, IncreaseLiquidityPosition, TransferPosition,
+};
+use crate::pb::uniswap::events::{PoolEvent, PositionEvent};
+use crate::pb::uniswap::Events;
+use crate::utils::NON_FUNGIBLE_POSITION_MANAGER;
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::BigInt;
+use substreams::store::{DeltaInt64, Deltas, StoreGet, StoreGetProto};
+use substreams::{key, Hex};
+
+pub struct LpLiquidityChange {
+    pub pool_address: String,
+    pub owner: String,
+    // the amount of the event, the item being skipped when it does not parse
+    pub liquidity: Result<BigInt, DataError>,
+    pub ordinal: u64,
+}
+
+pub struct LpCountChange {
+    pub key: String,
+    pub change: BigInt,
+    pub ordinal: u64,
+}
+
+// Liquidity added and removed by every owner of a position in the pools, ordered by ordinal.
+// The positions minted directly on a pool belong to the owner of the Mint and Burn events,
+// the ones of the NonfungiblePositionManager to the owner of their NFT: their liquidity
+// changes and NFT transfers are followed instead of the Mint and Burn of the manager.
+// The positions are looked up by token id: the pool they were created in, the owner of
+// their NFT and their liquidity as of an ordinal.
+pub fn extract_lp_liquidity_changes<P, O, L>(
+    pool_events: &Vec<PoolEvent>,
+    position_manager_events: &Events,
+    position_pool: P,
+    position_owner: O,
+    position_liquidity: L,
+) -> Vec<LpLiquidityChange>
+where
+    P: Fn(&String) -> Option<String>,
+    O: Fn(&String, u64) -> Option<String>,
+    L: Fn(&String, u64) -> Option<BigInt>,
+{
+    let position_manager = Hex(NON_FUNGIBLE_POSITION_MANAGER).to_string();
+    let mut changes = vec![];
+
+    for event in pool_events {
+        let (owner, liquidity) = match event.r#type.as_ref().unwrap() {
+            MintEvent(mint) => (&mint.owner, errors::parse_int("mint amount", &mint.amount)),
+            BurnEvent(burn) => (
+                &burn.owner,
+                errors::parse_int("burn amount", &burn.amount).map(|amount| amount.neg()),
+            ),
+            _ => continue,
+        };
+        if *owner == position_manager {
+            continue;
+        }
+        changes.push(LpLiquidityChange {
+            pool_address: event.pool_address.clone(),
+            owner: owner.clone(),
+            liquidity,
+            ordinal: event.log_ordinal,
+        });
+    }
+
+    for position_event in position_manager_events.position_events() {
+        let ord = position_event.get_ordinal();
+        let token_id = position_event.get_token_id();
+        let pool_address = match position_pool(token_id) {
+            Some(pool_address) => pool_address,
+            None => continue,
+        };
+
+        match position_event.r#type.as_ref().unwrap() {
+            IncreaseLiquidityPosition(pos) => {
+                if let Some(owner) = position_owner(token_id, ord) {
+                    changes.push(LpLiquidityChange {
+                        pool_address,
+                        owner,
+                        liquidity: errors::parse_int("position liquidity", &pos.liquidity),
+                        ordinal: ord,
+                    });
+                }
+            }
+            DecreaseLiquidityPosition(pos) => {
+                if let Some(owner) = position_owner(token_id, ord) {
+                    changes.push(LpLiquidityChange {
+                        pool_address,
+                        owner,
+                        liquidity: errors::parse_int("position liquidity", &pos.liquidity)
+                            .map(|liquidity| liquidity.neg()),
+                        ordinal: ord,
+                    });
+                }
+            }
+            // the liquidity of the position moves to the new owner, a position is minted
+            // and burned without liquidity
+            TransferPosition(transfer) => {
+                let liquidity = position_liquidity(token_id, ord).unwrap_or_else(BigInt::zero);
+                if liquidity.is_zero() {
+                    continue;
+                }
+                changes.push(LpLiquidityChange {
+                    pool_address: pool_address.clone(),
+                    owner: transfer.from.clone(),
+                    liquidity: Ok(liquidity.clone().neg()),
+                    ordinal: ord,
+                });
+                changes.push(LpLiquidityChange {
+                    pool_address,
+                    owner: transfer.owner.clone(),
+                    liquidity: Ok(liquidity),
+                    ordinal: ord,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    changes.sort_by_key(|change| change.ordinal);
+    changes
+}
+
+// 1 when the owner of an lp key starts holding liquidity, -1 when it stops
+pub fn active_lp_change(old_liquidity: &BigInt, new_liquidity: &BigInt) -> Option<i64> {
+    match (old_liquidity > &BigInt::zero(), new_liquidity > &BigInt::zero()) {
+        (false, true) => Some(1),
+        (true, false) => Some(-1),
+        _ => None,
+    }
+}
+
+// Changes of the liquidity provider counts: the current count follows the providers becoming
+// active and inactive, the all-time count the providers active for the first time
+pub fn lp_count_changes(
+    active_lps_deltas: &Deltas<DeltaInt64>,
+    all_time_lps_deltas: &Deltas<DeltaInt64>,
+) -> Vec<LpCountChange> {
+    let mut changes = vec![];
+
+    for delta in &active_lps_deltas.deltas {
+        let change = match (delta.old_value > 0, delta.new_value > 0) {
+            (false, true) => BigInt::one(),
+            (true, false) => BigInt::one().neg(),
+            _ => continue,
+        };
+        changes.push(LpCountChange {
+            key: count_key(&delta.key, "liquidityProviderCount"),
+            change,
+            ordinal: delta.ordinal,
+        });
+    }
+
+    for delta in all_time_lps_deltas
+        .deltas
+        .iter()
+        .filter(|delta| delta.operation == Operation::Create)
+    {
+        changes.push(LpCountChange {
+            key: count_key(&delta.key, "allTimeLiquidityProviderCount"),
+            change: BigInt::one(),
+            ordinal: delta.ordinal,
+        });
+    }
+
+    changes
+}
+
+// key of a count of liquidity providers of the pool, token or factory of an active_lps key
+pub fn count_key(active_lp_key: &String, field: &str) -> String {
+    match key::first_segment(active_lp_key) {
+        "factory" => format!("factory:{field}"),
+        kind => format!("{kind}:{}:{field}", key::segment_at(active_lp_key, 1)),
+    }
+}
+
+// pool in which a position was created
+pub fn position_pool(positions_store: &StoreGetProto<PositionEvent>, token_id: &String) -> Option<String> {
+    match positions_store.get_last(format!("position_created:{token_id}")) {
+        Some(PositionEvent {
+            r#type: Some(CreatedPosition(created_position)),
+        }) => Some(created_position.pool),
+        _ => None,
+    }
+}
+
+// owner of the NFT of a position as of the ordinal
+pub fn position_owner(
+    positions_store: &StoreGetProto<PositionEvent>,
+    token_id: &String,
+    ordinal: u64,
+) -> Option<String> {
+    match positions_store.get_at(ordinal, format!("position_transfer:{token_id}")) {
+        Some(PositionEvent {
+            r#type: Some(TransferPosition(transfer)),
+        }) if !transfer.is_burn() => Some(transfer.owner),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lps::{active_lp_change, extract_lp_liquidity_changes, lp_count_changes};
+    use crate::pb::uniswap::events::pool_event::{self, Burn, Mint};
+    use crate::pb::uniswap::events::{IncreaseLiquidityPosition, PoolEvent, TransferPosition};
+    use crate::pb::uniswap::Events;
+    use crate::utils::NON_FUNGIBLE_POSITION_MANAGER;
+    use substreams::pb::substreams::store_delta::Operation;
+    use substreams::scalar::BigInt;
+    use substreams::store::{DeltaInt64, Deltas};
+    use substreams::Hex;
+
+    fn pool_event(ordinal: u64, r#type: pool_event::Type) -> PoolEvent {
+        PoolEvent {
+            log_ordinal: ordinal,
+            pool_address: "pool".to_string(),
+            r#type: Some(r#type),
+            ..Default::default()
+        }
+    }
+
+    fn mint(owner: &str, amount: &str) -> pool_event::Type {
+        pool_event::Type::Mint(Mint {
+            owner: owner.to_string(),
+            amount: amount.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn delta(operation: Operation, key: &str, old_value: i64, new_value: i64) -> DeltaInt64 {
+        DeltaInt64 {
+            operation,
+            ordinal: 3,
+            key: key.to_string(),
+            old_value,
+            new_value,
+        }
+    }
+
+    #[test]
+    fn test_direct_mint_and_burn() {
+        let pool_events = vec![
+            pool_event(2, mint("owner", "100")),
+            pool_event(
+                3,
+                pool_event::Type::Burn(Burn {
+                    owner: "owner".to_string(),
+                    amount: "40".to_string(),
+                    ..Default::default()
+                }),
+            ),
+            // followed through the position events instead
+            pool_event(4, mint(&Hex(NON_FUNGIBLE_POSITION_MANAGER).to_string(), "500")),
+        ];
+
+        let changes =
+            extract_lp_liquidity_changes(&pool_events, &Events::default(), |_| None, |_, _| None, |_, _| None);
+        assert_eq!(2, changes.len());
+        assert_eq!(
+            ("pool", "owner", 2),
+            (
+                changes[0].pool_address.as_str(),
+                changes[0].owner.as_str(),
+                changes[0].ordinal
+            )
+        );
+        assert_eq!(&BigInt::from(100), changes[0].liquidity.as_ref().unwrap());
+        assert_eq!(("owner", 3), (changes[1].owner.as_str(), changes[1].ordinal));
+        assert_eq!(&BigInt::from(-40), changes[1].liquidity.as_ref().unwrap());
+    }
+
+    #[test]
+    fn test_unparsable_amount() {
+        let changes = extract_lp_liquidity_changes(
+            &vec![pool_event(2, mint("owner", "1e18"))],
+            &Events::default(),
+            |_| None,
+            |_, _| None,
+            |_, _| None,
+        );
+        assert_eq!(1, changes.len());
+        assert_eq!("invalid_number", changes[0].liquidity.as_ref().unwrap_err().reason());
+    }
+
+    #[test]
+    fn test_position_mint_then_transfer() {
+        let events = Events {
+            increase_liquidity_positions: vec![IncreaseLiquidityPosition {
+                token_id: "1".to_string(),
+                liquidity: "100".to_string(),
+                log_ordinal: 5,
+                ..Default::default()
+            }],
+            transfer_positions: vec![TransferPosition {
+                token_id: "1".to_string(),
+                owner: "new_owner".to_string(),
+                from: "owner".to_string(),
+                log_ordinal: 6,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let changes = extract_lp_liquidity_changes(
+            &vec![],
+            &events,
+            |token_id| (token_id == "1").then(|| "pool".to_string()),
+            |_, ord| (ord < 6).then(|| "owner".to_string()),
+            |_, ord| (ord >= 5).then(|| BigInt::from(100)),
+        );
+        let changes: Vec<(&str, &str, BigInt, u64)> = changes
+            .iter()
+            .map(|change| {
+                (
+                    change.pool_address.as_str(),
+                    change.owner.as_str(),
+                    change.liquidity.clone().unwrap(),
+                    change.ordinal,
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("pool", "owner", BigInt::from(100), 5),
+                ("pool", "owner", BigInt::from(-100), 6),
+                ("pool", "new_owner", BigInt::from(100), 6),
+            ],
+            changes
+        );
+    }
+
+    #[test]
+    fn test_transfer_without_liquidity() {
+        let events = Events {
+            transfer_positions: vec![TransferPosition {
+                token_id: "1".to_string(),
+                owner: "new_owner".to_string(),
+                from: "owner".to_string(),
+                log_ordinal: 6,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let changes = extract_lp_liquidity_changes(
+            &vec![],
+            &events,
+            |_| Some("pool".to_string()),
+            |_, _| Some("owner".to_string()),
+            |_, _| Some(BigInt::zero()),
+        );
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_lp_becoming_inactive() {
+        assert_eq!(Some(1), active_lp_change(&BigInt::zero(), &BigInt::from(100)));
+        assert_eq!(None, active_lp_change(&BigInt::from(100), &BigInt::from(40)));
+        assert_eq!(Some(-1), active_lp_change(&BigInt::from(40), &BigInt::zero()));
+
+        // the owner was already counted in the all-time providers, set_if_not_exists leaving
+        // no delta
+        let active_lps_deltas = Deltas {
+            deltas: vec![
+                delta(Operation::Update, "pool:pool:owner", 1, 0),
+                delta(Operation::Update, "factory:owner", 2, 1),
+            ],
+        };
+        let changes = lp_count_changes(&active_lps_deltas, &Deltas { deltas: vec![] });
+        assert_eq!(1, changes.len());
+        assert_eq!("pool:pool:liquidityProviderCount", changes[0].key);
+        assert_eq!(BigInt::from(-1), changes[0].change);
+    }
+
+    #[test]
+    fn test_lp_becoming_active() {
+        let active_lps_deltas = Deltas {
+            deltas: vec![delta(Operation::Create, "token:token:owner", 0, 1)],
+        };
+        let all_time_lps_deltas = Deltas {
+            deltas: vec![delta(Operation::Create, "token:token:owner", 0, 12369621)],
+        };
+        let changes: Vec<(String, BigInt)> = lp_count_changes(&active_lps_deltas, &all_time_lps_deltas)
+            .into_iter()
+            .map(|change| (change.key, change.change))
+            .collect();
+        assert_eq!(
+            vec![
+                ("token:token:liquidityProviderCount".to_string(), BigInt::one()),
+                ("token:token:allTimeLiquidityProviderCount".to_string(), BigInt::one()),
+            ],
+            changes
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use crate::pb::uniswap::{Erc20Token, Pool};
+use crate::price::WHITELIST_TOKENS;
+
+// symbols of the WHITELIST_TOKENS, in the same order. A token of another address whose
+// symbol is one of them once normalized is taken as imitating the whitelisted token.
+const WHITELIST_SYMBOLS: [&str; 21] = [
+    "WETH", "DAI", "USDC", "USDT", "TUSD", "WBTC", "CDAI", "CUSDC", "EBASE", "SUSD", "MKR", "COMP", "LINK", "SNX",
+    "YFI", "1INCH", "YCRV", "FEI", "MATIC", "AAVE", "SETH2",
+];
+// hex digits of the address shown next to the symbols of the tokens imitating another one
+const SHORT_ADDRESS_LENGTH: usize = 6;
+
+// Sets the display name and symbol of a token, and flags its symbol when it uses look-alike
+// letters from other scripts or imitates the symbol of a whitelisted token. The display
+// symbol of an imitating token carries the start of its address, e.g. `USDC (0x1a2b3c)`,
+// and empty names and symbols fall back on the symbol and the address.
+pub fn set_display_metadata(token: &mut Erc20Token) {
+    token.homoglyph_symbol = token
+        .symbol
+        .chars()
+        .flat_map(char::to_uppercase)
+        .any(|c| lookalike(c).is_some());
+    token.impersonating_symbol = impersonated_symbol(token).is_some();
+
+    let short_address = format!("0x{}", &token.address[..SHORT_ADDRESS_LENGTH]);
+    let symbol = clean(&token.symbol);
+    token.display_symbol = if symbol.is_empty() {
+        short_address
+    } else if token.impersonating_symbol {
+        format!("{symbol} ({short_address})")
+    } else {
+        symbol
+    };
+
+    let name = clean(&token.name);
+    token.display_name = match name.is_empty() {
+        true => token.display_symbol.clone(),
+        false => name,
+    };
+}
+
+// Label of the pool from the display symbols of its tokens and its fee tier, e.g. `USDC/WETH 0.05%`
+pub fn pool_label(pool: &Pool) -> String {
+    format!(
+        "{}/{} {}%",
+        pool.token0_ref().display_symbol,
+        pool.token1_ref().display_symbol,
+        fee_tier_percent(&pool.fee_tier)
+    )
+}
+
+// Removes the control characters, the invisible ones (zero width spaces and joiners,
+// bidirectional overrides, byte order marks) and the replacement characters left by the
+// lossy UTF-8 decodings, and collapses the whitespace
+pub fn clean(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control() && !is_invisible(*c) && *c != char::REPLACEMENT_CHARACTER)
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// The whitelisted symbol imitated by a token of another address
+pub fn impersonated_symbol(token: &Erc20Token) -> Option<&'static str> {
+    if WHITELIST_TOKENS.contains(&token.address.as_str()) {
+        return None;
+    }
+    let symbol = normalize_symbol(&token.symbol);
+    WHITELIST_SYMBOLS
+        .into_iter()
+        .find(|whitelist_symbol| *whitelist_symbol == symbol)
+}
+
+// uppercase ASCII letters and digits of the symbol, the Cyrillic, Greek and fullwidth
+// letters looking like latin ones being replaced by them
+fn normalize_symbol(symbol: &str) -> String {
+    symbol
+        .chars()
+        .flat_map(char::to_uppercase)
+        .map(|c| lookalike(c).unwrap_or(c))
+        .filter(char::is_ascii_alphanumeric)
+        .collect()
+}
+
+// ASCII letter looking like an uppercase Cyrillic, Greek or fullwidth letter
+fn lookalike(c: char) -> Option<char> {
+    match c {
+        'А' | 'Α' | 'Ａ' => Some('A'),
+        'В' | 'Β' | 'Ｂ' => Some('B'),
+        'С' | 'Ｃ' => Some('C'),
+        'Е' | 'Ε' | 'Ｅ' => Some('E'),
+        'Н' | 'Η' | 'Ｈ' => Some('H'),
+        'І' | 'Ι' | 'Ｉ' => Some('I'),
+        'Ј' | 'Ｊ' => Some('J'),
+        'К' | 'Κ' | 'Ｋ' => Some('K'),
+        'М' | 'Μ' | 'Ｍ' => Some('M'),
+        'Ν' | 'Ｎ' => Some('N'),
+        'О' | 'Ο' | 'Ｏ' => Some('O'),
+        'Р' | 'Ρ' | 'Ｐ' => Some('P'),
+        'Ѕ' | 'Ｓ' => Some('S'),
+        'Т' | 'Τ' | 'Ｔ' => Some('T'),
+        'Ｕ' => Some('U'),
+        'Ｗ' => Some('W'),
+        'Х' | 'Χ' | 'Ｘ' => Some('X'),
+        'У' | 'Υ' | 'Ｙ' => Some('Y'),
+        'Ζ' | 'Ｚ' => Some('Z'),
+        _ => None,
+    }
+}
+
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00ad}' | '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2060}'..='\u{2064}' | '\u{2066}'..='\u{2069}' | '\u{feff}'
+    )
+}
+
+// fee tier in hundredths of a bip as a percentage, e.g. 500 as 0.05
+fn fee_tier_percent(fee_tier: &str) -> String {
+    let fee_tier: u64 = fee_tier.parse().unwrap_or_default();
+    let fraction = format!("{:04}", fee_tier % 10000);
+    let fraction = fraction.trim_end_matches('0');
+    match fraction.is_empty() {
+        true => (fee_tier / 10000).to_string(),
+        false => format!("{}.{fraction}", fee_tier / 10000),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metadata::{clean, fee_tier_percent, normalize_symbol, set_display_metadata};
+    use crate::pb::uniswap::Erc20Token;
+
+    #[test]
+    fn test_normalize_symbol() {
+        assert_eq!("USDC", normalize_symbol("usdc"));
+        assert_eq!("USDC", normalize_symbol("$USDC"));
+        // Cyrillic С and Greek Τ
+        assert_eq!("USDC", normalize_symbol("USDС"));
+        assert_eq!("USDT", normalize_symbol("USDΤ"));
+        assert_eq!("WETH", normalize_symbol("Ｗ ＥＴＨ"));
+        assert_eq!("USDCE", normalize_symbol("USDC.e"));
+    }
+
+    #[test]
+    fn test_clean() {
+        assert_eq!("Maker", clean("Maker\u{0}\u{1b}"));
+        assert_eq!("Wrapped Ether", clean(" Wrapped\u{200b}  Ether\n"));
+        assert_eq!("USDC", clean("\u{202e}USDC\u{fffd}"));
+    }
+
+    #[test]
+    fn test_fee_tier_percent() {
+        assert_eq!("0.01", fee_tier_percent("100"));
+        assert_eq!("0.05", fee_tier_percent("500"));
+        assert_eq!("0.3", fee_tier_percent("3000"));
+        assert_eq!("1", fee_tier_percent("10000"));
+    }
+
+    #[test]
+    fn test_set_display_metadata() {
+        let mut token = Erc20Token {
+            address: "1a2b3c4d5e6f1a2b3c4d5e6f1a2b3c4d5e6f1a2b".to_string(),
+            name: "USD Coin\u{0}".to_string(),
+            symbol: "UЅDС".to_string(),
+            ..Default::default()
+        };
+        set_display_metadata(&mut token);
+
+        assert!(token.homoglyph_symbol);
+        assert!(token.impersonating_symbol);
+        assert_eq!("UЅDС (0x1a2b3c)", token.display_symbol);
+        assert_eq!("USD Coin", token.display_name);
+    }
+}
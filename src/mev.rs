@@ -0,0 +1,450 @@
+use crate::diagnostics::SkippedItems;
+use crate::errors;
+use crate::pb::uniswap::events::pool_event::Swap;
+use crate::pb::uniswap::events::pool_event::Type::Swap as SwapEvent;
+use crate::pb::uniswap::events::PoolEvent;
+use crate::pb::uniswap::sandwich::SandwichVictim;
+use crate::pb::uniswap::{CyclicArbitrage, Sandwich};
+use crate::pb::SwapLegs;
+use crate::sources::UNKNOWN_SOURCE;
+use std::collections::{BTreeMap, HashSet};
+use std::ops::{Add, Div, Mul, Sub};
+use substreams::scalar::BigDecimal;
+
+const MODULE: &str = "map_mev";
+
+// A swap of the block with its amounts parsed
+pub struct ParsedSwap<'a> {
+    event: &'a PoolEvent,
+    swap: &'a Swap,
+    amount0: BigDecimal,
+    amount1: BigDecimal,
+    sqrt_price: BigDecimal,
+    legs: SwapLegs,
+}
+
+impl ParsedSwap<'_> {
+    fn zero_for_one(&self) -> bool {
+        self.amount0.gt(&BigDecimal::zero())
+    }
+
+    // the swaps going through a router or an aggregator of the SourceRegistry are made
+    // on behalf of their origin, which is no attacker
+    fn through_router(&self) -> bool {
+        !self.swap.source.is_empty() && self.swap.source != UNKNOWN_SOURCE
+    }
+}
+
+// The swaps of the pool events whose amounts parse, the others being skipped
+pub fn parse_swaps<'a>(pool_events: &'a [PoolEvent], skipped_items: &mut SkippedItems) -> Vec<ParsedSwap<'a>> {
+    pool_events
+        .iter()
+        .filter_map(|event| {
+            let swap = match &event.r#type {
+                Some(SwapEvent(swap)) => swap,
+                _ => return None,
+            };
+            let parsed_swap = (|| {
+                Ok(ParsedSwap {
+                    event,
+                    swap,
+                    amount0: errors::parse_decimal("amount0", &swap.amount_0)?,
+                    amount1: errors::parse_decimal("amount1", &swap.amount_1)?,
+                    sqrt_price: errors::parse_decimal("sqrt price", &swap.sqrt_price)?,
+                    legs: event.get_swap_legs()?,
+                })
+            })();
+            skipped_items.check(MODULE, event.log_ordinal, &event.pool_address, parsed_swap)
+        })
+        .collect()
+}
+
+// A sandwich is a swap followed, in another transaction of the same origin, by a swap in the
+// opposite direction on the same pool, with swaps of other origins in the front-run direction
+// in between. The front-run and back-run go through the contract of the attacker, the swaps
+// of a router of the SourceRegistry are never taken for one, as a router is the sender of the
+// swaps of all of its users. The sqrt price of a pool before an ordinal is given by
+// pool_sqrt_price and the USD prices of the tokens by token_price_usd, by ordinal and token
+// address.
+pub fn extract_sandwiches<S, P>(
+    swaps: &[ParsedSwap],
+    pool_sqrt_price: S,
+    token_price_usd: P,
+    skipped_items: &mut SkippedItems,
+    block_number: u64,
+    timestamp: u64,
+) -> Vec<Sandwich>
+where
+    S: Fn(&String, u64) -> Option<String>,
+    P: Fn(u64, &String) -> BigDecimal,
+{
+    let mut sandwiches = vec![];
+
+    let mut swaps_by_pool: BTreeMap<&String, Vec<&ParsedSwap>> = BTreeMap::new();
+    for parsed_swap in swaps {
+        swaps_by_pool
+            .entry(&parsed_swap.event.pool_address)
+            .or_default()
+            .push(parsed_swap);
+    }
+
+    for (pool_address, mut swaps) in swaps_by_pool {
+        swaps.sort_by_key(|parsed_swap| parsed_swap.event.log_ordinal);
+        let mut used: HashSet<u64> = HashSet::new();
+
+        for (i, front_run) in swaps.iter().enumerate() {
+            if used.contains(&front_run.event.log_ordinal) || front_run.through_router() {
+                continue;
+            }
+            let attacker = &front_run.swap.origin;
+
+            let back_run_idx = swaps.iter().enumerate().skip(i + 1).find_map(|(j, back_run)| {
+                if !used.contains(&back_run.event.log_ordinal)
+                    && back_run.swap.origin == *attacker
+                    && !back_run.through_router()
+                    && back_run.event.transaction_id != front_run.event.transaction_id
+                    && back_run.zero_for_one() != front_run.zero_for_one()
+                {
+                    Some(j)
+                } else {
+                    None
+                }
+            });
+            let back_run_idx = match back_run_idx {
+                Some(j) => j,
+                None => continue,
+            };
+            let back_run = swaps[back_run_idx];
+
+            let victims: Vec<&ParsedSwap> = swaps[i + 1..back_run_idx]
+                .iter()
+                .copied()
+                .filter(|victim| {
+                    victim.event.transaction_id != front_run.event.transaction_id
+                        && victim.event.transaction_id != back_run.event.transaction_id
+                        && victim.swap.origin != *attacker
+                        && victim.zero_for_one() == front_run.zero_for_one()
+                })
+                .collect();
+            if victims.is_empty() {
+                continue;
+            }
+
+            used.insert(front_run.event.log_ordinal);
+            used.insert(back_run.event.log_ordinal);
+
+            // price move caused by the front-run, expressed as output per input in the
+            // victims direction, a victim swap would have received this much more
+            let front_run_ord = front_run.event.log_ordinal;
+            let pre_front_run_sqrt_price =
+                pool_sqrt_price(pool_address, front_run_ord.saturating_sub(1)).and_then(|sqrt_price| {
+                    skipped_items.check(
+                        MODULE,
+                        front_run_ord,
+                        pool_address,
+                        errors::parse_decimal("sqrt price", &sqrt_price),
+                    )
+                });
+            let post_front_run_sqrt_price = &front_run.sqrt_price;
+            let price_ratio = match pre_front_run_sqrt_price {
+                Some(pre) if !pre.is_zero() && !post_front_run_sqrt_price.is_zero() => {
+                    let ratio = pre
+                        .clone()
+                        .mul(pre)
+                        .div(post_front_run_sqrt_price.clone().mul(post_front_run_sqrt_price.clone()));
+                    if front_run.zero_for_one() {
+                        ratio
+                    } else {
+                        BigDecimal::one().div(ratio)
+                    }
+                }
+                _ => BigDecimal::one(),
+            };
+
+            let mut victim_loss_usd = BigDecimal::zero();
+            let sandwich_victims = victims
+                .iter()
+                .map(|victim| {
+                    let legs = &victim.legs;
+                    let loss = legs.amount_out.clone().mul(price_ratio.clone().sub(BigDecimal::one()));
+                    let loss_usd = loss
+                        .clone()
+                        .mul(token_price_usd(victim.event.log_ordinal, &legs.token_out));
+                    victim_loss_usd = victim_loss_usd.clone().add(loss_usd.clone());
+
+                    SandwichVictim {
+                        transaction: victim.event.transaction_id.clone(),
+                        origin: victim.swap.origin.clone(),
+                        ordinal: victim.event.log_ordinal,
+                        token_out: legs.token_out.clone(),
+                        loss: loss.to_string(),
+                        loss_usd: loss_usd.to_string(),
+                    }
+                })
+                .collect();
+
+            // the attacker pays what goes into the pool and receives what comes out of it
+            let back_run_ord = back_run.event.log_ordinal;
+            let net_amount0 = front_run.amount0.clone().add(back_run.amount0.clone()).neg();
+            let net_amount1 = front_run.amount1.clone().add(back_run.amount1.clone()).neg();
+            let attacker_profit_usd = net_amount0
+                .mul(token_price_usd(back_run_ord, &back_run.event.token0))
+                .add(net_amount1.mul(token_price_usd(back_run_ord, &back_run.event.token1)));
+
+            sandwiches.push(Sandwich {
+                pool_address: pool_address.clone(),
+                attacker: attacker.clone(),
+                attacker_contract: front_run.swap.sender.clone(),
+                front_run_transaction: front_run.event.transaction_id.clone(),
+                back_run_transaction: back_run.event.transaction_id.clone(),
+                front_run_ordinal: front_run_ord,
+                back_run_ordinal: back_run_ord,
+                victims: sandwich_victims,
+                victim_loss_usd: victim_loss_usd.to_string(),
+                attacker_profit_usd: attacker_profit_usd.to_string(),
+                block_number,
+                timestamp,
+            });
+        }
+    }
+
+    sandwiches
+}
+
+// Chains the swaps of each transaction, in ordinal order, while the output token of a
+// swap is the input token of the next one. A chain of at least two swaps coming back
+// to its starting token is a cyclic arbitrage.
+pub fn extract_cyclic_arbitrages<P>(
+    swaps: &[ParsedSwap],
+    token_price_usd: P,
+    block_number: u64,
+    timestamp: u64,
+) -> Vec<CyclicArbitrage>
+where
+    P: Fn(u64, &String) -> BigDecimal,
+{
+    let mut cyclic_arbitrages = vec![];
+
+    let mut swaps_by_transaction: BTreeMap<&String, Vec<&ParsedSwap>> = BTreeMap::new();
+    for parsed_swap in swaps {
+        swaps_by_transaction
+            .entry(&parsed_swap.event.transaction_id)
+            .or_default()
+            .push(parsed_swap);
+    }
+
+    for (transaction_id, mut swaps) in swaps_by_transaction {
+        swaps.sort_by_key(|parsed_swap| parsed_swap.event.log_ordinal);
+
+        let mut start = 0;
+        while start < swaps.len() {
+            let first = swaps[start];
+            let first_legs = &first.legs;
+            let mut pools = vec![first.event.pool_address.clone()];
+            let mut token_out = first_legs.token_out.clone();
+            let mut amount_out = first_legs.amount_out.clone();
+            let mut end = start;
+
+            while end + 1 < swaps.len() && token_out != first_legs.token_in {
+                let next = swaps[end + 1];
+                let next_legs = &next.legs;
+                if next_legs.token_in != token_out {
+                    break;
+                }
+                end += 1;
+                pools.push(next.event.pool_address.clone());
+                token_out = next_legs.token_out.clone();
+                amount_out = next_legs.amount_out.clone();
+            }
+
+            if end > start && token_out == first_legs.token_in {
+                let last = swaps[end];
+                let profit = amount_out.clone().sub(first_legs.amount_in.clone());
+                let profit_usd = profit
+                    .clone()
+                    .mul(token_price_usd(last.event.log_ordinal, &first_legs.token_in));
+
+                cyclic_arbitrages.push(CyclicArbitrage {
+                    transaction: transaction_id.clone(),
+                    origin: first.swap.origin.clone(),
+                    token: first_legs.token_in.clone(),
+                    pools,
+                    amount_in: first_legs.amount_in.to_string(),
+                    amount_out: amount_out.to_string(),
+                    profit: profit.to_string(),
+                    profit_usd: profit_usd.to_string(),
+                    block_number,
+                    timestamp,
+                });
+            }
+
+            start = end + 1;
+        }
+    }
+
+    cyclic_arbitrages
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::SkippedItems;
+    use crate::errors::Policy;
+    use crate::mev::{extract_cyclic_arbitrages, extract_sandwiches, parse_swaps};
+    use crate::pb::uniswap::events::pool_event::{Swap, Type};
+    use crate::pb::uniswap::events::PoolEvent;
+    use crate::pb::uniswap::{CyclicArbitrage, Sandwich};
+    use substreams::scalar::BigDecimal;
+
+    // swap of the contract of the attacker on the pool, at a sqrt price of 1
+    fn swap(ordinal: u64, transaction: &str, origin: &str, amount0: &str, amount1: &str) -> PoolEvent {
+        PoolEvent {
+            log_ordinal: ordinal,
+            transaction_id: transaction.to_string(),
+            pool_address: "pool".to_string(),
+            token0: "token0".to_string(),
+            token1: "token1".to_string(),
+            r#type: Some(Type::Swap(Swap {
+                sender: "bot".to_string(),
+                origin: origin.to_string(),
+                amount_0: amount0.to_string(),
+                amount_1: amount1.to_string(),
+                sqrt_price: "1".to_string(),
+                source: "unknown".to_string(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn through_router(mut event: PoolEvent) -> PoolEvent {
+        if let Some(Type::Swap(swap)) = event.r#type.as_mut() {
+            swap.sender = "router".to_string();
+            swap.source = "uniswap-swap-router".to_string();
+        }
+        event
+    }
+
+    fn on_pool(mut event: PoolEvent, pool_address: &str, token0: &str, token1: &str) -> PoolEvent {
+        event.pool_address = pool_address.to_string();
+        event.token0 = token0.to_string();
+        event.token1 = token1.to_string();
+        event
+    }
+
+    // the sqrt price of the pool is 2 before the front-run and every token is worth 1 USD
+    fn sandwiches(pool_events: Vec<PoolEvent>) -> Vec<Sandwich> {
+        let mut skipped_items = SkippedItems::new(Policy::Fail);
+        let swaps = parse_swaps(&pool_events, &mut skipped_items);
+        extract_sandwiches(
+            &swaps,
+            |_, _| Some("2".to_string()),
+            |_, _| BigDecimal::one(),
+            &mut skipped_items,
+            1,
+            1,
+        )
+    }
+
+    fn cyclic_arbitrages(pool_events: Vec<PoolEvent>) -> Vec<CyclicArbitrage> {
+        let swaps = parse_swaps(&pool_events, &mut SkippedItems::new(Policy::Fail));
+        extract_cyclic_arbitrages(&swaps, |_, _| BigDecimal::one(), 1, 1)
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn test_sandwich() {
+        let sandwiches = sandwiches(vec![
+            swap(1, "front", "attacker", "100", "-90"),
+            through_router(swap(2, "victim", "victim", "50", "-40")),
+            swap(3, "back", "attacker", "-101", "92"),
+        ]);
+
+        assert_eq!(1, sandwiches.len());
+        let sandwich = &sandwiches[0];
+        assert_eq!("attacker", sandwich.attacker);
+        assert_eq!("bot", sandwich.attacker_contract);
+        assert_eq!(
+            ("front", "back"),
+            (
+                sandwich.front_run_transaction.as_str(),
+                sandwich.back_run_transaction.as_str()
+            )
+        );
+        assert_eq!(1, sandwich.victims.len());
+        assert_eq!("victim", sandwich.victims[0].origin);
+        // the front-run moved the price of token0 from 4 to 1 token1, the victim would have
+        // received 4 times its 40 token1
+        assert_eq!(decimal("120"), decimal(&sandwich.victims[0].loss));
+        assert_eq!(decimal("120"), decimal(&sandwich.victim_loss_usd));
+        // 1 token0 gained for 2 token1 spent
+        assert_eq!(decimal("-1"), decimal(&sandwich.attacker_profit_usd));
+    }
+
+    #[test]
+    fn test_swaps_through_the_same_router() {
+        // the router is the sender of the swaps of every user
+        let sandwiches = sandwiches(vec![
+            through_router(swap(1, "alice-1", "alice", "100", "-90")),
+            through_router(swap(2, "carol", "carol", "50", "-40")),
+            through_router(swap(3, "bob", "bob", "-101", "92")),
+            through_router(swap(4, "alice-2", "alice", "-101", "92")),
+        ]);
+        assert!(sandwiches.is_empty());
+    }
+
+    #[test]
+    fn test_swaps_of_different_origins() {
+        // the same contract swapping for two origins is no sandwich
+        let sandwiches = sandwiches(vec![
+            swap(1, "alice", "alice", "100", "-90"),
+            through_router(swap(2, "carol", "carol", "50", "-40")),
+            swap(3, "bob", "bob", "-101", "92"),
+        ]);
+        assert!(sandwiches.is_empty());
+    }
+
+    #[test]
+    fn test_cyclic_arbitrage() {
+        let cyclic_arbitrages = cyclic_arbitrages(vec![
+            // A -> B -> C -> A
+            on_pool(swap(1, "arb", "arbitrageur", "100", "-200"), "pool-ab", "a", "b"),
+            on_pool(swap(2, "arb", "arbitrageur", "200", "-50"), "pool-bc", "b", "c"),
+            on_pool(swap(3, "arb", "arbitrageur", "-103", "50"), "pool-ac", "a", "c"),
+        ]);
+
+        assert_eq!(1, cyclic_arbitrages.len());
+        let cyclic_arbitrage = &cyclic_arbitrages[0];
+        assert_eq!("arbitrageur", cyclic_arbitrage.origin);
+        assert_eq!("a", cyclic_arbitrage.token);
+        assert_eq!(vec!["pool-ab", "pool-bc", "pool-ac"], cyclic_arbitrage.pools);
+        assert_eq!(decimal("3"), decimal(&cyclic_arbitrage.profit));
+        assert_eq!(decimal("3"), decimal(&cyclic_arbitrage.profit_usd));
+    }
+
+    #[test]
+    fn test_chain_broken() {
+        let cyclic_arbitrages = cyclic_arbitrages(vec![
+            // A -> B, then C -> A which doesn't start from B
+            on_pool(swap(1, "tx-1", "trader", "100", "-200"), "pool-ab", "a", "b"),
+            on_pool(swap(2, "tx-1", "trader", "-103", "50"), "pool-ac", "a", "c"),
+            // A -> B -> C, not coming back to A
+            on_pool(swap(3, "tx-2", "trader", "100", "-200"), "pool-ab", "a", "b"),
+            on_pool(swap(4, "tx-2", "trader", "200", "-50"), "pool-bc", "b", "c"),
+        ]);
+        assert!(cyclic_arbitrages.is_empty());
+    }
+
+    #[test]
+    fn test_unparsable_swap_skipped() {
+        let pool_events = vec![swap(1, "tx", "trader", "n/a", "-90")];
+        let mut skipped_items = SkippedItems::new(Policy::SkipAndReport);
+        assert!(parse_swaps(&pool_events, &mut skipped_items).is_empty());
+
+        let items = skipped_items.into_items();
+        assert_eq!(1, items.len());
+        assert_eq!(("map_mev", 1), (items[0].module.as_str(), items[0].ordinal));
+    }
+}
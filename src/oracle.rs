@@ -0,0 +1,171 @@
+use crate::abi::chainlink_aggregator::events::{AnswerUpdated, NewTransmission};
+use std::collections::HashMap;
+use substreams::errors::Error;
+use substreams::scalar::BigDecimal;
+use substreams::Hex;
+use substreams_ethereum::pb::eth::v2::Log;
+use substreams_ethereum::Event;
+
+// answers of the Chainlink USD feeds have 8 decimals
+const USD_FEED_DECIMALS: u64 = 8;
+
+pub struct OracleFeed {
+    pub token: String,
+    pub decimals: u64,
+}
+
+pub struct OracleFeeds {
+    // aggregator address to its feed
+    feeds: HashMap<String, OracleFeed>,
+}
+
+impl OracleFeeds {
+    // params are `token=aggregator` entries separated by `&`, the decimals of the answers
+    // following the aggregator as `token=aggregator:decimals` when they are not 8, e.g. WETH
+    // with the ETH / USD aggregator. The aggregator is the contract emitting the answers, not
+    // the proxy: a proxy moving to a new aggregator on a phase change is only followed when
+    // the aggregators of each of its phases are given, several of them may price a token.
+    pub fn from_params(params: &str) -> Result<Self, Error> {
+        let mut feeds = HashMap::new();
+
+        for entry in params.split('&').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (token, feed) = match entry.split_once('=') {
+                Some((token, feed)) => (normalize_address(token), feed),
+                None => return Err(invalid_entry(entry)),
+            };
+            let (aggregator, decimals) = match feed.split_once(':') {
+                Some((aggregator, decimals)) => match decimals.trim().parse::<u64>() {
+                    Ok(decimals) => (normalize_address(aggregator), decimals),
+                    Err(_) => return Err(invalid_entry(entry)),
+                },
+                None => (normalize_address(feed), USD_FEED_DECIMALS),
+            };
+            if !is_address(&token) || !is_address(&aggregator) {
+                return Err(invalid_entry(entry));
+            }
+            feeds.insert(aggregator, OracleFeed { token, decimals });
+        }
+
+        Ok(OracleFeeds { feeds })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.feeds.is_empty()
+    }
+
+    pub fn feed(&self, aggregator: &[u8]) -> Option<&OracleFeed> {
+        self.feeds.get(&Hex(aggregator).to_string())
+    }
+}
+
+// USD price reported by an AnswerUpdated or an OCR NewTransmission log, the answer having
+// the decimals of its feed
+pub fn decode_usd_price(log: &Log, decimals: u64) -> Option<BigDecimal> {
+    let answer = if let Some(answer_updated) = AnswerUpdated::match_and_decode(log) {
+        answer_updated.current
+    } else if let Some(new_transmission) = NewTransmission::match_and_decode(log) {
+        new_transmission.answer
+    } else {
+        return None;
+    };
+
+    Some(answer.to_decimal(decimals))
+}
+
+fn normalize_address(address: &str) -> String {
+    address.trim().trim_start_matches("0x").to_lowercase()
+}
+
+fn is_address(address: &String) -> bool {
+    address.len() == 40 && hex::decode(address).is_ok()
+}
+
+fn invalid_entry(entry: &str) -> Error {
+    Error::msg(format!(
+        "invalid oracle feed entry {entry}, expected token=aggregator or token=aggregator:decimals"
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::oracle::{decode_usd_price, OracleFeeds};
+    use std::str::FromStr;
+    use substreams::hex;
+    use substreams::scalar::BigDecimal;
+    use substreams_ethereum::pb::eth::v2::Log;
+
+    const WETH: &str = "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+    const ETH_USD_AGGREGATOR: &str = "37bc7498f4ff12c19678ee8fe19d713b87f6a9e6";
+
+    // AnswerUpdated(int256 indexed current, uint256 indexed roundId, uint256 updatedAt)
+    fn answer_updated(current: [u8; 32]) -> Log {
+        Log {
+            address: hex!("37bc7498f4ff12c19678ee8fe19d713b87f6a9e6").to_vec(),
+            topics: vec![
+                hex!("0559884fd3a460db3073b7fc896cc77986f16e378210ded43186175bf646fc5f").to_vec(),
+                current.to_vec(),
+                hex!("0000000000000000000000000000000000000000000000000000000000000001").to_vec(),
+            ],
+            data: hex!("0000000000000000000000000000000000000000000000000000000064000000").to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_feeds_from_params() {
+        let feeds = OracleFeeds::from_params(&format!(
+            " 0x{}=0x{} & {WETH}=0x{}:18",
+            WETH.to_uppercase(),
+            ETH_USD_AGGREGATOR,
+            "0000000000000000000000000000000000000001"
+        ))
+        .unwrap();
+
+        let feed = feeds.feed(&hex::decode(ETH_USD_AGGREGATOR).unwrap()).unwrap();
+        assert_eq!(WETH, feed.token);
+        assert_eq!(8, feed.decimals);
+        // the aggregator of another phase pricing the same token
+        let feed = feeds
+            .feed(&hex::decode("0000000000000000000000000000000000000001").unwrap())
+            .unwrap();
+        assert_eq!(WETH, feed.token);
+        assert_eq!(18, feed.decimals);
+        assert!(feeds.feed(&hex::decode(WETH).unwrap()).is_none());
+
+        assert!(OracleFeeds::from_params("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_feeds_from_invalid_params() {
+        assert!(OracleFeeds::from_params(WETH).is_err());
+        assert!(OracleFeeds::from_params(&format!("{WETH}=0x1234")).is_err());
+        assert!(OracleFeeds::from_params(&format!("weth={ETH_USD_AGGREGATOR}")).is_err());
+        assert!(OracleFeeds::from_params(&format!("{WETH}={ETH_USD_AGGREGATOR}:eight")).is_err());
+        assert!(OracleFeeds::from_params(&format!("{WETH}={ETH_USD_AGGREGATOR}&{WETH}")).is_err());
+    }
+
+    #[test]
+    fn test_decode_usd_price() {
+        // 1850.12345678 USD
+        let log = answer_updated(hex!("0000000000000000000000000000000000000000000000000000002b13985b4e"));
+        assert_eq!(
+            Some(BigDecimal::from_str("1850.12345678").unwrap()),
+            decode_usd_price(&log, 8)
+        );
+        assert_eq!(
+            Some(BigDecimal::from_str("0.000000185012345678").unwrap()),
+            decode_usd_price(&log, 18)
+        );
+
+        // answers are signed
+        let log = answer_updated(hex!("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffc4653600"));
+        assert_eq!(Some(BigDecimal::from_str("-10").unwrap()), decode_usd_price(&log, 8));
+    }
+
+    #[test]
+    fn test_decode_other_log() {
+        let mut log = answer_updated(hex!("0000000000000000000000000000000000000000000000000000002b13985b4e"));
+        log.topics[0] = hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef").to_vec();
+        assert_eq!(None, decode_usd_price(&log, 8));
+    }
+}
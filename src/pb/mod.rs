@@ -1,10 +1,13 @@
+use crate::errors::{self, DataError};
 use crate::pb::uniswap::events::pool_event::Type;
 use crate::pb::uniswap::events::position_event::Type::{
     CollectPosition, CreatedPosition, DecreaseLiquidityPosition, IncreaseLiquidityPosition, TransferPosition,
 };
 use crate::pb::uniswap::events::PoolEvent;
 use crate::pb::uniswap::events::PositionEvent;
-use crate::utils::ERROR_POOL;
+use crate::pb::uniswap::events::TransferPosition as TransferPositionEvent;
+use crate::pb::uniswap::Events;
+use crate::utils::{ERROR_POOL, ZERO_ADDRESS};
 use crate::{Erc20Token, Pool};
 use substreams::scalar::BigDecimal;
 use substreams::{log, Hex};
@@ -24,6 +27,85 @@ impl PositionEvent {
             TransferPosition(item) => item.log_ordinal,
         };
     }
+
+    pub fn get_token_id(&self) -> &String {
+        return match self.r#type.as_ref().unwrap() {
+            CreatedPosition(item) => &item.token_id,
+            IncreaseLiquidityPosition(item) => &item.token_id,
+            DecreaseLiquidityPosition(item) => &item.token_id,
+            CollectPosition(item) => &item.token_id,
+            TransferPosition(item) => &item.token_id,
+        };
+    }
+
+    // fee growths inside the position of the event, a transfer carries none
+    pub fn get_fee_growth_inside_last_x128(&self) -> (Option<String>, Option<String>) {
+        match self.r#type.as_ref().unwrap() {
+            CreatedPosition(item) => (
+                item.fee_growth_inside0_last_x128.clone(),
+                item.fee_growth_inside1_last_x128.clone(),
+            ),
+            IncreaseLiquidityPosition(item) => (
+                item.fee_growth_inside0_last_x128.clone(),
+                item.fee_growth_inside1_last_x128.clone(),
+            ),
+            DecreaseLiquidityPosition(item) => (
+                item.fee_growth_inside0_last_x128.clone(),
+                item.fee_growth_inside1_last_x128.clone(),
+            ),
+            CollectPosition(item) => (
+                item.fee_growth_inside0_last_x128.clone(),
+                item.fee_growth_inside1_last_x128.clone(),
+            ),
+            TransferPosition(_) => (None, None),
+        }
+    }
+}
+
+impl Events {
+    // all the position events, ordered by log ordinal
+    pub fn position_events(&self) -> Vec<PositionEvent> {
+        let mut position_events: Vec<PositionEvent> = vec![];
+        for pos in &self.created_positions {
+            position_events.push(PositionEvent {
+                r#type: Some(CreatedPosition(pos.clone())),
+            });
+        }
+
+        for pos in &self.increase_liquidity_positions {
+            position_events.push(PositionEvent {
+                r#type: Some(IncreaseLiquidityPosition(pos.clone())),
+            });
+        }
+
+        for pos in &self.decrease_liquidity_positions {
+            position_events.push(PositionEvent {
+                r#type: Some(DecreaseLiquidityPosition(pos.clone())),
+            });
+        }
+
+        for pos in &self.collect_positions {
+            position_events.push(PositionEvent {
+                r#type: Some(CollectPosition(pos.clone())),
+            });
+        }
+
+        for pos in &self.transfer_positions {
+            position_events.push(PositionEvent {
+                r#type: Some(TransferPosition(pos.clone())),
+            });
+        }
+
+        position_events.sort_by_key(|x| x.get_ordinal());
+        position_events
+    }
+}
+
+impl TransferPositionEvent {
+    // the NonfungiblePositionManager burns the NFT by transferring it to the zero address
+    pub fn is_burn(&self) -> bool {
+        self.owner == Hex(ZERO_ADDRESS).to_string()
+    }
 }
 
 impl Erc20Token {
@@ -80,6 +162,14 @@ pub struct TokenAmounts {
     pub token1_addr: String,
 }
 
+// Token going into the pool and token coming out of it for a swap, amounts are absolute
+pub struct SwapLegs {
+    pub token_in: String,
+    pub amount_in: BigDecimal,
+    pub token_out: String,
+    pub amount_out: BigDecimal,
+}
+
 pub struct AdjustedAmounts {
     // pub token0: BigDecimal,
     // pub token0_abs: BigDecimal,
@@ -96,26 +186,51 @@ pub struct AdjustedAmounts {
 }
 
 impl PoolEvent {
-    pub fn get_amounts(&self) -> Option<TokenAmounts> {
-        return match self.r#type.as_ref().unwrap().clone() {
-            Type::Mint(evt) => Some(TokenAmounts {
-                amount0: BigDecimal::try_from(evt.amount_0).unwrap(),
-                amount1: BigDecimal::try_from(evt.amount_1).unwrap(),
-                token0_addr: self.token0.clone(),
-                token1_addr: self.token1.clone(),
-            }),
-            Type::Burn(evt) => Some(TokenAmounts {
-                amount0: BigDecimal::try_from(evt.amount_0).unwrap().neg(),
-                amount1: BigDecimal::try_from(evt.amount_1).unwrap().neg(),
-                token0_addr: self.token0.clone(),
-                token1_addr: self.token1.clone(),
-            }),
-            Type::Swap(evt) => Some(TokenAmounts {
-                amount0: BigDecimal::try_from(evt.amount_0).unwrap(),
-                amount1: BigDecimal::try_from(evt.amount_1).unwrap(),
-                token0_addr: self.token0.clone(),
-                token1_addr: self.token1.clone(),
-            }),
+    // an error for an event which is not a swap or whose amounts don't parse
+    pub fn get_swap_legs(&self) -> Result<SwapLegs, DataError> {
+        let swap = match self.r#type.as_ref() {
+            Some(Type::Swap(swap)) => swap,
+            _ => return Err(DataError::UnexpectedEvent("swap")),
         };
+
+        let amount0 = errors::parse_decimal("amount0", &swap.amount_0)?;
+        let amount1 = errors::parse_decimal("amount1", &swap.amount_1)?;
+
+        if amount0.gt(&BigDecimal::zero()) {
+            Ok(SwapLegs {
+                token_in: self.token0.clone(),
+                amount_in: amount0,
+                token_out: self.token1.clone(),
+                amount_out: amount1.absolute(),
+            })
+        } else {
+            Ok(SwapLegs {
+                token_in: self.token1.clone(),
+                amount_in: amount1,
+                token_out: self.token0.clone(),
+                amount_out: amount0.absolute(),
+            })
+        }
+    }
+
+    pub fn get_amounts(&self) -> Result<TokenAmounts, DataError> {
+        let (amount0, amount1) = match self.r#type.as_ref() {
+            Some(Type::Mint(evt)) => (&evt.amount_0, &evt.amount_1),
+            Some(Type::Burn(evt)) => (&evt.amount_0, &evt.amount_1),
+            Some(Type::Swap(evt)) => (&evt.amount_0, &evt.amount_1),
+            None => return Err(DataError::UnexpectedEvent("mint, burn or swap")),
+        };
+        let [mut amount0, mut amount1] = errors::parse_decimals([("amount0", amount0), ("amount1", amount1)])?;
+        if let Some(Type::Burn(_)) = self.r#type {
+            amount0 = amount0.neg();
+            amount1 = amount1.neg();
+        }
+
+        Ok(TokenAmounts {
+            amount0,
+            amount1,
+            token0_addr: self.token0.clone(),
+            token1_addr: self.token1.clone(),
+        })
     }
 }
@@ -20,6 +20,24 @@ pub struct Erc20Token {
     pub total_supply: ::prost::alloc::string::String,
     #[prost(string, repeated, tag="6")]
     pub whitelist_pools: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// name or symbol returned as a bytes32 instead of a string
+    #[prost(bool, tag="7")]
+    pub bytes32_metadata: bool,
+    /// decimals() is not implemented, the decimals come from the hard-coded tokens
+    #[prost(bool, tag="8")]
+    pub missing_decimals: bool,
+    /// name and symbol without control and invisible characters, falling back on the symbol
+    /// and the address, the symbol of an imitating token followed by its address
+    #[prost(string, tag="9")]
+    pub display_name: ::prost::alloc::string::String,
+    #[prost(string, tag="10")]
+    pub display_symbol: ::prost::alloc::string::String,
+    /// the symbol has Cyrillic, Greek or fullwidth letters looking like latin ones
+    #[prost(bool, tag="11")]
+    pub homoglyph_symbol: bool,
+    /// the symbol imitates the one of a whitelisted token of another address
+    #[prost(bool, tag="12")]
+    pub impersonating_symbol: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -35,6 +53,9 @@ pub struct Liquidity {
 pub struct Pools {
     #[prost(message, repeated, tag="1")]
     pub pools: ::prost::alloc::vec::Vec<Pool>,
+    /// pools left out by the spam filter of map_pools_created, with ignore_pool set
+    #[prost(message, repeated, tag="2")]
+    pub filtered_pools: ::prost::alloc::vec::Vec<FilteredPool>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -52,6 +73,9 @@ pub struct Pool {
     /// Integer
     #[prost(string, tag="7")]
     pub fee_tier: ::prost::alloc::string::String,
+    /// display symbols of the tokens and fee tier, e.g. USDC/WETH 0.05%
+    #[prost(string, tag="8")]
+    pub label: ::prost::alloc::string::String,
     /// internals
     #[prost(int32, tag="30")]
     pub tick_spacing: i32,
@@ -85,6 +109,14 @@ pub struct Events {
     pub ticks_created: ::prost::alloc::vec::Vec<events::TickCreated>,
     #[prost(message, repeated, tag="9")]
     pub ticks_updated: ::prost::alloc::vec::Vec<events::TickUpdated>,
+    #[prost(message, repeated, tag="12")]
+    pub tick_bitmap_updates: ::prost::alloc::vec::Vec<events::TickBitmapUpdate>,
+    #[prost(message, repeated, tag="13")]
+    pub observation_updates: ::prost::alloc::vec::Vec<events::ObservationUpdate>,
+    #[prost(message, repeated, tag="14")]
+    pub protocol_fees_updates: ::prost::alloc::vec::Vec<events::ProtocolFeesUpdate>,
+    #[prost(message, repeated, tag="15")]
+    pub pool_position_updates: ::prost::alloc::vec::Vec<events::PoolPositionUpdate>,
     #[prost(message, repeated, tag="20")]
     pub created_positions: ::prost::alloc::vec::Vec<events::CreatedPosition>,
     #[prost(message, repeated, tag="21")]
@@ -95,6 +127,12 @@ pub struct Events {
     pub collect_positions: ::prost::alloc::vec::Vec<events::CollectPosition>,
     #[prost(message, repeated, tag="24")]
     pub transfer_positions: ::prost::alloc::vec::Vec<events::TransferPosition>,
+    #[prost(message, repeated, tag="25")]
+    pub closed_positions: ::prost::alloc::vec::Vec<events::ClosedPosition>,
+    #[prost(message, repeated, tag="26")]
+    pub approval_positions: ::prost::alloc::vec::Vec<events::ApprovalPosition>,
+    #[prost(message, repeated, tag="27")]
+    pub operator_approvals: ::prost::alloc::vec::Vec<events::OperatorApproval>,
 }
 /// Nested message and enum types in `Events`.
 pub mod events {
@@ -181,6 +219,96 @@ pub mod events {
         pub fee_growth_outside_1x_128: ::prost::alloc::string::String,
         #[prost(uint64, tag="6")]
         pub timestamp: u64,
+        /// Integer
+        #[prost(string, tag="7")]
+        pub liquidity_gross: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="8")]
+        pub liquidity_net: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="9")]
+        pub tick_cumulative_outside: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="10")]
+        pub seconds_per_liquidity_outside_x128: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="11")]
+        pub seconds_outside: ::prost::alloc::string::String,
+    }
+    // The fields read from the pool storage are left empty when the log did not change them
+
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct TickBitmapUpdate {
+        #[prost(string, tag="1")]
+        pub pool_address: ::prost::alloc::string::String,
+        #[prost(uint64, tag="2")]
+        pub ordinal: u64,
+        #[prost(int32, tag="3")]
+        pub word_position: i32,
+        /// Integer
+        #[prost(string, tag="4")]
+        pub new_value: ::prost::alloc::string::String,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ObservationUpdate {
+        #[prost(string, tag="1")]
+        pub pool_address: ::prost::alloc::string::String,
+        #[prost(uint64, tag="2")]
+        pub ordinal: u64,
+        #[prost(uint32, tag="3")]
+        pub index: u32,
+        #[prost(uint64, tag="4")]
+        pub block_timestamp: u64,
+        /// Integer
+        #[prost(string, tag="5")]
+        pub tick_cumulative: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="6")]
+        pub seconds_per_liquidity_cumulative_x128: ::prost::alloc::string::String,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProtocolFeesUpdate {
+        #[prost(string, tag="1")]
+        pub pool_address: ::prost::alloc::string::String,
+        #[prost(uint64, tag="2")]
+        pub ordinal: u64,
+        #[prost(int32, tag="3")]
+        pub token_idx: i32,
+        /// Integer
+        #[prost(string, tag="4")]
+        pub new_value: ::prost::alloc::string::String,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct PoolPositionUpdate {
+        #[prost(string, tag="1")]
+        pub pool_address: ::prost::alloc::string::String,
+        #[prost(uint64, tag="2")]
+        pub ordinal: u64,
+        #[prost(string, tag="3")]
+        pub owner: ::prost::alloc::string::String,
+        #[prost(int32, tag="4")]
+        pub tick_lower: i32,
+        #[prost(int32, tag="5")]
+        pub tick_upper: i32,
+        /// Integer
+        #[prost(string, tag="6")]
+        pub liquidity: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="7")]
+        pub fee_growth_inside_0_last_x128: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="8")]
+        pub fee_growth_inside_1_last_x128: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="9")]
+        pub tokens_owed_0: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="10")]
+        pub tokens_owed_1: ::prost::alloc::string::String,
     }
     #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -197,6 +325,13 @@ pub mod events {
         pub tick: ::prost::alloc::string::String,
         #[prost(bool, tag="5")]
         pub initialized: bool,
+        /// Integer, in-range liquidity after the swap, empty on the initialization of the pool
+        #[prost(string, tag="6")]
+        pub liquidity: ::prost::alloc::string::String,
+        /// the sqrt price sits at one of the sqrt ratio bounds, or the swap left no in-range
+        /// liquidity: the pool has no price
+        #[prost(bool, tag="7")]
+        pub price_unavailable: bool,
     }
     #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -248,6 +383,9 @@ pub mod events {
             /// Integer
             #[prost(string, tag="8")]
             pub tick: ::prost::alloc::string::String,
+            /// label of the router or aggregator the swap went through
+            #[prost(string, tag="9")]
+            pub source: ::prost::alloc::string::String,
         }
         #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -477,10 +615,64 @@ pub mod events {
     #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct TransferPosition {
+        #[prost(string, tag="1")]
+        pub token_id: ::prost::alloc::string::String,
+        /// new owner of the NFT, the zero address when the position is burned
+        #[prost(string, tag="2")]
+        pub owner: ::prost::alloc::string::String,
+        #[prost(string, tag="3")]
+        pub from: ::prost::alloc::string::String,
+        #[prost(string, tag="4")]
+        pub transaction: ::prost::alloc::string::String,
+        #[prost(uint64, tag="5")]
+        pub block_number: u64,
+        #[prost(uint64, tag="6")]
+        pub timestamp: u64,
+        #[prost(uint64, tag="10")]
+        pub log_ordinal: u64,
+    }
+    /// The NFT was burned through the NonfungiblePositionManager, the position
+    /// cannot be used anymore.
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ClosedPosition {
+        #[prost(string, tag="1")]
+        pub token_id: ::prost::alloc::string::String,
+        /// last owner of the NFT before it was burned
+        #[prost(string, tag="2")]
+        pub owner: ::prost::alloc::string::String,
+        #[prost(string, tag="3")]
+        pub transaction: ::prost::alloc::string::String,
+        #[prost(uint64, tag="4")]
+        pub block_number: u64,
+        #[prost(uint64, tag="5")]
+        pub timestamp: u64,
+        #[prost(uint64, tag="10")]
+        pub log_ordinal: u64,
+    }
+    /// ERC721 Approval, a zero approved address means the approval was cleared
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ApprovalPosition {
         #[prost(string, tag="1")]
         pub token_id: ::prost::alloc::string::String,
         #[prost(string, tag="2")]
         pub owner: ::prost::alloc::string::String,
+        #[prost(string, tag="3")]
+        pub approved: ::prost::alloc::string::String,
+        #[prost(uint64, tag="10")]
+        pub log_ordinal: u64,
+    }
+    /// ERC721 ApprovalForAll, the operator manages every position of the owner
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct OperatorApproval {
+        #[prost(string, tag="1")]
+        pub owner: ::prost::alloc::string::String,
+        #[prost(string, tag="2")]
+        pub operator: ::prost::alloc::string::String,
+        #[prost(bool, tag="3")]
+        pub approved: bool,
         #[prost(uint64, tag="10")]
         pub log_ordinal: u64,
     }
@@ -537,5 +729,817 @@ pub struct SnapshotPosition {
     /// internal
     #[prost(uint64, tag="17")]
     pub log_ordinal: u64,
+    #[prost(string, tag="18")]
+    pub token0: ::prost::alloc::string::String,
+    #[prost(string, tag="19")]
+    pub token1: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="20")]
+    pub tick_lower: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="21")]
+    pub tick_upper: ::prost::alloc::string::String,
+    /// pool state at the ordinal of the snapshot
+    /// Integer
+    #[prost(string, tag="22")]
+    pub sqrt_price: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="23")]
+    pub tick: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="24")]
+    pub token0_price_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="25")]
+    pub token1_price_usd: ::prost::alloc::string::String,
+    #[prost(bool, tag="26")]
+    pub closed: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JitLiquidities {
+    #[prost(message, repeated, tag="1")]
+    pub jit_liquidities: ::prost::alloc::vec::Vec<JitLiquidity>,
+    /// events whose amounts don't parse under the skip_and_report policy, for map_diagnostics
+    #[prost(message, repeated, tag="2")]
+    pub skipped_items: ::prost::alloc::vec::Vec<Diagnostic>,
+}
+/// Liquidity minted right before swaps and burned right after them, in the same
+/// block, on the same pool and tick range.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JitLiquidity {
+    #[prost(string, tag="1")]
+    pub pool_address: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub owner: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub origin: ::prost::alloc::string::String,
+    #[prost(string, tag="4")]
+    pub mint_transaction: ::prost::alloc::string::String,
+    #[prost(string, tag="5")]
+    pub burn_transaction: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="6")]
+    pub tick_lower: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="7")]
+    pub tick_upper: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="8")]
+    pub liquidity: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="9")]
+    pub amount0_added: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="10")]
+    pub amount1_added: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="11")]
+    pub amount0_removed: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="12")]
+    pub amount1_removed: ::prost::alloc::string::String,
+    #[prost(uint64, tag="13")]
+    pub swap_count: u64,
+    /// Decimal
+    #[prost(string, tag="14")]
+    pub fees_token0: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="15")]
+    pub fees_token1: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="16")]
+    pub fees_usd: ::prost::alloc::string::String,
+    /// volume of the swaps between the mint and the burn
+    /// Decimal
+    #[prost(string, tag="17")]
+    pub swap_volume_usd: ::prost::alloc::string::String,
+    /// part of that volume filled by the JIT liquidity
+    /// Decimal
+    #[prost(string, tag="18")]
+    pub jit_volume_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="19")]
+    pub volume_share: ::prost::alloc::string::String,
+    #[prost(uint64, tag="20")]
+    pub mint_ordinal: u64,
+    #[prost(uint64, tag="21")]
+    pub burn_ordinal: u64,
+    #[prost(uint64, tag="22")]
+    pub block_number: u64,
+    #[prost(uint64, tag="23")]
+    pub timestamp: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MevEvents {
+    #[prost(message, repeated, tag="1")]
+    pub sandwiches: ::prost::alloc::vec::Vec<Sandwich>,
+    #[prost(message, repeated, tag="2")]
+    pub cyclic_arbitrages: ::prost::alloc::vec::Vec<CyclicArbitrage>,
+    /// swaps whose amounts don't parse under the skip_and_report policy, for map_diagnostics
+    #[prost(message, repeated, tag="3")]
+    pub skipped_items: ::prost::alloc::vec::Vec<Diagnostic>,
+}
+/// A front-run and a back-run swap of the same origin around one or more victim
+/// swaps on the same pool.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Sandwich {
+    #[prost(string, tag="1")]
+    pub pool_address: ::prost::alloc::string::String,
+    /// origin of the front-run and back-run transactions
+    #[prost(string, tag="2")]
+    pub attacker: ::prost::alloc::string::String,
+    /// sender of the front-run and back-run swaps, the contract of the attacker
+    #[prost(string, tag="3")]
+    pub attacker_contract: ::prost::alloc::string::String,
+    #[prost(string, tag="4")]
+    pub front_run_transaction: ::prost::alloc::string::String,
+    #[prost(string, tag="5")]
+    pub back_run_transaction: ::prost::alloc::string::String,
+    #[prost(uint64, tag="6")]
+    pub front_run_ordinal: u64,
+    #[prost(uint64, tag="7")]
+    pub back_run_ordinal: u64,
+    #[prost(message, repeated, tag="8")]
+    pub victims: ::prost::alloc::vec::Vec<sandwich::SandwichVictim>,
+    /// Decimal
+    #[prost(string, tag="9")]
+    pub victim_loss_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="10")]
+    pub attacker_profit_usd: ::prost::alloc::string::String,
+    #[prost(uint64, tag="11")]
+    pub block_number: u64,
+    #[prost(uint64, tag="12")]
+    pub timestamp: u64,
+}
+/// Nested message and enum types in `Sandwich`.
+pub mod sandwich {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SandwichVictim {
+        #[prost(string, tag="1")]
+        pub transaction: ::prost::alloc::string::String,
+        #[prost(string, tag="2")]
+        pub origin: ::prost::alloc::string::String,
+        #[prost(uint64, tag="3")]
+        pub ordinal: u64,
+        /// output token of the victim swap
+        #[prost(string, tag="4")]
+        pub token_out: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="5")]
+        pub loss: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="6")]
+        pub loss_usd: ::prost::alloc::string::String,
+    }
+}
+/// Swaps of a single transaction chained into a cycle, starting and ending on
+/// the same token.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CyclicArbitrage {
+    #[prost(string, tag="1")]
+    pub transaction: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub origin: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub token: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag="4")]
+    pub pools: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Decimal
+    #[prost(string, tag="5")]
+    pub amount_in: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="6")]
+    pub amount_out: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="7")]
+    pub profit: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="8")]
+    pub profit_usd: ::prost::alloc::string::String,
+    #[prost(uint64, tag="9")]
+    pub block_number: u64,
+    #[prost(uint64, tag="10")]
+    pub timestamp: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Trades {
+    #[prost(message, repeated, tag="1")]
+    pub trades: ::prost::alloc::vec::Vec<Trade>,
+    /// swaps whose amounts don't parse under the skip_and_report policy, for map_diagnostics
+    #[prost(message, repeated, tag="2")]
+    pub skipped_items: ::prost::alloc::vec::Vec<Diagnostic>,
+}
+/// Swaps of a single transaction chained while the output token of a swap is the
+/// input token of the next one, e.g. a router swap USDC -> WETH -> WBTC.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Trade {
+    #[prost(string, tag="1")]
+    pub transaction: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub origin: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub token_in: ::prost::alloc::string::String,
+    #[prost(string, tag="4")]
+    pub token_out: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="5")]
+    pub amount_in: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="6")]
+    pub amount_out: ::prost::alloc::string::String,
+    /// tokens traversed, from token_in to token_out
+    #[prost(string, repeated, tag="7")]
+    pub path: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag="8")]
+    pub pools: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint64, repeated, tag="9")]
+    pub log_indexes: ::prost::alloc::vec::Vec<u64>,
+    #[prost(uint64, tag="10")]
+    pub hops: u64,
+    /// amount of token_out received per token_in
+    /// Decimal
+    #[prost(string, tag="11")]
+    pub execution_price: ::prost::alloc::string::String,
+    /// volume of the trade, counted once whatever the number of hops
+    /// Decimal
+    #[prost(string, tag="12")]
+    pub amount_usd: ::prost::alloc::string::String,
+    /// ordinal of the last swap of the trade
+    #[prost(uint64, tag="13")]
+    pub log_ordinal: u64,
+    #[prost(uint64, tag="14")]
+    pub block_number: u64,
+    #[prost(uint64, tag="15")]
+    pub timestamp: u64,
+}
+/// Route used to price a token, from the token to WETH or to a stablecoin.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TokenPricePath {
+    #[prost(string, tag="1")]
+    pub token: ::prost::alloc::string::String,
+    /// WETH or a stablecoin
+    #[prost(string, tag="2")]
+    pub target: ::prost::alloc::string::String,
+    /// price of the token in target
+    /// Decimal
+    #[prost(string, tag="3")]
+    pub price: ::prost::alloc::string::String,
+    /// tokens traversed, from token to target
+    #[prost(string, repeated, tag="4")]
+    pub tokens: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// pools traversed, the last hop to WETH is not recorded when it goes through
+    /// the most liquid WETH pair of the complementary token
+    #[prost(string, repeated, tag="5")]
+    pub pools: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint64, tag="6")]
+    pub depth: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TokenPriceProvenances {
+    #[prost(message, repeated, tag="1")]
+    pub provenances: ::prost::alloc::vec::Vec<TokenPriceProvenance>,
+    /// pools not found under the skip_and_report policy, for map_diagnostics
+    #[prost(message, repeated, tag="2")]
+    pub skipped_items: ::prost::alloc::vec::Vec<Diagnostic>,
+}
+/// Where the derived ETH price of a token comes from and how much it can be trusted.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TokenPriceProvenance {
+    #[prost(string, tag="1")]
+    pub token: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="2")]
+    pub derived_eth: ::prost::alloc::string::String,
+    /// pool the token is priced in, empty for WETH and the stablecoins
+    #[prost(string, tag="3")]
+    pub source_pool: ::prost::alloc::string::String,
+    /// ETH value of the tokens locked in the source pool
+    /// Decimal
+    #[prost(string, tag="4")]
+    pub eth_locked: ::prost::alloc::string::String,
+    /// number of hops between the token and WETH or a stablecoin
+    #[prost(uint64, tag="5")]
+    pub depth: u64,
+    /// ordinal of the last price update
+    #[prost(uint64, tag="6")]
+    pub ordinal: u64,
+    /// between 0 and 1, grows with the ETH locked and decays with the depth
+    /// Decimal
+    #[prost(string, tag="7")]
+    pub confidence: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OraclePrices {
+    #[prost(message, repeated, tag="1")]
+    pub prices: ::prost::alloc::vec::Vec<OraclePrice>,
+}
+/// USD price of a token reported by one of the Chainlink aggregators of the params.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OraclePrice {
+    #[prost(string, tag="1")]
+    pub token: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub aggregator: ::prost::alloc::string::String,
+    /// answer scaled by the decimals of the feed
+    /// Decimal
+    #[prost(string, tag="3")]
+    pub price_usd: ::prost::alloc::string::String,
+    #[prost(uint64, tag="4")]
+    pub ordinal: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OracleDeviations {
+    #[prost(message, repeated, tag="1")]
+    pub deviations: ::prost::alloc::vec::Vec<OracleDeviation>,
+}
+/// USD price of a token derived by the pools too far away from its Chainlink price.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OracleDeviation {
+    #[prost(string, tag="1")]
+    pub token: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="2")]
+    pub oracle_price_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="3")]
+    pub derived_price_usd: ::prost::alloc::string::String,
+    /// relative distance to the oracle price
+    /// Decimal
+    #[prost(string, tag="4")]
+    pub deviation: ::prost::alloc::string::String,
+    #[prost(uint64, tag="5")]
+    pub ordinal: u64,
+    #[prost(uint64, tag="6")]
+    pub block_number: u64,
+    #[prost(uint64, tag="7")]
+    pub timestamp: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PriceGuardFlags {
+    #[prost(message, repeated, tag="1")]
+    pub flags: ::prost::alloc::vec::Vec<PriceGuardFlag>,
+}
+/// Pool price update moving too far away from the pool price at the end of the
+/// previous block. Flagged updates are not propagated to the `pair:` prices of
+/// store_prices nor to the derived ETH prices of store_eth_prices.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PriceGuardFlag {
+    #[prost(string, tag="1")]
+    pub pool_address: ::prost::alloc::string::String,
+    #[prost(uint64, tag="2")]
+    pub ordinal: u64,
+    /// pool price at the end of the previous block
+    /// Integer
+    #[prost(string, tag="3")]
+    pub reference_sqrt_price: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="4")]
+    pub sqrt_price: ::prost::alloc::string::String,
+    /// relative price move since the end of the previous block
+    /// Decimal
+    #[prost(string, tag="5")]
+    pub deviation: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="6")]
+    pub max_deviation: ::prost::alloc::string::String,
+    /// active liquidity at the end of the previous block
+    /// Integer
+    #[prost(string, tag="7")]
+    pub reference_liquidity: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="8")]
+    pub liquidity: ::prost::alloc::string::String,
+    #[prost(uint64, tag="9")]
+    pub block_number: u64,
+    #[prost(uint64, tag="10")]
+    pub timestamp: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PoolTokenTransfers {
+    #[prost(message, repeated, tag="1")]
+    pub transfers: ::prost::alloc::vec::Vec<PoolTokenTransfer>,
+}
+/// ERC20 transfer of one of the tokens of a pool to or from the pool, the amount is
+/// negative when the tokens leave the pool.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PoolTokenTransfer {
+    #[prost(string, tag="1")]
+    pub pool_address: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub token: ::prost::alloc::string::String,
+    #[prost(int32, tag="3")]
+    pub token_idx: i32,
+    /// Decimal
+    #[prost(string, tag="4")]
+    pub amount: ::prost::alloc::string::String,
+    #[prost(string, tag="5")]
+    pub from: ::prost::alloc::string::String,
+    #[prost(string, tag="6")]
+    pub to: ::prost::alloc::string::String,
+    #[prost(string, tag="7")]
+    pub transaction_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="8")]
+    pub ordinal: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TokenBehaviours {
+    #[prost(message, repeated, tag="1")]
+    pub behaviours: ::prost::alloc::vec::Vec<TokenBehaviour>,
+}
+/// Balance change of a pool token not matching its transfers, read from the balanceOf
+/// storage of the token.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TokenBehaviour {
+    #[prost(string, tag="1")]
+    pub token: ::prost::alloc::string::String,
+    /// fee_on_transfer when the recipient of a Transfer is credited less than the sender is
+    /// debited, rebasing when the balance of a pool is written without any Transfer to or
+    /// from the pool
+    #[prost(string, tag="2")]
+    pub kind: ::prost::alloc::string::String,
+    /// Integer, zero for rebasing
+    #[prost(string, tag="3")]
+    pub amount: ::prost::alloc::string::String,
+    /// Integer, zero for rebasing
+    #[prost(string, tag="4")]
+    pub debited: ::prost::alloc::string::String,
+    /// Integer, the change of the pool balance for rebasing
+    #[prost(string, tag="5")]
+    pub credited: ::prost::alloc::string::String,
+    #[prost(string, tag="6")]
+    pub transaction_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="7")]
+    pub ordinal: u64,
+}
+/// Spam filter parsed from the params of map_spam_filter, shared by map_pools_created and
+/// map_spam_pools.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SpamFilterParams {
+    /// pool and token addresses
+    #[prost(string, repeated, tag="1")]
+    pub allow: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag="2")]
+    pub deny: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(bool, tag="3")]
+    pub fake_symbols: bool,
+    /// 0 when off
+    #[prost(uint64, tag="4")]
+    pub zero_liquidity_blocks: u64,
+    /// 0 when off
+    #[prost(uint64, tag="5")]
+    pub transfer_failures: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FilteredPools {
+    #[prost(message, repeated, tag="1")]
+    pub filtered_pools: ::prost::alloc::vec::Vec<FilteredPool>,
+}
+/// Pool ignored by the spam filter, either left out when created or ignored afterwards,
+/// its events being skipped from then on.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FilteredPool {
+    /// with ignore_pool set
+    #[prost(message, optional, tag="1")]
+    pub pool: ::core::option::Option<Pool>,
+    /// denylist, fake_symbol, zero_liquidity or transfer_failures
+    #[prost(string, tag="2")]
+    pub reason: ::prost::alloc::string::String,
+    /// denied pool or token address, address of the token imitating a whitelisted symbol,
+    /// liquidity left in the pool or number of failed transfers
+    #[prost(string, tag="3")]
+    pub detail: ::prost::alloc::string::String,
+    /// left out of map_pools_created, the pool is not indexed at all
+    #[prost(bool, tag="4")]
+    pub at_creation: bool,
+    #[prost(uint64, tag="5")]
+    pub block_number: u64,
+    /// ordinal of the PoolCreated log, 0 when ignored for zero liquidity and of the failed
+    /// transfer reaching the limit otherwise
+    #[prost(uint64, tag="6")]
+    pub ordinal: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ErrorPolicy {
+    /// fail, skip or skip_and_report
+    #[prost(string, tag="1")]
+    pub policy: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Diagnostics {
+    #[prost(message, repeated, tag="1")]
+    pub diagnostics: ::prost::alloc::vec::Vec<Diagnostic>,
+}
+/// Item skipped by a handler because of a data issue, e.g. a missing price, reported when
+/// the error policy is skip_and_report.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Diagnostic {
+    /// handler which skipped the item
+    #[prost(string, tag="1")]
+    pub module: ::prost::alloc::string::String,
+    /// pool_not_found, bundle_price_not_found, derived_eth_price_not_found,
+    /// token_tvl_not_found or invalid_number
+    #[prost(string, tag="2")]
+    pub reason: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub detail: ::prost::alloc::string::String,
+    #[prost(string, tag="4")]
+    pub pool_address: ::prost::alloc::string::String,
+    /// token of a derived_eth_price_not_found or token_tvl_not_found
+    #[prost(string, tag="5")]
+    pub token: ::prost::alloc::string::String,
+    #[prost(string, tag="6")]
+    pub transaction_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="7")]
+    pub ordinal: u64,
+    #[prost(uint64, tag="8")]
+    pub block_number: u64,
+    #[prost(uint64, tag="9")]
+    pub timestamp: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InvariantChecks {
+    #[prost(message, repeated, tag="1")]
+    pub discrepancies: ::prost::alloc::vec::Vec<InvariantDiscrepancy>,
+}
+/// Value tracked by summing the events which does not match the one read from the
+/// contract storage at the same ordinal.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InvariantDiscrepancy {
+    /// tick_liquidity_gross, tick_liquidity_net, pool_liquidity or pool_balance
+    #[prost(string, tag="1")]
+    pub check: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub pool_address: ::prost::alloc::string::String,
+    /// token of a pool_balance discrepancy
+    #[prost(string, tag="3")]
+    pub token: ::prost::alloc::string::String,
+    /// tick of a tick_liquidity_gross or tick_liquidity_net discrepancy
+    #[prost(string, tag="4")]
+    pub tick_idx: ::prost::alloc::string::String,
+    /// Integer, Decimal for pool_balance
+    #[prost(string, tag="5")]
+    pub storage_value: ::prost::alloc::string::String,
+    /// Integer, Decimal for pool_balance
+    #[prost(string, tag="6")]
+    pub tracked_value: ::prost::alloc::string::String,
+    /// storage value minus tracked value
+    /// Integer, Decimal for pool_balance
+    #[prost(string, tag="7")]
+    pub difference: ::prost::alloc::string::String,
+    #[prost(string, tag="8")]
+    pub transaction_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="9")]
+    pub ordinal: u64,
+    #[prost(uint64, tag="10")]
+    pub block_number: u64,
+    #[prost(uint64, tag="11")]
+    pub timestamp: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CandleUpdates {
+    #[prost(message, repeated, tag="1")]
+    pub windows: ::prost::alloc::vec::Vec<CandleWindow>,
+    #[prost(message, repeated, tag="2")]
+    pub updates: ::prost::alloc::vec::Vec<CandleUpdate>,
+}
+/// Current bucket of a candle interval, the buckets are counted from the unix epoch,
+/// weeks starting on Monday.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CandleWindow {
+    #[prost(string, tag="1")]
+    pub interval: ::prost::alloc::string::String,
+    #[prost(int64, tag="2")]
+    pub seconds: i64,
+    #[prost(int64, tag="3")]
+    pub bucket: i64,
+}
+/// A swap seen from a candle interval, with the prices of the pool after the swap.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CandleUpdate {
+    #[prost(string, tag="1")]
+    pub interval: ::prost::alloc::string::String,
+    #[prost(int64, tag="2")]
+    pub bucket: i64,
+    #[prost(string, tag="3")]
+    pub pool_address: ::prost::alloc::string::String,
+    #[prost(uint64, tag="4")]
+    pub ordinal: u64,
+    /// Decimal
+    #[prost(string, tag="5")]
+    pub token0_price: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="6")]
+    pub token1_price: ::prost::alloc::string::String,
+    /// USD price of token0, empty when token0 has no price
+    /// Decimal
+    #[prost(string, tag="7")]
+    pub token0_price_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="8")]
+    pub volume_token0: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="9")]
+    pub volume_token1: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="10")]
+    pub volume_usd: ::prost::alloc::string::String,
+    /// USD price of token1, empty when token1 has no price
+    /// Decimal
+    #[prost(string, tag="11")]
+    pub token1_price_usd: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Candles {
+    #[prost(message, repeated, tag="1")]
+    pub candles: ::prost::alloc::vec::Vec<Candle>,
+}
+/// OHLCV candle of a pool. The token0 and token1 prices are the token0Price and
+/// token1Price of the pool, the USD prices are the USD prices of token0 and token1.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Candle {
+    #[prost(string, tag="1")]
+    pub interval: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub pool_address: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub token0: ::prost::alloc::string::String,
+    #[prost(string, tag="4")]
+    pub token1: ::prost::alloc::string::String,
+    #[prost(int64, tag="5")]
+    pub open_time: i64,
+    #[prost(int64, tag="6")]
+    pub close_time: i64,
+    /// set once a block past close_time has been seen, the candle will not change anymore
+    #[prost(bool, tag="7")]
+    pub closed: bool,
+    /// Decimal
+    #[prost(string, tag="8")]
+    pub open_token0: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="9")]
+    pub high_token0: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="10")]
+    pub low_token0: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="11")]
+    pub close_token0: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="12")]
+    pub open_token1: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="13")]
+    pub high_token1: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="14")]
+    pub low_token1: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="15")]
+    pub close_token1: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="16")]
+    pub open_token0_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="17")]
+    pub high_token0_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="18")]
+    pub low_token0_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="19")]
+    pub close_token0_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="20")]
+    pub volume_token0: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="21")]
+    pub volume_token1: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="22")]
+    pub volume_usd: ::prost::alloc::string::String,
+    #[prost(uint64, tag="23")]
+    pub trade_count: u64,
+    #[prost(uint64, tag="24")]
+    pub block_number: u64,
+    #[prost(uint64, tag="25")]
+    pub timestamp: u64,
+    /// Decimal
+    #[prost(string, tag="26")]
+    pub open_token1_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="27")]
+    pub high_token1_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="28")]
+    pub low_token1_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="29")]
+    pub close_token1_usd: ::prost::alloc::string::String,
+}
+/// Pool and token windows carried forward from the previous window, emitted at each
+/// period boundary for every tracked pool and token, and on creation for the current
+/// window of new pools and tokens, only for the window tables given in the params of
+/// map_gap_filled_windows.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GapFilledWindows {
+    /// the window tables which are gap filled, e.g. `PoolDayData` or `TokenHourData`
+    #[prost(string, repeated, tag="1")]
+    pub tables: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(message, repeated, tag="2")]
+    pub pools: ::prost::alloc::vec::Vec<GapFilledPoolWindow>,
+    #[prost(message, repeated, tag="3")]
+    pub tokens: ::prost::alloc::vec::Vec<GapFilledTokenWindow>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GapFilledPoolWindow {
+    #[prost(string, tag="1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(int64, tag="2")]
+    pub time_id: i64,
+    #[prost(string, tag="3")]
+    pub pool_address: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="4")]
+    pub liquidity: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="5")]
+    pub sqrt_price: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="6")]
+    pub tick: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="7")]
+    pub token0_price: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="8")]
+    pub token1_price: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="9")]
+    pub total_value_locked_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="10")]
+    pub balance_token0: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="11")]
+    pub balance_token1: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="12")]
+    pub balance_total_value_locked_usd: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GapFilledTokenWindow {
+    #[prost(string, tag="1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(int64, tag="2")]
+    pub time_id: i64,
+    #[prost(string, tag="3")]
+    pub token_address: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="4")]
+    pub total_value_locked: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="5")]
+    pub total_value_locked_usd: ::prost::alloc::string::String,
+    /// Decimal
+    #[prost(string, tag="6")]
+    pub price_usd: ::prost::alloc::string::String,
 }
 // @@protoc_insertion_point(module)
@@ -1,12 +1,39 @@
+use crate::pb::uniswap::{TokenPricePath, TokenPriceProvenance};
 use crate::{math, Erc20Token, Pool};
-use std::ops::{Div, Mul};
+use std::collections::{BTreeMap, HashSet};
+use std::ops::{Add, Div, Mul, Sub};
 use std::str;
 use std::str::FromStr;
 use substreams::log;
 use substreams::scalar::{BigDecimal, BigInt};
-use substreams::store::{StoreGet, StoreGetBigDecimal, StoreGetBigInt, StoreGetProto, StoreGetRaw};
+use substreams::store::{StoreGet, StoreGetBigDecimal, StoreGetBigInt, StoreGetProto, StoreGetRaw, StoreGetString};
 
 const USDC_WETH_03_POOL: &str = "8ad599c3a0ff1de082011efddc58f1908eb6e6d8";
+
+// stablecoin/WETH pools the bundle ETH price in USD is read from, unless others are
+// given through the params of store_eth_prices
+pub const REFERENCE_POOLS: [&str; 4] = [
+    "88e6a0c2ddd26feeb64f039a2c41296fcb3f5640", // USDC/WETH 0.05%
+    "8ad599c3a0ff1de082011efddc58f1908eb6e6d8", // USDC/WETH 0.3%
+    "4e68ccd3e89f51c3074ca5072bbac773960dfa36", // WETH/USDT 0.3%
+    "c2e9f25be6257c210d7adf0d4cd6e3e881ba25f8", // DAI/WETH 0.3%
+];
+// relative distance to the median above which a reference pool price is rejected
+const MAX_REFERENCE_PRICE_DEVIATION: &str = "0.02";
+// relative distance between the derived USD price of a token and its Chainlink price
+// above which a deviation is reported
+pub const MAX_ORACLE_PRICE_DEVIATION: &str = "0.05";
+// bounds of the sqrt price of a pool, TickMath.MIN_SQRT_RATIO and MAX_SQRT_RATIO. A swap
+// stops one step inside of them at most, the price limits are exclusive.
+const MIN_SQRT_RATIO: &str = "4295128739";
+const MAX_SQRT_RATIO: &str = "1461446703485210103287273052203988822378723970342";
+const SQRT_TEN: &str = "3.162277660168379332";
+// ETH locked in the source pool for which a single hop price gets a confidence of 0.5
+const HALF_CONFIDENCE_ETH_LOCKED: &str = "500";
+// confidence kept on each hop after the first one
+const CONFIDENCE_DECAY_PER_HOP: &str = "0.8";
+// maximum number of pools between a token and WETH or a stablecoin in the pricing graph
+const MAX_PRICING_DEPTH: usize = 3;
 const USDC_ADDRESS: &str = "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
 const WETH_ADDRESS: &str = "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
 
@@ -43,6 +70,25 @@ pub const WHITELIST_TOKENS: [&str; 21] = [
     "fe2e637202056d30016725477c5da089ab0a043a", // sETH2
 ];
 
+struct WhitelistedPoolPrice {
+    price: BigDecimal,
+    pool_address: String,
+    complementary_token: String,
+}
+
+// Stores the token prices are derived from
+pub struct PricingStores<'a> {
+    pub pools: &'a StoreGetProto<Pool>,
+    pub pool_liquidities: &'a StoreGetBigInt,
+    // pool of the last `pair:` price of each pair of tokens
+    pub pair_pools: &'a StoreGetString,
+    // pools of each token, created and not left out by the spam filter
+    pub token_pools: &'a StoreGetRaw,
+    pub tokens_whitelist_pools: &'a StoreGetRaw,
+    pub total_native_amounts: &'a StoreGetBigDecimal,
+    pub prices: &'a StoreGetBigDecimal,
+}
+
 pub fn sqrt_price_x96_to_token_prices(
     sqrt_price: BigDecimal,
     token_0: &Erc20Token,
@@ -70,217 +116,538 @@ pub fn sqrt_price_x96_to_token_prices(
     return (price0, price1);
 }
 
-pub fn find_eth_per_token(
+// Route pricing a token: WETH and the stablecoins are priced in themselves, other tokens
+// through find_eth_per_token, or when no whitelisted pool prices them, through the most
+// liquid path of the pricing graph.
+pub fn find_token_price_path(
     ord: u64,
     pool_address: &String,
     token_address: &String,
-    pools_store: &StoreGetProto<Pool>,
-    pool_liquidities_store: &StoreGetBigInt,
-    tokens_whitelist_pools_store: &StoreGetRaw,
-    total_native_amounts_store: &StoreGetBigDecimal,
-    prices_store: &StoreGetBigDecimal,
-) -> BigDecimal {
-    log::debug!("finding ETH per token for {} in pool {}", token_address, pool_address);
-    if token_address.eq(WETH_ADDRESS) {
-        log::debug!("is ETH return 1");
-        return BigDecimal::one();
+    stores: &PricingStores,
+) -> TokenPricePath {
+    if token_address.eq(WETH_ADDRESS) || STABLE_COINS.contains(&token_address.as_str()) {
+        return new_token_price_path(token_address, BigDecimal::one(), vec![token_address.clone()], vec![]);
     }
 
-    let mut price_so_far = BigDecimal::zero();
+    if let Some(whitelisted_pool_price) = find_eth_per_token(ord, pool_address, token_address, stores) {
+        let complementary_token = whitelisted_pool_price.complementary_token;
+        let mut tokens = vec![token_address.clone(), complementary_token.clone()];
+        let mut pools = vec![whitelisted_pool_price.pool_address];
+        // the complementary token is priced through its WETH pair
+        if complementary_token != WETH_ADDRESS {
+            tokens.push(WETH_ADDRESS.to_string());
+            pools.extend(find_weth_pair_pool(ord, &complementary_token, stores));
+        }
+        return new_token_price_path(token_address, whitelisted_pool_price.price, tokens, pools);
+    }
 
-    if STABLE_COINS.contains(&token_address.as_str()) {
-        log::debug!("token addr: {} is a stable coin", token_address);
-        let eth_price_usd = get_eth_price_in_usd(prices_store, ord);
-        log::info!("eth_price_usd {}", eth_price_usd);
-        price_so_far = math::safe_div(&BigDecimal::one(), &eth_price_usd);
-    } else {
-        // TODO: @eduard change this once the changes for store of list has been merged
-        let wl = match tokens_whitelist_pools_store.get_last(&format!("token:{token_address}")) {
-            None => {
-                log::debug!("failed to get whitelisted pools for token {}", token_address);
-                return BigDecimal::zero();
-            }
-            Some(bytes) => String::from_utf8(bytes.to_vec()).unwrap(),
-        };
+    match find_pricing_graph_path(ord, token_address, stores) {
+        Some(path) => path,
+        None => TokenPricePath {
+            token: token_address.clone(),
+            price: BigDecimal::zero().to_string(),
+            tokens: vec![token_address.clone()],
+            ..Default::default()
+        },
+    }
+}
 
-        let mut whitelisted_pools: Vec<&str> = vec![];
-        for p in wl.split(";") {
-            if !p.is_empty() {
-                whitelisted_pools.push(p);
-            }
-        }
-        log::debug!("found whitelisted pools {}", whitelisted_pools.len());
+// The path ends on its target, its depth is its number of pools
+fn new_token_price_path(token: &str, price: BigDecimal, tokens: Vec<String>, pools: Vec<String>) -> TokenPricePath {
+    TokenPricePath {
+        token: token.to_string(),
+        target: tokens.last().unwrap().clone(),
+        price: price.to_string(),
+        depth: pools.len() as u64,
+        tokens,
+        pools,
+    }
+}
+
+// WETH pool of the pair price of a token, the last one of its WETH pools updated
+fn find_weth_pair_pool(ord: u64, token_address: &str, stores: &PricingStores) -> Option<String> {
+    stores
+        .pair_pools
+        .get_at(ord, format!("pair:{WETH_ADDRESS}:{token_address}"))
+}
+
+// Searches, hop by hop, the pools of the tokens reached so far for a path to WETH or a
+// stablecoin of at most MAX_PRICING_DEPTH pools. A path is as liquid as its least liquid
+// pool, the most liquid one is kept and the shortest one on a tie.
+fn find_pricing_graph_path(ord: u64, token_address: &str, stores: &PricingStores) -> Option<TokenPricePath> {
+    pricing_graph_path(
+        token_address,
+        |token| {
+            stores
+                .token_pools
+                .get_last(format!("token:{token}"))
+                .map(|bytes| String::from_utf8(bytes.to_vec()).unwrap())
+        },
+        |pool_address| stores.pools.get_last(format!("pool:{pool_address}")),
+        |pool_address| {
+            stores
+                .pool_liquidities
+                .get_at(ord, format!("pool:{pool_address}"))
+                .unwrap_or_default()
+        },
+        |price_key| stores.prices.get_at(ord, price_key).unwrap_or_default(),
+    )
+}
+
+fn pricing_graph_path<T, P, L, R>(
+    token_address: &str,
+    token_pools: T,
+    pool: P,
+    pool_liquidity: L,
+    price: R,
+) -> Option<TokenPricePath>
+where
+    T: Fn(&str) -> Option<String>,
+    P: Fn(&str) -> Option<Pool>,
+    L: Fn(&str) -> BigInt,
+    R: Fn(&str) -> BigDecimal,
+{
+    struct Route {
+        tokens: Vec<String>,
+        pools: Vec<String>,
+        price: BigDecimal,
+        liquidity: Option<BigDecimal>,
+    }
 
-        let mut largest_eth_locked = BigDecimal::zero();
-        let minimum_eth_locked = BigDecimal::from_str("52").unwrap();
-        let mut eth_locked: BigDecimal;
+    let mut best: Option<Route> = None;
+    let mut frontier = vec![Route {
+        tokens: vec![token_address.to_string()],
+        pools: vec![],
+        price: BigDecimal::one(),
+        liquidity: None,
+    }];
 
-        for pool_address in whitelisted_pools.iter() {
-            log::debug!("checking pool: {}", pool_address);
-            let pool = match pools_store.get_last(format!("pool:{pool_address}")) {
+    let mut visited: HashSet<String> = HashSet::from([token_address.to_string()]);
+
+    for _ in 0..MAX_PRICING_DEPTH {
+        // most liquid route reaching each token of the next hop
+        let mut next_frontier: BTreeMap<String, Route> = BTreeMap::new();
+
+        for route in frontier.iter() {
+            let current_token = route.tokens.last().unwrap();
+            let token_pools = match token_pools(current_token) {
                 None => continue,
-                Some(p) => p,
+                Some(token_pools) => token_pools,
             };
-            let token0 = pool.token0.as_ref().unwrap();
-            let token1 = pool.token1.as_ref().unwrap();
-            let token0_addr = &token0.address;
-            let token1_addr = &token1.address;
 
-            log::debug!("found pool: {pool_address} with token0 {token0_addr} and with token1 {token1_addr}",);
+            for pool_address in token_pools.split(';').filter(|p| !p.is_empty()) {
+                let pool = match pool(pool_address) {
+                    Some(pool) if !pool.ignore_pool => pool,
+                    _ => continue,
+                };
+                let token0 = pool.token0.as_ref().unwrap();
+                let token1 = pool.token1.as_ref().unwrap();
 
-            let liquidity: BigInt = match pool_liquidities_store.get_at(ord, format!("pool:{pool_address}")) {
-                None => {
-                    log::debug!("No liquidity for pool {pool_address}");
-                    BigInt::zero()
+                // price of the current token in the complementary one
+                let (complementary_token, price_key) = if &token0.address == current_token {
+                    (
+                        &token1.address,
+                        format!("pool:{pool_address}:{}:token1", token1.address),
+                    )
+                } else {
+                    (
+                        &token0.address,
+                        format!("pool:{pool_address}:{}:token0", token0.address),
+                    )
+                };
+                if visited.contains(complementary_token) {
+                    continue;
                 }
-                Some(l) => l,
-            };
 
-            if liquidity.gt(&BigInt::zero()) {
-                if &token0.address == token_address {
-                    log::info!(
-                        "current pool token 0 matches desired token, complementary token is {} {}",
-                        token1_addr,
-                        token1.symbol
-                    );
-                    let native_amount = match total_native_amounts_store
-                        .get_at(ord, format!("pool:{pool_address}:{token1_addr}:native"))
-                    {
+                let liquidity = pool_liquidity(pool_address);
+                let price = price(&price_key);
+                if liquidity.is_zero() || price.is_zero() {
+                    continue;
+                }
+
+                let pool_liquidity = normalized_liquidity(liquidity, token0.decimals, token1.decimals);
+                let liquidity = match &route.liquidity {
+                    Some(route_liquidity) if route_liquidity < &pool_liquidity => route_liquidity.clone(),
+                    _ => pool_liquidity,
+                };
+
+                let mut tokens = route.tokens.clone();
+                tokens.push(complementary_token.clone());
+                let mut pools = route.pools.clone();
+                pools.push(pool_address.to_string());
+                let next_route = Route {
+                    tokens,
+                    pools,
+                    price: route.price.clone().mul(price),
+                    liquidity: Some(liquidity),
+                };
+
+                if complementary_token == WETH_ADDRESS || STABLE_COINS.contains(&complementary_token.as_str()) {
+                    let more_liquid = match &best {
+                        None => true,
+                        Some(best_route) => next_route.liquidity > best_route.liquidity,
+                    };
+                    if more_liquid {
+                        best = Some(next_route);
+                    }
+                } else {
+                    let more_liquid = match next_frontier.get(complementary_token) {
+                        None => true,
+                        Some(frontier_route) => next_route.liquidity > frontier_route.liquidity,
+                    };
+                    if more_liquid {
+                        next_frontier.insert(complementary_token.clone(), next_route);
+                    }
+                }
+            }
+        }
+
+        visited.extend(next_frontier.keys().cloned());
+        frontier = next_frontier.into_values().collect();
+    }
+
+    best.map(|route| new_token_price_path(token_address, route.price, route.tokens, route.pools))
+}
+
+// A pool has no price when its sqrt price reached one of the bounds, which only happens
+// once a swap has gone through all the liquidity on its side, or when a swap leaves it
+// without in-range liquidity, any amount then moves the price to the next initialized tick.
+// The sqrt price of the initialization is the one picked by the creator of the pool, which
+// has no liquidity yet, and is only checked against the bounds.
+pub fn is_price_unavailable(sqrt_price: &BigInt, liquidity: Option<&BigInt>) -> bool {
+    let min_sqrt_price = BigInt::from_str(MIN_SQRT_RATIO).unwrap().add(BigInt::one());
+    let max_sqrt_price = BigInt::from_str(MAX_SQRT_RATIO).unwrap().sub(BigInt::one());
+    if *sqrt_price <= min_sqrt_price || *sqrt_price >= max_sqrt_price {
+        return true;
+    }
+
+    liquidity.map_or(false, |liquidity| liquidity.is_zero())
+}
+
+// Price in ETH of a token from its pricing path, a path ending on a stablecoin being
+// converted with the bundle price.
+pub fn get_derived_eth_price(
+    token_price_path: Option<TokenPricePath>,
+    bundle_eth_price_usd: &BigDecimal,
+) -> BigDecimal {
+    let token_price_path = match token_price_path {
+        None => return BigDecimal::zero(),
+        Some(path) => path,
+    };
+    let price = BigDecimal::try_from(token_price_path.price).unwrap();
+
+    if token_price_path.target == WETH_ADDRESS {
+        price
+    } else if STABLE_COINS.contains(&token_price_path.target.as_str()) {
+        math::safe_div(&price, bundle_eth_price_usd)
+    } else {
+        BigDecimal::zero()
+    }
+}
+
+// Provenance of the derived ETH price of a token. The confidence is
+// eth_locked / (eth_locked + HALF_CONFIDENCE_ETH_LOCKED), decayed by CONFIDENCE_DECAY_PER_HOP
+// for each hop after the first one. WETH and the stablecoins have a confidence of 1 and
+// unpriced tokens a confidence of 0.
+pub fn get_token_price_provenance(
+    ord: u64,
+    token_address: &String,
+    token_price_path: Option<TokenPricePath>,
+    store_eth_prices: &StoreGetBigDecimal,
+    total_native_amounts_store: &StoreGetBigDecimal,
+) -> TokenPriceProvenance {
+    token_price_provenance(
+        ord,
+        token_address,
+        token_price_path,
+        |token| {
+            store_eth_prices
+                .get_at(ord, format!("token:{token}:dprice:eth"))
+                .unwrap_or_default()
+        },
+        |pool_address, token| {
+            total_native_amounts_store
+                .get_at(ord, format!("pool:{pool_address}:{token}:native"))
+                .unwrap_or_default()
+        },
+    )
+}
+
+fn token_price_provenance<D, N>(
+    ord: u64,
+    token_address: &str,
+    token_price_path: Option<TokenPricePath>,
+    derived_eth_price: D,
+    native_amount: N,
+) -> TokenPriceProvenance
+where
+    D: Fn(&str) -> BigDecimal,
+    N: Fn(&str, &str) -> BigDecimal,
+{
+    let derived_eth = derived_eth_price(token_address);
+    let mut provenance = TokenPriceProvenance {
+        token: token_address.to_string(),
+        derived_eth: derived_eth.to_string(),
+        eth_locked: BigDecimal::zero().to_string(),
+        ordinal: ord,
+        confidence: BigDecimal::zero().to_string(),
+        ..Default::default()
+    };
+
+    let token_price_path = match token_price_path {
+        Some(path) => path,
+        None => return provenance,
+    };
+    provenance.depth = token_price_path.depth;
+
+    if token_price_path.depth == 0 && !token_price_path.target.is_empty() {
+        provenance.confidence = BigDecimal::one().to_string();
+        return provenance;
+    }
+    if derived_eth.is_zero() || token_price_path.pools.is_empty() {
+        return provenance;
+    }
+
+    let source_pool = &token_price_path.pools[0];
+    // the source pool holds the token and the next one on the path
+    let eth_locked = token_price_path.tokens[..2]
+        .iter()
+        .fold(BigDecimal::zero(), |eth_locked, token| {
+            eth_locked.add(native_amount(source_pool, token).mul(derived_eth_price(token)))
+        });
+
+    let mut confidence = math::safe_div(
+        &eth_locked,
+        &eth_locked
+            .clone()
+            .add(BigDecimal::from_str(HALF_CONFIDENCE_ETH_LOCKED).unwrap()),
+    );
+    for _ in 1..token_price_path.depth {
+        confidence = confidence.mul(BigDecimal::from_str(CONFIDENCE_DECAY_PER_HOP).unwrap());
+    }
+
+    provenance.source_pool = source_pool.clone();
+    provenance.eth_locked = eth_locked.to_string();
+    provenance.confidence = confidence.to_string();
+    provenance
+}
+
+// In-range liquidity scaled by the decimals of the tokens of the pool, so that the
+// liquidity of pools of tokens with different decimals can be compared
+fn normalized_liquidity(liquidity: BigInt, decimals0: u64, decimals1: u64) -> BigDecimal {
+    let decimals = decimals0 + decimals1;
+    let mut scale = math::exponent_to_big_decimal(decimals / 2);
+    if decimals % 2 == 1 {
+        scale = scale.mul(BigDecimal::from_str(SQRT_TEN).unwrap());
+    }
+    BigDecimal::from(liquidity).div(scale)
+}
+
+// Price of a token in ETH through the whitelisted pool with the most ETH locked, the
+// complementary token being WETH or having a direct WETH pair.
+fn find_eth_per_token(
+    ord: u64,
+    pool_address: &String,
+    token_address: &String,
+    stores: &PricingStores,
+) -> Option<WhitelistedPoolPrice> {
+    log::debug!("finding ETH per token for {} in pool {}", token_address, pool_address);
+    let pools_store = stores.pools;
+    let pool_liquidities_store = stores.pool_liquidities;
+    let total_native_amounts_store = stores.total_native_amounts;
+    let prices_store = stores.prices;
+    let mut price_so_far: Option<WhitelistedPoolPrice> = None;
+
+    // TODO: @eduard change this once the changes for store of list has been merged
+    let wl = match stores.tokens_whitelist_pools.get_last(format!("token:{token_address}")) {
+        None => {
+            log::debug!("failed to get whitelisted pools for token {}", token_address);
+            return None;
+        }
+        Some(bytes) => String::from_utf8(bytes.to_vec()).unwrap(),
+    };
+
+    let mut whitelisted_pools: Vec<&str> = vec![];
+    for p in wl.split(';') {
+        if !p.is_empty() {
+            whitelisted_pools.push(p);
+        }
+    }
+    log::debug!("found whitelisted pools {}", whitelisted_pools.len());
+
+    let mut largest_eth_locked = BigDecimal::zero();
+    let minimum_eth_locked = BigDecimal::from_str("52").unwrap();
+    let mut eth_locked: BigDecimal;
+
+    for pool_address in whitelisted_pools.iter() {
+        log::debug!("checking pool: {}", pool_address);
+        // the whitelist entries of the pools ignored by the spam filter are kept by the
+        // appender store, the stored pool tells them apart
+        let pool = match pools_store.get_last(format!("pool:{pool_address}")) {
+            Some(p) if !p.ignore_pool => p,
+            _ => continue,
+        };
+        let token0 = pool.token0.as_ref().unwrap();
+        let token1 = pool.token1.as_ref().unwrap();
+        let token0_addr = &token0.address;
+        let token1_addr = &token1.address;
+
+        log::debug!("found pool: {pool_address} with token0 {token0_addr} and with token1 {token1_addr}",);
+
+        let liquidity: BigInt = match pool_liquidities_store.get_at(ord, format!("pool:{pool_address}")) {
+            None => {
+                log::debug!("No liquidity for pool {pool_address}");
+                BigInt::zero()
+            }
+            Some(l) => l,
+        };
+
+        if liquidity.gt(&BigInt::zero()) {
+            if &token0.address == token_address {
+                log::info!(
+                    "current pool token 0 matches desired token, complementary token is {} {}",
+                    token1_addr,
+                    token1.symbol
+                );
+                let native_amount =
+                    match total_native_amounts_store.get_at(ord, format!("pool:{pool_address}:{token1_addr}:native")) {
                         None => BigDecimal::zero(),
                         Some(amount) => amount,
                     };
-                    log::debug!("native amount value of token1 in pool {}", native_amount);
-
-                    let token1_eth_price;
-                    // If the counter token is WETH we know the derived price is 1
-                    if token1.address.eq(WETH_ADDRESS) {
-                        log::debug!("token 1 is WETH");
-                        eth_locked = native_amount;
-                        token1_eth_price = BigDecimal::one();
-                    } else {
-                        log::debug!("token 1 is NOT WETH");
-
-                        match pool_liquidities_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token1_addr}")) {
-                            None => {
-                                log::debug!("unable to find liquidity for {:?}", token1_addr);
+                log::debug!("native amount value of token1 in pool {}", native_amount);
+
+                let token1_eth_price;
+                // If the counter token is WETH we know the derived price is 1
+                if token1.address.eq(WETH_ADDRESS) {
+                    log::debug!("token 1 is WETH");
+                    eth_locked = native_amount;
+                    token1_eth_price = BigDecimal::one();
+                } else {
+                    log::debug!("token 1 is NOT WETH");
+
+                    match pool_liquidities_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token1_addr}")) {
+                        None => {
+                            log::debug!("unable to find liquidity for {:?}", token1_addr);
+                            continue;
+                        }
+                        Some(l) => {
+                            // There is no liquidity in the pool. We can't compute the eth_price
+                            // of the token.
+                            if l.eq(&BigInt::zero()) {
                                 continue;
                             }
-                            Some(l) => {
-                                // There is no liquidity in the pool. We can't compute the eth_price
-                                // of the token.
-                                if l.eq(&BigInt::zero()) {
-                                    continue;
-                                }
-
-                                // Else we have enough liquidity to compute the price
-                            }
+
+                            // Else we have enough liquidity to compute the price
                         }
+                    }
 
-                        token1_eth_price = match prices_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token1_addr}"))
-                        {
+                    token1_eth_price = match prices_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token1_addr}")) {
+                        None => {
+                            log::debug!("unable to find token 1 price in eth {token1_addr}");
+                            continue;
+                        }
+                        Some(price) => price,
+                    };
+                    log::debug!("token 1 is price in eth {}", token1_eth_price);
+                    eth_locked = native_amount.mul(token1_eth_price.clone());
+                    log::debug!("computed eth locked {}", eth_locked);
+                }
+                log::debug!(
+                    "eth locked in pool {pool_address} {} (largest {})",
+                    eth_locked,
+                    largest_eth_locked
+                );
+                // should the check below make more sens if we EITHER have eth.gt > largest && (eth_locked > min BUT !Whitelist || whitelist)???
+                if eth_locked.gt(&largest_eth_locked)
+                    && (eth_locked.gt(&minimum_eth_locked) || WHITELIST_TOKENS.contains(&token0_addr.as_str()))
+                {
+                    log::debug!("eth locked passed test");
+                    let token1_price =
+                        match prices_store.get_at(ord, format!("pool:{pool_address}:{token1_addr}:token1")) {
                             None => {
-                                log::debug!("unable to find token 1 price in eth {token1_addr}");
+                                log::debug!("unable to find pool {pool_address} for token {token1_addr} price",);
                                 continue;
                             }
                             Some(price) => price,
                         };
-                        log::debug!("token 1 is price in eth {}", token1_eth_price);
-                        eth_locked = native_amount.mul(token1_eth_price.clone());
-                        log::debug!("computed eth locked {}", eth_locked);
-                    }
-                    log::debug!(
-                        "eth locked in pool {pool_address} {} (largest {})",
-                        eth_locked,
-                        largest_eth_locked
-                    );
-                    // should the check below make more sens if we EITHER have eth.gt > largest && (eth_locked > min BUT !Whitelist || whitelist)???
-                    if eth_locked.gt(&largest_eth_locked)
-                        && (eth_locked.gt(&minimum_eth_locked) || WHITELIST_TOKENS.contains(&token0_addr.as_str()))
-                    {
-                        log::debug!("eth locked passed test");
-                        let token1_price =
-                            match prices_store.get_at(ord, format!("pool:{pool_address}:{token1_addr}:token1")) {
-                                None => {
-                                    log::debug!("unable to find pool {pool_address} for token {token1_addr} price",);
-                                    continue;
-                                }
-                                Some(price) => price,
-                            };
-                        log::debug!("found token 1 price {}", token1_price);
-                        largest_eth_locked = eth_locked.clone();
-                        price_so_far = token1_price.mul(token1_eth_price.clone());
-                        log::debug!("price_so_far {}", price_so_far);
-                    }
+                    log::debug!("found token 1 price {}", token1_price);
+                    largest_eth_locked = eth_locked.clone();
+                    price_so_far = Some(WhitelistedPoolPrice {
+                        price: token1_price.mul(token1_eth_price.clone()),
+                        pool_address: pool_address.to_string(),
+                        complementary_token: token1_addr.clone(),
+                    });
                 }
-                if &token1.address == token_address {
-                    log::debug!(
-                        "current pool token 1 matches desired token, complementary token is {} {}",
-                        token0.address,
-                        token1.symbol
-                    );
-                    let native_amount = match total_native_amounts_store
-                        .get_at(ord, format!("pool:{pool_address}:{token0_addr}:native"))
-                    {
+            }
+            if &token1.address == token_address {
+                log::debug!(
+                    "current pool token 1 matches desired token, complementary token is {} {}",
+                    token0.address,
+                    token1.symbol
+                );
+                let native_amount =
+                    match total_native_amounts_store.get_at(ord, format!("pool:{pool_address}:{token0_addr}:native")) {
                         None => BigDecimal::zero(),
                         Some(price) => price,
                     };
-                    log::debug!("native amount value of token0 in pool {}", native_amount);
+                log::debug!("native amount value of token0 in pool {}", native_amount);
 
-                    let mut token0_eth_price = BigDecimal::zero();
+                let mut token0_eth_price = BigDecimal::zero();
 
-                    // If the counter token is WETH we know the derived price is 1
-                    if token0.address.eq(WETH_ADDRESS) {
-                        log::debug!("token 0 is WETH");
-                        eth_locked = native_amount
-                    } else {
-                        log::debug!("token 0 is NOT WETH");
+                // If the counter token is WETH we know the derived price is 1
+                if token0.address.eq(WETH_ADDRESS) {
+                    log::debug!("token 0 is WETH");
+                    eth_locked = native_amount
+                } else {
+                    log::debug!("token 0 is NOT WETH");
 
-                        match pool_liquidities_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token0_addr}")) {
-                            None => {
-                                log::debug!("unable to find liquidity for {:?}", token0_addr);
+                    match pool_liquidities_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token0_addr}")) {
+                        None => {
+                            log::debug!("unable to find liquidity for {:?}", token0_addr);
+                            continue;
+                        }
+                        Some(l) => {
+                            // There is no liquidity in the pool. We can't compute the eth_price
+                            // of the token.
+                            if l.eq(&BigInt::zero()) {
                                 continue;
                             }
-                            Some(l) => {
-                                // There is no liquidity in the pool. We can't compute the eth_price
-                                // of the token.
-                                if l.eq(&BigInt::zero()) {
-                                    continue;
-                                }
-
-                                // Else we have enough liquidity to compute the price
-                            }
+
+                            // Else we have enough liquidity to compute the price
                         }
+                    }
 
-                        token0_eth_price = match prices_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token0_addr}"))
-                        {
+                    token0_eth_price = match prices_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token0_addr}")) {
+                        None => {
+                            log::debug!("unable to find token 0 price in eth {:?}", token0.address);
+                            continue;
+                        }
+                        Some(price) => price,
+                    };
+                    log::debug!("token 0 is price in eth {}", token0_eth_price);
+                    eth_locked = native_amount.mul(token0_eth_price.clone());
+                    log::debug!("computed eth locked {}", eth_locked);
+                }
+                log::debug!("eth locked in pool {pool_address} {eth_locked} (largest {largest_eth_locked})",);
+                if eth_locked.gt(&largest_eth_locked)
+                    && (eth_locked.gt(&minimum_eth_locked) || WHITELIST_TOKENS.contains(&token1_addr.as_str()))
+                {
+                    log::debug!("eth locked passed test");
+                    let token0_price =
+                        match prices_store.get_at(ord, format!("pool:{pool_address}:{token0_addr}:token0")) {
                             None => {
-                                log::debug!("unable to find token 0 price in eth {:?}", token0.address);
+                                log::debug!("unable to find pool {pool_address} for token {token0_addr} price",);
                                 continue;
                             }
                             Some(price) => price,
                         };
-                        log::debug!("token 0 is price in eth {}", token0_eth_price);
-                        eth_locked = native_amount.mul(token0_eth_price.clone());
-                        log::debug!("computed eth locked {}", eth_locked);
-                    }
-                    log::debug!("eth locked in pool {pool_address} {eth_locked} (largest {largest_eth_locked})",);
-                    if eth_locked.gt(&largest_eth_locked)
-                        && (eth_locked.gt(&minimum_eth_locked) || WHITELIST_TOKENS.contains(&token1_addr.as_str()))
-                    {
-                        log::debug!("eth locked passed test");
-                        let token0_price =
-                            match prices_store.get_at(ord, format!("pool:{pool_address}:{token0_addr}:token0")) {
-                                None => {
-                                    log::debug!("unable to find pool {pool_address} for token {token0_addr} price",);
-                                    continue;
-                                }
-                                Some(price) => price,
-                            };
-                        log::debug!("found token 0 price {}", token0_price);
-                        largest_eth_locked = eth_locked.clone();
-                        price_so_far = token0_price.mul(token0_eth_price.clone());
-                        log::debug!("price_so_far {}", price_so_far);
-                    }
+                    log::debug!("found token 0 price {}", token0_price);
+                    largest_eth_locked = eth_locked.clone();
+                    price_so_far = Some(WhitelistedPoolPrice {
+                        price: token0_price.mul(token0_eth_price.clone()),
+                        pool_address: pool_address.to_string(),
+                        complementary_token: token0_addr.clone(),
+                    });
                 }
             }
         }
@@ -298,3 +665,468 @@ pub fn get_eth_price_in_usd(prices_store: &StoreGetBigDecimal, ordinal: u64) ->
         Some(price) => price,
     };
 }
+
+pub struct ReferencePrice {
+    pub pool_address: String,
+    pub price: BigDecimal,
+    pub weight: BigDecimal,
+}
+
+pub struct BundleConfig {
+    pub reference_pools: Vec<String>,
+    // read the bundle price from the ETH / USD Chainlink feed of store_oracle_prices
+    pub oracle: bool,
+}
+
+// params are reference pool addresses separated by `&`, plus `oracle` to take the bundle
+// price from store_oracle_prices, the reference pools being used until the feed reports
+pub fn bundle_config_from_params(params: &str) -> BundleConfig {
+    let mut oracle = false;
+    let mut reference_pools: Vec<String> = vec![];
+
+    for entry in params.split('&').map(str::trim).filter(|entry| !entry.is_empty()) {
+        if entry == "oracle" {
+            oracle = true;
+            continue;
+        }
+        reference_pools.push(entry.trim_start_matches("0x").to_lowercase());
+    }
+
+    if reference_pools.is_empty() {
+        reference_pools = REFERENCE_POOLS.iter().map(|address| address.to_string()).collect();
+    }
+    BundleConfig {
+        reference_pools,
+        oracle,
+    }
+}
+
+pub fn get_oracle_eth_price_in_usd(oracle_prices_store: &StoreGetBigDecimal, ordinal: u64) -> Option<BigDecimal> {
+    oracle_prices_store
+        .get_at(ordinal, format!("token:{WETH_ADDRESS}:usd"))
+        .filter(|price| !price.is_zero())
+}
+
+// ETH price in USD of each reference pool with some in-range liquidity at the given
+// ordinal, weighted by the normalized in-range liquidity of the pool.
+pub fn get_reference_eth_prices_in_usd(
+    reference_pools: &Vec<String>,
+    pools_store: &StoreGetProto<Pool>,
+    prices_store: &StoreGetBigDecimal,
+    pool_liquidities_store: &StoreGetBigInt,
+    ordinal: u64,
+) -> Vec<ReferencePrice> {
+    let mut reference_prices = vec![];
+
+    for pool_address in reference_pools {
+        let pool = match pools_store.get_last(format!("pool:{pool_address}")) {
+            Some(pool) => pool,
+            None => continue,
+        };
+        let token0 = pool.token0.as_ref().unwrap();
+        let token1 = pool.token1.as_ref().unwrap();
+
+        // amount of the stablecoin per WETH
+        let price_key = if token1.address == WETH_ADDRESS {
+            format!("pool:{pool_address}:{}:token0", token0.address)
+        } else if token0.address == WETH_ADDRESS {
+            format!("pool:{pool_address}:{}:token1", token1.address)
+        } else {
+            log::info!("reference pool {} is not a WETH pool", pool_address);
+            continue;
+        };
+
+        let price = prices_store.get_at(ordinal, &price_key).unwrap_or_default();
+        let liquidity = pool_liquidities_store
+            .get_at(ordinal, format!("pool:{pool_address}"))
+            .unwrap_or_default();
+        if price.is_zero() || liquidity.is_zero() {
+            continue;
+        }
+
+        reference_prices.push(ReferencePrice {
+            pool_address: pool_address.clone(),
+            price,
+            weight: normalized_liquidity(liquidity, token0.decimals, token1.decimals),
+        });
+    }
+
+    reference_prices
+}
+
+// Liquidity-weighted average of the reference prices within MAX_REFERENCE_PRICE_DEVIATION
+// of their liquidity-weighted median. Falls back on the USDC/WETH 0.3% pool when no
+// reference pool has any liquidity or every one of them is rejected.
+pub fn get_bundle_eth_price_in_usd(
+    reference_prices: &Vec<ReferencePrice>,
+    prices_store: &StoreGetBigDecimal,
+    ordinal: u64,
+) -> BigDecimal {
+    let references: Vec<(BigDecimal, BigDecimal)> = reference_prices
+        .iter()
+        .map(|reference| (reference.price.clone(), reference.weight.clone()))
+        .collect();
+
+    match weighted_reference_price(&references) {
+        Some(price) => price,
+        None => get_eth_price_in_usd(prices_store, ordinal),
+    }
+}
+
+// Liquidity-weighted average of the (price, liquidity) pairs within
+// MAX_REFERENCE_PRICE_DEVIATION of their weighted median, None when there is nothing
+// left to average
+pub fn weighted_reference_price(references: &[(BigDecimal, BigDecimal)]) -> Option<BigDecimal> {
+    let median = weighted_median(references)?;
+
+    let max_deviation = BigDecimal::from_str(MAX_REFERENCE_PRICE_DEVIATION).unwrap();
+    let mut weighted_price = BigDecimal::zero();
+    let mut kept_weight = BigDecimal::zero();
+    for (price, weight) in references {
+        if price.is_zero() || weight.is_zero() {
+            continue;
+        }
+        let deviation = price.clone().sub(median.clone()).absolute().div(median.clone());
+        if deviation > max_deviation {
+            log::info!("rejecting reference price {} median {}", price, median);
+            continue;
+        }
+        weighted_price = weighted_price.add(price.clone().mul(weight.clone()));
+        kept_weight = kept_weight.add(weight.clone());
+    }
+
+    if kept_weight.is_zero() {
+        return None;
+    }
+    Some(weighted_price.div(kept_weight))
+}
+
+// Price at which the cumulative liquidity reaches half of the total, the midpoint of the
+// two middle prices when it lands exactly on half. Pairs without a price or liquidity
+// are left out, None when none remains.
+pub fn weighted_median(references: &[(BigDecimal, BigDecimal)]) -> Option<BigDecimal> {
+    let mut sorted: Vec<&(BigDecimal, BigDecimal)> = references
+        .iter()
+        .filter(|(price, weight)| !price.is_zero() && !weight.is_zero())
+        .collect();
+    if sorted.is_empty() {
+        return None;
+    }
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let total_weight = sorted
+        .iter()
+        .fold(BigDecimal::zero(), |total, (_, weight)| total.add(weight.clone()));
+    let half_weight = total_weight.div(BigDecimal::from(2i32));
+
+    let mut cumulative_weight = BigDecimal::zero();
+    for (i, (price, weight)) in sorted.iter().enumerate() {
+        cumulative_weight = cumulative_weight.add(weight.clone());
+        if cumulative_weight == half_weight {
+            if let Some((next_price, _)) = sorted.get(i + 1) {
+                return Some(price.clone().add(next_price.clone()).div(BigDecimal::from(2i32)));
+            }
+        }
+        if cumulative_weight >= half_weight {
+            return Some(price.clone());
+        }
+    }
+
+    sorted.last().map(|(price, _)| price.clone())
+}
+
+pub fn get_token_price_usd(store_eth_prices: &StoreGetBigDecimal, ordinal: u64, token_address: &String) -> BigDecimal {
+    let eth_price_in_usd = match store_eth_prices.get_at(ordinal, "bundle") {
+        None => return BigDecimal::zero(),
+        Some(price) => price,
+    };
+
+    match store_eth_prices.get_at(ordinal, format!("token:{token_address}:dprice:eth")) {
+        None => BigDecimal::zero(),
+        Some(derived_eth_price) => derived_eth_price.mul(eth_price_in_usd),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pb::uniswap::TokenPricePath;
+    use crate::price::{
+        is_price_unavailable, pricing_graph_path, token_price_provenance, weighted_median, weighted_reference_price,
+        MAX_SQRT_RATIO, MIN_SQRT_RATIO, USDC_ADDRESS, WETH_ADDRESS,
+    };
+    use crate::{Erc20Token, Pool};
+    use std::ops::{Add, Sub};
+    use std::str::FromStr;
+    use substreams::scalar::{BigDecimal, BigInt};
+
+    fn sqrt_price(value: &str) -> BigInt {
+        BigInt::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_price_unavailable_at_min_sqrt_ratio() {
+        let min_sqrt_ratio = sqrt_price(MIN_SQRT_RATIO);
+        assert!(is_price_unavailable(&min_sqrt_ratio, None));
+        // a swap stops one step inside of the bound at most
+        assert!(is_price_unavailable(&min_sqrt_ratio.clone().add(BigInt::one()), None));
+        assert!(!is_price_unavailable(&min_sqrt_ratio.add(BigInt::from(2)), None));
+    }
+
+    #[test]
+    fn test_price_unavailable_at_max_sqrt_ratio() {
+        let max_sqrt_ratio = sqrt_price(MAX_SQRT_RATIO);
+        assert!(is_price_unavailable(&max_sqrt_ratio, None));
+        assert!(is_price_unavailable(&max_sqrt_ratio.clone().sub(BigInt::one()), None));
+        assert!(!is_price_unavailable(&max_sqrt_ratio.sub(BigInt::from(2)), None));
+    }
+
+    #[test]
+    fn test_price_unavailable_without_liquidity() {
+        // 1:1 price
+        let sqrt_price = sqrt_price("79228162514264337593543950336");
+        assert!(is_price_unavailable(&sqrt_price, Some(&BigInt::zero())));
+        assert!(!is_price_unavailable(&sqrt_price, Some(&BigInt::from(1000))));
+        // the initialization is only checked against the bounds
+        assert!(!is_price_unavailable(&sqrt_price, None));
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(BigDecimal, BigDecimal)> {
+        pairs
+            .iter()
+            .map(|(price, liquidity)| {
+                (
+                    BigDecimal::from_str(price).unwrap(),
+                    BigDecimal::from_str(liquidity).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_weighted_median_odd() {
+        let references = pairs(&[("2010", "1"), ("2000", "1"), ("1990", "1")]);
+        assert_eq!(weighted_median(&references), Some(decimal("2000")));
+
+        // the liquidity moves the median, not the number of pools
+        let references = pairs(&[("2010", "5"), ("2000", "1"), ("1990", "1")]);
+        assert_eq!(weighted_median(&references), Some(decimal("2010")));
+    }
+
+    #[test]
+    fn test_weighted_median_even() {
+        let references = pairs(&[("2010", "1"), ("2000", "1"), ("1990", "1"), ("2020", "1")]);
+        assert_eq!(weighted_median(&references), Some(decimal("2005")));
+
+        let references = pairs(&[("2010", "1"), ("2000", "3")]);
+        assert_eq!(weighted_median(&references), Some(decimal("2000")));
+    }
+
+    #[test]
+    fn test_weighted_reference_price_rejects_manipulated_pool() {
+        // a thin pool pushed far off the market does not move the price
+        let references = pairs(&[("2000", "10"), ("2010", "10"), ("500", "1")]);
+        assert_eq!(weighted_reference_price(&references), Some(decimal("2005")));
+
+        let references = pairs(&[("2000", "10"), ("2010", "10")]);
+        assert_eq!(weighted_reference_price(&references), Some(decimal("2005")));
+    }
+
+    #[test]
+    fn test_weighted_reference_price_all_rejected() {
+        // without any usable pool the bundle falls back on the USDC/WETH 0.3% pool
+        assert_eq!(weighted_reference_price(&[]), None);
+        let references = pairs(&[("0", "10"), ("0", "20")]);
+        assert_eq!(weighted_reference_price(&references), None);
+    }
+
+    #[test]
+    fn test_weighted_reference_price_zero_liquidity() {
+        let references = pairs(&[("2000", "0"), ("2010", "0")]);
+        assert_eq!(weighted_median(&references), None);
+        assert_eq!(weighted_reference_price(&references), None);
+
+        // a pool without liquidity does not weigh in the median either
+        let references = pairs(&[("2000", "0"), ("2010", "1")]);
+        assert_eq!(weighted_reference_price(&references), Some(decimal("2010")));
+    }
+
+    // (pool address, token0, token1, in-range liquidity), every pool pricing its tokens at 2
+    fn graph_path(pools: &[(&str, &str, &str, u64)], token: &str) -> Option<TokenPricePath> {
+        pricing_graph_path(
+            token,
+            |token| {
+                let token_pools: Vec<&str> = pools
+                    .iter()
+                    .filter(|(_, token0, token1, _)| *token0 == token || *token1 == token)
+                    .map(|(pool_address, _, _, _)| *pool_address)
+                    .collect();
+                Some(token_pools.join(";"))
+            },
+            |pool_address| {
+                pools
+                    .iter()
+                    .find(|(address, _, _, _)| *address == pool_address)
+                    .map(|(address, token0, token1, _)| Pool {
+                        address: address.to_string(),
+                        token0: Some(Erc20Token {
+                            address: token0.to_string(),
+                            ..Default::default()
+                        }),
+                        token1: Some(Erc20Token {
+                            address: token1.to_string(),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    })
+            },
+            |pool_address| {
+                pools
+                    .iter()
+                    .find(|(address, _, _, _)| *address == pool_address)
+                    .map_or(BigInt::zero(), |(_, _, _, liquidity)| BigInt::from(*liquidity))
+            },
+            |_| decimal("2"),
+        )
+    }
+
+    #[test]
+    fn test_pricing_graph_path_depth_limit() {
+        let path = graph_path(
+            &[
+                ("p1", "a", "b", 10),
+                ("p2", "b", "c", 10),
+                ("p3", "c", WETH_ADDRESS, 10),
+            ],
+            "a",
+        )
+        .unwrap();
+        assert_eq!(vec!["p1", "p2", "p3"], path.pools);
+        assert_eq!(3, path.depth);
+        assert_eq!(WETH_ADDRESS, path.target);
+        assert_eq!(decimal("8"), decimal(&path.price));
+
+        // a fourth hop is past MAX_PRICING_DEPTH
+        let path = graph_path(
+            &[
+                ("p1", "a", "b", 10),
+                ("p2", "b", "c", 10),
+                ("p3", "c", "d", 10),
+                ("p4", "d", WETH_ADDRESS, 10),
+            ],
+            "a",
+        );
+        assert!(path.is_none());
+    }
+
+    #[test]
+    fn test_pricing_graph_path_most_liquid_least_liquid_pool() {
+        // through b the least liquid pool holds 5, through c it holds 50
+        let path = graph_path(
+            &[
+                ("p1", "a", "b", 1000),
+                ("p2", "b", WETH_ADDRESS, 5),
+                ("p3", "a", "c", 50),
+                ("p4", "c", USDC_ADDRESS, 100),
+            ],
+            "a",
+        )
+        .unwrap();
+        assert_eq!(vec!["p3", "p4"], path.pools);
+        assert_eq!(vec!["a", "c", USDC_ADDRESS], path.tokens);
+        assert_eq!(USDC_ADDRESS, path.target);
+
+        // pools without liquidity are left out
+        let path = graph_path(
+            &[
+                ("p1", "a", WETH_ADDRESS, 0),
+                ("p2", "a", "b", 1),
+                ("p3", "b", WETH_ADDRESS, 1),
+            ],
+            "a",
+        );
+        assert_eq!(vec!["p2", "p3"], path.unwrap().pools);
+    }
+
+    #[test]
+    fn test_pricing_graph_path_shortest_on_tie() {
+        let path = graph_path(
+            &[
+                ("p1", "a", "b", 10),
+                ("p2", "b", WETH_ADDRESS, 10),
+                ("p3", "a", WETH_ADDRESS, 10),
+            ],
+            "a",
+        )
+        .unwrap();
+        assert_eq!(vec!["p3"], path.pools);
+        assert_eq!(1, path.depth);
+    }
+
+    // derived ETH prices of a at 0.5, b and WETH at 1, the source pool p1 holding 600 a and
+    // 200 of the next token, 500 ETH locked
+    fn provenance_confidence(tokens: &[&str], pools: &[&str]) -> BigDecimal {
+        let path = TokenPricePath {
+            token: tokens[0].to_string(),
+            target: tokens.last().unwrap().to_string(),
+            tokens: tokens.iter().map(|token| token.to_string()).collect(),
+            pools: pools.iter().map(|pool| pool.to_string()).collect(),
+            depth: pools.len() as u64,
+            ..Default::default()
+        };
+        let provenance = token_price_provenance(
+            1,
+            tokens[0],
+            Some(path),
+            |token| match token {
+                "a" => decimal("0.5"),
+                _ => decimal("1"),
+            },
+            |pool_address, token| match (pool_address, token) {
+                ("p1", "a") => decimal("600"),
+                ("p1", _) => decimal("200"),
+                _ => decimal("1000000"),
+            },
+        );
+        decimal(&provenance.confidence)
+    }
+
+    #[test]
+    fn test_token_price_provenance_confidence() {
+        // half of the confidence at HALF_CONFIDENCE_ETH_LOCKED
+        assert_eq!(decimal("0.5"), provenance_confidence(&["a", WETH_ADDRESS], &["p1"]));
+        // decayed by CONFIDENCE_DECAY_PER_HOP after the first hop, the ETH locked being the
+        // one of the source pool only
+        assert_eq!(
+            decimal("0.4"),
+            provenance_confidence(&["a", "b", WETH_ADDRESS], &["p1", "p2"])
+        );
+        assert_eq!(
+            decimal("0.32"),
+            provenance_confidence(&["a", "b", "c", WETH_ADDRESS], &["p1", "p2", "p3"])
+        );
+    }
+
+    #[test]
+    fn test_token_price_provenance_bounds() {
+        // WETH and the stablecoins are priced in themselves
+        assert_eq!(decimal("1"), provenance_confidence(&[WETH_ADDRESS], &[]));
+
+        // unpriced tokens
+        let provenance = token_price_provenance(1, "a", None, |_| decimal("1"), |_, _| decimal("1"));
+        assert_eq!(decimal("0"), decimal(&provenance.confidence));
+        let path = TokenPricePath {
+            token: "a".to_string(),
+            target: WETH_ADDRESS.to_string(),
+            tokens: vec!["a".to_string(), WETH_ADDRESS.to_string()],
+            pools: vec!["p1".to_string()],
+            depth: 1,
+            ..Default::default()
+        };
+        let provenance = token_price_provenance(1, "a", Some(path), |_| decimal("0"), |_, _| decimal("1"));
+        assert_eq!(decimal("0"), decimal(&provenance.confidence));
+    }
+}
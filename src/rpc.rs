@@ -1,8 +1,6 @@
-use crate::{abi, eth, utils, Erc20Token};
-use prost::Message;
+use crate::{abi, eth, metadata, utils, Erc20Token};
 use substreams::log;
 use substreams::scalar::BigInt;
-use substreams::Hex;
 use substreams_ethereum::rpc::RpcBatch;
 
 pub fn create_uniswap_token(token_address: &String) -> Option<Erc20Token> {
@@ -15,6 +13,10 @@ pub fn create_uniswap_token(token_address: &String) -> Option<Erc20Token> {
         .unwrap()
         .responses;
 
+    let static_token = utils::get_static_uniswap_tokens(&hex::decode(token_address).unwrap());
+    let mut missing_decimals = false;
+    let mut bytes32_metadata = false;
+
     let decimals: u64;
     match RpcBatch::decode::<_, abi::erc20::functions::Decimals>(&responses[0]) {
         Some(decoded_decimals) => {
@@ -28,17 +30,14 @@ pub fn create_uniswap_token(token_address: &String) -> Option<Erc20Token> {
             }
             decimals = decoded_decimals.to_u64();
         }
-        None => match utils::get_static_uniswap_tokens(token_address.encode_to_vec().as_slice()) {
-            Some(token) => decimals = token.decimals,
-            None => {
-                log::debug!(
-                    "{} is not an ERC20 token contract decimal `eth_call` failed",
-                    Hex(&token_address),
-                );
-
-                return None;
-            }
-        },
+        None => {
+            log::debug!("{} does not implement decimals, `eth_call` failed", &token_address);
+            missing_decimals = true;
+            decimals = match &static_token {
+                Some(token) => token.decimals,
+                None => 0,
+            };
+        }
     };
     log::debug!("decoded_decimals ok");
 
@@ -47,14 +46,20 @@ pub fn create_uniswap_token(token_address: &String) -> Option<Erc20Token> {
         Some(decoded_name) => {
             name = decoded_name;
         }
-        None => match utils::get_static_uniswap_tokens(token_address.encode_to_vec().as_slice()) {
-            Some(token) => name = token.name,
+        None => match &static_token {
+            Some(token) => name = token.name.clone(),
             None => {
                 log::debug!(
                     "{} is not an ERC20 token contract name `eth_call` failed",
                     &token_address,
                 );
-                name = eth::read_string_from_bytes(responses[1].raw.as_ref());
+                name = match RpcBatch::decode::<_, abi::erc20_name_bytes::functions::Name>(&responses[1]) {
+                    Some(decoded_name) => {
+                        bytes32_metadata = true;
+                        eth::read_string_from_bytes(&decoded_name)
+                    }
+                    None => eth::read_string_from_return_data(responses[1].raw.as_ref()),
+                };
             }
         },
     };
@@ -65,27 +70,39 @@ pub fn create_uniswap_token(token_address: &String) -> Option<Erc20Token> {
         Some(decoded_symbol) => {
             symbol = decoded_symbol;
         }
-        None => match utils::get_static_uniswap_tokens(token_address.encode_to_vec().as_slice()) {
-            Some(token) => symbol = token.symbol,
+        None => match &static_token {
+            Some(token) => symbol = token.symbol.clone(),
             None => {
                 log::debug!(
                     "{} is not a an ERC20 token contract symbol `eth_call` failed",
                     &token_address,
                 );
-                symbol = eth::read_string_from_bytes(responses[2].raw.as_ref());
+                symbol = match RpcBatch::decode::<_, abi::erc20_symbol_bytes::functions::Symbol>(&responses[2]) {
+                    Some(decoded_symbol) => {
+                        bytes32_metadata = true;
+                        eth::read_string_from_bytes(&decoded_symbol)
+                    }
+                    None => eth::read_string_from_return_data(responses[2].raw.as_ref()),
+                };
             }
         },
     };
     log::debug!("decoded_symbol ok");
 
-    return Some(Erc20Token {
+    let mut token = Erc20Token {
         address: token_address.clone(),
-        name,
-        symbol,
+        name: metadata::clean(&name),
+        symbol: metadata::clean(&symbol),
         decimals,
         total_supply: "".to_string(),
         whitelist_pools: vec![],
-    });
+        bytes32_metadata,
+        missing_decimals,
+        ..Default::default()
+    };
+    metadata::set_display_metadata(&mut token);
+
+    Some(token)
 }
 
 pub fn token_total_supply_call(token_address: &String) -> Option<BigInt> {
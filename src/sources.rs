@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use substreams::errors::Error;
+use substreams::{hex, Hex};
+use substreams_ethereum::block_view::CallView;
+
+pub const UNKNOWN_SOURCE: &str = "unknown";
+
+// routers and aggregators known out of the box, more can be registered through
+// the params of map_extract_data_types
+const KNOWN_SOURCES: [(&str, [u8; 20]); 11] = [
+    ("uniswap-swap-router", hex!("e592427a0aece92de3edee1f18e0157c05861564")),
+    (
+        "uniswap-swap-router02",
+        hex!("68b3465833fb72a70ecdf485e0e4c7bd8665fc45"),
+    ),
+    (
+        "uniswap-universal-router",
+        hex!("ef1c6e67703c7bd7107eed8303fbe6ec2554bf6b"),
+    ),
+    (
+        "uniswap-universal-router",
+        hex!("3fc91a3afd70395cd496c647d5a6cc9d4b2b7fad"),
+    ),
+    ("1inch", hex!("1111111254fb6c44bac0bed2854e76f90643097d")),
+    ("1inch", hex!("1111111254eeb25477b68fb85ed929f73a960582")),
+    ("1inch", hex!("111111125421ca6dc452d289314280a0f8842a65")),
+    ("0x", hex!("def1c0ded9bec7f1a1670819833240f027b25eff")),
+    ("cow-protocol", hex!("9008d19f58aabd9ed0d60971565aa8510560ab41")),
+    ("paraswap", hex!("def171fe48cf0115b1d80b88dc8eab59176fee57")),
+    ("metamask", hex!("881d40237659c251811cec9c364ef91dc08d300c")),
+];
+
+pub struct SourceRegistry {
+    sources: HashMap<String, String>,
+}
+
+impl SourceRegistry {
+    // params are `label=address` entries separated by `&`, e.g.
+    // `kyberswap=0x6131b5fae19ea4f9d964eac0408e4408b66337b5&odos=0xcf5540fffcdc3d510b18bfca6d2b9987b0772559`,
+    // an entry overrides the label of a known source with the same address
+    pub fn from_params(params: &str) -> Result<Self, Error> {
+        let mut sources: HashMap<String, String> = KNOWN_SOURCES
+            .iter()
+            .map(|(label, address)| (Hex(address).to_string(), label.to_string()))
+            .collect();
+
+        for entry in params.split('&').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (label, address) = match entry.split_once('=') {
+                Some((label, address)) => (label.trim(), address.trim()),
+                None => return Err(invalid_entry(entry)),
+            };
+            let address = address.trim_start_matches("0x").to_lowercase();
+            if label.is_empty() || label.contains(':') || address.len() != 40 || hex::decode(&address).is_err() {
+                return Err(invalid_entry(entry));
+            }
+            sources.insert(address, label.to_string());
+        }
+
+        Ok(SourceRegistry { sources })
+    }
+
+    // Walks up the call tree from the call which emitted the log, the entry point is the
+    // outermost registered contract so that an aggregator going through a router is
+    // attributed to the aggregator.
+    pub fn attribute(&self, call_view: &CallView) -> String {
+        let mut source = UNKNOWN_SOURCE.to_string();
+        let mut call = call_view.call;
+
+        loop {
+            if let Some(label) = self.sources.get(&Hex(&call.address).to_string()) {
+                source = label.clone();
+            }
+            if call.depth == 0 {
+                break;
+            }
+            call = match call_view
+                .transaction
+                .calls
+                .iter()
+                .find(|parent| parent.index == call.parent_index)
+            {
+                Some(parent) => parent,
+                None => break,
+            };
+        }
+
+        source
+    }
+}
+
+fn invalid_entry(entry: &str) -> Error {
+    Error::msg(format!("invalid source registry entry {entry}, expected label=address"))
+}
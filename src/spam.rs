@@ -0,0 +1,378 @@
+use crate::abi::erc20::functions::Transfer;
+use crate::abi::pool::events::{Burn, Mint};
+use crate::metadata;
+use crate::pb::uniswap::{FilteredPool, Pool, SpamFilterParams};
+use crate::price::WHITELIST_TOKENS;
+use std::collections::HashSet;
+use substreams::errors::Error;
+use substreams::scalar::BigInt;
+use substreams::store::{DeltaInt64, Deltas, StoreGet, StoreGetBigInt, StoreGetProto, StoreGetRaw};
+use substreams::Hex;
+use substreams_ethereum::pb::eth::v2::Block;
+use substreams_ethereum::Event;
+
+pub struct SpamFilter {
+    allow: HashSet<String>,
+    deny: HashSet<String>,
+    fake_symbols: bool,
+    zero_liquidity_blocks: u64,
+    transfer_failures: i64,
+}
+
+impl SpamFilter {
+    // params are entries separated by `&`, given to map_spam_filter for map_pools_created and
+    // map_spam_pools:
+    // - `allow=address` and `deny=address`, repeated as needed, for pools or tokens. Denied
+    //   pools and pools of denied tokens are left out, allowed ones are never filtered by the
+    //   heuristics
+    // - `fake_symbols=true` to leave out the pools of tokens imitating the symbol of a
+    //   whitelisted token
+    // - `zero_liquidity_blocks=N` to ignore the pools without liquidity N blocks after their
+    //   creation
+    // - `transfer_failures=N` to ignore the pools once N transfers of one of their tokens made
+    //   by the pool failed, as with tokens blocking the pool from paying out
+    // the heuristics are off by default, the two last ones also with 0
+    pub fn from_params(params: &str) -> Result<Self, Error> {
+        let mut spam_filter = SpamFilter {
+            allow: HashSet::new(),
+            deny: HashSet::new(),
+            fake_symbols: false,
+            zero_liquidity_blocks: 0,
+            transfer_failures: 0,
+        };
+
+        for entry in params.split('&').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (name, value) = match entry.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => return Err(invalid_entry(entry, "expected name=value")),
+            };
+            match name {
+                "allow" => {
+                    spam_filter.allow.insert(parse_address(entry, value)?);
+                }
+                "deny" => {
+                    spam_filter.deny.insert(parse_address(entry, value)?);
+                }
+                "fake_symbols" => {
+                    spam_filter.fake_symbols = value
+                        .parse()
+                        .map_err(|_| invalid_entry(entry, "expected true or false"))?
+                }
+                "zero_liquidity_blocks" => {
+                    spam_filter.zero_liquidity_blocks = value
+                        .parse()
+                        .map_err(|_| invalid_entry(entry, "expected a number of blocks"))?
+                }
+                "transfer_failures" => {
+                    spam_filter.transfer_failures = value
+                        .parse::<u32>()
+                        .map_err(|_| invalid_entry(entry, "expected a number of failures"))?
+                        .into()
+                }
+                _ => {
+                    return Err(invalid_entry(
+                        entry,
+                        "expected allow, deny, fake_symbols, zero_liquidity_blocks or transfer_failures",
+                    ))
+                }
+            }
+        }
+
+        Ok(spam_filter)
+    }
+
+    pub fn from_spam_filter_params(spam_filter_params: &SpamFilterParams) -> Self {
+        SpamFilter {
+            allow: spam_filter_params.allow.iter().cloned().collect(),
+            deny: spam_filter_params.deny.iter().cloned().collect(),
+            fake_symbols: spam_filter_params.fake_symbols,
+            zero_liquidity_blocks: spam_filter_params.zero_liquidity_blocks,
+            transfer_failures: spam_filter_params.transfer_failures as i64,
+        }
+    }
+
+    pub fn to_spam_filter_params(&self) -> SpamFilterParams {
+        let mut allow: Vec<String> = self.allow.iter().cloned().collect();
+        let mut deny: Vec<String> = self.deny.iter().cloned().collect();
+        allow.sort();
+        deny.sort();
+
+        SpamFilterParams {
+            allow,
+            deny,
+            fake_symbols: self.fake_symbols,
+            zero_liquidity_blocks: self.zero_liquidity_blocks,
+            transfer_failures: self.transfer_failures as u64,
+        }
+    }
+
+    // Decision taken by map_pools_created, denied pools and tokens first, allowed ones being
+    // kept before the fake symbols are looked for
+    pub fn filter_created(&self, pool: &Pool) -> Option<FilteredPool> {
+        let (reason, detail) = if let Some(address) = pool_addresses(pool).find(|address| self.deny.contains(*address))
+        {
+            ("denylist", address.clone())
+        } else if self.is_allowed(pool) {
+            return None;
+        } else if let Some(token) = self.fake_symbol_token(pool) {
+            ("fake_symbol", token)
+        } else {
+            return None;
+        };
+
+        Some(filtered_pool(
+            pool,
+            reason,
+            detail,
+            true,
+            pool.created_at_block_number,
+            pool.log_ordinal,
+        ))
+    }
+
+    // Pools created zero_liquidity_blocks ago, which have no liquidity left
+    pub fn zero_liquidity_pools(
+        &self,
+        block_number: u64,
+        spam_candidates_store: &StoreGetProto<Pool>,
+        spam_creation_blocks_store: &StoreGetRaw,
+        spam_pool_liquidities_store: &StoreGetBigInt,
+    ) -> Vec<FilteredPool> {
+        if self.zero_liquidity_blocks == 0 || block_number < self.zero_liquidity_blocks {
+            return vec![];
+        }
+        let created_block_number = block_number - self.zero_liquidity_blocks;
+        let pool_addresses = match spam_creation_blocks_store.get_last(format!("block:{created_block_number}")) {
+            Some(bytes) => String::from_utf8(bytes).unwrap(),
+            None => return vec![],
+        };
+
+        pool_addresses
+            .split(';')
+            .filter(|address| !address.is_empty())
+            .filter_map(|pool_address| {
+                let pool = spam_candidates_store.get_last(format!("pool:{pool_address}"))?;
+                if self.is_allowed(&pool) {
+                    return None;
+                }
+                let liquidity = spam_pool_liquidities_store
+                    .get_last(format!("pool:{pool_address}"))
+                    .unwrap_or_else(BigInt::zero);
+                if liquidity > BigInt::zero() {
+                    return None;
+                }
+
+                Some(filtered_pool(
+                    &pool,
+                    "zero_liquidity",
+                    liquidity.to_string(),
+                    false,
+                    block_number,
+                    0,
+                ))
+            })
+            .collect()
+    }
+
+    // Pools whose failed transfers reach transfer_failures with this block
+    pub fn transfer_failure_pools(
+        &self,
+        block_number: u64,
+        spam_candidates_store: &StoreGetProto<Pool>,
+        spam_transfer_failures_deltas: &Deltas<DeltaInt64>,
+    ) -> Vec<FilteredPool> {
+        if self.transfer_failures == 0 {
+            return vec![];
+        }
+
+        spam_transfer_failures_deltas
+            .deltas
+            .iter()
+            .filter(|delta| delta.old_value < self.transfer_failures && delta.new_value >= self.transfer_failures)
+            .filter_map(|delta| {
+                let pool = spam_candidates_store.get_last(&delta.key)?;
+                if self.is_allowed(&pool) {
+                    return None;
+                }
+
+                Some(filtered_pool(
+                    &pool,
+                    "transfer_failures",
+                    delta.new_value.to_string(),
+                    false,
+                    block_number,
+                    delta.ordinal,
+                ))
+            })
+            .collect()
+    }
+
+    fn is_allowed(&self, pool: &Pool) -> bool {
+        pool_addresses(pool).any(|address| self.allow.contains(address))
+    }
+
+    fn fake_symbol_token(&self, pool: &Pool) -> Option<String> {
+        if !self.fake_symbols {
+            return None;
+        }
+
+        [pool.token0_ref(), pool.token1_ref()]
+            .into_iter()
+            .find(|token| metadata::impersonated_symbol(token).is_some())
+            .map(|token| token.address.clone())
+    }
+}
+
+// Net liquidity minted into the pools watched by the spam filter, burns being negative
+pub fn extract_liquidity_changes(
+    block: &Block,
+    spam_candidates_store: &StoreGetProto<Pool>,
+) -> Vec<(String, BigInt, u64)> {
+    block
+        .logs()
+        .filter_map(|log| {
+            let amount = if let Some(mint) = Mint::match_and_decode(log.log) {
+                mint.amount
+            } else if let Some(burn) = Burn::match_and_decode(log.log) {
+                burn.amount.neg()
+            } else {
+                return None;
+            };
+            let pool_address = Hex(log.address()).to_string();
+            spam_candidates_store.get_last(format!("pool:{pool_address}"))?;
+
+            Some((pool_address, amount, log.ordinal()))
+        })
+        .collect()
+}
+
+// Failed transfer calls of a token of a pool watched by the spam filter made by the pool
+// paying out, the transferFrom calls of the swap callbacks being left out as they also fail
+// for a payer without balance or allowance. The calls of failed transactions are included,
+// whitelisted tokens are not looked at.
+pub fn extract_transfer_failures(block: &Block, spam_candidates_store: &StoreGetProto<Pool>) -> Vec<(String, u64)> {
+    let mut failures = vec![];
+
+    for call in block
+        .transaction_traces
+        .iter()
+        .flat_map(|trx| trx.calls.iter())
+        .filter(|call| call.status_failed)
+    {
+        if !Transfer::match_call(call) {
+            continue;
+        }
+        let pool_address = Hex(&call.caller).to_string();
+        let token_address = Hex(&call.address).to_string();
+        if WHITELIST_TOKENS.contains(&token_address.as_str()) {
+            continue;
+        }
+        let pool = match spam_candidates_store.get_last(format!("pool:{pool_address}")) {
+            Some(pool) => pool,
+            None => continue,
+        };
+        if pool.token0_ref().address == token_address || pool.token1_ref().address == token_address {
+            failures.push((pool_address, call.end_ordinal));
+        }
+    }
+
+    failures
+}
+
+fn pool_addresses(pool: &Pool) -> impl Iterator<Item = &String> {
+    [&pool.address, &pool.token0_ref().address, &pool.token1_ref().address].into_iter()
+}
+
+fn parse_address(entry: &str, address: &str) -> Result<String, Error> {
+    let address = address.trim_start_matches("0x").to_lowercase();
+    if address.len() != 40 || hex::decode(&address).is_err() {
+        return Err(invalid_entry(entry, "expected an address"));
+    }
+    Ok(address)
+}
+
+fn invalid_entry(entry: &str, expected: &str) -> Error {
+    Error::msg(format!("invalid spam filter entry {entry}, {expected}"))
+}
+
+fn filtered_pool(
+    pool: &Pool,
+    reason: &str,
+    detail: String,
+    at_creation: bool,
+    block_number: u64,
+    ordinal: u64,
+) -> FilteredPool {
+    FilteredPool {
+        pool: Some(Pool {
+            ignore_pool: true,
+            ..pool.clone()
+        }),
+        reason: reason.to_string(),
+        detail,
+        at_creation,
+        block_number,
+        ordinal,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spam::SpamFilter;
+
+    #[test]
+    fn test_from_params() {
+        let spam_filter = SpamFilter::from_params(
+            "allow=0xA0b86991c6218b36c1d19d4a2e9eb0ce3606eb48&deny=8ad599c3a0ff1de082011efddc58f1908eb6e6d8&fake_symbols=true&transfer_failures=3",
+        )
+        .unwrap();
+        assert!(spam_filter.allow.contains("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"));
+        assert!(spam_filter.deny.contains("8ad599c3a0ff1de082011efddc58f1908eb6e6d8"));
+        assert!(spam_filter.fake_symbols);
+        assert_eq!(0, spam_filter.zero_liquidity_blocks);
+        assert_eq!(3, spam_filter.transfer_failures);
+    }
+
+    #[test]
+    fn test_from_empty_params() {
+        let spam_filter = SpamFilter::from_params("").unwrap();
+        assert!(spam_filter.allow.is_empty());
+        assert!(spam_filter.deny.is_empty());
+        assert!(!spam_filter.fake_symbols);
+        assert_eq!(0, spam_filter.zero_liquidity_blocks);
+        assert_eq!(0, spam_filter.transfer_failures);
+    }
+
+    #[test]
+    fn test_from_invalid_params() {
+        assert!(SpamFilter::from_params("allow").is_err());
+        assert!(SpamFilter::from_params("allow=0x1234").is_err());
+        assert!(SpamFilter::from_params("fake_symbols=yes").is_err());
+        assert!(SpamFilter::from_params("zero_liquidity_blocks=-1").is_err());
+        assert!(SpamFilter::from_params("transfer_failures=many").is_err());
+        assert!(SpamFilter::from_params("fake_symbols=true&deny_all=true").is_err());
+    }
+
+    #[test]
+    fn test_spam_filter_params_round_trip() {
+        let spam_filter = SpamFilter::from_params(
+            "deny=8ad599c3a0ff1de082011efddc58f1908eb6e6d8&deny=a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48&zero_liquidity_blocks=100&transfer_failures=2",
+        )
+        .unwrap();
+        let spam_filter_params = spam_filter.to_spam_filter_params();
+        assert_eq!(
+            vec![
+                "8ad599c3a0ff1de082011efddc58f1908eb6e6d8",
+                "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"
+            ],
+            spam_filter_params.deny
+        );
+
+        let spam_filter = SpamFilter::from_spam_filter_params(&spam_filter_params);
+        assert_eq!(2, spam_filter.deny.len());
+        assert!(spam_filter.allow.is_empty());
+        assert!(!spam_filter.fake_symbols);
+        assert_eq!(100, spam_filter.zero_liquidity_blocks);
+        assert_eq!(2, spam_filter.transfer_failures);
+    }
+}
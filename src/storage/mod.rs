@@ -1,3 +1,5 @@
+// The uniswap_v3_pool and position_manager modules are generated by build.rs from the
+// storage layouts in abis/storage
 #[allow(unused_imports)]
 #[allow(dead_code)]
 pub mod uniswap_v3_pool;
@@ -6,4 +8,9 @@ pub mod uniswap_v3_pool;
 #[allow(dead_code)]
 pub mod position_manager;
 
-mod utils;
+pub mod utils;
+
+#[cfg(test)]
+mod position_manager_tests;
+#[cfg(test)]
+mod uniswap_v3_pool_tests;
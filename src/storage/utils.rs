@@ -41,6 +41,41 @@ pub fn calc_map_slot(map_index: &[u8; 32], base_slot: &[u8; 32]) -> [u8; 32] {
     return output;
 }
 
+// the elements of a dynamic array start at the hash of its slot
+pub fn calc_array_slot(array_slot: &[u8; 32]) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(array_slot);
+    hasher.finalize(&mut output);
+    output
+}
+
+// the pool keeps its positions under keccak256(abi.encodePacked(owner, tickLower, tickUpper))
+pub fn calc_position_key(owner: &[u8], tick_lower: i32, tick_upper: i32) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(owner);
+    hasher.update(&tick_lower.to_be_bytes()[1..]);
+    hasher.update(&tick_upper.to_be_bytes()[1..]);
+    hasher.finalize(&mut output);
+    output
+}
+
+// the index of the element of a fixed size array held at the storage key, none when the key
+// is outside of the array
+pub fn array_index(slot_key: &[u8], array_slot: u64, length: u64, element_slots: u64) -> Option<u64> {
+    if slot_key.len() != 32 || slot_key[..24].iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    let slot = u64::from_be_bytes(slot_key[24..].try_into().unwrap());
+    let position = slot.checked_sub(array_slot)?;
+    if position >= length * element_slots {
+        return None;
+    }
+
+    Some(position / element_slots)
+}
+
 pub fn calc_struct_slot(struct_slot: &[u8; 32], member_slot: BigInt) -> [u8; 32] {
     let mut key = BigInt::from_signed_bytes_be(struct_slot.as_slice());
     key = key.add(member_slot);
@@ -68,6 +103,17 @@ pub fn left_pad(input: &Vec<u8>, padding_value: u8) -> [u8; 32] {
     return data;
 }
 
+// fixed size byte arrays are left aligned when used as mapping keys
+pub fn right_pad(input: &[u8]) -> [u8; 32] {
+    if input.len() > 32 {
+        panic!("cannot convert vec<u8> to H256");
+    }
+    let mut data = [0u8; 32];
+    data[..input.len()].copy_from_slice(input);
+
+    data
+}
+
 pub fn contract_pad(input: &Vec<u8>) -> [u8; 20] {
     if input.len() > 20 {
         panic!("cannot convert vec<u8> to H256");
@@ -114,9 +160,27 @@ pub fn read_bytes(buf: &Vec<u8>, offset: usize, number_of_bytes: usize) -> &[u8]
     &buf[start..=end]
 }
 
+pub fn to_uint(data: &[u8]) -> BigInt {
+    BigInt::from_unsigned_bytes_be(data)
+}
+
+pub fn to_int(data: &[u8]) -> BigInt {
+    BigInt::from_signed_bytes_be(data)
+}
+
+pub fn to_bool(data: &[u8]) -> bool {
+    data == [1u8]
+}
+
+pub fn to_bytes<const N: usize>(data: &[u8]) -> [u8; N] {
+    <[u8; N]>::try_from(data).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::storage::utils::{get_storage_change, left_pad, read_bytes};
+    use crate::storage::utils::{
+        array_index, calc_array_slot, calc_position_key, get_storage_change, left_pad, read_bytes, right_pad,
+    };
     use std::{fmt::Write, num::ParseIntError};
     use substreams::hex;
     use substreams_ethereum::pb::eth::v2::StorageChange;
@@ -158,6 +222,53 @@ mod tests {
         let _ = left_pad(&input, 0);
     }
 
+    #[test]
+    fn right_pad_lt_32_bytes() {
+        let input = [0x01u8, 0xff, 0xc9, 0xa7];
+        assert_eq!(
+            hex!("01ffc9a700000000000000000000000000000000000000000000000000000000"),
+            right_pad(&input)
+        )
+    }
+
+    #[test]
+    fn array_slot() {
+        let slot = [0u8; 32];
+        assert_eq!(
+            hex!("290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"),
+            calc_array_slot(&slot)
+        )
+    }
+
+    #[test]
+    fn position_key() {
+        let owner = hex!("c36442b4a4522e871399cd717abdd847ab11fe88");
+        assert_eq!(
+            hex!("381aa6c2062f30ec4294109119a35ae6c664cb629c3c1fad6610316d27488a48"),
+            calc_position_key(&owner, -887220, 887220)
+        )
+    }
+
+    #[test]
+    fn array_index_in_bounds() {
+        let slot = hex!("0000000000000000000000000000000000000000000000000000000000000008");
+        assert_eq!(Some(0), array_index(&slot, 8, 65535, 1));
+        let slot = hex!("0000000000000000000000000000000000000000000000000000000000010006");
+        assert_eq!(Some(65534), array_index(&slot, 8, 65535, 1));
+        let slot = hex!("000000000000000000000000000000000000000000000000000000000000000d");
+        assert_eq!(Some(2), array_index(&slot, 8, 10, 2));
+    }
+
+    #[test]
+    fn array_index_out_of_bounds() {
+        let slot = hex!("0000000000000000000000000000000000000000000000000000000000000007");
+        assert_eq!(None, array_index(&slot, 8, 65535, 1));
+        let slot = hex!("0000000000000000000000000000000000000000000000000000000000010007");
+        assert_eq!(None, array_index(&slot, 8, 65535, 1));
+        let slot = hex!("290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563");
+        assert_eq!(None, array_index(&slot, 8, 65535, 1));
+    }
+
     #[test]
     #[should_panic]
     fn read_bytes_buf_too_small() {
@@ -0,0 +1,253 @@
+use crate::diagnostics::SkippedItems;
+use crate::math;
+use crate::pb::uniswap::events::pool_event::Type::Swap as SwapEvent;
+use crate::pb::uniswap::events::PoolEvent;
+use crate::pb::uniswap::Trade;
+use crate::pb::SwapLegs;
+use crate::windows::Window;
+use std::collections::BTreeMap;
+use std::ops::Mul;
+use substreams::scalar::BigDecimal;
+
+// Groups the swaps of each transaction, in ordinal order, into trades. A swap whose
+// input token is the output token of the previous swap is another hop of the same
+// trade, otherwise it starts a new one. The volume of a trade is taken on its input
+// side, falling back on its output side when the input token has no price, so that
+// a multi-hop trade is only counted once. The USD prices of the tokens are given by
+// token_price_usd, by ordinal and token address. A swap whose amounts don't parse is
+// skipped, ending the trade going through it.
+pub fn extract_trades<P>(
+    pool_events: &Vec<PoolEvent>,
+    token_price_usd: P,
+    skipped_items: &mut SkippedItems,
+    block_number: u64,
+    timestamp: u64,
+) -> Vec<Trade>
+where
+    P: Fn(u64, &String) -> BigDecimal,
+{
+    let mut trades = vec![];
+
+    let mut swaps_by_transaction: BTreeMap<&String, Vec<(&PoolEvent, SwapLegs)>> = BTreeMap::new();
+    for event in pool_events {
+        if let Some(SwapEvent(_)) = event.r#type {
+            let legs = match skipped_items.check(
+                "map_trades",
+                event.log_ordinal,
+                &event.pool_address,
+                event.get_swap_legs(),
+            ) {
+                Some(legs) => legs,
+                None => continue,
+            };
+            swaps_by_transaction
+                .entry(&event.transaction_id)
+                .or_default()
+                .push((event, legs));
+        }
+    }
+
+    for (transaction_id, mut swaps) in swaps_by_transaction {
+        swaps.sort_by_key(|(event, _)| event.log_ordinal);
+
+        let mut start = 0;
+        while start < swaps.len() {
+            let (first, first_legs) = &swaps[start];
+            let mut path = vec![first_legs.token_in.clone(), first_legs.token_out.clone()];
+            let mut pools = vec![first.pool_address.clone()];
+            let mut log_indexes = vec![first.log_index];
+            let mut amount_out = first_legs.amount_out.clone();
+            let mut end = start;
+
+            while end + 1 < swaps.len() {
+                let (next, next_legs) = &swaps[end + 1];
+                if next_legs.token_in != *path.last().unwrap() {
+                    break;
+                }
+                end += 1;
+                path.push(next_legs.token_out.clone());
+                pools.push(next.pool_address.clone());
+                log_indexes.push(next.log_index);
+                amount_out = next_legs.amount_out.clone();
+            }
+
+            let (last, _) = &swaps[end];
+            let token_out = path.last().unwrap().clone();
+
+            let mut amount_usd = first_legs
+                .amount_in
+                .clone()
+                .mul(token_price_usd(first.log_ordinal, &first_legs.token_in));
+            if amount_usd.is_zero() {
+                amount_usd = amount_out.clone().mul(token_price_usd(last.log_ordinal, &token_out));
+            }
+
+            let origin = match first.r#type.as_ref().unwrap() {
+                SwapEvent(swap) => swap.origin.clone(),
+                _ => "".to_string(),
+            };
+
+            trades.push(Trade {
+                transaction: transaction_id.clone(),
+                origin,
+                token_in: first_legs.token_in.clone(),
+                token_out,
+                amount_in: first_legs.amount_in.to_string(),
+                amount_out: amount_out.to_string(),
+                hops: pools.len() as u64,
+                path,
+                pools,
+                log_indexes,
+                execution_price: math::safe_div(&amount_out, &first_legs.amount_in).to_string(),
+                amount_usd: amount_usd.to_string(),
+                log_ordinal: last.log_ordinal,
+                block_number,
+                timestamp,
+            });
+
+            start = end + 1;
+        }
+    }
+
+    trades
+}
+
+// Amounts store_trade_volumes adds for a trade, by key: one to the trade count and its USD
+// volume to the trade volume of the factory and of each of the windows
+pub fn trade_volume_additions(amount_usd: &BigDecimal, windows: &[Window]) -> Vec<(String, BigDecimal)> {
+    let mut additions = vec![
+        ("factory:tradeCount".to_string(), BigDecimal::one()),
+        ("factory:tradeVolumeUSD".to_string(), amount_usd.clone()),
+    ];
+    for window in windows {
+        let window_key = window.key("Uniswap");
+        additions.push((format!("{window_key}:tradeCount"), BigDecimal::one()));
+        additions.push((format!("{window_key}:tradeVolumeUSD"), amount_usd.clone()));
+    }
+
+    additions
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::SkippedItems;
+    use crate::errors::Policy;
+    use crate::pb::uniswap::events::pool_event::{Swap, Type};
+    use crate::pb::uniswap::events::PoolEvent;
+    use crate::pb::uniswap::Trade;
+    use crate::trades::{extract_trades, trade_volume_additions};
+    use crate::windows::{self, UNISWAP_WINDOW_KINDS};
+    use std::collections::BTreeMap;
+    use std::ops::Add;
+    use substreams::scalar::BigDecimal;
+
+    fn swap(ordinal: u64, pool_address: &str, tokens: (&str, &str), amounts: (&str, &str)) -> PoolEvent {
+        PoolEvent {
+            log_ordinal: ordinal,
+            log_index: ordinal,
+            transaction_id: "tx".to_string(),
+            pool_address: pool_address.to_string(),
+            token0: tokens.0.to_string(),
+            token1: tokens.1.to_string(),
+            r#type: Some(Type::Swap(Swap {
+                origin: "trader".to_string(),
+                amount_0: amounts.0.to_string(),
+                amount_1: amounts.1.to_string(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    // USDC is worth 1 USD, the other tokens have no price
+    fn trades(pool_events: Vec<PoolEvent>) -> Vec<Trade> {
+        extract_trades(
+            &pool_events,
+            |_, token_address| match token_address.as_str() {
+                "usdc" => BigDecimal::one(),
+                _ => BigDecimal::zero(),
+            },
+            &mut SkippedItems::new(Policy::Fail),
+            1,
+            1,
+        )
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn test_multi_hop_trade() {
+        let trades = trades(vec![
+            // USDC -> WETH -> WBTC
+            swap(1, "usdc-weth", ("usdc", "weth"), ("3000", "-1")),
+            swap(2, "wbtc-weth", ("wbtc", "weth"), ("-0.05", "1")),
+        ]);
+
+        assert_eq!(1, trades.len());
+        let trade = &trades[0];
+        assert_eq!(vec!["usdc", "weth", "wbtc"], trade.path);
+        assert_eq!(vec!["usdc-weth", "wbtc-weth"], trade.pools);
+        assert_eq!(2, trade.hops);
+        assert_eq!(("usdc", "wbtc"), (trade.token_in.as_str(), trade.token_out.as_str()));
+        assert_eq!(decimal("3000"), decimal(&trade.amount_in));
+        assert_eq!(decimal("0.05"), decimal(&trade.amount_out));
+        // the volume is counted once, on the input side
+        assert_eq!(decimal("3000"), decimal(&trade.amount_usd));
+        assert_eq!(2, trade.log_ordinal);
+    }
+
+    #[test]
+    fn test_token_mismatch_breaks_the_trade() {
+        let trades = trades(vec![
+            swap(1, "usdc-weth", ("usdc", "weth"), ("3000", "-1")),
+            // starts from WBTC, not from the WETH received
+            swap(2, "wbtc-dai", ("wbtc", "dai"), ("0.05", "-3000")),
+        ]);
+
+        assert_eq!(2, trades.len());
+        assert_eq!(vec!["usdc", "weth"], trades[0].path);
+        assert_eq!(vec!["wbtc", "dai"], trades[1].path);
+        // neither WBTC nor DAI has a price
+        assert_eq!(decimal("0"), decimal(&trades[1].amount_usd));
+    }
+
+    #[test]
+    fn test_single_swap_trade() {
+        let trades = trades(vec![swap(1, "usdc-weth", ("usdc", "weth"), ("-3000", "1"))]);
+
+        assert_eq!(1, trades.len());
+        let trade = &trades[0];
+        assert_eq!(1, trade.hops);
+        assert_eq!(("weth", "usdc"), (trade.token_in.as_str(), trade.token_out.as_str()));
+        assert_eq!(decimal("3000"), decimal(&trade.execution_price));
+        // WETH has no price, the volume is taken on the output side
+        assert_eq!(decimal("3000"), decimal(&trade.amount_usd));
+    }
+
+    #[test]
+    fn test_trade_volume_additions() {
+        // 2021-05-05T00:00:00Z
+        let windows = windows::current_windows(&UNISWAP_WINDOW_KINDS, 1620172800);
+        let mut totals: BTreeMap<String, BigDecimal> = BTreeMap::new();
+        for amount_usd in ["3000", "150.5"] {
+            for (key, value) in trade_volume_additions(&decimal(amount_usd), &windows) {
+                let total = totals.remove(&key).unwrap_or_else(BigDecimal::zero);
+                totals.insert(key, total.add(value));
+            }
+        }
+
+        assert_eq!(2 + 2 * windows.len(), totals.len());
+        assert_eq!(&decimal("2"), totals.get("factory:tradeCount").unwrap());
+        assert_eq!(&decimal("3150.5"), totals.get("factory:tradeVolumeUSD").unwrap());
+        for window in &windows {
+            let window_key = window.key("Uniswap");
+            assert_eq!(&decimal("2"), totals.get(&format!("{window_key}:tradeCount")).unwrap());
+            assert_eq!(
+                &decimal("3150.5"),
+                totals.get(&format!("{window_key}:tradeVolumeUSD")).unwrap()
+            );
+        }
+    }
+}
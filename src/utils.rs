@@ -1,5 +1,9 @@
+use crate::errors;
+use crate::errors::DataError;
 use crate::ethpb::v2::TransactionTrace;
 use crate::pb::uniswap::events;
+use crate::pb::uniswap::events::pool_event::Swap;
+use crate::pb::uniswap::{FilteredPool, Pool};
 use crate::pb::AdjustedAmounts;
 use crate::uniswap::events::Transaction;
 use crate::{storage, Erc20Token, StorageChange, WHITELIST_TOKENS};
@@ -7,14 +11,16 @@ use std::ops::{Add, Mul};
 use std::string::ToString;
 use substreams::prelude::StoreGetBigDecimal;
 use substreams::scalar::{BigDecimal, BigInt};
-use substreams::store::StoreGet;
+use substreams::store::{StoreGet, StoreGetProto};
 use substreams::{hex, key, log, Hex};
+use tiny_keccak::{Hasher, Keccak};
 
 pub const UNISWAP_V3_FACTORY: [u8; 20] = hex!("1f98431c8ad98523631ae4a59f267346ea31f984");
 
 pub const ZERO_ADDRESS: [u8; 20] = hex!("0000000000000000000000000000000000000000");
 pub const NON_FUNGIBLE_POSITION_MANAGER: [u8; 20] = hex!("c36442b4a4522e871399cd717abdd847ab11fe88");
 pub const ERROR_POOL: [u8; 20] = hex!("8fe8d9bb8eeba3ed688069c3d6b556c9ca258248");
+const POOL_INIT_CODE_HASH: [u8; 32] = hex!("e34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54");
 
 const DGD_TOKEN_ADDRESS: [u8; 20] = hex!("e0b7927c4af23765cb51314a0e0521a9645f0e2a");
 const AAVE_TOKEN_ADDRESS: [u8; 20] = hex!("7fc66500c84a76ad7e9c93437bfc5ac33e2ddae9");
@@ -23,6 +29,27 @@ const SVD_TOKEN_ADDRESS: [u8; 20] = hex!("bdeb4b83251fb146687fa19d1c660f99411eef
 const THEDAO_TOKEN_ADDRESS: [u8; 20] = hex!("bb9bc244d798123fde783fcc1c72d3bb8c189413");
 const HPB_TOKEN_ADDRESS: [u8; 20] = hex!("38c6a68304cdefb9bec48bbfaaba5c5b47818bb2");
 
+// CREATE2 address of the pool deployed by the factory for the given pool key,
+// same computation as the PoolAddress library of the periphery contracts
+pub fn compute_pool_address(token0: &[u8; 20], token1: &[u8; 20], fee: &BigInt) -> [u8; 20] {
+    let mut salt = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(&storage::utils::left_pad(&token0.to_vec(), 0));
+    hasher.update(&storage::utils::left_pad(&token1.to_vec(), 0));
+    hasher.update(&storage::utils::left_pad_from_bigint(fee));
+    hasher.finalize(&mut salt);
+
+    let mut output = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(&[0xff]);
+    hasher.update(&UNISWAP_V3_FACTORY);
+    hasher.update(&salt);
+    hasher.update(&POOL_INIT_CODE_HASH);
+    hasher.finalize(&mut output);
+
+    <[u8; 20]>::try_from(&output[12..]).unwrap()
+}
+
 // hard-coded tokens which have various behaviours but for which a UniswapV3 valid pool
 // exists, some are tokens which were migrated to new addresses
 pub fn get_static_uniswap_tokens(token_address: &[u8]) -> Option<Erc20Token> {
@@ -34,6 +61,7 @@ pub fn get_static_uniswap_tokens(token_address: &[u8]) -> Option<Erc20Token> {
             decimals: 9,
             total_supply: "".to_string(), // subgraph doesn't check the total supply
             whitelist_pools: vec![],
+            ..Default::default()
         }),
         x if x == AAVE_TOKEN_ADDRESS => Some(Erc20Token {
             address: Hex(&AAVE_TOKEN_ADDRESS).to_string(),
@@ -42,6 +70,7 @@ pub fn get_static_uniswap_tokens(token_address: &[u8]) -> Option<Erc20Token> {
             decimals: 18,
             total_supply: "".to_string(), // subgraph doesn't check the total supply
             whitelist_pools: vec![],
+            ..Default::default()
         }),
         x if x == LIF_TOKEN_ADDRESS => Some(Erc20Token {
             address: Hex(&LIF_TOKEN_ADDRESS).to_string(),
@@ -50,6 +79,7 @@ pub fn get_static_uniswap_tokens(token_address: &[u8]) -> Option<Erc20Token> {
             decimals: 18,
             total_supply: "".to_string(), // subgraph doesn't check the total supply
             whitelist_pools: vec![],
+            ..Default::default()
         }),
         x if x == SVD_TOKEN_ADDRESS => Some(Erc20Token {
             address: Hex(&SVD_TOKEN_ADDRESS).to_string(),
@@ -58,6 +88,7 @@ pub fn get_static_uniswap_tokens(token_address: &[u8]) -> Option<Erc20Token> {
             decimals: 18,
             total_supply: "".to_string(), // subgraph doesn't check the total supply
             whitelist_pools: vec![],
+            ..Default::default()
         }),
         x if x == THEDAO_TOKEN_ADDRESS => Some(Erc20Token {
             address: Hex(&THEDAO_TOKEN_ADDRESS).to_string(),
@@ -66,6 +97,7 @@ pub fn get_static_uniswap_tokens(token_address: &[u8]) -> Option<Erc20Token> {
             decimals: 16,
             total_supply: "".to_string(), // subgraph doesn't check the total supply
             whitelist_pools: vec![],
+            ..Default::default()
         }),
         x if x == HPB_TOKEN_ADDRESS => Some(Erc20Token {
             address: Hex(&HPB_TOKEN_ADDRESS).to_string(),
@@ -74,6 +106,7 @@ pub fn get_static_uniswap_tokens(token_address: &[u8]) -> Option<Erc20Token> {
             decimals: 18,
             total_supply: "".to_string(), // subgraph doesn't check the total supply
             whitelist_pools: vec![],
+            ..Default::default()
         }),
         _ => None,
     }
@@ -88,7 +121,7 @@ pub fn extract_pool_fee_growth_global_updates(
 
     let storage = storage::uniswap_v3_pool::UniswapPoolStorage::new(storage_changes, pool_address);
 
-    if let Some((_, new_value)) = storage.fee_growth_global0x128() {
+    if let Some((_, new_value)) = storage.fee_growth_global0_x128() {
         fee_growth_global.push(events::FeeGrowthGlobal {
             pool_address: Hex(&pool_address).to_string(),
             ordinal: log_ordinal,
@@ -97,7 +130,7 @@ pub fn extract_pool_fee_growth_global_updates(
         })
     }
 
-    if let Some((_, new_value)) = storage.fee_growth_global1x128() {
+    if let Some((_, new_value)) = storage.fee_growth_global1_x128() {
         fee_growth_global.push(events::FeeGrowthGlobal {
             pool_address: Hex(&pool_address).to_string(),
             ordinal: log_ordinal,
@@ -246,11 +279,14 @@ pub fn load_transaction(
     transaction
 }
 
-pub fn get_derived_eth_price(ordinal: u64, token_addr: &String, eth_prices_store: &StoreGetBigDecimal) -> BigDecimal {
-    return match eth_prices_store.get_at(ordinal, format!("token:{token_addr}:dprice:eth")) {
-        None => panic!("token eth price not found for token {}", token_addr),
-        Some(price) => price,
-    };
+pub fn get_derived_eth_price(
+    ordinal: u64,
+    token_addr: &String,
+    eth_prices_store: &StoreGetBigDecimal,
+) -> Result<BigDecimal, DataError> {
+    eth_prices_store
+        .get_at(ordinal, format!("token:{token_addr}:dprice:eth"))
+        .ok_or_else(|| DataError::DerivedEthPriceNotFound(token_addr.clone()))
 }
 
 pub fn get_token_tvl_in_pool(
@@ -259,16 +295,119 @@ pub fn get_token_tvl_in_pool(
     token_addr: &String,
     token_denom: &str,
     total_value_locked_store: &StoreGetBigDecimal,
-) -> BigDecimal {
+) -> Result<BigDecimal, DataError> {
+    let key = format!("pool:{pool_addr}:{token_addr}:{token_denom}");
     total_value_locked_store
-        .get_at(ordinal, format!("pool:{pool_addr}:{token_addr}:{token_denom}"))
-        .unwrap() // impossible
+        .get_at(ordinal, &key)
+        .ok_or_else(|| DataError::TokenTvlNotFound(token_addr.clone(), key))
 }
 
-pub fn get_token_tvl(ordinal: u64, token_addr: &String, total_value_locked_store: &StoreGetBigDecimal) -> BigDecimal {
+pub fn get_token_tvl(
+    ordinal: u64,
+    token_addr: &String,
+    total_value_locked_store: &StoreGetBigDecimal,
+) -> Result<BigDecimal, DataError> {
+    let key = format!("token:{token_addr}");
     total_value_locked_store
-        .get_at(ordinal, format!("token:{token_addr}"))
-        .unwrap() // impossible
+        .get_at(ordinal, &key)
+        .ok_or_else(|| DataError::TokenTvlNotFound(token_addr.clone(), key))
+}
+
+// The pool, token prices and TVLs store_derived_tvl derives the USD TVL from
+pub struct PoolTvlInputs {
+    pub pool: Pool,
+    pub token0_derived_eth_price: BigDecimal,
+    pub token1_derived_eth_price: BigDecimal,
+    pub token0_tvl_in_pool: BigDecimal,
+    pub token1_tvl_in_pool: BigDecimal,
+    pub token0_tvl: BigDecimal,
+    pub token1_tvl: BigDecimal,
+}
+
+pub fn get_pool_tvl_inputs(
+    ordinal: u64,
+    pool_addr: &String,
+    pools_store: &StoreGetProto<Pool>,
+    eth_prices_store: &StoreGetBigDecimal,
+    total_value_locked_store: &StoreGetBigDecimal,
+) -> Result<PoolTvlInputs, DataError> {
+    let pool = errors::get_pool(pools_store, pool_addr)?;
+    let token0_addr = &pool.token0_ref().address;
+    let token1_addr = &pool.token1_ref().address;
+
+    Ok(PoolTvlInputs {
+        token0_derived_eth_price: get_derived_eth_price(ordinal, token0_addr, eth_prices_store)?,
+        token1_derived_eth_price: get_derived_eth_price(ordinal, token1_addr, eth_prices_store)?,
+        token0_tvl_in_pool: get_token_tvl_in_pool(ordinal, pool_addr, token0_addr, "token0", total_value_locked_store)?,
+        token1_tvl_in_pool: get_token_tvl_in_pool(ordinal, pool_addr, token1_addr, "token1", total_value_locked_store)?,
+        token0_tvl: get_token_tvl(ordinal, token0_addr, total_value_locked_store)?,
+        token1_tvl: get_token_tvl(ordinal, token1_addr, total_value_locked_store)?,
+        pool,
+    })
+}
+
+// Fee growths of a position event, each one the event doesn't carry, e.g. on a transfer or
+// when the positions call failed, being carried forward from the last event carrying them
+pub fn position_fee_growths(
+    position_event: &events::PositionEvent,
+    last_fee_growth_event: Option<events::PositionEvent>,
+) -> (Option<String>, Option<String>) {
+    let (fee_growth_inside0, fee_growth_inside1) = position_event.get_fee_growth_inside_last_x128();
+    let (last_fee_growth_inside0, last_fee_growth_inside1) = last_fee_growth_event
+        .map(|last_event| last_event.get_fee_growth_inside_last_x128())
+        .unwrap_or((None, None));
+
+    (
+        fee_growth_inside0.or(last_fee_growth_inside0),
+        fee_growth_inside1.or(last_fee_growth_inside1),
+    )
+}
+
+// Ordinals and pools store_derived_tvl values again. The pools ignored after their creation are
+// valued again once their amounts are taken out of store_token_tvl, the pool and factory TVLs
+// dropping to zero and the token ones leaving them out.
+pub fn pool_tvl_updates(pool_events: &[events::PoolEvent], filtered_pools: &[FilteredPool]) -> Vec<(u64, String)> {
+    pool_events
+        .iter()
+        .map(|pool_event| (pool_event.log_ordinal, pool_event.pool_address.clone()))
+        .chain(
+            filtered_pools
+                .iter()
+                .filter(|filtered_pool| !filtered_pool.at_creation)
+                .map(|filtered_pool| {
+                    (
+                        filtered_pool.ordinal,
+                        filtered_pool.pool.as_ref().unwrap().address.clone(),
+                    )
+                }),
+        )
+        .collect()
+}
+
+// The bundle and token prices and the amounts store_swaps_volume derives the volumes from
+pub struct SwapVolumeInputs {
+    pub eth_price_in_usd: BigDecimal,
+    pub token0_derived_eth_price: BigDecimal,
+    pub token1_derived_eth_price: BigDecimal,
+    pub amount0_abs: BigDecimal,
+    pub amount1_abs: BigDecimal,
+    pub fee_tier: BigDecimal,
+}
+
+pub fn get_swap_volume_inputs(
+    ordinal: u64,
+    swap: &Swap,
+    pool: &Pool,
+    eth_prices_store: &StoreGetBigDecimal,
+) -> Result<SwapVolumeInputs, DataError> {
+    Ok(SwapVolumeInputs {
+        eth_price_in_usd: errors::get_bundle_eth_price(ordinal, eth_prices_store)?,
+        token0_derived_eth_price: get_derived_eth_price(ordinal, &pool.token0_ref().address, eth_prices_store)?,
+        token1_derived_eth_price: get_derived_eth_price(ordinal, &pool.token1_ref().address, eth_prices_store)?,
+        amount0_abs: errors::parse_decimal("amount0", &swap.amount_0)?.absolute(),
+        amount1_abs: errors::parse_decimal("amount1", &swap.amount_1)?.absolute(),
+        fee_tier: errors::parse_decimal("fee tier", &pool.fee_tier)?,
+    })
 }
 
 pub fn time_as_i64_address_as_str(key: &String) -> (i64, &str) {
@@ -290,3 +429,86 @@ pub fn token_windows_id_fields(key: &String) -> (&str, &str, &str) {
 
     return (table_name, time_id, token_address);
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::pb::uniswap::events::position_event::Type;
+    use crate::pb::uniswap::events::{CollectPosition, IncreaseLiquidityPosition, PositionEvent, TransferPosition};
+    use crate::utils::{get_static_uniswap_tokens, position_fee_growths};
+    use prost::Message;
+
+    #[test]
+    fn static_tokens_matched_on_decoded_address() {
+        let token_address = "e0b7927c4af23765cb51314a0e0521a9645f0e2a".to_string();
+
+        let token = get_static_uniswap_tokens(&hex::decode(&token_address).unwrap()).unwrap();
+        assert_eq!(token_address, token.address);
+        assert_eq!(9, token.decimals);
+
+        assert_eq!(None, get_static_uniswap_tokens(&token_address.encode_to_vec()));
+    }
+
+    fn increase_liquidity(fee_growth_inside0: &str, fee_growth_inside1: &str) -> PositionEvent {
+        PositionEvent {
+            r#type: Some(Type::IncreaseLiquidityPosition(IncreaseLiquidityPosition {
+                token_id: "1".to_string(),
+                fee_growth_inside0_last_x128: Some(fee_growth_inside0.to_string()),
+                fee_growth_inside1_last_x128: Some(fee_growth_inside1.to_string()),
+                ..Default::default()
+            })),
+        }
+    }
+
+    fn collect(fee_growth_inside0: Option<&str>, fee_growth_inside1: Option<&str>) -> PositionEvent {
+        PositionEvent {
+            r#type: Some(Type::CollectPosition(CollectPosition {
+                token_id: "1".to_string(),
+                fee_growth_inside0_last_x128: fee_growth_inside0.map(str::to_string),
+                fee_growth_inside1_last_x128: fee_growth_inside1.map(str::to_string),
+                ..Default::default()
+            })),
+        }
+    }
+
+    fn some(fee_growth_inside0: &str, fee_growth_inside1: &str) -> (Option<String>, Option<String>) {
+        (
+            Some(fee_growth_inside0.to_string()),
+            Some(fee_growth_inside1.to_string()),
+        )
+    }
+
+    #[test]
+    fn position_fee_growths_of_the_event() {
+        let last_event = Some(increase_liquidity("10", "20"));
+        assert_eq!(
+            some("11", "21"),
+            position_fee_growths(&collect(Some("11"), Some("21")), last_event)
+        );
+    }
+
+    #[test]
+    fn position_fee_growths_carried_forward() {
+        let transfer = PositionEvent {
+            r#type: Some(Type::TransferPosition(TransferPosition {
+                token_id: "1".to_string(),
+                ..Default::default()
+            })),
+        };
+        assert_eq!(
+            some("10", "20"),
+            position_fee_growths(&transfer, Some(increase_liquidity("10", "20")))
+        );
+
+        // an event missing its fee growths falls back on the last one carrying them,
+        // value by value
+        assert_eq!(
+            some("10", "20"),
+            position_fee_growths(&collect(None, None), Some(increase_liquidity("10", "20")))
+        );
+        assert_eq!(
+            some("10", "21"),
+            position_fee_growths(&collect(None, Some("21")), Some(increase_liquidity("10", "20")))
+        );
+        assert_eq!((None, None), position_fee_growths(&collect(None, None), None));
+    }
+}
@@ -0,0 +1,207 @@
+// Time windows the Pool, Token and Uniswap data entities are aggregated over. The keys
+// of the window stores are `{table}:{time_id}:...` and the entity ids `0x{address}-{time_id}`,
+// the table being the entity name, e.g. `PoolDayData` or `TokenMonthData`.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowKind {
+    Hour,
+    Day,
+    // ISO week, starting on Monday
+    Week,
+    // calendar month
+    Month,
+}
+
+pub const ALL_WINDOW_KINDS: [WindowKind; 4] = [WindowKind::Day, WindowKind::Hour, WindowKind::Week, WindowKind::Month];
+// there is no UniswapHourData
+pub const UNISWAP_WINDOW_KINDS: [WindowKind; 3] = [WindowKind::Day, WindowKind::Week, WindowKind::Month];
+// swap sources are only aggregated per day
+pub const SOURCE_WINDOW_KINDS: [WindowKind; 1] = [WindowKind::Day];
+
+pub const POOL_WINDOW_TABLES: [&str; 4] = ["PoolDayData", "PoolHourData", "PoolWeekData", "PoolMonthData"];
+pub const TOKEN_WINDOW_TABLES: [&str; 4] = ["TokenDayData", "TokenHourData", "TokenWeekData", "TokenMonthData"];
+pub const UNISWAP_WINDOW_TABLES: [&str; 3] = ["UniswapDayData", "UniswapWeekData", "UniswapMonthData"];
+
+// the unix epoch is a Thursday
+const EPOCH_WEEKDAY_OFFSET: i64 = 3;
+
+impl WindowKind {
+    pub fn from_table(table_name: &str) -> WindowKind {
+        ALL_WINDOW_KINDS
+            .into_iter()
+            .find(|kind| table_name.ends_with(&format!("{}Data", kind.name())))
+            .unwrap_or_else(|| panic!("unknown window table {table_name}"))
+    }
+
+    pub fn from_name(name: &str) -> WindowKind {
+        ALL_WINDOW_KINDS
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+            .unwrap_or_else(|| panic!("unknown window kind {name}, expected one of hour, day, week or month"))
+    }
+
+    pub fn table(&self, entity: &str) -> String {
+        format!("{entity}{}Data", self.name())
+    }
+
+    pub fn time_id(&self, timestamp: i64) -> i64 {
+        let day_id = timestamp / 86400;
+        match self {
+            WindowKind::Hour => timestamp / 3600,
+            WindowKind::Day => day_id,
+            WindowKind::Week => (day_id + EPOCH_WEEKDAY_OFFSET) / 7,
+            WindowKind::Month => {
+                let (year, month) = civil_from_days(day_id);
+                (year - 1970) * 12 + month - 1
+            }
+        }
+    }
+
+    pub fn start_timestamp(&self, time_id: i64) -> i64 {
+        match self {
+            WindowKind::Hour => time_id * 3600,
+            WindowKind::Day => time_id * 86400,
+            WindowKind::Week => (time_id * 7 - EPOCH_WEEKDAY_OFFSET) * 86400,
+            WindowKind::Month => days_from_civil(1970 + time_id / 12, time_id % 12 + 1) * 86400,
+        }
+    }
+
+    // the hourly entities have a `periodStartUnix` instead of a `date`
+    pub fn start_field(&self) -> &'static str {
+        match self {
+            WindowKind::Hour => "periodStartUnix",
+            _ => "date",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowKind::Hour => "Hour",
+            WindowKind::Day => "Day",
+            WindowKind::Week => "Week",
+            WindowKind::Month => "Month",
+        }
+    }
+}
+
+pub struct Window {
+    pub kind: WindowKind,
+    pub time_id: i64,
+}
+
+impl Window {
+    // `{table}:{time_id}`
+    pub fn key(&self, entity: &str) -> String {
+        format!("{}:{}", self.kind.table(entity), self.time_id)
+    }
+
+    // prefix of the keys of the previous window, dropped on the first block of this one
+    pub fn prev_prefix(&self, entity: &str) -> String {
+        format!("{}:{}:", self.kind.table(entity), self.time_id - 1)
+    }
+
+    pub fn entity_id(&self, address: &str) -> String {
+        format!("0x{address}-{}", self.time_id)
+    }
+}
+
+pub fn current_windows(kinds: &[WindowKind], timestamp: i64) -> Vec<Window> {
+    kinds
+        .iter()
+        .map(|kind| Window {
+            kind: *kind,
+            time_id: kind.time_id(timestamp),
+        })
+        .collect()
+}
+
+// params are window kinds separated by `&`, e.g. `hour&day`
+pub fn kinds_from_params(params: &str) -> Vec<WindowKind> {
+    let mut kinds: Vec<WindowKind> = vec![];
+    for name in params.split('&').map(str::trim).filter(|name| !name.is_empty()) {
+        let kind = WindowKind::from_name(name);
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+
+    kinds
+}
+
+// year and month of a number of days since the unix epoch, see
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month)
+}
+
+// number of days since the unix epoch of the first day of a month, see
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod test {
+    use crate::windows;
+    use crate::windows::WindowKind;
+
+    // 2021-05-05T00:00:00Z, a Wednesday
+    const TIMESTAMP: i64 = 1620172800;
+
+    #[test]
+    fn test_week_starts_on_monday() {
+        let week_id = WindowKind::Week.time_id(TIMESTAMP);
+        // 2021-05-03T00:00:00Z
+        assert_eq!(1620000000, WindowKind::Week.start_timestamp(week_id));
+        assert_eq!(week_id, WindowKind::Week.time_id(1620000000));
+        assert_eq!(week_id - 1, WindowKind::Week.time_id(1620000000 - 1));
+    }
+
+    #[test]
+    fn test_calendar_month() {
+        let month_id = WindowKind::Month.time_id(TIMESTAMP);
+        assert_eq!((2021 - 1970) * 12 + 4, month_id);
+        // 2021-05-01T00:00:00Z
+        assert_eq!(1619827200, WindowKind::Month.start_timestamp(month_id));
+        // 2021-02-28T23:59:59Z and 2021-03-01T00:00:00Z
+        assert_eq!(
+            WindowKind::Month.time_id(1614556799) + 1,
+            WindowKind::Month.time_id(1614556800)
+        );
+        // 2020-02-29T12:00:00Z, leap day
+        assert_eq!((2020 - 1970) * 12 + 1, WindowKind::Month.time_id(1582977600));
+    }
+
+    #[test]
+    fn test_from_table() {
+        assert_eq!(WindowKind::Hour, WindowKind::from_table("PoolHourData"));
+        assert_eq!(WindowKind::Week, WindowKind::from_table("TokenWeekData"));
+        assert_eq!(WindowKind::Month, WindowKind::from_table("UniswapMonthData"));
+        assert_eq!("TokenDayData", WindowKind::Day.table("Token"));
+    }
+
+    #[test]
+    fn test_kinds_from_params() {
+        assert_eq!(
+            vec![WindowKind::Hour, WindowKind::Day],
+            windows::kinds_from_params("hour&Day&hour")
+        );
+        assert!(windows::kinds_from_params("").is_empty());
+    }
+}